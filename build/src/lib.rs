@@ -0,0 +1,169 @@
+//! Build script support for quack-protobuf
+//!
+//! Compiles .proto files at build time and writes the generated modules into
+//! `OUT_DIR`:
+//!
+//! ```rust,no_run
+//! // in build.rs
+//! quack_protobuf_build::Config::new()
+//!     .owned_strings(".foo.Config")
+//!     .derive(".foo", "Eq")
+//!     .compile(&["protos/foo.proto"], &["protos/"])
+//!     .unwrap();
+//! ```
+//!
+//! The generated modules are then included in the crate with:
+//!
+//! ```rust,ignore
+//! include!(concat!(env!("OUT_DIR"), "/mod.rs"));
+//! ```
+//!
+//! Messages and fields are selected by their protobuf path: `.` for
+//! everything, `.foo` for a package, `.foo.Bar` for a message and its nested
+//! types, `.foo.Bar.baz` for a single field.
+
+#![deny(missing_docs)]
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use quack_protobuf_codegen::generator::Generator;
+
+pub use quack_protobuf_codegen::errors::{Error, Result};
+
+/// Configuration of the code generation
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    generator: Generator,
+    out_dir: Option<PathBuf>,
+}
+
+impl Config {
+    /// Creates a new configuration, writing into `OUT_DIR`
+    pub fn new() -> Self {
+        Config::default()
+    }
+
+    /// Writes the generated files into `dir` instead of `OUT_DIR`
+    pub fn out_dir<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+    ) -> &mut Self {
+        self.out_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Stores the strings and bytes of the selected fields as `String` and
+    /// `Vec<u8>` instead of `Cow<'a, str>` and `Cow<'a, [u8]>`
+    pub fn owned_strings(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.generator.owned_strings(path);
+        self
+    }
+
+    /// Stores the selected `bytes` fields as `bytes::Bytes` instead of
+    /// `Cow<'a, [u8]>`, sharing the decoded buffer without copying it
+    pub fn shared_bytes(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.generator.shared_bytes(path);
+        self
+    }
+
+    /// Stores the selected packed fixed size fields as `Vec<T>` instead of
+    /// `PackedFixed<'a, T>`
+    pub fn vec_for_packed(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.generator.vec_for_packed(path);
+        self
+    }
+
+    /// Adds a derive to the selected messages and enums
+    pub fn derive(
+        &mut self,
+        path: &str,
+        derive: &str,
+    ) -> &mut Self {
+        self.generator.derive(path, derive);
+        self
+    }
+
+    /// Keeps the fields the selected messages do not know, so that decoding
+    /// then encoding them is lossless
+    pub fn preserve_unknown_fields(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.generator.preserve_unknown_fields(path);
+        self
+    }
+
+    /// Caches the sizes of the selected messages while they are encoded,
+    /// instead of computing the sizes of nested messages at every level
+    pub fn cached_size(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.generator.cached_size(path);
+        self
+    }
+
+    /// Generates a trait, a server and a client for the selected services,
+    /// whose messages must use owned strings and bytes
+    pub fn services(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.generator.services(path);
+        self
+    }
+
+    /// Compiles `protos` and all the files they import, searched in `includes`
+    ///
+    /// A `cargo:rerun-if-changed` line is printed for every loaded file so
+    /// that the build script reruns when any of them changes.
+    pub fn compile<P: AsRef<Path>, I: AsRef<Path>>(
+        &self,
+        protos: &[P],
+        includes: &[I],
+    ) -> Result<()> {
+        let out_dir = match &self.out_dir {
+            Some(dir) => dir.clone(),
+            None => env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or_else(|| {
+                    Error::Io(
+                        PathBuf::from("OUT_DIR"),
+                        io::Error::new(
+                            io::ErrorKind::NotFound,
+                            "environment variable not set, `compile` must run in a build script",
+                        ),
+                    )
+                })?,
+        };
+
+        let mut generator = self.generator.clone();
+        for include in includes {
+            generator.include(include);
+        }
+        let set = generator.load(protos)?;
+        for path in set.paths() {
+            println!(
+                "cargo:rerun-if-changed={}",
+                path.display()
+            );
+        }
+        fs::create_dir_all(&out_dir).map_err(|e| Error::Io(out_dir.clone(), e))?;
+        for file in generator.generate_set(&set)? {
+            file.write_to(&out_dir)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+//! Compiles the .proto files of `tests/protos` as a build script would
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use quack_protobuf_build::{Config, Error};
+
+const P3: &str = "../tests/protos/fixtures/p3.proto";
+const INCLUDE: &str = "../tests/protos";
+
+/// An empty directory for the files of `test`
+fn out_dir(test: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(test);
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn output_files() {
+    let dir = out_dir("output_files");
+    Config::new()
+        .out_dir(&dir)
+        .compile(&[P3], &[INCLUDE])
+        .unwrap();
+
+    // imported files are generated too, in a module tree rooted at `mod.rs`
+    let mut files = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(
+        files,
+        ["fixtures.p2.rs", "fixtures.p3.rs", "mod.rs"]
+    );
+    let root = fs::read_to_string(dir.join("mod.rs")).unwrap();
+    assert!(root.contains("include!(\"fixtures.p3.rs\");"));
+    // proto3 strings are borrowed by default
+    let p3 = fs::read_to_string(dir.join("fixtures.p3.rs")).unwrap();
+    assert!(p3.contains("pub struct Shape<'a> {"));
+}
+
+#[test]
+fn config_is_applied() {
+    let dir = out_dir("config_is_applied");
+    Config::new()
+        .out_dir(&dir)
+        .owned_strings(".fixtures.p3.Shape")
+        .derive(".fixtures.p3.Point", "Eq")
+        .compile(&[P3], &[INCLUDE])
+        .unwrap();
+    let p3 = fs::read_to_string(dir.join("fixtures.p3.rs")).unwrap();
+    assert!(p3.contains("pub name: ::std::string::String,"));
+    assert!(p3.contains("#[derive(Debug, Default, PartialEq, Clone, Eq)]\npub struct Point {"));
+}
+
+#[test]
+fn rerun_if_changed() {
+    // `compile` prints to stdout: run it in a child process and read its output
+    if env::var_os("QUACK_PROTOBUF_BUILD_CHILD").is_some() {
+        Config::new()
+            .out_dir(out_dir("rerun_if_changed"))
+            .compile(&[P3], &[INCLUDE])
+            .unwrap();
+        return;
+    }
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "rerun_if_changed", "--nocapture"])
+        .env(
+            "QUACK_PROTOBUF_BUILD_CHILD",
+            "1",
+        )
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // the first line follows the name of the test
+    let mut lines = stdout
+        .lines()
+        .filter_map(|l| l.find("cargo:").map(|i| &l[i..]))
+        .collect::<Vec<_>>();
+    lines.sort();
+    // the imported file is found in the include directory
+    assert_eq!(
+        lines,
+        [
+            "cargo:rerun-if-changed=../tests/protos/fixtures/p2.proto",
+            "cargo:rerun-if-changed=../tests/protos/fixtures/p3.proto",
+        ]
+    );
+}
+
+#[test]
+fn missing_include() {
+    // without the include directory, the import of p2.proto cannot be found
+    let dir = out_dir("missing_include");
+    let err = Config::new()
+        .out_dir(&dir)
+        .compile(&[P3], &[] as &[&str])
+        .unwrap_err();
+    match err {
+        Error::ImportNotFound { import, .. } => assert_eq!(import, "fixtures/p2.proto"),
+        e => panic!(
+            "expected ImportNotFound, got {:?}",
+            e
+        ),
+    }
+    assert!(!dir.exists());
+}
+
+#[test]
+fn invalid_proto() {
+    let dir = out_dir("invalid_proto");
+    fs::create_dir_all(&dir).unwrap();
+    let proto = dir.join("bad.proto");
+    fs::write(
+        &proto,
+        "syntax = \"proto3\";\nmessage A {\n  int32 a = 0;\n}\n",
+    )
+    .unwrap();
+    let err = Config::new()
+        .out_dir(dir.join("out"))
+        .compile(&[&proto], &[&dir])
+        .unwrap_err();
+    match &err {
+        Error::Parse { file, error } => {
+            assert_eq!(file.path, proto);
+            assert_eq!(error.span.line, 3);
+        }
+        e => panic!(
+            "expected a parse error, got {:?}",
+            e
+        ),
+    }
+    // the diagnostic points at the file and the line
+    let message = err.to_string();
+    assert!(
+        message.contains("bad.proto:3:"),
+        "{}",
+        message
+    );
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn out_dir_from_env() {
+    // outside of a build script, `OUT_DIR` is not set
+    if env::var_os("OUT_DIR").is_none() {
+        match Config::new().compile(&[P3], &[INCLUDE]) {
+            Err(Error::Io(path, e)) => {
+                assert_eq!(path, Path::new("OUT_DIR"));
+                assert_eq!(
+                    e.kind(),
+                    std::io::ErrorKind::NotFound
+                );
+            }
+            r => panic!(
+                "expected an error, got {:?}",
+                r
+            ),
+        }
+    }
+}
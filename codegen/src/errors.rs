@@ -0,0 +1,158 @@
+//! A module to handle all errors
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::parser::ast::Span;
+use crate::parser::{snippet, ParseError};
+
+/// An error enum
+#[derive(Debug)]
+pub enum Error {
+    /// Io error on a given path
+    Io(PathBuf, io::Error),
+    /// A .proto file could not be parsed
+    Parse {
+        /// File being parsed
+        file: SourceFile,
+        /// Parsing error
+        error: ParseError,
+    },
+    /// An imported file cannot be found in any include directory
+    ImportNotFound {
+        /// File containing the `import` statement
+        file: SourceFile,
+        /// Imported file, as written
+        import: String,
+        /// Location of the `import` statement
+        span: Span,
+    },
+    /// A type used in a field or a method cannot be resolved
+    UnresolvedType {
+        /// File containing the reference
+        file: SourceFile,
+        /// Type name, as written
+        name: String,
+        /// Location of the reference
+        span: Span,
+    },
+    /// The default value of a field does not match its type
+    InvalidDefault {
+        /// File containing the field
+        file: SourceFile,
+        /// What is wrong with the value
+        message: String,
+        /// Location of the field
+        span: Span,
+    },
+    /// A feature of the .proto language which is not supported yet
+    Unsupported {
+        /// File containing the definition
+        file: SourceFile,
+        /// Unsupported feature
+        what: String,
+        /// Location of the definition
+        span: Span,
+    },
+}
+
+/// A .proto file an error points into
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// Path on disk
+    pub path: PathBuf,
+    /// Content of the file, kept to render the diagnostic
+    pub source: String,
+}
+
+/// A wrapper for `Result<T, Error>`
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Writes `message` followed by the snippet of `file` pointed by `span`
+fn located(
+    f: &mut fmt::Formatter,
+    message: fmt::Arguments,
+    file: &SourceFile,
+    span: Span,
+) -> fmt::Result {
+    write!(
+        f,
+        "{}\n{}",
+        message,
+        snippet(
+            &file.path.display().to_string(),
+            &file.source,
+            span
+        )
+    )
+}
+
+/// Errors pointing into a .proto file are followed by the offending line,
+/// as in:
+///
+/// ```text
+/// cannot resolve type `Foo`
+///  --> foo.proto:3:5
+///   |
+/// 3 |     Foo foo = 1;
+///   |     ^^^^^^^^^^^^
+/// ```
+///
+/// The severity is left to the caller, e.g. `error: ` for the command line.
+impl fmt::Display for Error {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        match self {
+            Error::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Error::Parse { file, error } => located(
+                f,
+                format_args!("{}", error.message),
+                file,
+                error.span,
+            ),
+            Error::ImportNotFound { file, import, span } => located(
+                f,
+                format_args!(
+                    "cannot find imported file \"{}\" in include directories",
+                    import
+                ),
+                file,
+                *span,
+            ),
+            Error::UnresolvedType { file, name, span } => located(
+                f,
+                format_args!(
+                    "cannot resolve type `{}`",
+                    name
+                ),
+                file,
+                *span,
+            ),
+            Error::InvalidDefault { file, message, span } => located(
+                f,
+                format_args!("{}", message),
+                file,
+                *span,
+            ),
+            Error::Unsupported { file, what, span } => located(
+                f,
+                format_args!("{} are not supported", what),
+                file,
+                *span,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, e) => Some(e),
+            Error::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
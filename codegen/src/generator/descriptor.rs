@@ -0,0 +1,519 @@
+//! A module to encode a `FileSet` as a `google.protobuf.FileDescriptorSet`
+//!
+//! The set is what `protoc --descriptor_set_out` writes: every file with its
+//! messages, enums and services, type references being fully qualified.
+//! Map fields come with their synthetic `...Entry` message, proto3
+//! `optional` fields with their synthetic oneof. Source locations, custom
+//! options and extensions are left out.
+
+use std::path::{Path, PathBuf};
+
+use crate::errors::Result;
+use crate::generator::resolve::{qualify, Model, Resolved};
+use crate::loader::{FileSet, ProtoFile};
+use crate::parser::ast::*;
+
+const WIRE_TYPE_VARINT: u32 = 0;
+const WIRE_TYPE_LENGTH_DELIMITED: u32 = 2;
+
+const LABEL_OPTIONAL: u64 = 1;
+const LABEL_REQUIRED: u64 = 2;
+const LABEL_REPEATED: u64 = 3;
+
+const TYPE_GROUP: u64 = 10;
+const TYPE_MESSAGE: u64 = 11;
+const TYPE_ENUM: u64 = 14;
+
+/// A protobuf encoder, for the few field types of descriptors
+#[derive(Default)]
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn varint(
+        &mut self,
+        mut v: u64,
+    ) {
+        while v >= 0x80 {
+            self.buf.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.buf.push(v as u8);
+    }
+
+    fn uint(
+        &mut self,
+        number: u32,
+        v: u64,
+    ) {
+        self.varint((number << 3 | WIRE_TYPE_VARINT) as u64);
+        self.varint(v);
+    }
+
+    /// Writes an `int32`, negative values taking 10 bytes
+    fn int(
+        &mut self,
+        number: u32,
+        v: i32,
+    ) {
+        self.uint(number, v as i64 as u64);
+    }
+
+    fn bool(
+        &mut self,
+        number: u32,
+        v: bool,
+    ) {
+        self.uint(number, v as u64);
+    }
+
+    fn bytes(
+        &mut self,
+        number: u32,
+        v: &[u8],
+    ) {
+        self.varint((number << 3 | WIRE_TYPE_LENGTH_DELIMITED) as u64);
+        self.varint(v.len() as u64);
+        self.buf.extend_from_slice(v);
+    }
+
+    fn string(
+        &mut self,
+        number: u32,
+        v: &str,
+    ) {
+        self.bytes(number, v.as_bytes());
+    }
+
+    fn message(
+        &mut self,
+        number: u32,
+        write: impl FnOnce(&mut Encoder) -> Result<()>,
+    ) -> Result<()> {
+        let mut nested = Encoder::default();
+        write(&mut nested)?;
+        self.bytes(number, &nested.buf);
+        Ok(())
+    }
+}
+
+/// Encodes all the files of `set`, naming them relative to the first of `includes` containing them
+pub(crate) fn encode(
+    set: &FileSet,
+    model: &Model,
+    includes: &[PathBuf],
+) -> Result<Vec<u8>> {
+    let mut out = Encoder::default();
+    for file in &set.files {
+        out.message(1, |e| {
+            FileEncoder { model, file }.file(
+                e,
+                &file_name(&file.path, includes),
+            )
+        })?;
+    }
+    Ok(out.buf)
+}
+
+/// Name of a file in the set, as it is imported
+fn file_name(
+    path: &Path,
+    includes: &[PathBuf],
+) -> String {
+    let relative = includes
+        .iter()
+        .find_map(|i| path.strip_prefix(i).ok())
+        .unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+struct FileEncoder<'a, 'f> {
+    model: &'a Model<'f>,
+    file: &'f ProtoFile,
+}
+
+impl FileEncoder<'_, '_> {
+    /// `FileDescriptorProto`
+    fn file(
+        &self,
+        e: &mut Encoder,
+        name: &str,
+    ) -> Result<()> {
+        let desc = &self.file.descriptor;
+        let package = desc.package.clone().unwrap_or_default();
+        e.string(1, name);
+        if !package.is_empty() {
+            e.string(2, &package);
+        }
+        for (i, import) in desc.imports.iter().enumerate() {
+            e.string(3, &import.path);
+            match import.kind {
+                ImportKind::Public => e.int(10, i as i32),
+                ImportKind::Weak => e.int(11, i as i32),
+                ImportKind::Default => {}
+            }
+        }
+        for message in &desc.messages {
+            e.message(4, |e| {
+                self.message(e, message, &package)
+            })?;
+        }
+        for enumeration in &desc.enums {
+            e.message(5, |e| {
+                enum_type(e, enumeration);
+                Ok(())
+            })?;
+        }
+        for service in &desc.services {
+            e.message(6, |e| {
+                self.service(e, service, &package)
+            })?;
+        }
+        e.string(
+            12,
+            match desc.syntax {
+                Syntax::Proto2 => "proto2",
+                Syntax::Proto3 => "proto3",
+            },
+        );
+        Ok(())
+    }
+
+    /// `DescriptorProto`
+    fn message(
+        &self,
+        e: &mut Encoder,
+        message: &Message,
+        scope: &str,
+    ) -> Result<()> {
+        let full_name = qualify(scope, &message.name);
+        let syntax = self.file.descriptor.syntax;
+        e.string(1, &message.name);
+
+        // fields in declaration order, oneof fields included
+        let mut fields = message
+            .fields
+            .iter()
+            .map(|f| (f, None))
+            .chain(
+                message
+                    .oneofs
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, o)| o.fields.iter().map(move |f| (f, Some(i)))),
+            )
+            .collect::<Vec<_>>();
+        fields.sort_by_key(|(f, _)| f.span.start);
+        let mut synthetic_oneofs = Vec::new();
+        let mut entries = Vec::new();
+        for (field, oneof) in fields {
+            let typ = self.model.resolve(
+                &full_name, &field.typ, self.file, field.span,
+            )?;
+            let proto3_optional = syntax == Syntax::Proto3 && field.label == Some(Label::Optional);
+            let oneof = if proto3_optional {
+                synthetic_oneofs.push(format!("_{}", field.name));
+                Some(message.oneofs.len() + synthetic_oneofs.len() - 1)
+            } else {
+                oneof
+            };
+            if let Resolved::Map(k, v) = &typ {
+                entries.push((
+                    entry_name(&field.name),
+                    k.clone(),
+                    v.clone(),
+                ));
+            }
+            e.message(2, |e| {
+                self.field(e, field, &full_name, &typ);
+                if let Some(i) = oneof {
+                    e.int(9, i as i32);
+                }
+                if proto3_optional {
+                    e.bool(17, true);
+                }
+                Ok(())
+            })?;
+        }
+
+        for nested in &message.messages {
+            e.message(3, |e| {
+                self.message(e, nested, &full_name)
+            })?;
+        }
+        for (name, k, v) in &entries {
+            e.message(3, |e| {
+                e.string(1, name);
+                for (number, name, typ) in [(1, "key", k), (2, "value", v)] {
+                    e.message(2, |e| {
+                        e.string(1, name);
+                        e.int(3, number);
+                        e.uint(4, LABEL_OPTIONAL);
+                        self.field_type(e, typ);
+                        Ok(())
+                    })?;
+                }
+                // options { map_entry: true }
+                e.message(7, |e| {
+                    e.bool(7, true);
+                    Ok(())
+                })
+            })?;
+        }
+        for enumeration in &message.enums {
+            e.message(4, |e| {
+                enum_type(e, enumeration);
+                Ok(())
+            })?;
+        }
+        for range in &message.extension_ranges {
+            e.message(5, |e| {
+                range_type(e, range);
+                Ok(())
+            })?;
+        }
+        for oneof in message
+            .oneofs
+            .iter()
+            .map(|o| &o.name)
+            .chain(&synthetic_oneofs)
+        {
+            e.message(8, |e| {
+                e.string(1, oneof);
+                Ok(())
+            })?;
+        }
+        for range in &message.reserved_numbers {
+            e.message(9, |e| {
+                range_type(e, range);
+                Ok(())
+            })?;
+        }
+        for name in &message.reserved_names {
+            e.string(10, name);
+        }
+        Ok(())
+    }
+
+    /// `FieldDescriptorProto`, but for its oneof
+    fn field(
+        &self,
+        e: &mut Encoder,
+        field: &Field,
+        scope: &str,
+        typ: &Resolved,
+    ) {
+        e.string(1, &field.name);
+        e.int(3, field.number);
+        let label = match (field.label, typ) {
+            (Some(Label::Repeated), _) | (_, Resolved::Map(..)) => LABEL_REPEATED,
+            (Some(Label::Required), _) => LABEL_REQUIRED,
+            _ => LABEL_OPTIONAL,
+        };
+        e.uint(4, label);
+        if let Resolved::Map(..) = typ {
+            e.uint(5, TYPE_MESSAGE);
+            e.string(
+                6,
+                &format!(
+                    ".{}",
+                    qualify(
+                        scope,
+                        &entry_name(&field.name)
+                    )
+                ),
+            );
+        } else {
+            self.field_type(e, typ);
+        }
+        if let Some(default) = field.default() {
+            e.string(
+                7,
+                &default_value(&field.typ, default),
+            );
+        }
+        if let Some(packed) = field.packed() {
+            // options { packed }
+            let mut options = Encoder::default();
+            options.bool(2, packed);
+            e.bytes(8, &options.buf);
+        }
+    }
+
+    /// `type` and `type_name` of a `FieldDescriptorProto`
+    fn field_type(
+        &self,
+        e: &mut Encoder,
+        typ: &Resolved,
+    ) {
+        let (number, name) = match typ {
+            Resolved::Scalar(t) => (scalar_type(t), None),
+            Resolved::Group(i) => (
+                TYPE_GROUP,
+                Some(&self.model.messages[*i].full_name),
+            ),
+            Resolved::Message(i) => (
+                TYPE_MESSAGE,
+                Some(&self.model.messages[*i].full_name),
+            ),
+            Resolved::Enum(i) => (
+                TYPE_ENUM,
+                Some(&self.model.enums[*i].full_name),
+            ),
+            Resolved::Map(..) => unreachable!("maps are repeated entries"),
+        };
+        e.uint(5, number);
+        if let Some(name) = name {
+            e.string(6, &format!(".{}", name));
+        }
+    }
+
+    /// `ServiceDescriptorProto`
+    fn service(
+        &self,
+        e: &mut Encoder,
+        service: &Service,
+        package: &str,
+    ) -> Result<()> {
+        e.string(1, &service.name);
+        for method in &service.methods {
+            e.message(2, |e| {
+                e.string(1, &method.name);
+                for (number, name) in [(2, &method.input_type), (3, &method.output_type)] {
+                    let typ = self.model.resolve(
+                        package,
+                        &FieldType::Named(name.clone()),
+                        self.file,
+                        method.span,
+                    )?;
+                    if let Resolved::Message(i) = typ {
+                        e.string(
+                            number,
+                            &format!(
+                                ".{}",
+                                self.model.messages[i].full_name
+                            ),
+                        );
+                    }
+                }
+                if method.client_streaming {
+                    e.bool(5, true);
+                }
+                if method.server_streaming {
+                    e.bool(6, true);
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// `EnumDescriptorProto`
+fn enum_type(
+    e: &mut Encoder,
+    enumeration: &Enumeration,
+) {
+    e.string(1, &enumeration.name);
+    for value in &enumeration.values {
+        let mut v = Encoder::default();
+        v.string(1, &value.name);
+        v.int(2, value.number);
+        e.bytes(2, &v.buf);
+    }
+    if let Some(allow_alias) = enumeration
+        .option("allow_alias")
+        .and_then(Constant::as_bool)
+    {
+        // options { allow_alias }
+        let mut options = Encoder::default();
+        options.bool(2, allow_alias);
+        e.bytes(3, &options.buf);
+    }
+}
+
+/// `ExtensionRange` or `ReservedRange`, whose end is exclusive
+fn range_type(
+    e: &mut Encoder,
+    range: &FieldRange,
+) {
+    e.int(1, range.start);
+    e.int(2, range.end.saturating_add(1));
+}
+
+/// Number of a scalar type in `FieldDescriptorProto.Type`
+fn scalar_type(typ: &FieldType) -> u64 {
+    match typ {
+        FieldType::Double => 1,
+        FieldType::Float => 2,
+        FieldType::Int64 => 3,
+        FieldType::Uint64 => 4,
+        FieldType::Int32 => 5,
+        FieldType::Fixed64 => 6,
+        FieldType::Fixed32 => 7,
+        FieldType::Bool => 8,
+        FieldType::String => 9,
+        FieldType::Bytes => 12,
+        FieldType::Uint32 => 13,
+        FieldType::Sfixed32 => 15,
+        FieldType::Sfixed64 => 16,
+        FieldType::Sint32 => 17,
+        FieldType::Sint64 => 18,
+        FieldType::Named(_) | FieldType::Map(..) | FieldType::Group(_) => unreachable!("not a scalar"),
+    }
+}
+
+/// Name of the message of the entries of a map field, `foo_bar` giving `FooBarEntry`
+fn entry_name(field: &str) -> String {
+    let mut name = String::new();
+    let mut upper = true;
+    for c in field.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            name.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            name.push(c);
+        }
+    }
+    name.push_str("Entry");
+    name
+}
+
+/// `default_value` of a field, as protoc writes it: bytes C escaped, the others as written
+fn default_value(
+    typ: &FieldType,
+    value: &Constant,
+) -> String {
+    match value {
+        Constant::Bool(b) => b.to_string(),
+        Constant::Int(i) => i.to_string(),
+        Constant::Float(x) if x.is_nan() => "nan".to_string(),
+        Constant::Float(x) if x.is_infinite() => if *x > 0. { "inf" } else { "-inf" }.to_string(),
+        Constant::Float(x) => x.to_string(),
+        Constant::Str(s) if *typ == FieldType::String => String::from_utf8_lossy(s).into_owned(),
+        Constant::Str(s) => {
+            let mut escaped = String::new();
+            for b in s {
+                match b {
+                    b'\n' => escaped.push_str("\\n"),
+                    b'\r' => escaped.push_str("\\r"),
+                    b'\t' => escaped.push_str("\\t"),
+                    b'"' => escaped.push_str("\\\""),
+                    b'\'' => escaped.push_str("\\'"),
+                    b'\\' => escaped.push_str("\\\\"),
+                    0x20..=0x7e => escaped.push(*b as char),
+                    _ => escaped.push_str(&format!("\\{:03o}", b)),
+                }
+            }
+            escaped
+        }
+        Constant::Ident(s) | Constant::Aggregate(s) => s.clone(),
+    }
+}
@@ -0,0 +1,1834 @@
+//! A module to write the Rust code of a resolved `Model`
+//!
+//! Every package is written in its own file. Nested types of a message
+//! `Foo` live in a `mod_Foo` module, and types are always referenced by their
+//! path relative to the current module so that the generated modules only
+//! need to be nested the same way as the packages.
+
+use std::fmt::Write;
+
+use crate::errors::{Error, Result};
+use crate::generator::options::Options;
+use crate::generator::resolve::{escape, is_fixed_size, is_packable, is_packed, FieldModel, Model, Resolved};
+use crate::parser::ast::*;
+use crate::parser::mismatched_default;
+
+const WIRE_TYPE_VARINT: u32 = 0;
+const WIRE_TYPE_FIXED64: u32 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u32 = 2;
+const WIRE_TYPE_START_GROUP: u32 = 3;
+const WIRE_TYPE_FIXED32: u32 = 5;
+
+const HEADER: &str = "// Automatically generated by quack-protobuf-codegen, do not edit.\n";
+
+// Generated code names everything by its absolute path: a message may be
+// called `Result`, `Option` or `Writer`, and nothing is imported.
+const OK: &str = "::core::result::Result::Ok";
+const DEFAULT: &str = "::core::default::Default::default()";
+const SIZEOFS: &str = "::quack_protobuf::sizeofs::";
+
+/// How a field is stored and encoded
+#[derive(Clone, Copy, PartialEq)]
+enum Cardinality {
+    /// proto2 `required`: always written
+    Required,
+    /// proto3 singular field: written when not equal to the type default
+    Implicit,
+    /// proto2 `optional` with an explicit default: written when different
+    WithDefault,
+    /// `Option<T>`
+    Optional,
+    /// `Vec<T>`, one tag per item
+    Repeated,
+    /// `Vec<T>` or `PackedFixed<T>`, one tag for all items
+    Packed,
+    /// `HashMap<K, V>`
+    Map,
+}
+
+fn sizeof_varint(v: u32) -> usize {
+    match v {
+        0x0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        0x4000..=0x1f_ffff => 3,
+        0x20_0000..=0xfff_ffff => 4,
+        _ => 5,
+    }
+}
+
+/// Removes one level of reference from an expression, so that
+/// `&self.a` becomes `self.a` and `m` becomes `*m`
+fn deref(m: &str) -> String {
+    match m.strip_prefix('&') {
+        Some(place) => place.to_string(),
+        None => format!("*{}", m),
+    }
+}
+
+/// Turns an expression into a method receiver, `&self.a` becomes `self.a`
+fn recv(m: &str) -> &str {
+    m.strip_prefix('&').unwrap_or(m)
+}
+
+/// Reference to the message `m` of field `f`, a reference, through its `Box` if any
+fn unboxed(
+    f: &FieldModel,
+    m: &str,
+) -> String {
+    match (f.boxed, m.strip_prefix('&')) {
+        (false, _) => m.to_string(),
+        (true, Some(place)) => format!("&*{}", place),
+        (true, None) => format!("&**{}", m),
+    }
+}
+
+pub(crate) struct Emitter<'m, 'f> {
+    model: &'m Model<'f>,
+    options: &'m Options,
+    out: String,
+    indent: usize,
+    /// module the code is currently written into
+    module: Vec<String>,
+}
+
+impl<'m, 'f> Emitter<'m, 'f> {
+    pub fn new(
+        model: &'m Model<'f>,
+        options: &'m Options,
+    ) -> Self {
+        Emitter {
+            model,
+            options,
+            out: String::new(),
+            indent: 0,
+            module: Vec::new(),
+        }
+    }
+
+    fn line(
+        &mut self,
+        line: &str,
+    ) {
+        if !line.is_empty() {
+            for _ in 0..self.indent {
+                self.out.push_str("    ");
+            }
+            self.out.push_str(line);
+        }
+        self.out.push('\n');
+    }
+
+    fn open(
+        &mut self,
+        line: &str,
+    ) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn close(
+        &mut self,
+        line: &str,
+    ) {
+        self.indent -= 1;
+        self.line(line);
+    }
+
+    /// Writes the derive attribute of a type, along with the custom derives
+    fn derive(
+        &mut self,
+        derives: &[&str],
+        full_name: &str,
+    ) {
+        let mut derives = derives
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+        for d in self.options.derives(full_name) {
+            if !derives.contains(&d) {
+                derives.push(d);
+            }
+        }
+        self.line(&format!(
+            "#[derive({})]",
+            derives.join(", ")
+        ));
+    }
+
+    /// Writes the code for all the top level definitions of a package
+    pub fn package(
+        mut self,
+        package: &str,
+        sources: &[String],
+    ) -> Result<String> {
+        let (_, top) = self
+            .model
+            .packages
+            .iter()
+            .find(|(p, _)| p == package)
+            .expect("unknown package");
+        self.module = package
+            .split('.')
+            .filter(|p| !p.is_empty())
+            .map(escape)
+            .collect();
+
+        self.out.push_str(HEADER);
+        for source in sources {
+            self.line(&format!(
+                "// source: {}",
+                source
+            ));
+        }
+        for &e in &top.enums {
+            self.line("");
+            self.enumeration(e);
+        }
+        for &m in &top.messages {
+            self.line("");
+            self.message(m)?;
+        }
+        for &s in &top.services {
+            self.line("");
+            self.service(s);
+        }
+        Ok(self.out)
+    }
+
+    fn enumeration(
+        &mut self,
+        index: usize,
+    ) {
+        let e = &self.model.enums[index];
+        let name = e.rust_name.clone();
+        let values = &e.enumeration.values;
+
+        // aliases cannot be variants as they share their discriminant
+        let mut variants: Vec<(String, i32)> = Vec::new();
+        let mut aliases = Vec::new();
+        for v in values {
+            match variants.iter().find(|(_, n)| *n == v.number) {
+                Some((canonical, _)) => aliases.push((
+                    escape(&v.name),
+                    canonical.clone(),
+                )),
+                None => variants.push((escape(&v.name), v.number)),
+            }
+        }
+
+        let full_name = e.full_name.clone();
+        self.derive(
+            &[
+                "Debug",
+                "Default",
+                "PartialEq",
+                "Eq",
+                "Clone",
+                "Copy",
+                "Hash",
+            ],
+            &full_name,
+        );
+        self.line("#[allow(non_camel_case_types, clippy::upper_case_acronyms)]");
+        self.open(&format!(
+            "pub enum {} {{",
+            name
+        ));
+        for (i, (v, n)) in variants.iter().enumerate() {
+            if i == 0 {
+                self.line("#[default]");
+            }
+            self.line(&format!("{} = {},", v, n));
+        }
+        self.close("}");
+
+        if !aliases.is_empty() {
+            self.line("");
+            self.line("#[allow(non_upper_case_globals)]");
+            self.open(&format!("impl {} {{", name));
+            for (alias, canonical) in &aliases {
+                self.line(&format!(
+                    "pub const {}: {} = {}::{};",
+                    alias, name, name, canonical
+                ));
+            }
+            self.close("}");
+        }
+
+        self.line("");
+        self.open(&format!(
+            "impl ::core::convert::From<i32> for {} {{",
+            name
+        ));
+        self.open("fn from(i: i32) -> Self {");
+        self.open("match i {");
+        for (v, n) in &variants {
+            self.line(&format!(
+                "{} => {}::{},",
+                n, name, v
+            ));
+        }
+        self.line("_ => <Self as ::core::default::Default>::default(),");
+        self.close("}");
+        self.close("}");
+        self.close("}");
+
+        self.line("");
+        self.open(&format!(
+            "impl ::core::convert::From<{}> for i32 {{",
+            name
+        ));
+        self.open(&format!(
+            "fn from(e: {}) -> i32 {{",
+            name
+        ));
+        self.line("e as i32");
+        self.close("}");
+        self.close("}");
+
+        self.line("");
+        self.open(&format!(
+            "impl<'a> ::core::convert::From<&'a str> for {} {{",
+            name
+        ));
+        self.open("fn from(s: &'a str) -> Self {");
+        self.open("match s {");
+        for v in values {
+            let canonical = variants
+                .iter()
+                .find(|(_, n)| *n == v.number)
+                .map(|(c, _)| c)
+                .unwrap();
+            self.line(&format!(
+                "{:?} => {}::{},",
+                v.name, name, canonical
+            ));
+        }
+        self.line("_ => <Self as ::core::default::Default>::default(),");
+        self.close("}");
+        self.close("}");
+        self.close("}");
+    }
+
+    fn cardinality(
+        &self,
+        message: usize,
+        f: &FieldModel,
+    ) -> Cardinality {
+        let syntax = self.model.messages[message].syntax;
+        match (f.field.label, &f.typ) {
+            (_, Resolved::Map(..)) => Cardinality::Map,
+            (Some(Label::Repeated), t) if is_packed(f.field, t, syntax) => Cardinality::Packed,
+            (Some(Label::Repeated), _) => Cardinality::Repeated,
+            (Some(Label::Required), _) => Cardinality::Required,
+            (Some(Label::Optional), _) if syntax == Syntax::Proto2 && f.field.default().is_some() => {
+                Cardinality::WithDefault
+            }
+            (Some(Label::Optional), _) => Cardinality::Optional,
+            (None, Resolved::Message(_) | Resolved::Group(_)) => Cardinality::Optional,
+            (None, _) => Cardinality::Implicit,
+        }
+    }
+
+    fn lifetime(
+        &self,
+        needs: bool,
+    ) -> &'static str {
+        if needs {
+            "<'a>"
+        } else {
+            ""
+        }
+    }
+
+    /// Whether a packed field is stored as a `PackedFixed`
+    fn is_packed_fixed(f: &FieldModel) -> bool {
+        matches!(&f.typ, Resolved::Scalar(t) if is_fixed_size(t)) && !f.vec_for_packed
+    }
+
+    /// Rust type of a single value `typ` of field `f`
+    fn value_type(
+        &self,
+        typ: &Resolved,
+        f: &FieldModel,
+    ) -> String {
+        let t = match typ {
+            Resolved::Scalar(s) => match s {
+                FieldType::Double => "f64".to_string(),
+                FieldType::Float => "f32".to_string(),
+                FieldType::Int32 | FieldType::Sint32 | FieldType::Sfixed32 => "i32".to_string(),
+                FieldType::Int64 | FieldType::Sint64 | FieldType::Sfixed64 => "i64".to_string(),
+                FieldType::Uint32 | FieldType::Fixed32 => "u32".to_string(),
+                FieldType::Uint64 | FieldType::Fixed64 => "u64".to_string(),
+                FieldType::Bool => "bool".to_string(),
+                FieldType::Bytes if f.shared => "::quack_protobuf::bytes::Bytes".to_string(),
+                FieldType::String if f.owned => "::std::string::String".to_string(),
+                FieldType::Bytes if f.owned => "::std::vec::Vec<u8>".to_string(),
+                FieldType::String => "::std::borrow::Cow<'a, str>".to_string(),
+                FieldType::Bytes => "::std::borrow::Cow<'a, [u8]>".to_string(),
+                _ => unreachable!("not a scalar"),
+            },
+            Resolved::Message(i) | Resolved::Group(i) => format!(
+                "{}{}",
+                self.model.message_path(*i, &self.module),
+                self.lifetime(self.model.messages[*i].needs_lifetime)
+            ),
+            Resolved::Enum(i) => self.model.enum_path(*i, &self.module),
+            Resolved::Map(..) => unreachable!("maps are not values"),
+        };
+        if f.boxed {
+            format!("::std::boxed::Box<{}>", t)
+        } else {
+            t
+        }
+    }
+
+    fn field_type(
+        &self,
+        message: usize,
+        f: &FieldModel,
+    ) -> String {
+        let value = || self.value_type(&f.typ, f);
+        match self.cardinality(message, f) {
+            Cardinality::Required | Cardinality::Implicit | Cardinality::WithDefault => value(),
+            Cardinality::Optional => format!(
+                "::core::option::Option<{}>",
+                value()
+            ),
+            Cardinality::Repeated => format!("::std::vec::Vec<{}>", value()),
+            Cardinality::Packed => match &f.typ {
+                _ if Self::is_packed_fixed(f) => format!(
+                    "::quack_protobuf::PackedFixed<'a, {}>",
+                    value()
+                ),
+                _ => format!("::std::vec::Vec<{}>", value()),
+            },
+            Cardinality::Map => match &f.typ {
+                Resolved::Map(k, v) => format!(
+                    "::std::collections::HashMap<{}, {}>",
+                    self.value_type(k, f),
+                    self.value_type(v, f)
+                ),
+                _ => unreachable!(),
+            },
+        }
+    }
+
+    fn wire_type(typ: &Resolved) -> u32 {
+        match typ {
+            Resolved::Scalar(t) => match t {
+                FieldType::Fixed64 | FieldType::Sfixed64 | FieldType::Double => WIRE_TYPE_FIXED64,
+                FieldType::Fixed32 | FieldType::Sfixed32 | FieldType::Float => WIRE_TYPE_FIXED32,
+                FieldType::String | FieldType::Bytes => WIRE_TYPE_LENGTH_DELIMITED,
+                _ => WIRE_TYPE_VARINT,
+            },
+            Resolved::Enum(_) => WIRE_TYPE_VARINT,
+            Resolved::Group(_) => WIRE_TYPE_START_GROUP,
+            _ => WIRE_TYPE_LENGTH_DELIMITED,
+        }
+    }
+
+    fn tag(
+        field: &Field,
+        wire_type: u32,
+    ) -> u32 {
+        (field.number as u32) << 3 | wire_type
+    }
+
+    /// Expression reading a single value `typ` of field `f`
+    fn read(
+        &self,
+        typ: &Resolved,
+        f: &FieldModel,
+    ) -> String {
+        let read = match typ {
+            Resolved::Scalar(t) => match t {
+                FieldType::Bytes if f.shared => "r.read_shared_bytes(bytes)?".to_string(),
+                FieldType::String if f.owned => "r.read_string(bytes).map(str::to_owned)?".to_string(),
+                FieldType::Bytes if f.owned => "r.read_bytes(bytes).map(<[u8]>::to_vec)?".to_string(),
+                FieldType::String => "r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?".to_string(),
+                FieldType::Bytes => "r.read_bytes(bytes).map(::std::borrow::Cow::Borrowed)?".to_string(),
+                t => format!(
+                    "r.read_{}(bytes)?",
+                    scalar_name(t)
+                ),
+            },
+            Resolved::Enum(_) => "r.read_enum(bytes)?".to_string(),
+            Resolved::Message(_) => "r.read_message(bytes)?".to_string(),
+            Resolved::Group(_) => format!(
+                "r.read_group(bytes, {})?",
+                Self::tag(f.field, WIRE_TYPE_START_GROUP)
+            ),
+            Resolved::Map(..) => unreachable!(),
+        };
+        if f.boxed {
+            format!(
+                "::std::boxed::Box::new({})",
+                read
+            )
+        } else {
+            read
+        }
+    }
+
+    /// Expression writing a single value `m` of field `f`, a reference
+    fn write(
+        &self,
+        typ: &Resolved,
+        f: &FieldModel,
+        m: &str,
+    ) -> String {
+        match typ {
+            Resolved::Scalar(FieldType::String) => format!("w.write_string({})", m),
+            Resolved::Scalar(FieldType::Bytes) => format!("w.write_bytes({})", m),
+            Resolved::Scalar(t) => format!(
+                "w.write_{}({})",
+                scalar_name(t),
+                deref(m)
+            ),
+            Resolved::Enum(_) => format!(
+                "w.write_enum({} as i32)",
+                deref(m)
+            ),
+            Resolved::Message(_) => format!(
+                "w.write_message_with_len_prefix({})",
+                m
+            ),
+            Resolved::Group(_) => format!(
+                "w.write_group({}, {})",
+                f.field.number, m
+            ),
+            Resolved::Map(..) => unreachable!(),
+        }
+    }
+
+    /// Same as `write` for messages which may be boxed
+    fn write_boxed(
+        &self,
+        typ: &Resolved,
+        f: &FieldModel,
+        m: &str,
+    ) -> String {
+        if f.boxed {
+            self.write(
+                typ,
+                f,
+                &format!("&*{}", deref(m)),
+            )
+        } else {
+            self.write(typ, f, m)
+        }
+    }
+
+    /// Binary size of values of a fixed size type
+    fn fixed_size(typ: &Resolved) -> Option<usize> {
+        match typ {
+            Resolved::Scalar(FieldType::Bool) => Some(1),
+            Resolved::Scalar(t) if is_fixed_size(t) => match Self::wire_type(typ) {
+                WIRE_TYPE_FIXED32 => Some(4),
+                _ => Some(8),
+            },
+            _ => None,
+        }
+    }
+
+    /// Expression computing the size of a single value `m` of field `f`, a reference
+    fn size(
+        &self,
+        typ: &Resolved,
+        f: &FieldModel,
+        m: &str,
+    ) -> String {
+        if let Some(size) = Self::fixed_size(typ) {
+            return size.to_string();
+        }
+        match typ {
+            Resolved::Scalar(FieldType::String | FieldType::Bytes) => format!(
+                "{}sizeof_len({}.len())",
+                SIZEOFS,
+                recv(m)
+            ),
+            Resolved::Scalar(t) => format!(
+                "{}sizeof_{}({})",
+                SIZEOFS,
+                scalar_name(t),
+                deref(m)
+            ),
+            Resolved::Enum(_) => format!(
+                "{}sizeof_enum({} as i32)",
+                SIZEOFS,
+                deref(m)
+            ),
+            Resolved::Message(_) => format!(
+                "{}sizeof_len(::quack_protobuf::MessageWrite::get_size({}))",
+                SIZEOFS,
+                unboxed(f, m)
+            ),
+            Resolved::Group(_) => format!(
+                "{}sizeof_group({}, ::quack_protobuf::MessageWrite::get_size({}))",
+                SIZEOFS,
+                f.field.number,
+                unboxed(f, m)
+            ),
+            Resolved::Map(..) => unreachable!(),
+        }
+    }
+
+    /// Rust literal of the default value of a field, to compare against
+    fn default_literal(
+        &self,
+        message: usize,
+        f: &FieldModel,
+    ) -> Result<String> {
+        let file = self.model.messages[message].file;
+        let default = f
+            .field
+            .options
+            .iter()
+            .find(|o| o.name == "default")
+            .expect("only called for fields with a default value");
+        let invalid = |message: String| Error::InvalidDefault {
+            file: file.source_file(),
+            message,
+            span: default.span,
+        };
+        let mismatch = |expected: &str| {
+            invalid(mismatched_default(
+                f.field, expected,
+            ))
+        };
+        let literal = match (&f.typ, &default.value) {
+            (Resolved::Scalar(FieldType::Bool), Constant::Bool(b)) => b.to_string(),
+            (Resolved::Scalar(FieldType::Bool), _) => return Err(mismatch("`true` or `false`")),
+            (Resolved::Scalar(t @ (FieldType::Float | FieldType::Double)), c) => {
+                let ty = if *t == FieldType::Float { "f32" } else { "f64" };
+                match c {
+                    Constant::Int(i) => format!("{}{}", i, ty),
+                    Constant::Float(x) if x.is_nan() => format!("{}::NAN", ty),
+                    Constant::Float(x) if x.is_infinite() && *x > 0. => format!("{}::INFINITY", ty),
+                    Constant::Float(x) if x.is_infinite() => format!("{}::NEG_INFINITY", ty),
+                    Constant::Float(x) => format!("{:?}{}", x, ty),
+                    _ => return Err(mismatch("a number")),
+                }
+            }
+            (Resolved::Scalar(FieldType::String), Constant::Str(s)) => {
+                format!(
+                    "{:?}",
+                    std::str::from_utf8(s).map_err(|_| mismatch("a valid UTF-8 string"))?
+                )
+            }
+            (Resolved::Scalar(FieldType::Bytes), Constant::Str(s)) => {
+                let mut lit = String::from(if f.owned || f.shared { "b\"" } else { "&b\"" });
+                for b in s {
+                    match b {
+                        b'"' | b'\\' => write!(lit, "\\{}", *b as char).unwrap(),
+                        0x20..=0x7e => lit.push(*b as char),
+                        _ => write!(lit, "\\x{:02x}", b).unwrap(),
+                    }
+                }
+                lit.push_str("\"[..]");
+                lit
+            }
+            (Resolved::Scalar(FieldType::String | FieldType::Bytes), _) => return Err(mismatch("a string")),
+            (Resolved::Scalar(_), Constant::Int(i)) => i.to_string(),
+            (Resolved::Scalar(_), _) => return Err(mismatch("an integer")),
+            (Resolved::Enum(e), Constant::Ident(v)) => {
+                let enumeration = &self.model.enums[*e];
+                if !enumeration
+                    .enumeration
+                    .values
+                    .iter()
+                    .any(|value| value.name == *v)
+                {
+                    return Err(invalid(format!(
+                        "default value `{}` of field `{}` is not a value of enum `{}`",
+                        v, f.field.name, enumeration.full_name
+                    )));
+                }
+                format!(
+                    "{}::{}",
+                    self.model.enum_path(*e, &self.module),
+                    escape(v)
+                )
+            }
+            (Resolved::Enum(_), _) => return Err(mismatch("an enum value")),
+            _ => {
+                return Err(invalid(
+                    "default values are only allowed on singular scalar fields".to_string(),
+                ))
+            }
+        };
+        Ok(literal)
+    }
+
+    /// Expression used to initialize a field in `Default::default`
+    fn default_value(
+        &self,
+        message: usize,
+        f: &FieldModel,
+    ) -> Result<String> {
+        let with_default = matches!(
+            self.cardinality(message, f),
+            Cardinality::Required | Cardinality::WithDefault
+        );
+        if !with_default || f.field.default().is_none() {
+            return Ok(DEFAULT.to_string());
+        }
+        let literal = self.default_literal(message, f)?;
+        Ok(match f.typ {
+            Resolved::Scalar(FieldType::String) if f.owned => format!(
+                "::std::string::String::from({})",
+                literal
+            ),
+            Resolved::Scalar(FieldType::Bytes) if f.shared => format!(
+                "::quack_protobuf::bytes::Bytes::from_static(&{})",
+                literal
+            ),
+            Resolved::Scalar(FieldType::Bytes) if f.owned => format!("{}.to_vec()", literal),
+            Resolved::Scalar(FieldType::String | FieldType::Bytes) => format!(
+                "::std::borrow::Cow::Borrowed({})",
+                literal
+            ),
+            _ => literal,
+        })
+    }
+
+    fn message(
+        &mut self,
+        index: usize,
+    ) -> Result<()> {
+        let model = self.model;
+        let m = &model.messages[index];
+        let name = m.rust_name.clone();
+        let lifetime = self.lifetime(m.needs_lifetime);
+
+        let custom_default = m.fields.iter().any(|f| {
+            f.field.default().is_some()
+                && matches!(
+                    self.cardinality(index, f),
+                    Cardinality::Required | Cardinality::WithDefault
+                )
+        });
+
+        // struct definition
+        if custom_default {
+            self.derive(
+                &["Debug", "PartialEq", "Clone"],
+                &m.full_name,
+            );
+        } else {
+            self.derive(
+                &["Debug", "Default", "PartialEq", "Clone"],
+                &m.full_name,
+            );
+        }
+        let names = m
+            .fields
+            .iter()
+            .map(|f| &f.field.name)
+            .chain(m.oneofs.iter().map(|o| &o.oneof.name));
+        if names.into_iter().any(|n| *n != n.to_lowercase()) {
+            self.line("#[allow(non_snake_case)]");
+        }
+        self.open(&format!(
+            "pub struct {}{} {{",
+            name, lifetime
+        ));
+        for f in &m.fields {
+            let typ = self.field_type(index, f);
+            self.line(&format!(
+                "pub {}: {},",
+                escape(&f.field.name),
+                typ
+            ));
+        }
+        for o in &m.oneofs {
+            let oneof_lifetime = self.lifetime(self.oneof_needs_lifetime(index, o.oneof));
+            self.line(&format!(
+                "pub {}: mod_{}::OneOf{}{},",
+                escape(&o.oneof.name),
+                name,
+                o.oneof.name,
+                oneof_lifetime
+            ));
+        }
+        if m.unknown_fields {
+            self.line("pub unknown_fields: ::quack_protobuf::UnknownFields<'a>,");
+        }
+        if m.cached_size {
+            self.line("pub cached_size: ::quack_protobuf::CachedSize,");
+        }
+        self.close("}");
+
+        if custom_default {
+            self.line("");
+            self.open(&format!(
+                "impl{} ::core::default::Default for {}{} {{",
+                lifetime, name, lifetime
+            ));
+            self.open("fn default() -> Self {");
+            self.open(&format!("{} {{", name));
+            for f in &m.fields {
+                let value = self.default_value(index, f)?;
+                self.line(&format!(
+                    "{}: {},",
+                    escape(&f.field.name),
+                    value
+                ));
+            }
+            for o in &m.oneofs {
+                self.line(&format!(
+                    "{}: {},",
+                    escape(&o.oneof.name),
+                    DEFAULT
+                ));
+            }
+            if m.unknown_fields {
+                self.line(&format!(
+                    "unknown_fields: {},",
+                    DEFAULT
+                ));
+            }
+            if m.cached_size {
+                self.line(&format!(
+                    "cached_size: {},",
+                    DEFAULT
+                ));
+            }
+            self.close("}");
+            self.close("}");
+            self.close("}");
+        }
+
+        // MessageInfo
+        self.line("");
+        self.open(&format!(
+            "impl{} ::quack_protobuf::MessageInfo for {}{} {{",
+            lifetime, name, lifetime
+        ));
+        self.line(&format!(
+            "const PATH: &'static str = {:?};",
+            m.full_name
+        ));
+        self.close("}");
+
+        self.message_read(index)?;
+        self.message_write(index)?;
+
+        if m.has_nested() {
+            self.line("");
+            self.line("#[allow(non_snake_case)]");
+            self.open(&format!(
+                "pub mod mod_{} {{",
+                name
+            ));
+            self.module.push(format!("mod_{}", name));
+            for &e in &m.enums {
+                self.line("");
+                self.enumeration(e);
+            }
+            for &n in &m.messages {
+                self.line("");
+                self.message(n)?;
+            }
+            for o in 0..m.oneofs.len() {
+                self.line("");
+                self.oneof(index, o);
+            }
+            self.module.pop();
+            self.close("}");
+        }
+        Ok(())
+    }
+
+    fn oneof_needs_lifetime(
+        &self,
+        message: usize,
+        oneof: &OneOf,
+    ) -> bool {
+        let m = &self.model.messages[message];
+        let o = m
+            .oneofs
+            .iter()
+            .find(|o| o.oneof.name == oneof.name)
+            .unwrap();
+        o.fields.iter().any(|f| match &f.typ {
+            Resolved::Scalar(FieldType::Bytes) if f.shared => false,
+            Resolved::Scalar(FieldType::String | FieldType::Bytes) => !f.owned,
+            Resolved::Message(i) => self.model.messages[*i].needs_lifetime,
+            _ => false,
+        })
+    }
+
+    fn oneof(
+        &mut self,
+        message: usize,
+        index: usize,
+    ) {
+        let model = self.model;
+        let o = &model.messages[message].oneofs[index];
+        let lifetime = self.lifetime(self.oneof_needs_lifetime(message, o.oneof));
+        self.derive(
+            &["Debug", "Default", "PartialEq", "Clone"],
+            &model.messages[message].full_name,
+        );
+        self.line("#[allow(non_camel_case_types)]");
+        self.open(&format!(
+            "pub enum OneOf{}{} {{",
+            o.oneof.name, lifetime
+        ));
+        for f in &o.fields {
+            let typ = self.value_type(&f.typ, f);
+            self.line(&format!(
+                "{}({}),",
+                escape(&f.field.name),
+                typ
+            ));
+        }
+        self.line("#[default]");
+        self.line("None,");
+        self.close("}");
+    }
+
+    fn oneof_path(
+        &self,
+        message: usize,
+        oneof: &OneOf,
+    ) -> String {
+        format!(
+            "mod_{}::OneOf{}",
+            self.model.messages[message].rust_name, oneof.name
+        )
+    }
+
+    /// Expression merging a single message `typ` of field `f` into `m`, a mutable reference
+    fn merge(
+        &self,
+        typ: &Resolved,
+        f: &FieldModel,
+        m: &str,
+    ) -> String {
+        match typ {
+            Resolved::Group(_) => format!(
+                "r.merge_group(bytes, {}, {})?",
+                Self::tag(f.field, WIRE_TYPE_START_GROUP),
+                m
+            ),
+            _ => format!(
+                "r.merge_message(bytes, {})?",
+                m
+            ),
+        }
+    }
+
+    fn message_read(
+        &mut self,
+        index: usize,
+    ) -> Result<()> {
+        let model = self.model;
+        let m = &model.messages[index];
+        let name = &m.rust_name;
+        let lifetime = self.lifetime(m.needs_lifetime);
+
+        self.line("");
+        self.open(&format!(
+            "impl<'a> ::quack_protobuf::MessageRead<'a> for {}{} {{",
+            name, lifetime
+        ));
+        self.open(
+            "fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {",
+        );
+        self.line("let mut msg = <Self as ::core::default::Default>::default();");
+        self.line("::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;");
+        self.line(&format!("{}(msg)", OK));
+        self.close("}");
+        self.close("}");
+
+        self.line("");
+        self.open(&format!(
+            "impl<'a> ::quack_protobuf::MessageMerge<'a> for {}{} {{",
+            name, lifetime
+        ));
+        self.open("fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {");
+        self.open("while !r.is_eof() {");
+        self.open("match r.next_tag(bytes)? {");
+
+        for f in &m.fields {
+            let field = escape(&f.field.name);
+            let tag = Self::tag(
+                f.field,
+                Self::wire_type(&f.typ),
+            );
+            let read = match f.typ {
+                Resolved::Map(..) => String::new(),
+                _ => self.read(&f.typ, f),
+            };
+            let is_message = matches!(
+                f.typ,
+                Resolved::Message(_) | Resolved::Group(_)
+            );
+            // mutable references to the message of the field, unboxed
+            let (place, inserted) = match f.boxed {
+                true => ("&mut *", "&mut **"),
+                false => ("&mut ", ""),
+            };
+            match self.cardinality(index, f) {
+                Cardinality::Required | Cardinality::Implicit if is_message => {
+                    let merge = self.merge(
+                        &f.typ,
+                        f,
+                        &format!("{}self.{}", place, field),
+                    );
+                    self.line(&format!(
+                        "{} => {},",
+                        tag, merge
+                    ));
+                }
+                Cardinality::Required | Cardinality::Implicit | Cardinality::WithDefault => {
+                    self.line(&format!(
+                        "{} => self.{} = {},",
+                        tag, field, read
+                    ));
+                }
+                Cardinality::Optional if is_message => {
+                    let merge = self.merge(
+                        &f.typ,
+                        f,
+                        &format!(
+                            "{}self.{}.get_or_insert_with(::core::default::Default::default)",
+                            inserted, field
+                        ),
+                    );
+                    self.line(&format!(
+                        "{} => {},",
+                        tag, merge
+                    ));
+                }
+                Cardinality::Optional => {
+                    self.line(&format!(
+                        "{} => self.{} = ::core::option::Option::Some({}),",
+                        tag, field, read
+                    ));
+                }
+                Cardinality::Repeated | Cardinality::Packed => {
+                    let packed_tag = Self::tag(
+                        f.field,
+                        WIRE_TYPE_LENGTH_DELIMITED,
+                    );
+                    let packable = is_packable(&f.typ);
+                    if Self::is_packed_fixed(f) && self.cardinality(index, f) == Cardinality::Packed {
+                        self.line(&format!(
+                            "{} => r.append_packed_fixed(bytes, &mut self.{})?,",
+                            packed_tag, field
+                        ));
+                        // parsers must accept both packed and unpacked encodings
+                        self.open(&format!("{} => {{", tag));
+                        self.line(&format!("let v = {};", read));
+                        self.line(&format!(
+                            "self.{}.own();",
+                            field
+                        ));
+                        self.open(&format!(
+                            "if let ::quack_protobuf::PackedFixed::Owned(ref mut values) = self.{} {{",
+                            field
+                        ));
+                        self.line("r.check_repeated_len(values.len())?;");
+                        self.line("values.push(v);");
+                        self.close("}");
+                        self.close("}");
+                    } else {
+                        self.open(&format!("{} => {{", tag));
+                        self.line(&format!(
+                            "r.check_repeated_len(self.{}.len())?;",
+                            field
+                        ));
+                        self.line(&format!(
+                            "self.{}.push({});",
+                            field, read
+                        ));
+                        self.close("}");
+                        if packable {
+                            self.line(&format!(
+                                "{} => r.append_packed(bytes, &mut self.{}, |r, bytes| {})?,",
+                                packed_tag,
+                                field,
+                                closure(&read)
+                            ));
+                        }
+                    }
+                }
+                Cardinality::Map => {
+                    let Resolved::Map(k, v) = &f.typ else { unreachable!() };
+                    self.open(&format!("{} => {{", tag));
+                    self.line(&format!(
+                        "let (key, value) = r.read_map(bytes, |r, bytes| {}, |r, bytes| {})?;",
+                        closure(&self.read(k, f)),
+                        closure(&self.read(v, f))
+                    ));
+                    self.line(&format!(
+                        "r.check_map_len(self.{}.len())?;",
+                        field
+                    ));
+                    self.line(&format!(
+                        "self.{}.insert(key, value);",
+                        field
+                    ));
+                    self.close("}");
+                }
+            }
+        }
+        for o in &m.oneofs {
+            let path = self.oneof_path(index, o.oneof);
+            let oneof = escape(&o.oneof.name);
+            for f in &o.fields {
+                let tag = Self::tag(
+                    f.field,
+                    Self::wire_type(&f.typ),
+                );
+                let variant = format!(
+                    "{}::{}",
+                    path,
+                    escape(&f.field.name)
+                );
+                let read = format!(
+                    "self.{} = {}({})",
+                    oneof,
+                    variant,
+                    self.read(&f.typ, f)
+                );
+                match f.typ {
+                    // a message already set is merged, any other field of the oneof is replaced
+                    Resolved::Message(_) | Resolved::Group(_) => {
+                        self.open(&format!(
+                            "{} => match &mut self.{} {{",
+                            tag, oneof
+                        ));
+                        let merge = self.merge(
+                            &f.typ,
+                            f,
+                            if f.boxed { "&mut **m" } else { "m" },
+                        );
+                        self.line(&format!(
+                            "{}(m) => {},",
+                            variant, merge
+                        ));
+                        self.line(&format!("_ => {},", read));
+                        self.close("},");
+                    }
+                    _ => self.line(&format!(
+                        "{} => {},",
+                        tag, read
+                    )),
+                }
+            }
+        }
+
+        if m.unknown_fields {
+            self.line("t => self.unknown_fields.read(r, bytes, t)?,");
+        } else {
+            self.open("t => {");
+            self.line("r.read_unknown(bytes, t)?;");
+            self.close("}");
+        }
+        self.close("}");
+        self.close("}");
+        self.line(&format!("{}(())", OK));
+        self.close("}");
+
+        self.line("");
+        if m.fields.is_empty() && m.oneofs.is_empty() && !m.unknown_fields {
+            self.line("fn clear(&mut self) {}");
+        } else {
+            self.open("fn clear(&mut self) {");
+            for f in &m.fields {
+                let field = escape(&f.field.name);
+                let value = self.default_value(index, f)?;
+                let clear = match (
+                    self.cardinality(index, f),
+                    &f.typ,
+                ) {
+                    (Cardinality::Repeated | Cardinality::Packed | Cardinality::Map, _) => true,
+                    (Cardinality::Optional, _) => false,
+                    (_, Resolved::Message(_) | Resolved::Group(_)) => true,
+                    // owned strings and bytes keep their allocation
+                    (_, Resolved::Scalar(FieldType::String | FieldType::Bytes)) => {
+                        f.owned && !f.shared && value == DEFAULT
+                    }
+                    _ => false,
+                };
+                if let (true, Resolved::Message(_) | Resolved::Group(_), Cardinality::Required) = (
+                    clear,
+                    &f.typ,
+                    self.cardinality(index, f),
+                ) {
+                    self.line(&format!(
+                        "::quack_protobuf::MessageMerge::clear(&mut {}self.{});",
+                        if f.boxed { "*" } else { "" },
+                        field
+                    ));
+                } else if clear {
+                    self.line(&format!(
+                        "self.{}.clear();",
+                        field
+                    ));
+                } else {
+                    self.line(&format!(
+                        "self.{} = {};",
+                        field, value
+                    ));
+                }
+            }
+            for o in &m.oneofs {
+                self.line(&format!(
+                    "self.{} = {};",
+                    escape(&o.oneof.name),
+                    DEFAULT
+                ));
+            }
+            if m.unknown_fields {
+                self.line("self.unknown_fields.clear();");
+            }
+            self.close("}");
+        }
+        self.close("}");
+        Ok(())
+    }
+
+    /// Condition under which a singular field is written, if any
+    fn write_condition(
+        &self,
+        message: usize,
+        f: &FieldModel,
+        field: &str,
+    ) -> Result<Option<String>> {
+        Ok(
+            match self.cardinality(message, f) {
+                Cardinality::Implicit => Some(match &f.typ {
+                    Resolved::Scalar(FieldType::String | FieldType::Bytes) => format!("!self.{}.is_empty()", field),
+                    Resolved::Scalar(FieldType::Bool) => format!("self.{}", field),
+                    Resolved::Scalar(FieldType::Float | FieldType::Double) => format!("self.{} != 0.", field),
+                    Resolved::Scalar(_) => format!("self.{} != 0", field),
+                    Resolved::Enum(e) => format!(
+                        "self.{} != <{} as ::core::default::Default>::default()",
+                        field,
+                        self.model.enum_path(*e, &self.module)
+                    ),
+                    _ => unreachable!("messages are optional"),
+                }),
+                Cardinality::WithDefault => Some(
+                    match (&f.typ, f.field.default()) {
+                        (Resolved::Scalar(FieldType::Bool), Some(Constant::Bool(true))) => format!("!self.{}", field),
+                        (Resolved::Scalar(FieldType::Bool), _) => format!("self.{}", field),
+                        // NaN is not equal to itself
+                        (Resolved::Scalar(FieldType::Float | FieldType::Double), Some(Constant::Float(x)))
+                            if x.is_nan() =>
+                        {
+                            format!("!self.{}.is_nan()", field)
+                        }
+                        _ => format!(
+                            "self.{} != {}",
+                            field,
+                            self.default_literal(message, f)?
+                        ),
+                    },
+                ),
+                _ => None,
+            },
+        )
+    }
+
+    fn message_write(
+        &mut self,
+        index: usize,
+    ) -> Result<()> {
+        let model = self.model;
+        let m = &model.messages[index];
+        let name = &m.rust_name;
+
+        self.line("");
+        self.open(&format!(
+            "impl{} ::quack_protobuf::MessageWrite for {}{} {{",
+            self.lifetime(m.needs_lifetime),
+            name,
+            self.lifetime(m.needs_lifetime)
+        ));
+
+        // get_size
+        self.open("fn get_size(&self) -> usize {");
+        let mut terms = Vec::new();
+        for f in &m.fields {
+            let field = escape(&f.field.name);
+            let place = format!("&self.{}", field);
+            let tag = Self::tag(
+                f.field,
+                Self::wire_type(&f.typ),
+            );
+            let tag_size = sizeof_varint(tag);
+            let size = match self.cardinality(index, f) {
+                Cardinality::Required => format!(
+                    "{} + {}",
+                    tag_size,
+                    self.size(&f.typ, f, &place)
+                ),
+                Cardinality::Implicit | Cardinality::WithDefault => format!(
+                    "if {} {{ {} + {} }} else {{ 0 }}",
+                    self.write_condition(index, f, &field)?.unwrap(),
+                    tag_size,
+                    self.size(&f.typ, f, &place)
+                ),
+                Cardinality::Optional => format!(
+                    "self.{}.as_ref().map_or(0, |{}| {} + {})",
+                    field,
+                    if Self::fixed_size(&f.typ).is_some() { "_" } else { "m" },
+                    tag_size,
+                    self.size(&f.typ, f, "m")
+                ),
+                Cardinality::Repeated => match Self::fixed_size(&f.typ) {
+                    Some(size) => format!(
+                        "self.{}.len() * {}",
+                        field,
+                        tag_size + size
+                    ),
+                    None => format!(
+                        "self.{}.iter().map(|m| {} + {}).sum::<usize>()",
+                        field,
+                        tag_size,
+                        self.size(&f.typ, f, "m")
+                    ),
+                },
+                Cardinality::Packed => {
+                    let packed_tag_size = sizeof_varint(Self::tag(
+                        f.field,
+                        WIRE_TYPE_LENGTH_DELIMITED,
+                    ));
+                    let len = match Self::fixed_size(&f.typ) {
+                        Some(1) => format!("self.{}.len()", field),
+                        Some(size) => format!(
+                            "self.{}.len() * {}",
+                            field, size
+                        ),
+                        None => format!(
+                            "self.{}.iter().map(|m| {}).sum::<usize>()",
+                            field,
+                            self.size(&f.typ, f, "m")
+                        ),
+                    };
+                    format!(
+                        "if self.{}.is_empty() {{ 0 }} else {{ {} + {}sizeof_len({}) }}",
+                        field, packed_tag_size, SIZEOFS, len
+                    )
+                }
+                Cardinality::Map => {
+                    let Resolved::Map(k, v) = &f.typ else { unreachable!() };
+                    let k_size = self.size(k, f, "k");
+                    let v_size = self.size(v, f, "v");
+                    format!(
+                        "self.{}.iter().map(|({}, {})| {} + {}sizeof_len(2 + {} + {})).sum::<usize>()",
+                        field,
+                        if k_size.contains('k') { "k" } else { "_" },
+                        if v_size.contains('v') { "v" } else { "_" },
+                        tag_size,
+                        SIZEOFS,
+                        k_size,
+                        v_size
+                    )
+                }
+            };
+            terms.push(vec![size]);
+        }
+        for o in &m.oneofs {
+            let path = self.oneof_path(index, o.oneof);
+            let mut lines = vec![format!(
+                "match self.{} {{",
+                escape(&o.oneof.name)
+            )];
+            for f in &o.fields {
+                let tag = Self::tag(
+                    f.field,
+                    Self::wire_type(&f.typ),
+                );
+                lines.push(format!(
+                    "    {}::{}({}) => {} + {},",
+                    path,
+                    escape(&f.field.name),
+                    if Self::fixed_size(&f.typ).is_some() {
+                        "_"
+                    } else {
+                        "ref m"
+                    },
+                    sizeof_varint(tag),
+                    self.size(&f.typ, f, "m")
+                ));
+            }
+            lines.push(format!(
+                "    {}::None => 0,",
+                path
+            ));
+            lines.push("}".to_string());
+            terms.push(lines);
+        }
+        if m.unknown_fields {
+            terms.push(vec![
+                "self.unknown_fields.get_size()".to_string(),
+            ]);
+        }
+        if m.cached_size {
+            self.open("let size = {");
+        }
+        if terms.is_empty() {
+            self.line("0");
+        }
+        // a leading `if` or `match` followed by `+` would be parsed as a statement
+        let wrap = terms.len() > 1 && (terms[0][0].starts_with("if ") || terms[0][0].starts_with("match "));
+        for (i, lines) in terms.iter().enumerate() {
+            for (j, line) in lines.iter().enumerate() {
+                let last = i == 0 && j + 1 == lines.len();
+                let line = match (i, j) {
+                    (0, 0) if wrap && last => format!("({})", line),
+                    (0, 0) if wrap => format!("({}", line),
+                    (0, _) if wrap && last => format!("{})", line),
+                    (0, _) => line.clone(),
+                    (_, 0) => format!("    + {}", line),
+                    _ => format!("    {}", line),
+                };
+                self.line(&line);
+            }
+        }
+        if m.cached_size {
+            self.close("};");
+            self.line("self.cached_size.set(size);");
+            self.line("size");
+        }
+        self.close("}");
+
+        if m.cached_size {
+            self.line("");
+            self.open("fn take_cached_size(&self) -> ::core::option::Option<usize> {");
+            self.line("self.cached_size.take()");
+            self.close("}");
+        }
+
+        // write_message
+        self.line("");
+        if m.fields.is_empty() && m.oneofs.is_empty() && !m.unknown_fields {
+            self.open(
+                "fn write_message<W: ::quack_protobuf::WriterBackend>(&self, _: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {",
+            );
+        } else {
+            self.open(
+                "fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {",
+            );
+        }
+        for f in &m.fields {
+            let field = escape(&f.field.name);
+            let place = format!("&self.{}", field);
+            let tag = Self::tag(
+                f.field,
+                Self::wire_type(&f.typ),
+            );
+            match self.cardinality(index, f) {
+                Cardinality::Required => {
+                    self.line(&format!(
+                        "w.write_with_tag({}, |w| {})?;",
+                        tag,
+                        self.write_boxed(&f.typ, f, &place)
+                    ));
+                }
+                Cardinality::Implicit | Cardinality::WithDefault => {
+                    let condition = self.write_condition(index, f, &field)?.unwrap();
+                    self.open(&format!(
+                        "if {} {{",
+                        condition
+                    ));
+                    self.line(&format!(
+                        "w.write_with_tag({}, |w| {})?;",
+                        tag,
+                        self.write_boxed(&f.typ, f, &place)
+                    ));
+                    self.close("}");
+                }
+                Cardinality::Optional => {
+                    self.open(&format!(
+                        "if let ::core::option::Option::Some(ref m) = self.{} {{",
+                        field
+                    ));
+                    self.line(&format!(
+                        "w.write_with_tag({}, |w| {})?;",
+                        tag,
+                        self.write_boxed(&f.typ, f, "m")
+                    ));
+                    self.close("}");
+                }
+                Cardinality::Repeated => {
+                    self.open(&format!(
+                        "for m in self.{}.iter() {{",
+                        field
+                    ));
+                    self.line(&format!(
+                        "w.write_with_tag({}, |w| {})?;",
+                        tag,
+                        self.write(&f.typ, f, "m")
+                    ));
+                    self.close("}");
+                }
+                Cardinality::Packed => {
+                    let packed_tag = Self::tag(
+                        f.field,
+                        WIRE_TYPE_LENGTH_DELIMITED,
+                    );
+                    match &f.typ {
+                        _ if Self::is_packed_fixed(f) => self.line(&format!(
+                            "w.write_packed_fixed_with_tag({}, &self.{})?;",
+                            packed_tag, field
+                        )),
+                        typ => {
+                            let size = match Self::fixed_size(typ) {
+                                Some(size) => format!("&|_| {}", size),
+                                None => format!(
+                                    "&|m| {}",
+                                    self.size(typ, f, "m")
+                                ),
+                            };
+                            self.line(&format!(
+                                "w.write_packed_with_tag({}, &self.{}, |w, m| {}, {})?;",
+                                packed_tag,
+                                field,
+                                self.write(typ, f, "m"),
+                                size
+                            ));
+                        }
+                    }
+                }
+                Cardinality::Map => {
+                    let Resolved::Map(k, v) = &f.typ else { unreachable!() };
+                    let k_tag = 8 | Self::wire_type(k);
+                    let v_tag = 16 | Self::wire_type(v);
+                    self.open(&format!(
+                        "for (k, v) in self.{}.iter() {{",
+                        field
+                    ));
+                    if let Resolved::Message(_) = **v {
+                        // the size of the message is computed once, by the write
+                        self.line(&format!(
+                            "w.write_with_tag({}, |w| w.write_map_message({}, {}, |w| {}, {}, v))?;",
+                            tag,
+                            self.size(k, f, "k"),
+                            k_tag,
+                            self.write(k, f, "k"),
+                            v_tag
+                        ));
+                    } else {
+                        self.line(&format!(
+                            "w.write_with_tag({}, |w| w.write_map(2 + {} + {}, {}, |w| {}, {}, |w| {}))?;",
+                            tag,
+                            self.size(k, f, "k"),
+                            self.size(v, f, "v"),
+                            k_tag,
+                            self.write(k, f, "k"),
+                            v_tag,
+                            self.write(v, f, "v")
+                        ));
+                    }
+                    self.close("}");
+                }
+            }
+        }
+        for o in &m.oneofs {
+            let path = self.oneof_path(index, o.oneof);
+            self.open(&format!(
+                "match self.{} {{",
+                escape(&o.oneof.name)
+            ));
+            for f in &o.fields {
+                let tag = Self::tag(
+                    f.field,
+                    Self::wire_type(&f.typ),
+                );
+                self.line(&format!(
+                    "{}::{}(ref m) => w.write_with_tag({}, |w| {})?,",
+                    path,
+                    escape(&f.field.name),
+                    tag,
+                    self.write_boxed(&f.typ, f, "m")
+                ));
+            }
+            self.line(&format!(
+                "{}::None => {{}}",
+                path
+            ));
+            self.close("}");
+        }
+        if m.unknown_fields {
+            self.line("w.write_unknown(&self.unknown_fields)?;");
+        }
+        self.line(&format!("{}(())", OK));
+        self.close("}");
+        self.close("}");
+        Ok(())
+    }
+
+    /// Writes the trait, the server and the client of a service
+    fn service(
+        &mut self,
+        index: usize,
+    ) {
+        let model = self.model;
+        let s = &model.services[index];
+        let name = &s.rust_name;
+        let types = s
+            .methods
+            .iter()
+            .map(|m| {
+                (
+                    m.method,
+                    snake_case(&m.method.name),
+                    self.model.message_path(m.input, &self.module),
+                    self.model.message_path(m.output, &self.module),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // trait implemented by the server
+        self.open(&format!(
+            "pub trait {}: ::core::marker::Send + ::core::marker::Sync + 'static {{",
+            name
+        ));
+        for (method, fn_name, input, output) in &types {
+            let (request, sender, response) = match (
+                method.client_streaming,
+                method.server_streaming,
+            ) {
+                (false, false) => (
+                    input.clone(),
+                    String::new(),
+                    format!(
+                        "::quack_protobuf::rpc::Response<{}>",
+                        output
+                    ),
+                ),
+                (true, false) => (
+                    format!(
+                        "::quack_protobuf::rpc::Streaming<{}>",
+                        input
+                    ),
+                    String::new(),
+                    format!(
+                        "::quack_protobuf::rpc::Response<{}>",
+                        output
+                    ),
+                ),
+                (false, true) => (
+                    input.clone(),
+                    format!(
+                        ", sender: &mut ::quack_protobuf::rpc::Sender<{}>",
+                        output
+                    ),
+                    "()".to_string(),
+                ),
+                (true, true) => (
+                    format!(
+                        "::quack_protobuf::rpc::Streaming<{}>",
+                        input
+                    ),
+                    format!(
+                        ", sender: &mut ::quack_protobuf::rpc::Sender<{}>",
+                        output
+                    ),
+                    "()".to_string(),
+                ),
+            };
+            self.line(&format!(
+                "fn {}(&self, request: ::quack_protobuf::rpc::Request<{}>{}) -> impl ::core::future::Future<Output = ::core::result::Result<{}, ::quack_protobuf::rpc::Status>> + ::core::marker::Send;",
+                fn_name, request, sender, response
+            ));
+        }
+        self.close("}");
+
+        // server
+        self.line("");
+        self.open(&format!(
+            "pub struct {}Server<S: {}> {{",
+            name, name
+        ));
+        self.line("inner: S,");
+        self.close("}");
+        self.line("");
+        self.open(&format!(
+            "impl<S: {}> {}Server<S> {{",
+            name, name
+        ));
+        self.open("pub fn new(inner: S) -> Self {");
+        self.line(&format!(
+            "{}Server {{ inner }}",
+            name
+        ));
+        self.close("}");
+        self.close("}");
+        self.line("");
+        self.open(&format!(
+            "impl<S: {}> ::quack_protobuf::rpc::Service for {}Server<S> {{",
+            name, name
+        ));
+        self.open("fn name(&self) -> &'static str {");
+        self.line(&format!("{:?}", s.full_name));
+        self.close("}");
+        self.line("");
+        self.open("fn call<'a>(&'a self, method: &'a str, call: ::quack_protobuf::rpc::Call) -> ::quack_protobuf::rpc::BoxFuture<'a, ()> {");
+        self.open("::std::boxed::Box::pin(async move {");
+        self.open("match method {");
+        for (method, fn_name, input, output) in &types {
+            self.open(&format!(
+                "{:?} => {{",
+                method.name
+            ));
+            let sender = match method.server_streaming {
+                true => "mut sender",
+                false => "sender",
+            };
+            let start = match method.client_streaming {
+                false => format!(
+                    "let ::core::option::Option::Some((request, {})) = call.unary::<{}, {}>().await else {{ return }};",
+                    sender, input, output
+                ),
+                true => format!(
+                    "let (request, {}) = call.streaming::<{}, {}>();",
+                    sender, input, output
+                ),
+            };
+            self.line(&start);
+            if method.server_streaming {
+                self.line(&format!(
+                    "let result = self.inner.{}(request, &mut sender).await;",
+                    fn_name
+                ));
+                self.line("sender.finish(result).await;");
+            } else {
+                self.line(&format!(
+                    "let result = self.inner.{}(request).await;",
+                    fn_name
+                ));
+                self.line("sender.respond(result).await;");
+            }
+            self.close("}");
+        }
+        self.line("_ => call.unimplemented().await,");
+        self.close("}");
+        self.close("})");
+        self.close("}");
+        self.close("}");
+
+        // client
+        self.line("");
+        self.line("#[derive(Debug, Clone)]");
+        self.open(&format!(
+            "pub struct {}Client {{",
+            name
+        ));
+        self.line("channel: ::quack_protobuf::rpc::Channel,");
+        self.close("}");
+        self.line("");
+        self.open(&format!(
+            "impl {}Client {{",
+            name
+        ));
+        self.open("pub fn new(channel: ::quack_protobuf::rpc::Channel) -> Self {");
+        self.line(&format!(
+            "{}Client {{ channel }}",
+            name
+        ));
+        self.close("}");
+        for (method, fn_name, input, output) in &types {
+            let path = format!(
+                "/{}/{}",
+                s.full_name, method.name
+            );
+            self.line("");
+            match (
+                method.client_streaming,
+                method.server_streaming,
+            ) {
+                (false, false) => {
+                    self.open(&format!(
+                        "pub async fn {}(&self, request: ::quack_protobuf::rpc::Request<{}>) -> ::core::result::Result<::quack_protobuf::rpc::Response<{}>, ::quack_protobuf::rpc::Status> {{",
+                        fn_name, input, output
+                    ));
+                    self.line(&format!(
+                        "self.channel.unary({:?}, request).await",
+                        path
+                    ));
+                }
+                (false, true) => {
+                    self.open(&format!(
+                        "pub async fn {}(&self, request: ::quack_protobuf::rpc::Request<{}>) -> ::core::result::Result<::quack_protobuf::rpc::Streaming<{}>, ::quack_protobuf::rpc::Status> {{",
+                        fn_name, input, output
+                    ));
+                    self.line(&format!(
+                        "self.channel.server_streaming({:?}, request).await",
+                        path
+                    ));
+                }
+                (true, _) => {
+                    self.open(&format!(
+                        "pub async fn {}(&self, metadata: ::quack_protobuf::rpc::Metadata) -> ::core::result::Result<(::quack_protobuf::rpc::Sender<{}>, ::quack_protobuf::rpc::Streaming<{}>), ::quack_protobuf::rpc::Status> {{",
+                        fn_name, input, output
+                    ));
+                    self.line(&format!(
+                        "self.channel.streaming({:?}, metadata).await",
+                        path
+                    ));
+                }
+            }
+            self.close("}");
+        }
+        self.close("}");
+    }
+}
+
+/// Rust name of a method, `SayHello` becomes `say_hello`
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if prev_lower {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+            prev_lower = false;
+        } else {
+            out.push(c);
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        }
+    }
+    escape(&out)
+}
+
+/// Closure body returning the value read by `read`, a `?` terminated expression
+fn closure(read: &str) -> String {
+    match read.strip_suffix('?') {
+        Some(result) => result.to_string(),
+        None => format!("{}({})", OK, read),
+    }
+}
+
+/// Name of a scalar type, as used in `read_*`, `write_*` and `sizeof_*`
+fn scalar_name(typ: &FieldType) -> &'static str {
+    match typ {
+        FieldType::Double => "double",
+        FieldType::Float => "float",
+        FieldType::Int32 => "int32",
+        FieldType::Int64 => "int64",
+        FieldType::Uint32 => "uint32",
+        FieldType::Uint64 => "uint64",
+        FieldType::Sint32 => "sint32",
+        FieldType::Sint64 => "sint64",
+        FieldType::Fixed32 => "fixed32",
+        FieldType::Fixed64 => "fixed64",
+        FieldType::Sfixed32 => "sfixed32",
+        FieldType::Sfixed64 => "sfixed64",
+        FieldType::Bool => "bool",
+        FieldType::String => "string",
+        FieldType::Bytes => "bytes",
+        _ => unreachable!("not a scalar"),
+    }
+}
@@ -0,0 +1,303 @@
+//! A module to generate Rust code out of .proto files
+//!
+//! The generated code implements `MessageRead`, `MessageWrite` and
+//! `MessageInfo` for every message so it can be used directly with
+//! quack-protobuf.
+//!
+//! One file is generated per package (`_.rs` for files without a package),
+//! along with a `mod.rs` file nesting the packages into modules:
+//!
+//! ```rust,ignore
+//! include!(concat!(env!("OUT_DIR"), "/mod.rs"));
+//! ```
+//!
+//! The generated types can be tuned per message or per field, selected by
+//! their protobuf path: `.` for everything, `.foo` for a package, `.foo.Bar`
+//! for a message and its nested types, `.foo.Bar.baz` for a single field.
+//! Services are selected the same way, `.foo.Greeter` for a single one.
+
+mod descriptor;
+mod emit;
+mod options;
+mod resolve;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::{Error, Result};
+use crate::generator::emit::Emitter;
+use crate::generator::options::Options;
+use crate::generator::resolve::{escape, Model};
+use crate::loader::FileSet;
+
+/// Name of the file nesting all the generated packages into modules
+pub const MOD_FILE: &str = "mod.rs";
+
+/// A generated Rust file
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedFile {
+    /// File name, relative to the output directory
+    pub name: String,
+    /// Rust code
+    pub content: String,
+}
+
+impl GeneratedFile {
+    /// Writes the file into `dir`, leaving it untouched if the content is the same
+    pub fn write_to<P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> Result<PathBuf> {
+        let path = dir.as_ref().join(&self.name);
+        if fs::read_to_string(&path).is_ok_and(|c| c == self.content) {
+            return Ok(path);
+        }
+        fs::write(&path, &self.content).map_err(|e| Error::Io(path.clone(), e))?;
+        Ok(path)
+    }
+}
+
+/// A code generator
+///
+/// ```rust,no_run
+/// use quack_protobuf_codegen::generator::Generator;
+///
+/// let files = Generator::new()
+///     .include("protos/")
+///     .generate(&["protos/foo.proto"])
+///     .unwrap();
+/// for file in files {
+///     file.write_to("src/generated/").unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Generator {
+    includes: Vec<PathBuf>,
+    options: Options,
+}
+
+impl Generator {
+    /// Creates a new generator with no include directories
+    pub fn new() -> Self {
+        Generator::default()
+    }
+
+    /// Adds a directory where imported files are searched
+    pub fn include<P: AsRef<Path>>(
+        &mut self,
+        dir: P,
+    ) -> &mut Self {
+        self.includes.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Stores the strings and bytes of the selected fields as `String` and
+    /// `Vec<u8>` instead of borrowing them with a `Cow`
+    pub fn owned_strings(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.options.owned(path);
+        self
+    }
+
+    /// Stores the selected `bytes` fields as `bytes::Bytes`, sharing the
+    /// decoded buffer when read with `BytesReader::from_shared`
+    ///
+    /// Requires the `bytes` feature of quack-protobuf. Takes precedence over
+    /// `owned_strings`.
+    pub fn shared_bytes(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.options.shared_bytes(path);
+        self
+    }
+
+    /// Stores the selected packed fixed size fields (`fixed32`, `double` ...)
+    /// as a `Vec` instead of a `PackedFixed`
+    pub fn vec_for_packed(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.options.vec_for_packed(path);
+        self
+    }
+
+    /// Adds a derive to the selected messages and enums, e.g. `"Eq"` or
+    /// `"serde::Serialize"`
+    pub fn derive(
+        &mut self,
+        path: &str,
+        derive: &str,
+    ) -> &mut Self {
+        self.options.derive(path, derive);
+        self
+    }
+
+    /// Keeps the fields the selected messages do not know in an
+    /// `unknown_fields` member, written back when the message is encoded
+    pub fn preserve_unknown_fields(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.options.unknown_fields(path);
+        self
+    }
+
+    /// Caches the size computed by `get_size` in a `cached_size` member of
+    /// the selected messages, so that nested sizes are computed once when
+    /// the messages are written
+    pub fn cached_size(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.options.cached_size(path);
+        self
+    }
+
+    /// Generates a trait, a server and a client for the selected services,
+    /// to be used with the `rpc` feature of quack-protobuf
+    ///
+    /// The messages of their methods must not borrow their input, see
+    /// `owned_strings`.
+    pub fn services(
+        &mut self,
+        path: &str,
+    ) -> &mut Self {
+        self.options.services(path);
+        self
+    }
+
+    /// Loads `files` along with all the files they import
+    pub fn load<P: AsRef<Path>>(
+        &self,
+        files: &[P],
+    ) -> Result<FileSet> {
+        FileSet::load(files, &self.includes)
+    }
+
+    /// Generates the Rust code for `files` and all the files they import
+    pub fn generate<P: AsRef<Path>>(
+        &self,
+        files: &[P],
+    ) -> Result<Vec<GeneratedFile>> {
+        let set = self.load(files)?;
+        self.generate_set(&set)
+    }
+
+    /// Encodes `files` and all the files they import as a
+    /// `google.protobuf.FileDescriptorSet`, as `protoc --descriptor_set_out` does
+    ///
+    /// Files are named relative to the include directory they are found in,
+    /// as they are imported. The set can be read back at runtime by
+    /// `DescriptorPool::from_file_descriptor_set`, with the `dynamic` feature
+    /// of quack-protobuf.
+    pub fn descriptor_set<P: AsRef<Path>>(
+        &self,
+        files: &[P],
+    ) -> Result<Vec<u8>> {
+        let set = self.load(files)?;
+        self.descriptor_set_of(&set)
+    }
+
+    /// Encodes an already loaded `FileSet` as a `google.protobuf.FileDescriptorSet`
+    pub fn descriptor_set_of(
+        &self,
+        set: &FileSet,
+    ) -> Result<Vec<u8>> {
+        let model = Model::new(set, &Options::default())?;
+        descriptor::encode(set, &model, &self.includes)
+    }
+
+    /// Generates the Rust code of an already loaded `FileSet`
+    pub fn generate_set(
+        &self,
+        set: &FileSet,
+    ) -> Result<Vec<GeneratedFile>> {
+        let model = Model::new(set, &self.options)?;
+        let mut files = Vec::new();
+        for (package, _) in &model.packages {
+            let sources = set
+                .files
+                .iter()
+                .filter(|f| {
+                    f.descriptor
+                        .package
+                        .as_deref()
+                        .unwrap_or_default()
+                        == package
+                })
+                .map(|f| f.path.display().to_string())
+                .collect::<Vec<_>>();
+            files.push(GeneratedFile {
+                name: package_file(package),
+                content: Emitter::new(&model, &self.options).package(package, &sources)?,
+            });
+        }
+        files.push(GeneratedFile {
+            name: MOD_FILE.to_string(),
+            content: mod_file(model.packages.iter().map(|(p, _)| p.as_str())),
+        });
+        Ok(files)
+    }
+}
+
+/// Name of the file containing the code of a package
+fn package_file(package: &str) -> String {
+    if package.is_empty() {
+        "_.rs".to_string()
+    } else {
+        format!("{}.rs", package)
+    }
+}
+
+/// A tree of modules, with the package file to include in each of them
+#[derive(Default)]
+struct ModuleTree {
+    file: Option<String>,
+    children: BTreeMap<String, ModuleTree>,
+}
+
+impl ModuleTree {
+    fn write(
+        &self,
+        out: &mut String,
+        depth: usize,
+    ) {
+        let indent = "    ".repeat(depth);
+        if depth > 0 {
+            out.push_str(&indent);
+            out.push_str("#![allow(non_snake_case, non_camel_case_types, non_upper_case_globals)]\n");
+        }
+        if let Some(file) = &self.file {
+            out.push_str(&format!(
+                "{}include!({:?});\n",
+                indent, file
+            ));
+        }
+        for (name, child) in &self.children {
+            out.push_str(&format!(
+                "{}pub mod {} {{\n",
+                indent, name
+            ));
+            child.write(out, depth + 1);
+            out.push_str(&format!("{}}}\n", indent));
+        }
+    }
+}
+
+fn mod_file<'a>(packages: impl Iterator<Item = &'a str>) -> String {
+    let mut root = ModuleTree::default();
+    for package in packages {
+        let mut node = &mut root;
+        for part in package.split('.').filter(|p| !p.is_empty()) {
+            node = node.children.entry(escape(part)).or_default();
+        }
+        node.file = Some(package_file(package));
+    }
+    let mut out = String::from("// Automatically generated by quack-protobuf-codegen, do not edit.\n");
+    root.write(&mut out, 0);
+    out
+}
@@ -0,0 +1,157 @@
+//! A module to store the per-message and per-field overrides of a `Generator`
+//!
+//! Overrides are selected by a protobuf path, either absolute (`.foo.Bar`)
+//! or relative to the root (`foo.Bar`):
+//! - `.` selects everything
+//! - `.foo` selects everything in package `foo`
+//! - `.foo.Bar` selects the message `foo.Bar`, its fields and nested types
+//! - `.foo.Bar.baz` selects the field `baz` of `foo.Bar`
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Options {
+    owned: Vec<String>,
+    shared_bytes: Vec<String>,
+    vec_for_packed: Vec<String>,
+    derives: Vec<(String, String)>,
+    unknown_fields: Vec<String>,
+    cached_size: Vec<String>,
+    services: Vec<String>,
+}
+
+impl Options {
+    pub fn owned(
+        &mut self,
+        path: &str,
+    ) {
+        self.owned.push(normalize(path));
+    }
+
+    pub fn shared_bytes(
+        &mut self,
+        path: &str,
+    ) {
+        self.shared_bytes.push(normalize(path));
+    }
+
+    pub fn vec_for_packed(
+        &mut self,
+        path: &str,
+    ) {
+        self.vec_for_packed.push(normalize(path));
+    }
+
+    pub fn derive(
+        &mut self,
+        path: &str,
+        derive: &str,
+    ) {
+        self.derives.push((
+            normalize(path),
+            derive.to_string(),
+        ));
+    }
+
+    pub fn unknown_fields(
+        &mut self,
+        path: &str,
+    ) {
+        self.unknown_fields.push(normalize(path));
+    }
+
+    pub fn cached_size(
+        &mut self,
+        path: &str,
+    ) {
+        self.cached_size.push(normalize(path));
+    }
+
+    pub fn services(
+        &mut self,
+        path: &str,
+    ) {
+        self.services.push(normalize(path));
+    }
+
+    /// Whether the strings and bytes of a field are stored as `String` and `Vec<u8>`
+    pub fn is_owned(
+        &self,
+        field: &str,
+    ) -> bool {
+        self.owned.iter().any(|p| matches(p, field))
+    }
+
+    /// Whether the bytes of a field are stored as `Bytes` sharing the decoded buffer
+    pub fn is_shared_bytes(
+        &self,
+        field: &str,
+    ) -> bool {
+        self.shared_bytes
+            .iter()
+            .any(|p| matches(p, field))
+    }
+
+    /// Whether a packed fixed size field is stored as a `Vec` rather than a `PackedFixed`
+    pub fn is_vec_for_packed(
+        &self,
+        field: &str,
+    ) -> bool {
+        self.vec_for_packed
+            .iter()
+            .any(|p| matches(p, field))
+    }
+
+    /// Whether a message keeps the fields it does not know in an `UnknownFields`
+    pub fn keeps_unknown_fields(
+        &self,
+        typ: &str,
+    ) -> bool {
+        self.unknown_fields
+            .iter()
+            .any(|p| matches(p, typ))
+    }
+
+    /// Whether a message caches its size in a `CachedSize`
+    pub fn caches_size(
+        &self,
+        typ: &str,
+    ) -> bool {
+        self.cached_size.iter().any(|p| matches(p, typ))
+    }
+
+    /// Whether the rpc server and client of a service are generated
+    pub fn generates_service(
+        &self,
+        service: &str,
+    ) -> bool {
+        self.services.iter().any(|p| matches(p, service))
+    }
+
+    /// Additional derives of a message or an enum, in declaration order
+    pub fn derives(
+        &self,
+        typ: &str,
+    ) -> Vec<String> {
+        let mut derives = Vec::new();
+        for (p, d) in &self.derives {
+            if matches(p, typ) && !derives.contains(d) {
+                derives.push(d.clone());
+            }
+        }
+        derives
+    }
+}
+
+fn normalize(path: &str) -> String {
+    path.trim_start_matches('.').to_string()
+}
+
+fn matches(
+    pattern: &str,
+    path: &str,
+) -> bool {
+    pattern.is_empty()
+        || path == pattern
+        || path
+            .strip_prefix(pattern)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
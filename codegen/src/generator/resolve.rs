@@ -0,0 +1,660 @@
+//! A module to resolve the types of a `FileSet` into a model ready to be
+//! emitted as Rust code
+//!
+//! It computes, for every message:
+//! - the Rust module it lives in and the type of each field
+//! - whether it needs a lifetime (i.e. it borrows from the input buffer or
+//!   keeps its unknown fields)
+//! - which fields must be boxed to break recursive types
+//!
+//! and the request and response messages of the generated services.
+
+use std::collections::HashMap;
+
+use crate::errors::{Error, Result};
+use crate::generator::options::Options;
+use crate::loader::{FileSet, ProtoFile};
+use crate::parser::ast::*;
+
+/// Rust keywords which cannot be used as identifiers
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+    "move", "mut", "override", "priv", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+    "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Escapes identifiers colliding with Rust keywords
+pub(crate) fn escape(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("{}_pb", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// A resolved field type
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Resolved {
+    /// A scalar type (never `Named`, `Map` nor `Group`)
+    Scalar(FieldType),
+    /// Index of a message in `Model::messages`
+    Message(usize),
+    /// Index of an enum in `Model::enums`
+    Enum(usize),
+    /// A map, key then value
+    Map(Box<Resolved>, Box<Resolved>),
+    /// Index of the message declared by a group
+    Group(usize),
+}
+
+pub(crate) struct FieldModel<'f> {
+    pub field: &'f Field,
+    pub typ: Resolved,
+    /// singular message fields which would make the type infinitely sized
+    pub boxed: bool,
+    /// strings and bytes stored as `String` and `Vec<u8>`
+    pub owned: bool,
+    /// bytes stored as `Bytes`, sharing the decoded buffer
+    pub shared: bool,
+    /// packed fixed size values stored as a `Vec` rather than a `PackedFixed`
+    pub vec_for_packed: bool,
+}
+
+pub(crate) struct OneOfModel<'f> {
+    pub oneof: &'f OneOf,
+    pub fields: Vec<FieldModel<'f>>,
+}
+
+pub(crate) struct MessageModel<'f> {
+    pub full_name: String,
+    pub module: Vec<String>,
+    pub rust_name: String,
+    pub message: &'f Message,
+    pub file: &'f ProtoFile,
+    pub syntax: Syntax,
+    pub needs_lifetime: bool,
+    /// unknown fields are kept in an `unknown_fields` member
+    pub unknown_fields: bool,
+    /// the size computed by `get_size` is kept in a `cached_size` member
+    pub cached_size: bool,
+    pub fields: Vec<FieldModel<'f>>,
+    pub oneofs: Vec<OneOfModel<'f>>,
+    pub messages: Vec<usize>,
+    pub enums: Vec<usize>,
+}
+
+impl MessageModel<'_> {
+    /// Module containing the nested types of this message
+    pub fn nested_module(&self) -> Vec<String> {
+        let mut module = self.module.clone();
+        module.push(format!(
+            "mod_{}",
+            self.rust_name
+        ));
+        module
+    }
+
+    pub fn has_nested(&self) -> bool {
+        !self.messages.is_empty() || !self.enums.is_empty() || !self.oneofs.is_empty()
+    }
+}
+
+pub(crate) struct EnumModel<'f> {
+    pub full_name: String,
+    pub module: Vec<String>,
+    pub rust_name: String,
+    pub enumeration: &'f Enumeration,
+}
+
+pub(crate) struct MethodModel<'f> {
+    pub method: &'f Method,
+    /// index of the request message in `Model::messages`
+    pub input: usize,
+    /// index of the response message in `Model::messages`
+    pub output: usize,
+}
+
+pub(crate) struct ServiceModel<'f> {
+    pub full_name: String,
+    pub rust_name: String,
+    pub methods: Vec<MethodModel<'f>>,
+}
+
+/// Top level definitions of a package
+#[derive(Default)]
+pub(crate) struct PackageModel {
+    pub messages: Vec<usize>,
+    pub enums: Vec<usize>,
+    /// only the services selected by `Options::generates_service`
+    pub services: Vec<usize>,
+}
+
+pub(crate) struct Model<'f> {
+    pub messages: Vec<MessageModel<'f>>,
+    pub enums: Vec<EnumModel<'f>>,
+    pub services: Vec<ServiceModel<'f>>,
+    /// packages, in the order they are first defined
+    pub packages: Vec<(String, PackageModel)>,
+    /// all the messages and enums, by full name
+    types: HashMap<String, TypeIndex>,
+}
+
+#[derive(Clone, Copy)]
+enum TypeIndex {
+    Message(usize),
+    Enum(usize),
+}
+
+impl<'f> Model<'f> {
+    pub fn new(
+        set: &'f FileSet,
+        options: &Options,
+    ) -> Result<Self> {
+        let mut model = Model {
+            messages: Vec::new(),
+            enums: Vec::new(),
+            services: Vec::new(),
+            packages: Vec::new(),
+            types: HashMap::new(),
+        };
+        let mut types = HashMap::new();
+
+        for file in &set.files {
+            let desc = &file.descriptor;
+            let package = desc.package.clone().unwrap_or_default();
+            let module = package
+                .split('.')
+                .filter(|p| !p.is_empty())
+                .map(escape)
+                .collect::<Vec<_>>();
+            let mut top = PackageModel::default();
+            for message in &desc.messages {
+                let index = model.register_message(
+                    &mut types,
+                    message,
+                    &package,
+                    &module,
+                    file,
+                    desc.syntax,
+                );
+                top.messages.push(index);
+            }
+            for enumeration in &desc.enums {
+                top.enums.push(model.register_enum(
+                    &mut types,
+                    enumeration,
+                    &package,
+                    &module,
+                ));
+            }
+            match model
+                .packages
+                .iter_mut()
+                .find(|(p, _)| *p == package)
+            {
+                Some((_, existing)) => {
+                    existing.messages.extend(top.messages);
+                    existing.enums.extend(top.enums);
+                    existing.services.extend(top.services);
+                }
+                None => model.packages.push((package, top)),
+            }
+        }
+
+        for i in 0..model.messages.len() {
+            let message = model.messages[i].message;
+            let scope = model.messages[i].full_name.clone();
+            let file = model.messages[i].file;
+            let mut fields = Vec::new();
+            for field in &message.fields {
+                fields.push(FieldModel {
+                    field,
+                    typ: resolve(
+                        &types, &scope, &field.typ, file, field.span,
+                    )?,
+                    boxed: false,
+                    owned: options.is_owned(&qualify(&scope, &field.name)),
+                    shared: options.is_shared_bytes(&qualify(&scope, &field.name)),
+                    vec_for_packed: options.is_vec_for_packed(&qualify(&scope, &field.name)),
+                });
+            }
+            let mut oneofs = Vec::new();
+            for oneof in &message.oneofs {
+                let mut fields = Vec::new();
+                for field in &oneof.fields {
+                    fields.push(FieldModel {
+                        field,
+                        typ: resolve(
+                            &types, &scope, &field.typ, file, field.span,
+                        )?,
+                        boxed: false,
+                        owned: options.is_owned(&qualify(&scope, &field.name)),
+                        shared: options.is_shared_bytes(&qualify(&scope, &field.name)),
+                        vec_for_packed: options.is_vec_for_packed(&qualify(&scope, &field.name)),
+                    });
+                }
+                oneofs.push(OneOfModel { oneof, fields });
+            }
+            model.messages[i].fields = fields;
+            model.messages[i].oneofs = oneofs;
+            model.messages[i].unknown_fields = options.keeps_unknown_fields(&scope);
+            model.messages[i].cached_size = options.caches_size(&scope);
+        }
+
+        model.compute_lifetimes();
+        model.compute_boxes();
+        model.resolve_services(set, options, &types)?;
+        model.types = types;
+        Ok(model)
+    }
+
+    /// Resolves the messages of the selected services, once their lifetimes are known
+    fn resolve_services(
+        &mut self,
+        set: &'f FileSet,
+        options: &Options,
+        types: &HashMap<String, TypeIndex>,
+    ) -> Result<()> {
+        for file in &set.files {
+            let desc = &file.descriptor;
+            let package = desc.package.clone().unwrap_or_default();
+            for service in &desc.services {
+                let full_name = qualify(&package, &service.name);
+                if !options.generates_service(&full_name) {
+                    continue;
+                }
+                let mut methods = Vec::new();
+                for method in &service.methods {
+                    let message = |name: &str| -> Result<usize> {
+                        let typ = FieldType::Named(name.to_string());
+                        let Resolved::Message(i) = resolve(
+                            types,
+                            &package,
+                            &typ,
+                            file,
+                            method.span,
+                        )?
+                        else {
+                            return Err(Error::UnresolvedType {
+                                file: file.source_file(),
+                                name: name.to_string(),
+                                span: method.span,
+                            });
+                        };
+                        if self.messages[i].needs_lifetime {
+                            return Err(Error::Unsupported {
+                                file: file.source_file(),
+                                what: format!(
+                                    "messages borrowing their input in services (`{}`, see `owned_strings`)",
+                                    self.messages[i].full_name
+                                ),
+                                span: method.span,
+                            });
+                        }
+                        Ok(i)
+                    };
+                    methods.push(MethodModel {
+                        method,
+                        input: message(&method.input_type)?,
+                        output: message(&method.output_type)?,
+                    });
+                }
+                let index = self.services.len();
+                self.services.push(ServiceModel {
+                    full_name,
+                    rust_name: escape(&service.name),
+                    methods,
+                });
+                let (_, top) = self
+                    .packages
+                    .iter_mut()
+                    .find(|(p, _)| *p == package)
+                    .expect("registered package");
+                top.services.push(index);
+            }
+        }
+        Ok(())
+    }
+
+    fn register_message(
+        &mut self,
+        types: &mut HashMap<String, TypeIndex>,
+        message: &'f Message,
+        scope: &str,
+        module: &[String],
+        file: &'f ProtoFile,
+        syntax: Syntax,
+    ) -> usize {
+        let full_name = qualify(scope, &message.name);
+        let index = self.messages.len();
+        types.insert(
+            full_name.clone(),
+            TypeIndex::Message(index),
+        );
+        self.messages.push(MessageModel {
+            full_name: full_name.clone(),
+            module: module.to_vec(),
+            rust_name: escape(&message.name),
+            message,
+            file,
+            syntax,
+            needs_lifetime: false,
+            unknown_fields: false,
+            cached_size: false,
+            fields: Vec::new(),
+            oneofs: Vec::new(),
+            messages: Vec::new(),
+            enums: Vec::new(),
+        });
+        let nested_module = self.messages[index].nested_module();
+        for nested in &message.messages {
+            let i = self.register_message(
+                types,
+                nested,
+                &full_name,
+                &nested_module,
+                file,
+                syntax,
+            );
+            self.messages[index].messages.push(i);
+        }
+        for nested in &message.enums {
+            let i = self.register_enum(
+                types,
+                nested,
+                &full_name,
+                &nested_module,
+            );
+            self.messages[index].enums.push(i);
+        }
+        index
+    }
+
+    fn register_enum(
+        &mut self,
+        types: &mut HashMap<String, TypeIndex>,
+        enumeration: &'f Enumeration,
+        scope: &str,
+        module: &[String],
+    ) -> usize {
+        let full_name = qualify(scope, &enumeration.name);
+        let index = self.enums.len();
+        types.insert(
+            full_name.clone(),
+            TypeIndex::Enum(index),
+        );
+        self.enums.push(EnumModel {
+            full_name,
+            module: module.to_vec(),
+            rust_name: escape(&enumeration.name),
+            enumeration,
+        });
+        index
+    }
+
+    /// Iterates over the message types directly contained in `message`
+    fn message_deps(
+        &self,
+        message: usize,
+        singular_only: bool,
+    ) -> Vec<usize> {
+        fn collect(
+            typ: &Resolved,
+            out: &mut Vec<usize>,
+        ) {
+            match typ {
+                Resolved::Message(i) | Resolved::Group(i) => out.push(*i),
+                Resolved::Map(k, v) => {
+                    collect(k, out);
+                    collect(v, out);
+                }
+                _ => {}
+            }
+        }
+        let m = &self.messages[message];
+        let mut out = Vec::new();
+        for f in &m.fields {
+            let singular = !f.field.is_repeated() && !matches!(f.typ, Resolved::Map(..));
+            if singular || !singular_only {
+                collect(&f.typ, &mut out);
+            }
+        }
+        for f in m.oneofs.iter().flat_map(|o| &o.fields) {
+            collect(&f.typ, &mut out);
+        }
+        out
+    }
+
+    fn compute_lifetimes(&mut self) {
+        for m in &mut self.messages {
+            m.needs_lifetime = m.unknown_fields
+                || m.fields
+                    .iter()
+                    .chain(m.oneofs.iter().flat_map(|o| &o.fields))
+                    .any(|f| borrows(f, &f.typ, m.syntax));
+        }
+        loop {
+            let mut changed = false;
+            for i in 0..self.messages.len() {
+                if self.messages[i].needs_lifetime {
+                    continue;
+                }
+                if self
+                    .message_deps(i, false)
+                    .into_iter()
+                    .any(|d| self.messages[d].needs_lifetime)
+                {
+                    self.messages[i].needs_lifetime = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return;
+            }
+        }
+    }
+
+    fn compute_boxes(&mut self) {
+        let deps = (0..self.messages.len())
+            .map(|i| self.message_deps(i, true))
+            .collect::<Vec<_>>();
+        let reaches = |from: usize, to: usize| {
+            let mut seen = vec![false; deps.len()];
+            let mut stack = vec![from];
+            while let Some(i) = stack.pop() {
+                if i == to {
+                    return true;
+                }
+                if !std::mem::replace(&mut seen[i], true) {
+                    stack.extend(&deps[i]);
+                }
+            }
+            false
+        };
+        for i in 0..self.messages.len() {
+            let m = &mut self.messages[i];
+            for f in m
+                .fields
+                .iter_mut()
+                .chain(m.oneofs.iter_mut().flat_map(|o| &mut o.fields))
+            {
+                if f.field.is_repeated() {
+                    continue;
+                }
+                if let Resolved::Message(d) | Resolved::Group(d) = f.typ {
+                    f.boxed = reaches(d, i);
+                }
+            }
+        }
+    }
+
+    /// Resolves a type used in `scope`, as written in `file`
+    pub fn resolve(
+        &self,
+        scope: &str,
+        typ: &FieldType,
+        file: &ProtoFile,
+        span: Span,
+    ) -> Result<Resolved> {
+        resolve(
+            &self.types,
+            scope,
+            typ,
+            file,
+            span,
+        )
+    }
+
+    /// Rust path of a message, as seen from `module`
+    pub fn message_path(
+        &self,
+        index: usize,
+        from: &[String],
+    ) -> String {
+        let m = &self.messages[index];
+        relative_path(from, &m.module, &m.rust_name)
+    }
+
+    /// Rust path of an enum, as seen from `module`
+    pub fn enum_path(
+        &self,
+        index: usize,
+        from: &[String],
+    ) -> String {
+        let e = &self.enums[index];
+        relative_path(from, &e.module, &e.rust_name)
+    }
+}
+
+/// Whether a field borrows from the input buffer by itself
+fn borrows(
+    f: &FieldModel,
+    typ: &Resolved,
+    syntax: Syntax,
+) -> bool {
+    match typ {
+        Resolved::Scalar(FieldType::Bytes) if f.shared => false,
+        Resolved::Scalar(FieldType::String | FieldType::Bytes) => !f.owned,
+        Resolved::Scalar(t) => is_fixed_size(t) && is_packed(f.field, typ, syntax) && !f.vec_for_packed,
+        Resolved::Map(k, v) => borrows(f, k, syntax) || borrows(f, v, syntax),
+        _ => false,
+    }
+}
+
+/// Whether a repeated field is encoded as a packed chunk of data
+///
+/// Packing is the default for numeric types in proto3, and must be asked
+/// explicitly with `[packed = true]` in proto2.
+pub(crate) fn is_packed(
+    field: &Field,
+    typ: &Resolved,
+    syntax: Syntax,
+) -> bool {
+    is_packable(typ) && field.is_repeated() && field.packed().unwrap_or(syntax == Syntax::Proto3)
+}
+
+/// Whether repeated values of `typ` may be packed: numbers and enums, never
+/// strings, messages or groups
+pub(crate) fn is_packable(typ: &Resolved) -> bool {
+    match typ {
+        Resolved::Scalar(t) => t.is_scalar_numeric(),
+        Resolved::Enum(_) => true,
+        _ => false,
+    }
+}
+
+/// Scalar types with a fixed binary size, stored as `PackedFixed` when repeated
+pub(crate) fn is_fixed_size(typ: &FieldType) -> bool {
+    matches!(
+        typ,
+        FieldType::Fixed32
+            | FieldType::Sfixed32
+            | FieldType::Float
+            | FieldType::Fixed64
+            | FieldType::Sfixed64
+            | FieldType::Double
+    )
+}
+
+pub(crate) fn qualify(
+    scope: &str,
+    name: &str,
+) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+fn resolve(
+    types: &HashMap<String, TypeIndex>,
+    scope: &str,
+    typ: &FieldType,
+    file: &ProtoFile,
+    span: Span,
+) -> Result<Resolved> {
+    let named = |name: &str| -> Result<TypeIndex> {
+        let found = if let Some(absolute) = name.strip_prefix('.') {
+            types.get(absolute)
+        } else {
+            // search from the innermost scope outwards
+            let mut scope = scope;
+            loop {
+                if let Some(t) = types.get(&qualify(scope, name)) {
+                    break Some(t);
+                }
+                if scope.is_empty() {
+                    break None;
+                }
+                scope = scope.rfind('.').map_or("", |i| &scope[..i]);
+            }
+        };
+        found
+            .copied()
+            .ok_or_else(|| Error::UnresolvedType {
+                file: file.source_file(),
+                name: name.to_string(),
+                span,
+            })
+    };
+    Ok(match typ {
+        FieldType::Named(name) => match named(name)? {
+            TypeIndex::Message(i) => Resolved::Message(i),
+            TypeIndex::Enum(i) => Resolved::Enum(i),
+        },
+        FieldType::Group(name) => match named(name)? {
+            TypeIndex::Message(i) => Resolved::Group(i),
+            TypeIndex::Enum(_) => unreachable!("groups always declare a message"),
+        },
+        FieldType::Map(k, v) => Resolved::Map(
+            Box::new(resolve(
+                types, scope, k, file, span,
+            )?),
+            Box::new(resolve(
+                types, scope, v, file, span,
+            )?),
+        ),
+        t => Resolved::Scalar(t.clone()),
+    })
+}
+
+fn relative_path(
+    from: &[String],
+    to: &[String],
+    name: &str,
+) -> String {
+    let common = from
+        .iter()
+        .zip(to)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut path = "super::".repeat(from.len() - common);
+    for m in &to[common..] {
+        path.push_str(m);
+        path.push_str("::");
+    }
+    path.push_str(name);
+    path
+}
@@ -0,0 +1,13 @@
+//! Code generation tooling for quack-protobuf
+//!
+//! It is mainly composed of:
+//! - a `parser` which reads .proto files (proto2 and proto3 syntax) into a typed AST
+//! - a `loader` which loads a set of .proto files along with their imports
+//! - a `generator` which writes the Rust code of the messages and enums
+
+#![deny(missing_docs)]
+
+pub mod errors;
+pub mod generator;
+pub mod loader;
+pub mod parser;
@@ -0,0 +1,127 @@
+//! A module to load .proto files along with all the files they import
+//!
+//! Imports are searched in the include directories, in order, then next to
+//! the importing file.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::{Error, Result, SourceFile};
+use crate::parser::{self, ast::FileDescriptor};
+
+/// A parsed .proto file
+#[derive(Debug, Clone)]
+pub struct ProtoFile {
+    /// Path on disk
+    pub path: PathBuf,
+    /// Content of the file, kept to render the errors pointing into it
+    pub source: String,
+    /// Parsed content
+    pub descriptor: FileDescriptor,
+}
+
+impl ProtoFile {
+    /// The file, for the errors pointing into it
+    pub(crate) fn source_file(&self) -> SourceFile {
+        SourceFile {
+            path: self.path.clone(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+/// A set of .proto files closed over imports
+#[derive(Debug, Clone, Default)]
+pub struct FileSet {
+    /// All loaded files, every file comes after the files it imports
+    pub files: Vec<ProtoFile>,
+}
+
+impl FileSet {
+    /// Loads `files` and, recursively, all the files they import
+    pub fn load<P: AsRef<Path>, I: AsRef<Path>>(
+        files: &[P],
+        includes: &[I],
+    ) -> Result<FileSet> {
+        let includes = includes
+            .iter()
+            .map(|i| i.as_ref().to_path_buf())
+            .collect::<Vec<_>>();
+        let mut loader = Loader {
+            includes,
+            loaded: HashSet::new(),
+            set: FileSet::default(),
+        };
+        for file in files {
+            loader.load(file.as_ref())?;
+        }
+        Ok(loader.set)
+    }
+
+    /// Iterates over the paths of all loaded files
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|f| f.path.as_path())
+    }
+}
+
+struct Loader {
+    includes: Vec<PathBuf>,
+    /// canonical paths of the files already seen
+    loaded: HashSet<PathBuf>,
+    set: FileSet,
+}
+
+impl Loader {
+    fn load(
+        &mut self,
+        path: &Path,
+    ) -> Result<()> {
+        let canonical = path
+            .canonicalize()
+            .map_err(|e| Error::Io(path.to_path_buf(), e))?;
+        if !self.loaded.insert(canonical) {
+            // either already loaded or an import cycle, which we tolerate
+            return Ok(());
+        }
+
+        let source = fs::read_to_string(path).map_err(|e| Error::Io(path.to_path_buf(), e))?;
+        let descriptor = match parser::parse(&source) {
+            Ok(descriptor) => descriptor,
+            Err(error) => {
+                return Err(Error::Parse {
+                    file: SourceFile {
+                        path: path.to_path_buf(),
+                        source,
+                    },
+                    error,
+                })
+            }
+        };
+
+        for import in &descriptor.imports {
+            let found = self
+                .includes
+                .iter()
+                .map(|i| i.join(&import.path))
+                .chain(path.parent().map(|p| p.join(&import.path)))
+                .find(|p| p.is_file())
+                .ok_or_else(|| Error::ImportNotFound {
+                    file: SourceFile {
+                        path: path.to_path_buf(),
+                        source: source.clone(),
+                    },
+                    import: import.path.clone(),
+                    span: import.span,
+                })?;
+            self.load(&found)?;
+        }
+
+        self.set.files.push(ProtoFile {
+            path: path.to_path_buf(),
+            source,
+            descriptor,
+        });
+        Ok(())
+    }
+}
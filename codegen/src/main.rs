@@ -0,0 +1,84 @@
+//! Command line interface of the code generator
+//!
+//! ```text
+//! quack-protobuf-codegen [-I DIR]... [-o OUT_DIR] FILE...
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use quack_protobuf_codegen::generator::Generator;
+
+const USAGE: &str = "\
+usage: quack-protobuf-codegen [-I DIR]... [-o OUT_DIR] FILE...
+
+options:
+    -I, --include DIR    search imported files in DIR (can be repeated)
+    -o, --output DIR     write the generated files in DIR (default: current directory)
+    -h, --help           print this message";
+
+fn main() {
+    let mut generator = Generator::new();
+    let mut output = PathBuf::from(".");
+    let mut files = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "-I" | "--include" => match args.next() {
+                Some(dir) => {
+                    generator.include(dir);
+                }
+                None => usage_error("missing directory after -I"),
+            },
+            "-o" | "--output" => match args.next() {
+                Some(dir) => output = PathBuf::from(dir),
+                None => usage_error("missing directory after -o"),
+            },
+            a if a.starts_with("-I") => {
+                generator.include(&a[2..]);
+            }
+            a if a.starts_with('-') => usage_error(&format!(
+                "unknown option {}",
+                a
+            )),
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
+    if files.is_empty() {
+        usage_error("no input file");
+    }
+
+    let generated = generator.generate(&files).unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    });
+    if let Err(e) = fs::create_dir_all(&output) {
+        eprintln!(
+            "error: {}: {}",
+            output.display(),
+            e
+        );
+        process::exit(1);
+    }
+    for file in generated {
+        if let Err(e) = file.write_to(&output) {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!(
+        "error: {}\n\n{}",
+        message, USAGE
+    );
+    process::exit(2);
+}
@@ -0,0 +1,503 @@
+//! A module defining the in-memory representation of a .proto file
+//!
+//! Type references are kept exactly as written in the source (e.g.
+//! `.foo.Bar` or `Bar.Baz`); resolving them against packages and imports is
+//! left to the consumer of the AST.
+
+use std::fmt;
+
+/// A location in the source file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Byte offset of the first character
+    pub start: usize,
+    /// Byte offset one past the last character
+    pub end: usize,
+    /// 1-based line of the first character
+    pub line: usize,
+    /// 1-based column of the first character
+    pub column: usize,
+}
+
+impl Span {
+    /// Returns a span covering both `self` and `other`
+    pub fn to(
+        self,
+        other: Span,
+    ) -> Span {
+        Span { end: other.end, ..self }
+    }
+}
+
+/// The `syntax` declared at the top of the file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// `syntax = "proto2";`, also the default when no syntax is declared
+    #[default]
+    Proto2,
+    /// `syntax = "proto3";`
+    Proto3,
+}
+
+/// A parsed .proto file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileDescriptor {
+    /// Declared syntax
+    pub syntax: Syntax,
+    /// Package, if any
+    pub package: Option<String>,
+    /// `import` statements, in declaration order
+    pub imports: Vec<Import>,
+    /// File level options
+    pub options: Vec<ProtoOption>,
+    /// Top level messages
+    pub messages: Vec<Message>,
+    /// Top level enums
+    pub enums: Vec<Enumeration>,
+    /// Services
+    pub services: Vec<Service>,
+    /// Top level `extend` blocks
+    pub extends: Vec<Extend>,
+}
+
+impl FileDescriptor {
+    /// Gets a file level option by name
+    pub fn option(
+        &self,
+        name: &str,
+    ) -> Option<&Constant> {
+        find_option(&self.options, name)
+    }
+}
+
+/// The flavour of an `import` statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportKind {
+    /// `import "foo.proto";`
+    Default,
+    /// `import public "foo.proto";`
+    Public,
+    /// `import weak "foo.proto";`
+    Weak,
+}
+
+/// An `import` statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    /// Imported path, as written
+    pub path: String,
+    /// Flavour of the import
+    pub kind: ImportKind,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// A constant, as found in options and default values
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    /// `true` or `false`
+    Bool(bool),
+    /// Any integer, sign included
+    Int(i128),
+    /// Any float, including `inf` and `nan`
+    Float(f64),
+    /// A string literal; may contain arbitrary bytes through escapes
+    Str(Vec<u8>),
+    /// An identifier, typically an enum value
+    Ident(String),
+    /// A text format aggregate (`{ ... }`), kept verbatim
+    Aggregate(String),
+}
+
+impl Constant {
+    /// Gets the value as a `&str` if it is a valid utf8 string literal
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Constant::Str(s) => std::str::from_utf8(s).ok(),
+            _ => None,
+        }
+    }
+
+    /// Gets the value as a `bool` if it is one
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Constant::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Describes the kind of the value, for diagnostics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Constant::Bool(_) => "a boolean",
+            Constant::Int(_) => "an integer",
+            Constant::Float(_) => "a float",
+            Constant::Str(_) => "a string",
+            Constant::Ident(_) => "an identifier",
+            Constant::Aggregate(_) => "an aggregate",
+        }
+    }
+}
+
+/// An `option name = value;` statement or a `[name = value]` field option
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtoOption {
+    /// Option name; custom options keep their parentheses, e.g. `(foo.bar).baz`
+    pub name: String,
+    /// Option value
+    pub value: Constant,
+    /// Location of the option
+    pub span: Span,
+}
+
+fn find_option<'a>(
+    options: &'a [ProtoOption],
+    name: &str,
+) -> Option<&'a Constant> {
+    options
+        .iter()
+        .rev()
+        .find(|o| o.name == name)
+        .map(|o| &o.value)
+}
+
+/// A message definition
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Message {
+    /// Message name (not qualified)
+    pub name: String,
+    /// Regular fields, including maps and groups; oneof fields are in `oneofs`
+    pub fields: Vec<Field>,
+    /// Oneofs
+    pub oneofs: Vec<OneOf>,
+    /// Nested messages (including the ones implicitly declared by groups)
+    pub messages: Vec<Message>,
+    /// Nested enums
+    pub enums: Vec<Enumeration>,
+    /// Message options
+    pub options: Vec<ProtoOption>,
+    /// `reserved` field numbers
+    pub reserved_numbers: Vec<FieldRange>,
+    /// `reserved` field names
+    pub reserved_names: Vec<String>,
+    /// `extensions` ranges
+    pub extension_ranges: Vec<FieldRange>,
+    /// Nested `extend` blocks
+    pub extends: Vec<Extend>,
+    /// Location of the message name
+    pub span: Span,
+}
+
+impl Message {
+    /// Gets a message option by name
+    pub fn option(
+        &self,
+        name: &str,
+    ) -> Option<&Constant> {
+        find_option(&self.options, name)
+    }
+
+    /// Iterates over all fields, the ones in oneofs included
+    pub fn all_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields
+            .iter()
+            .chain(self.oneofs.iter().flat_map(|o| o.fields.iter()))
+    }
+}
+
+/// An inclusive range of field (or enum value) numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldRange {
+    /// First number of the range
+    pub start: i32,
+    /// Last number of the range (inclusive)
+    pub end: i32,
+    /// Location of the range
+    pub span: Span,
+}
+
+impl FieldRange {
+    /// Checks whether `number` belongs to the range
+    pub fn contains(
+        &self,
+        number: i32,
+    ) -> bool {
+        self.start <= number && number <= self.end
+    }
+}
+
+/// The label of a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Label {
+    /// `optional`
+    Optional,
+    /// `required` (proto2 only)
+    Required,
+    /// `repeated`
+    Repeated,
+}
+
+/// The type of a field
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+    /// `double`
+    Double,
+    /// `float`
+    Float,
+    /// `int32`
+    Int32,
+    /// `int64`
+    Int64,
+    /// `uint32`
+    Uint32,
+    /// `uint64`
+    Uint64,
+    /// `sint32`
+    Sint32,
+    /// `sint64`
+    Sint64,
+    /// `fixed32`
+    Fixed32,
+    /// `fixed64`
+    Fixed64,
+    /// `sfixed32`
+    Sfixed32,
+    /// `sfixed64`
+    Sfixed64,
+    /// `bool`
+    Bool,
+    /// `string`
+    String,
+    /// `bytes`
+    Bytes,
+    /// A message or an enum, as written in the source
+    Named(String),
+    /// `map<key, value>`
+    Map(Box<FieldType>, Box<FieldType>),
+    /// A proto2 group; the name is the one of the nested message it declares
+    Group(String),
+}
+
+impl FieldType {
+    /// Parses a scalar type keyword
+    pub fn from_scalar(name: &str) -> Option<FieldType> {
+        Some(match name {
+            "double" => FieldType::Double,
+            "float" => FieldType::Float,
+            "int32" => FieldType::Int32,
+            "int64" => FieldType::Int64,
+            "uint32" => FieldType::Uint32,
+            "uint64" => FieldType::Uint64,
+            "sint32" => FieldType::Sint32,
+            "sint64" => FieldType::Sint64,
+            "fixed32" => FieldType::Fixed32,
+            "fixed64" => FieldType::Fixed64,
+            "sfixed32" => FieldType::Sfixed32,
+            "sfixed64" => FieldType::Sfixed64,
+            "bool" => FieldType::Bool,
+            "string" => FieldType::String,
+            "bytes" => FieldType::Bytes,
+            _ => return None,
+        })
+    }
+
+    /// Gets the range of the values of an integer type
+    pub fn int_range(&self) -> Option<(i128, i128)> {
+        Some(match self {
+            FieldType::Int32 | FieldType::Sint32 | FieldType::Sfixed32 => (
+                i32::MIN as i128,
+                i32::MAX as i128,
+            ),
+            FieldType::Int64 | FieldType::Sint64 | FieldType::Sfixed64 => (
+                i64::MIN as i128,
+                i64::MAX as i128,
+            ),
+            FieldType::Uint32 | FieldType::Fixed32 => (0, u32::MAX as i128),
+            FieldType::Uint64 | FieldType::Fixed64 => (0, u64::MAX as i128),
+            _ => return None,
+        })
+    }
+
+    /// Checks whether the type can be used in a packed repeated field
+    ///
+    /// `Named` types are assumed not to be packable since they may be messages
+    pub fn is_scalar_numeric(&self) -> bool {
+        !matches!(
+            self,
+            FieldType::String | FieldType::Bytes | FieldType::Named(_) | FieldType::Map(..) | FieldType::Group(_)
+        )
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        let name = match self {
+            FieldType::Double => "double",
+            FieldType::Float => "float",
+            FieldType::Int32 => "int32",
+            FieldType::Int64 => "int64",
+            FieldType::Uint32 => "uint32",
+            FieldType::Uint64 => "uint64",
+            FieldType::Sint32 => "sint32",
+            FieldType::Sint64 => "sint64",
+            FieldType::Fixed32 => "fixed32",
+            FieldType::Fixed64 => "fixed64",
+            FieldType::Sfixed32 => "sfixed32",
+            FieldType::Sfixed64 => "sfixed64",
+            FieldType::Bool => "bool",
+            FieldType::String => "string",
+            FieldType::Bytes => "bytes",
+            FieldType::Named(name) | FieldType::Group(name) => name,
+            FieldType::Map(k, v) => return write!(f, "map<{}, {}>", k, v),
+        };
+        f.write_str(name)
+    }
+}
+
+/// A field definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    /// Field name
+    pub name: String,
+    /// Field number
+    pub number: i32,
+    /// Label, `None` for proto3 singular fields, map fields and oneof fields
+    pub label: Option<Label>,
+    /// Field type
+    pub typ: FieldType,
+    /// Options between brackets, `default` and `packed` included
+    pub options: Vec<ProtoOption>,
+    /// Location of the field name
+    pub span: Span,
+}
+
+impl Field {
+    /// Gets a field option by name
+    pub fn option(
+        &self,
+        name: &str,
+    ) -> Option<&Constant> {
+        find_option(&self.options, name)
+    }
+
+    /// Gets the `default` value (proto2 only)
+    pub fn default(&self) -> Option<&Constant> {
+        self.option("default")
+    }
+
+    /// Gets the explicit `packed` option, if any
+    pub fn packed(&self) -> Option<bool> {
+        self.option("packed").and_then(Constant::as_bool)
+    }
+
+    /// Checks whether the field has been marked as `deprecated`
+    pub fn deprecated(&self) -> bool {
+        self.option("deprecated")
+            .and_then(Constant::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Checks whether the field is `repeated`
+    pub fn is_repeated(&self) -> bool {
+        self.label == Some(Label::Repeated)
+    }
+}
+
+/// A `oneof` definition
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OneOf {
+    /// Oneof name
+    pub name: String,
+    /// Fields of the oneof
+    pub fields: Vec<Field>,
+    /// Oneof options
+    pub options: Vec<ProtoOption>,
+    /// Location of the oneof name
+    pub span: Span,
+}
+
+/// An enum definition
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Enumeration {
+    /// Enum name (not qualified)
+    pub name: String,
+    /// Values, in declaration order
+    pub values: Vec<EnumValue>,
+    /// Enum options
+    pub options: Vec<ProtoOption>,
+    /// `reserved` numbers
+    pub reserved_numbers: Vec<FieldRange>,
+    /// `reserved` names
+    pub reserved_names: Vec<String>,
+    /// Location of the enum name
+    pub span: Span,
+}
+
+impl Enumeration {
+    /// Gets an enum option by name
+    pub fn option(
+        &self,
+        name: &str,
+    ) -> Option<&Constant> {
+        find_option(&self.options, name)
+    }
+}
+
+/// An enum value
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValue {
+    /// Value name
+    pub name: String,
+    /// Value number
+    pub number: i32,
+    /// Value options
+    pub options: Vec<ProtoOption>,
+    /// Location of the value name
+    pub span: Span,
+}
+
+/// A service definition
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Service {
+    /// Service name
+    pub name: String,
+    /// Rpc methods
+    pub methods: Vec<Method>,
+    /// Service options
+    pub options: Vec<ProtoOption>,
+    /// Location of the service name
+    pub span: Span,
+}
+
+/// A `rpc` method definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct Method {
+    /// Method name
+    pub name: String,
+    /// Request message type, as written
+    pub input_type: String,
+    /// Response message type, as written
+    pub output_type: String,
+    /// Whether the request is a `stream`
+    pub client_streaming: bool,
+    /// Whether the response is a `stream`
+    pub server_streaming: bool,
+    /// Method options
+    pub options: Vec<ProtoOption>,
+    /// Location of the method name
+    pub span: Span,
+}
+
+/// An `extend` block (proto2)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extend {
+    /// Extended message, as written
+    pub extendee: String,
+    /// Extension fields
+    pub fields: Vec<Field>,
+    /// Location of the extendee
+    pub span: Span,
+}
@@ -0,0 +1,94 @@
+//! A module to report .proto parsing errors
+
+use std::fmt;
+
+use crate::parser::ast::Span;
+
+/// An error found while parsing or validating a .proto file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// What went wrong
+    pub message: String,
+    /// Where it went wrong
+    pub span: Span,
+}
+
+impl ParseError {
+    /// Creates a new `ParseError`
+    pub fn new(
+        message: impl Into<String>,
+        span: Span,
+    ) -> Self {
+        ParseError {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Renders the error the way compilers do: location, message, then the
+    /// offending source line with the span underlined
+    ///
+    /// `source` must be the text the error was produced from, `file` is only
+    /// used for display.
+    pub fn render(
+        &self,
+        file: &str,
+        source: &str,
+    ) -> String {
+        format!(
+            "error: {}\n{}\n",
+            self.message,
+            snippet(file, source, self.span)
+        )
+    }
+}
+
+/// Renders the location of `span` in `source` followed by its source line,
+/// with the span underlined
+///
+/// This is what follows the message of all the errors pointing into a
+/// .proto file, see `ParseError::render`.
+pub fn snippet(
+    file: &str,
+    source: &str,
+    span: Span,
+) -> String {
+    let line = source
+        .lines()
+        .nth(span.line.max(1) - 1)
+        .unwrap_or("");
+    let column = span.column.max(1);
+    let width = (span.end.saturating_sub(span.start)).clamp(
+        1,
+        line.len().saturating_sub(column - 1).max(1),
+    );
+    let gutter = " ".repeat(span.line.to_string().len());
+    format!(
+        "{}--> {}:{}:{}\n{} |\n{} | {}\n{} | {}{}",
+        gutter,
+        file,
+        span.line,
+        column,
+        gutter,
+        span.line,
+        line,
+        gutter,
+        " ".repeat(column - 1),
+        "^".repeat(width),
+    )
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.span.line, self.span.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,406 @@
+//! A module to split a .proto source into tokens
+//!
+//! Comments and whitespaces are skipped, every other token keeps its `Span`
+//! so that the parser can report precise diagnostics.
+
+use crate::parser::ast::Span;
+use crate::parser::error::ParseError;
+
+/// The kind of a lexical token
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// An identifier or a keyword (keywords are contextual in protobuf)
+    Ident(String),
+    /// An unsigned integer literal (decimal, octal or hexadecimal)
+    Int(u64),
+    /// A floating point literal
+    Float(f64),
+    /// A string literal, with escapes already decoded
+    Str(Vec<u8>),
+    /// Any single punctuation character
+    Symbol(char),
+    /// End of input
+    Eof,
+}
+
+impl TokenKind {
+    /// Short human readable description, used in diagnostics
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::Ident(s) => format!("identifier `{}`", s),
+            TokenKind::Int(i) => format!("integer `{}`", i),
+            TokenKind::Float(f) => format!("float `{}`", f),
+            TokenKind::Str(_) => "string literal".to_string(),
+            TokenKind::Symbol(c) => format!("`{}`", c),
+            TokenKind::Eof => "end of file".to_string(),
+        }
+    }
+}
+
+/// A token and its location in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    /// What has been read
+    pub kind: TokenKind,
+    /// Where it has been read
+    pub span: Span,
+}
+
+/// A lexer over a .proto source
+pub struct Lexer<'a> {
+    src: &'a [u8],
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a new lexer
+    pub fn new(src: &'a str) -> Self {
+        Lexer {
+            src: src.as_bytes(),
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Reads the whole input, the last token is always `TokenKind::Eof`
+    pub fn tokenize(mut self) -> Result<Vec<Token>, ParseError> {
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.next_token()?;
+            let eof = token.kind == TokenKind::Eof;
+            tokens.push(token);
+            if eof {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn peek_at(
+        &self,
+        n: usize,
+    ) -> Option<u8> {
+        self.src.get(self.pos + n).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn here(&self) -> Span {
+        Span {
+            start: self.pos,
+            end: self.pos,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error<T>(
+        &self,
+        mut span: Span,
+        message: impl Into<String>,
+    ) -> Result<T, ParseError> {
+        span.end = self.pos.max(span.start + 1);
+        Err(ParseError::new(message, span))
+    }
+
+    fn skip_trivia(&mut self) -> Result<(), ParseError> {
+        loop {
+            match (self.peek(), self.peek_at(1)) {
+                (Some(c), _) if c.is_ascii_whitespace() => {
+                    self.bump();
+                }
+                (Some(b'/'), Some(b'/')) => {
+                    while let Some(c) = self.bump() {
+                        if c == b'\n' {
+                            break;
+                        }
+                    }
+                }
+                (Some(b'/'), Some(b'*')) => {
+                    let start = self.here();
+                    self.bump();
+                    self.bump();
+                    loop {
+                        match (self.peek(), self.peek_at(1)) {
+                            (Some(b'*'), Some(b'/')) => {
+                                self.bump();
+                                self.bump();
+                                break;
+                            }
+                            (Some(_), _) => {
+                                self.bump();
+                            }
+                            (None, _) => {
+                                return self.error(
+                                    start,
+                                    "unterminated block comment",
+                                )
+                            }
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token, ParseError> {
+        self.skip_trivia()?;
+        let mut span = self.here();
+        let kind = match self.peek() {
+            None => TokenKind::Eof,
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => {
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || c == b'_' {
+                        self.bump();
+                    } else {
+                        break;
+                    }
+                }
+                let ident = &self.src[span.start..self.pos];
+                TokenKind::Ident(String::from_utf8_lossy(ident).into_owned())
+            }
+            Some(c) if c.is_ascii_digit() => self.number(span)?,
+            Some(b'.')
+                if self
+                    .peek_at(1)
+                    .is_some_and(|c| c.is_ascii_digit()) =>
+            {
+                self.number(span)?
+            }
+            Some(q @ (b'"' | b'\'')) => self.string(span, q)?,
+            Some(c) if c.is_ascii() => {
+                self.bump();
+                TokenKind::Symbol(c as char)
+            }
+            Some(_) => {
+                // consume the whole utf8 sequence so the span is meaningful
+                self.bump();
+                while self.peek().is_some_and(|c| c & 0xc0 == 0x80) {
+                    self.bump();
+                }
+                return self.error(
+                    span,
+                    "unexpected non-ascii character",
+                );
+            }
+        };
+        span.end = self.pos;
+        Ok(Token { kind, span })
+    }
+
+    fn number(
+        &mut self,
+        span: Span,
+    ) -> Result<TokenKind, ParseError> {
+        let start = self.pos;
+        if self.peek() == Some(b'0')
+            && matches!(
+                self.peek_at(1),
+                Some(b'x' | b'X')
+            )
+        {
+            self.bump();
+            self.bump();
+            let digits = self.pos;
+            while self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+                self.bump();
+            }
+            let text = std::str::from_utf8(&self.src[digits..self.pos]).unwrap();
+            return match u64::from_str_radix(text, 16) {
+                Ok(i) => Ok(TokenKind::Int(i)),
+                Err(_) => self.error(
+                    span,
+                    "invalid hexadecimal literal",
+                ),
+            };
+        }
+
+        let mut is_float = false;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+        if self.peek() == Some(b'.') {
+            is_float = true;
+            self.bump();
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.bump();
+            }
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            is_float = true;
+            self.bump();
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.bump();
+            }
+            if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                return self.error(span, "invalid float exponent");
+            }
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.bump();
+            }
+        }
+        if self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == b'_')
+        {
+            self.bump();
+            return self.error(
+                span,
+                "invalid numeric literal",
+            );
+        }
+
+        let text = std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+        if is_float {
+            match text.parse::<f64>() {
+                Ok(f) => Ok(TokenKind::Float(f)),
+                Err(_) => self.error(span, "invalid float literal"),
+            }
+        } else if text.len() > 1 && text.starts_with('0') {
+            match u64::from_str_radix(&text[1..], 8) {
+                Ok(i) => Ok(TokenKind::Int(i)),
+                Err(_) => self.error(span, "invalid octal literal"),
+            }
+        } else {
+            match text.parse::<u64>() {
+                Ok(i) => Ok(TokenKind::Int(i)),
+                Err(_) => self.error(
+                    span,
+                    "integer literal out of range",
+                ),
+            }
+        }
+    }
+
+    fn string(
+        &mut self,
+        span: Span,
+        quote: u8,
+    ) -> Result<TokenKind, ParseError> {
+        self.bump();
+        let mut out = Vec::new();
+        loop {
+            match self.bump() {
+                None | Some(b'\n') => {
+                    return self.error(
+                        span,
+                        "unterminated string literal",
+                    )
+                }
+                Some(c) if c == quote => return Ok(TokenKind::Str(out)),
+                Some(b'\\') => {
+                    let escape = self.here();
+                    match self.bump() {
+                        Some(b'a') => out.push(0x07),
+                        Some(b'b') => out.push(0x08),
+                        Some(b'f') => out.push(0x0c),
+                        Some(b'n') => out.push(b'\n'),
+                        Some(b'r') => out.push(b'\r'),
+                        Some(b't') => out.push(b'\t'),
+                        Some(b'v') => out.push(0x0b),
+                        Some(c @ (b'\\' | b'\'' | b'"' | b'?')) => out.push(c),
+                        Some(b'x' | b'X') => {
+                            let v = self.radix_digits(16, 2);
+                            match v {
+                                Some(v) => out.push(v as u8),
+                                None => {
+                                    return self.error(
+                                        escape,
+                                        "invalid hexadecimal escape",
+                                    )
+                                }
+                            }
+                        }
+                        Some(c @ b'0'..=b'7') => {
+                            let mut v = (c - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(c @ b'0'..=b'7') => {
+                                        self.bump();
+                                        v = v * 8 + (c - b'0') as u32;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            if v > 0xff {
+                                return self.error(
+                                    escape,
+                                    "octal escape out of range",
+                                );
+                            }
+                            out.push(v as u8);
+                        }
+                        Some(b'u') => self.unicode_escape(escape, 4, &mut out)?,
+                        Some(b'U') => self.unicode_escape(escape, 8, &mut out)?,
+                        _ => {
+                            return self.error(
+                                escape,
+                                "invalid escape sequence",
+                            )
+                        }
+                    }
+                }
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn radix_digits(
+        &mut self,
+        radix: u32,
+        max: usize,
+    ) -> Option<u32> {
+        let mut v = None;
+        for _ in 0..max {
+            match self
+                .peek()
+                .and_then(|c| (c as char).to_digit(radix))
+            {
+                Some(d) => {
+                    self.bump();
+                    v = Some(v.unwrap_or(0) * radix + d);
+                }
+                None => break,
+            }
+        }
+        v
+    }
+
+    fn unicode_escape(
+        &mut self,
+        escape: Span,
+        len: usize,
+        out: &mut Vec<u8>,
+    ) -> Result<(), ParseError> {
+        let start = self.pos;
+        let v = self.radix_digits(16, len);
+        match v.and_then(char::from_u32) {
+            Some(c) if self.pos - start == len => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                Ok(())
+            }
+            _ => self.error(
+                escape,
+                "invalid unicode escape",
+            ),
+        }
+    }
+}
@@ -0,0 +1,998 @@
+//! A module to parse .proto files (proto2 and proto3 syntax) into an AST
+//!
+//! Parsing is done in three steps:
+//! - the source is split into tokens by the lexer
+//! - a recursive descent parser builds the `FileDescriptor`
+//! - a validation pass checks the rules which cannot be expressed in the
+//!   grammar (field number ranges, duplicates, reserved numbers, ...)
+//!
+//! Every error carries a `Span` pointing to the offending token.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use quack_protobuf_codegen::parser::{self, ast::FieldType};
+//!
+//! let file = parser::parse(
+//!     r#"
+//!     syntax = "proto3";
+//!     package foo;
+//!
+//!     message Bar {
+//!         string name = 1;
+//!         map<string, int32> counts = 2;
+//!     }
+//!     "#,
+//! )
+//! .expect("invalid proto");
+//!
+//! assert_eq!(file.package.as_deref(), Some("foo"));
+//! assert_eq!(file.messages[0].fields[0].typ, FieldType::String);
+//! ```
+
+pub mod ast;
+mod error;
+mod lexer;
+mod validate;
+
+pub(crate) use self::error::snippet;
+pub use self::error::ParseError;
+pub(crate) use self::validate::mismatched_default;
+
+use self::ast::*;
+use self::lexer::{Lexer, Token, TokenKind};
+
+/// Highest field number allowed by the protobuf specification
+pub const MAX_FIELD_NUMBER: i32 = 536_870_911;
+
+/// Parses and validates a .proto source
+pub fn parse(source: &str) -> Result<FileDescriptor, ParseError> {
+    let tokens = Lexer::new(source).tokenize()?;
+    let file = Parser {
+        source,
+        tokens,
+        pos: 0,
+        syntax: Syntax::Proto2,
+    }
+    .file()?;
+    validate::validate(&file)?;
+    Ok(file)
+}
+
+type PResult<T> = Result<T, ParseError>;
+
+/// The context in which a field is being parsed
+#[derive(Clone, Copy, PartialEq)]
+enum FieldContext {
+    Message,
+    OneOf,
+    Extend,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    syntax: Syntax,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn peek_nth(
+        &self,
+        n: usize,
+    ) -> &TokenKind {
+        let i = (self.pos + n).min(self.tokens.len() - 1);
+        &self.tokens[i].kind
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn span(&self) -> Span {
+        self.peek().span
+    }
+
+    fn prev_span(&self) -> Span {
+        self.tokens[self.pos.saturating_sub(1)].span
+    }
+
+    fn unexpected<T>(
+        &self,
+        expected: &str,
+    ) -> PResult<T> {
+        let token = self.peek();
+        Err(ParseError::new(
+            format!(
+                "expected {}, found {}",
+                expected,
+                token.kind.describe()
+            ),
+            token.span,
+        ))
+    }
+
+    fn is_ident(
+        &self,
+        keyword: &str,
+    ) -> bool {
+        matches!(&self.peek().kind, TokenKind::Ident(s) if s == keyword)
+    }
+
+    fn is_symbol(
+        &self,
+        c: char,
+    ) -> bool {
+        self.peek().kind == TokenKind::Symbol(c)
+    }
+
+    fn eat_ident(
+        &mut self,
+        keyword: &str,
+    ) -> bool {
+        let found = self.is_ident(keyword);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn eat_symbol(
+        &mut self,
+        c: char,
+    ) -> bool {
+        let found = self.is_symbol(c);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect_symbol(
+        &mut self,
+        c: char,
+    ) -> PResult<Span> {
+        if self.is_symbol(c) {
+            Ok(self.bump().span)
+        } else {
+            self.unexpected(&format!("`{}`", c))
+        }
+    }
+
+    fn expect_keyword(
+        &mut self,
+        keyword: &str,
+    ) -> PResult<Span> {
+        if self.is_ident(keyword) {
+            Ok(self.bump().span)
+        } else {
+            self.unexpected(&format!("`{}`", keyword))
+        }
+    }
+
+    fn ident(&mut self) -> PResult<(String, Span)> {
+        match &self.peek().kind {
+            TokenKind::Ident(s) => {
+                let s = s.clone();
+                Ok((s, self.bump().span))
+            }
+            _ => self.unexpected("identifier"),
+        }
+    }
+
+    /// ident { "." ident }
+    fn full_ident(&mut self) -> PResult<(String, Span)> {
+        let (mut name, mut span) = self.ident()?;
+        while self.is_symbol('.') {
+            self.bump();
+            let (part, s) = self.ident()?;
+            name.push('.');
+            name.push_str(&part);
+            span = span.to(s);
+        }
+        Ok((name, span))
+    }
+
+    /// [ "." ] ident { "." ident }
+    fn type_name(&mut self) -> PResult<(String, Span)> {
+        if self.is_symbol('.') {
+            let start = self.bump().span;
+            let (name, span) = self.full_ident()?;
+            Ok((
+                format!(".{}", name),
+                start.to(span),
+            ))
+        } else {
+            self.full_ident()
+        }
+    }
+
+    fn string(&mut self) -> PResult<(Vec<u8>, Span)> {
+        let TokenKind::Str(first) = &self.peek().kind else {
+            return self.unexpected("string literal");
+        };
+        let mut out = first.clone();
+        let mut span = self.bump().span;
+        // adjacent literals are concatenated, as in C
+        while let TokenKind::Str(s) = &self.peek().kind {
+            out.extend_from_slice(s);
+            span = span.to(self.bump().span);
+        }
+        Ok((out, span))
+    }
+
+    fn utf8_string(&mut self) -> PResult<(String, Span)> {
+        let (s, span) = self.string()?;
+        match String::from_utf8(s) {
+            Ok(s) => Ok((s, span)),
+            Err(_) => Err(ParseError::new(
+                "string is not valid utf8",
+                span,
+            )),
+        }
+    }
+
+    /// An optionally signed integer
+    fn int(&mut self) -> PResult<(i128, Span)> {
+        let start = self.span();
+        let negative = if self.eat_symbol('-') {
+            true
+        } else {
+            self.eat_symbol('+');
+            false
+        };
+        match self.peek().kind {
+            TokenKind::Int(i) => {
+                let span = start.to(self.bump().span);
+                Ok((
+                    if negative { -(i as i128) } else { i as i128 },
+                    span,
+                ))
+            }
+            _ => self.unexpected("integer"),
+        }
+    }
+
+    fn int32(
+        &mut self,
+        what: &str,
+    ) -> PResult<(i32, Span)> {
+        let (i, span) = self.int()?;
+        match i32::try_from(i) {
+            Ok(i) => Ok((i, span)),
+            Err(_) => Err(ParseError::new(
+                format!(
+                    "{} `{}` is out of range",
+                    what, i
+                ),
+                span,
+            )),
+        }
+    }
+
+    fn constant(&mut self) -> PResult<Constant> {
+        let start = self.span();
+        match self.peek().kind.clone() {
+            TokenKind::Str(_) => Ok(Constant::Str(
+                self.string()?.0,
+            )),
+            TokenKind::Int(_) => Ok(Constant::Int(self.int()?.0)),
+            TokenKind::Float(f) => {
+                self.bump();
+                Ok(Constant::Float(f))
+            }
+            TokenKind::Symbol('-') | TokenKind::Symbol('+') => {
+                let negative = self.bump().kind == TokenKind::Symbol('-');
+                let sign = if negative { -1. } else { 1. };
+                match self.peek().kind.clone() {
+                    TokenKind::Int(_) => {
+                        self.pos -= 1;
+                        Ok(Constant::Int(self.int()?.0))
+                    }
+                    TokenKind::Float(f) => {
+                        self.bump();
+                        Ok(Constant::Float(sign * f))
+                    }
+                    TokenKind::Ident(s) if s == "inf" => {
+                        self.bump();
+                        Ok(Constant::Float(
+                            sign * f64::INFINITY,
+                        ))
+                    }
+                    TokenKind::Ident(s) if s == "nan" => {
+                        self.bump();
+                        Ok(Constant::Float(f64::NAN))
+                    }
+                    _ => self.unexpected("number"),
+                }
+            }
+            TokenKind::Ident(_) => {
+                let (ident, _) = self.full_ident()?;
+                Ok(match ident.as_str() {
+                    "true" => Constant::Bool(true),
+                    "false" => Constant::Bool(false),
+                    "inf" => Constant::Float(f64::INFINITY),
+                    "nan" => Constant::Float(f64::NAN),
+                    _ => Constant::Ident(ident),
+                })
+            }
+            TokenKind::Symbol('{') => {
+                let mut depth = 0;
+                loop {
+                    match self.bump().kind {
+                        TokenKind::Symbol('{') => depth += 1,
+                        TokenKind::Symbol('}') => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        TokenKind::Eof => {
+                            return Err(ParseError::new(
+                                "unterminated aggregate value",
+                                start,
+                            ));
+                        }
+                        _ => {}
+                    }
+                }
+                let end = self.prev_span().end;
+                Ok(Constant::Aggregate(
+                    self.source[start.start..end].to_string(),
+                ))
+            }
+            _ => self.unexpected("constant"),
+        }
+    }
+
+    /// optionName = ( ident | "(" fullIdent ")" ) { "." ident }
+    fn option_name(&mut self) -> PResult<(String, Span)> {
+        let start = self.span();
+        let mut name = String::new();
+        loop {
+            if self.eat_symbol('(') {
+                let (ident, _) = self.type_name()?;
+                self.expect_symbol(')')?;
+                name.push('(');
+                name.push_str(&ident);
+                name.push(')');
+            } else {
+                name.push_str(&self.ident()?.0);
+            }
+            if !self.eat_symbol('.') {
+                break;
+            }
+            name.push('.');
+        }
+        Ok((
+            name,
+            start.to(self.prev_span()),
+        ))
+    }
+
+    /// "option" optionName "=" constant ";"
+    fn option_statement(&mut self) -> PResult<ProtoOption> {
+        self.expect_keyword("option")?;
+        let option = self.option_body()?;
+        self.expect_symbol(';')?;
+        Ok(option)
+    }
+
+    fn option_body(&mut self) -> PResult<ProtoOption> {
+        let (name, span) = self.option_name()?;
+        self.expect_symbol('=')?;
+        let value = self.constant()?;
+        Ok(ProtoOption {
+            name,
+            value,
+            span: span.to(self.prev_span()),
+        })
+    }
+
+    /// [ "[" option { "," option } "]" ]
+    fn field_options(&mut self) -> PResult<Vec<ProtoOption>> {
+        let mut options = Vec::new();
+        if self.eat_symbol('[') {
+            loop {
+                options.push(self.option_body()?);
+                if !self.eat_symbol(',') {
+                    break;
+                }
+            }
+            self.expect_symbol(']')?;
+        }
+        Ok(options)
+    }
+
+    fn file(mut self) -> PResult<FileDescriptor> {
+        let mut file = FileDescriptor::default();
+
+        if self.is_ident("syntax") {
+            self.bump();
+            self.expect_symbol('=')?;
+            let (syntax, span) = self.utf8_string()?;
+            self.syntax = match syntax.as_str() {
+                "proto2" => Syntax::Proto2,
+                "proto3" => Syntax::Proto3,
+                s => {
+                    return Err(ParseError::new(
+                        format!(
+                            "unsupported syntax \"{}\", expecting \"proto2\" or \"proto3\"",
+                            s
+                        ),
+                        span,
+                    ));
+                }
+            };
+            self.expect_symbol(';')?;
+        } else if self.is_ident("edition") {
+            return Err(ParseError::new(
+                "editions are not supported, expecting proto2 or proto3 syntax",
+                self.span(),
+            ));
+        }
+        file.syntax = self.syntax;
+
+        loop {
+            let TokenKind::Ident(keyword) = self.peek().kind.clone() else {
+                if self.eat_symbol(';') {
+                    continue;
+                }
+                if self.peek().kind == TokenKind::Eof {
+                    return Ok(file);
+                }
+                return self.unexpected("top level definition");
+            };
+            match keyword.as_str() {
+                "import" => {
+                    let start = self.bump().span;
+                    let kind = if self.eat_ident("public") {
+                        ImportKind::Public
+                    } else if self.eat_ident("weak") {
+                        ImportKind::Weak
+                    } else {
+                        ImportKind::Default
+                    };
+                    let (path, _) = self.utf8_string()?;
+                    let end = self.expect_symbol(';')?;
+                    file.imports.push(Import {
+                        path,
+                        kind,
+                        span: start.to(end),
+                    });
+                }
+                "package" => {
+                    let start = self.bump().span;
+                    let (package, span) = self.full_ident()?;
+                    self.expect_symbol(';')?;
+                    if file.package.is_some() {
+                        return Err(ParseError::new(
+                            "multiple package definitions",
+                            start.to(span),
+                        ));
+                    }
+                    file.package = Some(package);
+                }
+                "option" => file.options.push(self.option_statement()?),
+                "message" => file.messages.push(self.message()?),
+                "enum" => file.enums.push(self.enumeration()?),
+                "service" => file.services.push(self.service()?),
+                "extend" => file.extends.push(self.extend()?),
+                "syntax" => {
+                    return Err(ParseError::new(
+                        "syntax must be declared first",
+                        self.span(),
+                    ))
+                }
+                _ => return self.unexpected("top level definition"),
+            }
+        }
+    }
+
+    /// "message" ident "{" messageBody "}"
+    fn message(&mut self) -> PResult<Message> {
+        self.expect_keyword("message")?;
+        let (name, span) = self.ident()?;
+        let mut message = Message {
+            name,
+            span,
+            ..Message::default()
+        };
+        self.message_body(&mut message)?;
+        Ok(message)
+    }
+
+    fn message_body(
+        &mut self,
+        message: &mut Message,
+    ) -> PResult<()> {
+        self.expect_symbol('{')?;
+        loop {
+            if self.eat_symbol('}') {
+                return Ok(());
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+            let keyword = match &self.peek().kind {
+                TokenKind::Ident(keyword) => keyword.clone(),
+                // a field of a fully qualified type, `.pkg.Type name = 1;`
+                TokenKind::Symbol('.') => String::new(),
+                _ => return self.unexpected("message element"),
+            };
+            match keyword.as_str() {
+                "message" => message.messages.push(self.message()?),
+                "enum" => message.enums.push(self.enumeration()?),
+                "extend" => message.extends.push(self.extend()?),
+                "option" => message.options.push(self.option_statement()?),
+                "oneof" => message
+                    .oneofs
+                    .push(self.oneof(&mut message.messages)?),
+                "reserved" => self.reserved(
+                    &mut message.reserved_numbers,
+                    &mut message.reserved_names,
+                    MAX_FIELD_NUMBER,
+                )?,
+                "extensions" => {
+                    self.bump();
+                    self.ranges(
+                        &mut message.extension_ranges,
+                        MAX_FIELD_NUMBER,
+                    )?;
+                    // extension range options are accepted but not kept
+                    self.field_options()?;
+                    self.expect_symbol(';')?;
+                }
+                "map" if *self.peek_nth(1) == TokenKind::Symbol('<') => {
+                    message.fields.push(self.map_field()?);
+                }
+                _ => message.fields.push(self.field(
+                    FieldContext::Message,
+                    &mut message.messages,
+                )?),
+            }
+        }
+    }
+
+    /// label? type ident "=" number options? ";"
+    /// label "group" ident "=" number options? "{" messageBody "}"
+    fn field(
+        &mut self,
+        context: FieldContext,
+        messages: &mut Vec<Message>,
+    ) -> PResult<Field> {
+        let label_span = self.span();
+        let label = if self.eat_ident("optional") {
+            Some(Label::Optional)
+        } else if self.eat_ident("required") {
+            Some(Label::Required)
+        } else if self.eat_ident("repeated") {
+            Some(Label::Repeated)
+        } else {
+            None
+        };
+
+        match (context, label) {
+            (FieldContext::OneOf, Some(_)) => {
+                return Err(ParseError::new(
+                    "fields in oneofs must not have labels",
+                    label_span,
+                ));
+            }
+            (FieldContext::Message | FieldContext::Extend, None) if self.syntax == Syntax::Proto2 => {
+                return Err(ParseError::new(
+                    "expected `required`, `optional` or `repeated` label",
+                    label_span,
+                ));
+            }
+            (_, Some(Label::Required)) if self.syntax == Syntax::Proto3 => {
+                return Err(ParseError::new(
+                    "required fields are not allowed in proto3",
+                    label_span,
+                ));
+            }
+            _ => {}
+        }
+
+        if self.is_ident("group")
+            && matches!(
+                self.peek_nth(1),
+                TokenKind::Ident(_)
+            )
+        {
+            return self.group(label, context, messages);
+        }
+
+        let (type_name, type_span) = self.type_name()?;
+        let typ = FieldType::from_scalar(&type_name).unwrap_or(FieldType::Named(type_name));
+        if typ == FieldType::Named("map".to_string()) {
+            return Err(ParseError::new(
+                "map fields cannot have a label",
+                type_span,
+            ));
+        }
+        let (name, span) = self.ident()?;
+        self.expect_symbol('=')?;
+        let (number, _) = self.int32("field number")?;
+        let options = self.field_options()?;
+        self.expect_symbol(';')?;
+        Ok(Field {
+            name,
+            number,
+            label,
+            typ,
+            options,
+            span,
+        })
+    }
+
+    fn group(
+        &mut self,
+        label: Option<Label>,
+        context: FieldContext,
+        messages: &mut Vec<Message>,
+    ) -> PResult<Field> {
+        let start = self.expect_keyword("group")?;
+        if self.syntax == Syntax::Proto3 {
+            return Err(ParseError::new(
+                "groups are not supported in proto3, use a nested message instead",
+                start,
+            ));
+        }
+        if context == FieldContext::Extend {
+            return Err(ParseError::new(
+                "groups are not supported in extend blocks",
+                start,
+            ));
+        }
+        let (name, span) = self.ident()?;
+        if !name.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return Err(ParseError::new(
+                "group names must start with a capital letter",
+                span,
+            ));
+        }
+        self.expect_symbol('=')?;
+        let (number, _) = self.int32("field number")?;
+        let options = self.field_options()?;
+        let mut message = Message {
+            name: name.clone(),
+            span,
+            ..Message::default()
+        };
+        self.message_body(&mut message)?;
+        messages.push(message);
+        Ok(Field {
+            name: name.to_ascii_lowercase(),
+            number,
+            label,
+            typ: FieldType::Group(name),
+            options,
+            span,
+        })
+    }
+
+    /// "map" "<" keyType "," type ">" ident "=" number options? ";"
+    fn map_field(&mut self) -> PResult<Field> {
+        self.expect_keyword("map")?;
+        self.expect_symbol('<')?;
+        let (key, key_span) = self.type_name()?;
+        let key = match FieldType::from_scalar(&key) {
+            Some(FieldType::Double | FieldType::Float | FieldType::Bytes) | None => {
+                return Err(ParseError::new(
+                    "map keys must be integral or string types",
+                    key_span,
+                ))
+            }
+            Some(k) => k,
+        };
+        self.expect_symbol(',')?;
+        let (value, value_span) = self.type_name()?;
+        if value == "map" {
+            return Err(ParseError::new(
+                "map values cannot be maps",
+                value_span,
+            ));
+        }
+        let value = FieldType::from_scalar(&value).unwrap_or(FieldType::Named(value));
+        self.expect_symbol('>')?;
+        let (name, span) = self.ident()?;
+        self.expect_symbol('=')?;
+        let (number, _) = self.int32("field number")?;
+        let options = self.field_options()?;
+        self.expect_symbol(';')?;
+        Ok(Field {
+            name,
+            number,
+            label: None,
+            typ: FieldType::Map(Box::new(key), Box::new(value)),
+            options,
+            span,
+        })
+    }
+
+    /// "oneof" ident "{" { option | field | ";" } "}"
+    fn oneof(
+        &mut self,
+        messages: &mut Vec<Message>,
+    ) -> PResult<OneOf> {
+        self.expect_keyword("oneof")?;
+        let (name, span) = self.ident()?;
+        let mut oneof = OneOf {
+            name,
+            span,
+            ..OneOf::default()
+        };
+        self.expect_symbol('{')?;
+        loop {
+            if self.eat_symbol('}') {
+                break;
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+            if self.is_ident("option") {
+                oneof.options.push(self.option_statement()?);
+            } else if self.is_ident("map") && *self.peek_nth(1) == TokenKind::Symbol('<') {
+                return Err(ParseError::new(
+                    "map fields are not allowed in oneofs",
+                    self.span(),
+                ));
+            } else {
+                oneof
+                    .fields
+                    .push(self.field(FieldContext::OneOf, messages)?);
+            }
+        }
+        if oneof.fields.is_empty() {
+            return Err(ParseError::new(
+                "oneof must have at least one field",
+                oneof.span,
+            ));
+        }
+        Ok(oneof)
+    }
+
+    /// "reserved" ( ranges | strFieldNames ) ";"
+    fn reserved(
+        &mut self,
+        numbers: &mut Vec<FieldRange>,
+        names: &mut Vec<String>,
+        max: i32,
+    ) -> PResult<()> {
+        self.expect_keyword("reserved")?;
+        if let TokenKind::Str(_) = self.peek().kind {
+            loop {
+                let (name, span) = self.utf8_string()?;
+                if !is_identifier(&name) {
+                    return Err(ParseError::new(
+                        format!(
+                            "reserved name \"{}\" is not a valid identifier",
+                            name
+                        ),
+                        span,
+                    ));
+                }
+                names.push(name);
+                if !self.eat_symbol(',') {
+                    break;
+                }
+            }
+        } else {
+            self.ranges(numbers, max)?;
+        }
+        self.expect_symbol(';')?;
+        Ok(())
+    }
+
+    /// range { "," range }, range = int [ "to" ( int | "max" ) ]
+    fn ranges(
+        &mut self,
+        ranges: &mut Vec<FieldRange>,
+        max: i32,
+    ) -> PResult<()> {
+        loop {
+            let (start, start_span) = self.int32("range bound")?;
+            let (end, span) = if self.eat_ident("to") {
+                if self.is_ident("max") {
+                    (
+                        max,
+                        start_span.to(self.bump().span),
+                    )
+                } else {
+                    let (end, end_span) = self.int32("range bound")?;
+                    (end, start_span.to(end_span))
+                }
+            } else {
+                (start, start_span)
+            };
+            if end < start {
+                return Err(ParseError::new(
+                    "range end must be greater than or equal to range start",
+                    span,
+                ));
+            }
+            ranges.push(FieldRange { start, end, span });
+            if !self.eat_symbol(',') {
+                return Ok(());
+            }
+        }
+    }
+
+    /// "enum" ident "{" { option | enumField | reserved | ";" } "}"
+    fn enumeration(&mut self) -> PResult<Enumeration> {
+        self.expect_keyword("enum")?;
+        let (name, span) = self.ident()?;
+        let mut enumeration = Enumeration {
+            name,
+            span,
+            ..Enumeration::default()
+        };
+        self.expect_symbol('{')?;
+        loop {
+            if self.eat_symbol('}') {
+                break;
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+            if self.is_ident("option") {
+                enumeration.options.push(self.option_statement()?);
+            } else if self.is_ident("reserved")
+                && !matches!(
+                    self.peek_nth(1),
+                    TokenKind::Symbol('=')
+                )
+            {
+                self.reserved(
+                    &mut enumeration.reserved_numbers,
+                    &mut enumeration.reserved_names,
+                    i32::MAX,
+                )?;
+            } else {
+                let (name, span) = self.ident()?;
+                self.expect_symbol('=')?;
+                let (number, _) = self.int32("enum value")?;
+                let options = self.field_options()?;
+                self.expect_symbol(';')?;
+                enumeration.values.push(EnumValue {
+                    name,
+                    number,
+                    options,
+                    span,
+                });
+            }
+        }
+        if enumeration.values.is_empty() {
+            return Err(ParseError::new(
+                "enums must contain at least one value",
+                enumeration.span,
+            ));
+        }
+        Ok(enumeration)
+    }
+
+    /// "service" ident "{" { option | rpc | ";" } "}"
+    fn service(&mut self) -> PResult<Service> {
+        self.expect_keyword("service")?;
+        let (name, span) = self.ident()?;
+        let mut service = Service {
+            name,
+            span,
+            ..Service::default()
+        };
+        self.expect_symbol('{')?;
+        loop {
+            if self.eat_symbol('}') {
+                return Ok(service);
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+            if self.is_ident("option") {
+                service.options.push(self.option_statement()?);
+            } else if self.is_ident("rpc") {
+                service.methods.push(self.method()?);
+            } else {
+                return self.unexpected("`rpc` or `option`");
+            }
+        }
+    }
+
+    /// "rpc" ident "(" [ "stream" ] type ")" "returns" "(" [ "stream" ] type ")"
+    /// ( "{" { option | ";" } "}" | ";" )
+    fn method(&mut self) -> PResult<Method> {
+        self.expect_keyword("rpc")?;
+        let (name, span) = self.ident()?;
+        let (client_streaming, input_type) = self.method_type()?;
+        self.expect_keyword("returns")?;
+        let (server_streaming, output_type) = self.method_type()?;
+        let mut options = Vec::new();
+        if self.eat_symbol('{') {
+            loop {
+                if self.eat_symbol('}') {
+                    break;
+                }
+                if self.eat_symbol(';') {
+                    continue;
+                }
+                options.push(self.option_statement()?);
+            }
+        } else {
+            self.expect_symbol(';')?;
+        }
+        Ok(Method {
+            name,
+            input_type,
+            output_type,
+            client_streaming,
+            server_streaming,
+            options,
+            span,
+        })
+    }
+
+    fn method_type(&mut self) -> PResult<(bool, String)> {
+        self.expect_symbol('(')?;
+        // `stream.Foo` is a type name, `stream .foo.Bar` a stream of `.foo.Bar`
+        let stream = self.is_ident("stream")
+            && match self.peek_nth(1) {
+                TokenKind::Symbol(')') => false,
+                TokenKind::Symbol('.') => self.tokens[self.pos + 1].span.start > self.span().end,
+                _ => true,
+            };
+        if stream {
+            self.bump();
+        }
+        let (typ, span) = self.type_name()?;
+        if FieldType::from_scalar(&typ).is_some() {
+            return Err(ParseError::new(
+                "rpc types must be messages",
+                span,
+            ));
+        }
+        self.expect_symbol(')')?;
+        Ok((stream, typ))
+    }
+
+    /// "extend" type "{" { field | ";" } "}"
+    fn extend(&mut self) -> PResult<Extend> {
+        self.expect_keyword("extend")?;
+        let (extendee, span) = self.type_name()?;
+        let mut extend = Extend {
+            extendee,
+            fields: Vec::new(),
+            span,
+        };
+        self.expect_symbol('{')?;
+        loop {
+            if self.eat_symbol('}') {
+                return Ok(extend);
+            }
+            if self.eat_symbol(';') {
+                continue;
+            }
+            let mut unused = Vec::new();
+            extend.fields.push(self.field(
+                FieldContext::Extend,
+                &mut unused,
+            )?);
+        }
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
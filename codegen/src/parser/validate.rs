@@ -0,0 +1,380 @@
+//! A module to check the rules of the protobuf language which are not
+//! enforced by the grammar itself
+
+use std::collections::HashMap;
+
+use crate::parser::ast::*;
+use crate::parser::error::ParseError;
+use crate::parser::MAX_FIELD_NUMBER;
+
+/// Range reserved for the protobuf implementation
+const RESERVED_IMPLEMENTATION: FieldRange = FieldRange {
+    start: 19_000,
+    end: 19_999,
+    span: Span {
+        start: 0,
+        end: 0,
+        line: 0,
+        column: 0,
+    },
+};
+
+pub(crate) fn validate(file: &FileDescriptor) -> Result<(), ParseError> {
+    unique_names(
+        file.messages
+            .iter()
+            .map(|m| (&m.name, m.span))
+            .chain(file.enums.iter().map(|e| (&e.name, e.span)))
+            .chain(file.services.iter().map(|s| (&s.name, s.span))),
+        "type",
+    )?;
+    for message in &file.messages {
+        validate_message(file.syntax, message)?;
+    }
+    for enumeration in &file.enums {
+        validate_enum(file.syntax, enumeration)?;
+    }
+    for extend in &file.extends {
+        validate_extend(file.syntax, extend)?;
+    }
+    for service in &file.services {
+        unique_names(
+            service.methods.iter().map(|m| (&m.name, m.span)),
+            "method",
+        )?;
+    }
+    Ok(())
+}
+
+fn unique_names<'a>(
+    names: impl Iterator<Item = (&'a String, Span)>,
+    what: &str,
+) -> Result<(), ParseError> {
+    let mut seen = HashMap::new();
+    for (name, span) in names {
+        if seen.insert(name, span).is_some() {
+            return Err(ParseError::new(
+                format!(
+                    "duplicate {} name `{}`",
+                    what, name
+                ),
+                span,
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_message(
+    syntax: Syntax,
+    message: &Message,
+) -> Result<(), ParseError> {
+    unique_names(
+        message
+            .messages
+            .iter()
+            .map(|m| (&m.name, m.span))
+            .chain(message.enums.iter().map(|e| (&e.name, e.span))),
+        "type",
+    )?;
+    unique_names(
+        message
+            .all_fields()
+            .map(|f| (&f.name, f.span))
+            .chain(message.oneofs.iter().map(|o| (&o.name, o.span))),
+        "field",
+    )?;
+
+    if syntax == Syntax::Proto3 {
+        if let Some(range) = message.extension_ranges.first() {
+            return Err(ParseError::new(
+                "extension ranges are not allowed in proto3",
+                range.span,
+            ));
+        }
+    }
+
+    let mut numbers = HashMap::new();
+    for field in message.all_fields() {
+        validate_field(syntax, field)?;
+        if let Some(other) = numbers.insert(field.number, &field.name) {
+            return Err(ParseError::new(
+                format!(
+                    "field number {} has already been used by `{}`",
+                    field.number, other
+                ),
+                field.span,
+            ));
+        }
+        if let Some(range) = message
+            .reserved_numbers
+            .iter()
+            .find(|r| r.contains(field.number))
+        {
+            return Err(ParseError::new(
+                format!(
+                    "field `{}` uses reserved number {} (reserved at line {})",
+                    field.name, field.number, range.span.line
+                ),
+                field.span,
+            ));
+        }
+        if let Some(range) = message
+            .extension_ranges
+            .iter()
+            .find(|r| r.contains(field.number))
+        {
+            return Err(ParseError::new(
+                format!(
+                    "field `{}` uses number {} which is in an extension range (declared at line {})",
+                    field.name, field.number, range.span.line
+                ),
+                field.span,
+            ));
+        }
+        if message.reserved_names.contains(&field.name) {
+            return Err(ParseError::new(
+                format!(
+                    "field name `{}` is reserved",
+                    field.name
+                ),
+                field.span,
+            ));
+        }
+    }
+
+    for range in &message.reserved_numbers {
+        check_range(range)?;
+    }
+    for range in &message.extension_ranges {
+        check_range(range)?;
+    }
+
+    for nested in &message.messages {
+        validate_message(syntax, nested)?;
+    }
+    for enumeration in &message.enums {
+        validate_enum(syntax, enumeration)?;
+    }
+    for extend in &message.extends {
+        validate_extend(syntax, extend)?;
+    }
+    Ok(())
+}
+
+fn check_range(range: &FieldRange) -> Result<(), ParseError> {
+    if range.start < 1 || range.end > MAX_FIELD_NUMBER {
+        return Err(ParseError::new(
+            format!(
+                "field numbers must be between 1 and {}",
+                MAX_FIELD_NUMBER
+            ),
+            range.span,
+        ));
+    }
+    Ok(())
+}
+
+fn validate_field(
+    syntax: Syntax,
+    field: &Field,
+) -> Result<(), ParseError> {
+    if field.number < 1 || field.number > MAX_FIELD_NUMBER {
+        return Err(ParseError::new(
+            format!(
+                "field number {} is out of range, must be between 1 and {}",
+                field.number, MAX_FIELD_NUMBER
+            ),
+            field.span,
+        ));
+    }
+    if RESERVED_IMPLEMENTATION.contains(field.number) {
+        return Err(ParseError::new(
+            format!(
+                "field numbers {} to {} are reserved for the protobuf implementation",
+                RESERVED_IMPLEMENTATION.start, RESERVED_IMPLEMENTATION.end
+            ),
+            field.span,
+        ));
+    }
+
+    if let Some(default) = field.options.iter().find(|o| o.name == "default") {
+        if syntax == Syntax::Proto3 {
+            return Err(ParseError::new(
+                "explicit default values are not allowed in proto3",
+                default.span,
+            ));
+        }
+        if field.is_repeated()
+            || matches!(
+                field.typ,
+                FieldType::Map(..) | FieldType::Group(_)
+            )
+        {
+            return Err(ParseError::new(
+                "default values are only allowed on singular scalar fields",
+                default.span,
+            ));
+        }
+        check_default(field, default)?;
+    }
+
+    if let Some(packed) = field.options.iter().find(|o| o.name == "packed") {
+        if packed.value.as_bool().is_none() {
+            return Err(ParseError::new(
+                "`packed` option must be `true` or `false`",
+                packed.span,
+            ));
+        }
+        let packable = matches!(field.typ, FieldType::Named(_)) || field.typ.is_scalar_numeric();
+        if !field.is_repeated() || !packable {
+            return Err(ParseError::new(
+                "`packed` can only be set on repeated scalar numeric fields",
+                packed.span,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the default value of a scalar field has the type of the field
+fn check_default(
+    field: &Field,
+    default: &ProtoOption,
+) -> Result<(), ParseError> {
+    let value = &default.value;
+    let expected = match (&field.typ, value) {
+        // enums are checked once resolved
+        (FieldType::Named(_), _) => return Ok(()),
+        (FieldType::Bool, Constant::Bool(_)) => return Ok(()),
+        (FieldType::Bool, _) => "`true` or `false`",
+        (FieldType::Float | FieldType::Double, Constant::Int(_) | Constant::Float(_)) => return Ok(()),
+        (FieldType::Float | FieldType::Double, _) => "a number",
+        (FieldType::String, Constant::Str(s)) if std::str::from_utf8(s).is_err() => {
+            return Err(ParseError::new(
+                format!(
+                    "default value of field `{}` of type `string` is not valid UTF-8",
+                    field.name
+                ),
+                default.span,
+            ));
+        }
+        (FieldType::String | FieldType::Bytes, Constant::Str(_)) => return Ok(()),
+        (FieldType::String | FieldType::Bytes, _) => "a string",
+        (t, Constant::Int(i)) => {
+            let (min, max) = t
+                .int_range()
+                .expect("only integer types are left");
+            if (min..=max).contains(i) {
+                return Ok(());
+            }
+            return Err(ParseError::new(
+                format!(
+                    "default value {} is out of range for field `{}` of type `{}`",
+                    i, field.name, field.typ
+                ),
+                default.span,
+            ));
+        }
+        _ => "an integer",
+    };
+    Err(ParseError::new(
+        mismatched_default(field, expected),
+        default.span,
+    ))
+}
+
+/// Message of a default value whose type does not match the one of `field`
+pub(crate) fn mismatched_default(
+    field: &Field,
+    expected: &str,
+) -> String {
+    format!(
+        "mismatched default value: field `{}` of type `{}` expects {}, found {}",
+        field.name,
+        field.typ,
+        expected,
+        field.default().map_or("nothing", Constant::kind)
+    )
+}
+
+fn validate_enum(
+    syntax: Syntax,
+    enumeration: &Enumeration,
+) -> Result<(), ParseError> {
+    unique_names(
+        enumeration
+            .values
+            .iter()
+            .map(|v| (&v.name, v.span)),
+        "enum value",
+    )?;
+
+    if syntax == Syntax::Proto3 && enumeration.values[0].number != 0 {
+        return Err(ParseError::new(
+            "the first enum value must be zero in proto3",
+            enumeration.values[0].span,
+        ));
+    }
+
+    let allow_alias = enumeration
+        .option("allow_alias")
+        .and_then(Constant::as_bool)
+        .unwrap_or(false);
+    let mut numbers = HashMap::new();
+    for value in &enumeration.values {
+        if let Some(other) = numbers.insert(value.number, &value.name) {
+            if !allow_alias {
+                return Err(ParseError::new(
+                    format!(
+                        "enum value {} has already been used by `{}`, set `option allow_alias = true;` to allow aliases",
+                        value.number, other
+                    ),
+                    value.span,
+                ));
+            }
+        }
+        if enumeration
+            .reserved_numbers
+            .iter()
+            .any(|r| r.contains(value.number))
+        {
+            return Err(ParseError::new(
+                format!(
+                    "enum value `{}` uses reserved number {}",
+                    value.name, value.number
+                ),
+                value.span,
+            ));
+        }
+        if enumeration.reserved_names.contains(&value.name) {
+            return Err(ParseError::new(
+                format!(
+                    "enum value name `{}` is reserved",
+                    value.name
+                ),
+                value.span,
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_extend(
+    syntax: Syntax,
+    extend: &Extend,
+) -> Result<(), ParseError> {
+    for field in &extend.fields {
+        validate_field(syntax, field)?;
+        if matches!(
+            field.label,
+            Some(Label::Required)
+        ) {
+            return Err(ParseError::new(
+                "extension fields cannot be required",
+                field.span,
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! Checks the code generated for the .proto files of `tests/protos` against
+//! the files of `tests/generated`, which the tests of quack-protobuf compile
+//!
+//! After a change of the generator, regenerate them with:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test -p quack-protobuf-codegen --test golden
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use quack_protobuf_codegen::generator::Generator;
+
+/// Compares the files generated for `files` with the files of `tests/generated/<name>`
+fn check(
+    name: &str,
+    generator: &Generator,
+    files: &[&str],
+) {
+    let dir = Path::new("tests/generated").join(name);
+    let generated = generator.generate(files).unwrap();
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for file in &generated {
+            file.write_to(&dir).unwrap();
+        }
+        return;
+    }
+    let mut expected = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    expected.sort();
+    let mut names = generated
+        .iter()
+        .map(|f| f.name.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(
+        names,
+        expected,
+        "files generated in {}, run with UPDATE_GOLDEN=1 to update them",
+        dir.display()
+    );
+    for file in &generated {
+        let path = dir.join(&file.name);
+        let golden = fs::read_to_string(&path).unwrap();
+        assert!(
+            golden == file.content,
+            "{} is out of date, run with UPDATE_GOLDEN=1 to update it",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn golden() {
+    // the generated files name their sources relative to the root of the repository
+    env::set_current_dir(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/.."
+    ))
+    .unwrap();
+
+    let mut generator = Generator::new();
+    generator
+        .include("tests/protos")
+        .owned_strings(".fixtures.p2.type")
+        .preserve_unknown_fields(".fixtures.p3.Partial")
+        .derive(".fixtures.p3.Partial", "Eq")
+        .derive(".fixtures.p3.Partial", "Hash")
+        .cached_size(".fixtures.p3.Counted")
+        .cached_size(".fixtures.p3.Point")
+        .derive(".fixtures.p3.Point", "Eq");
+    check(
+        "fixtures",
+        &generator,
+        &["tests/protos/fixtures/p3.proto"],
+    );
+
+    let mut generator = Generator::new();
+    generator
+        .include("tests/protos")
+        .owned_strings(".")
+        .services(".");
+    check(
+        "greeter",
+        &generator,
+        &["tests/protos/greeter.proto"],
+    );
+
+    // messages named `Result`, `Option`... do not shadow the types of the generated code
+    let mut generator = Generator::new();
+    generator.include("tests/protos");
+    check(
+        "collisions",
+        &generator,
+        &["tests/protos/collisions.proto"],
+    );
+}
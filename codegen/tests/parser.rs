@@ -0,0 +1,451 @@
+//! Checks the parser, the spans of the AST and the diagnostics of errors
+//! found in .proto files
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use quack_protobuf_codegen::generator::Generator;
+use quack_protobuf_codegen::loader::{FileSet, ProtoFile};
+use quack_protobuf_codegen::parser::ast::{FieldType, Span};
+use quack_protobuf_codegen::parser::{self, ParseError};
+
+/// Parses `source`, expecting an error
+fn error(source: &str) -> ParseError {
+    match parser::parse(source) {
+        Ok(file) => panic!(
+            "expected an error, parsed {:?}",
+            file
+        ),
+        Err(e) => e,
+    }
+}
+
+/// Checks that parsing `source` fails with `message` at `line` and `column`
+fn assert_error(
+    source: &str,
+    message: &str,
+    line: usize,
+    column: usize,
+) {
+    let e = error(source);
+    assert_eq!(
+        (
+            e.message.as_str(),
+            e.span.line,
+            e.span.column
+        ),
+        (message, line, column),
+        "{}",
+        source
+    );
+}
+
+/// Gets the text pointed by `span`
+fn text(
+    source: &str,
+    span: Span,
+) -> &str {
+    &source[span.start..span.end]
+}
+
+#[test]
+fn spans() {
+    let source = "syntax = \"proto3\";\n\nmessage Foo {\n  string name = 1;\n  enum Kind { A = 0; }\n}\n";
+    let file = parser::parse(source).unwrap();
+    let message = &file.messages[0];
+    assert_eq!(
+        text(source, message.span),
+        "Foo"
+    );
+    assert_eq!(
+        (
+            message.span.line,
+            message.span.column
+        ),
+        (3, 9)
+    );
+    let field = &message.fields[0];
+    assert_eq!(
+        text(source, field.span),
+        "name"
+    );
+    assert_eq!(
+        (
+            field.span.line,
+            field.span.column
+        ),
+        (4, 10)
+    );
+    let value = &message.enums[0].values[0];
+    assert_eq!(text(source, value.span), "A");
+    assert_eq!(
+        (
+            value.span.line,
+            value.span.column
+        ),
+        (5, 15)
+    );
+}
+
+#[test]
+fn duplicates() {
+    assert_error(
+        "message A {\n  optional int32 a = 1;\n  optional int32 b = 1;\n}",
+        "field number 1 has already been used by `a`",
+        3,
+        18,
+    );
+    assert_error(
+        "message A {\n  optional int32 a = 1;\n  optional int32 a = 2;\n}",
+        "duplicate field name `a`",
+        3,
+        18,
+    );
+    assert_error(
+        "message A {}\nenum A { X = 0; }",
+        "duplicate type name `A`",
+        2,
+        6,
+    );
+    assert_error(
+        "enum E {\n  X = 0;\n  Y = 0;\n}",
+        "enum value 0 has already been used by `X`, set `option allow_alias = true;` to allow aliases",
+        3,
+        3,
+    );
+    parser::parse("enum E {\n  option allow_alias = true;\n  X = 0;\n  Y = 0;\n}").unwrap();
+}
+
+#[test]
+fn reserved() {
+    assert_error(
+        "message A {\n  reserved 2 to 4, 8;\n  optional int32 a = 4;\n}",
+        "field `a` uses reserved number 4 (reserved at line 2)",
+        3,
+        18,
+    );
+    assert_error(
+        "message A {\n  reserved 2 to max;\n  optional int32 a = 1000;\n}",
+        "field `a` uses reserved number 1000 (reserved at line 2)",
+        3,
+        18,
+    );
+    assert_error(
+        "message A {\n  reserved \"a\";\n  optional int32 a = 1;\n}",
+        "field name `a` is reserved",
+        3,
+        18,
+    );
+    assert_error(
+        "enum E {\n  reserved 1;\n  X = 0;\n  Y = 1;\n}",
+        "enum value `Y` uses reserved number 1",
+        4,
+        3,
+    );
+    parser::parse("message A {\n  reserved 2 to 4;\n  optional int32 a = 1;\n  optional int32 b = 5;\n}").unwrap();
+}
+
+#[test]
+fn implementation_range() {
+    for number in [19000, 19500, 19999] {
+        assert_error(
+            &format!(
+                "message A {{\n  optional int32 a = {};\n}}",
+                number
+            ),
+            "field numbers 19000 to 19999 are reserved for the protobuf implementation",
+            2,
+            18,
+        );
+    }
+    parser::parse("message A {\n  optional int32 a = 18999;\n  optional int32 b = 20000;\n}").unwrap();
+    assert_error(
+        "message A {\n  optional int32 a = 536870912;\n}",
+        "field number 536870912 is out of range, must be between 1 and 536870911",
+        2,
+        18,
+    );
+}
+
+#[test]
+fn first_enum_value() {
+    assert_error(
+        "syntax = \"proto3\";\nenum E {\n  A = 1;\n  B = 0;\n}",
+        "the first enum value must be zero in proto3",
+        3,
+        3,
+    );
+    parser::parse("syntax = \"proto2\";\nenum E {\n  A = 1;\n  B = 0;\n}").unwrap();
+}
+
+#[test]
+fn unexpected_eof() {
+    for (source, message) in [
+        (
+            "message A {\n  optional int32 a = 1;\n",
+            "expected message element, found end of file",
+        ),
+        (
+            "message A {\n  optional int32 a = ",
+            "expected integer, found end of file",
+        ),
+        (
+            "syntax = ",
+            "expected string literal, found end of file",
+        ),
+        (
+            "enum E {\n  A = 0;",
+            "expected identifier, found end of file",
+        ),
+    ] {
+        let e = error(source);
+        assert_eq!(
+            e.message, message,
+            "{}",
+            source
+        );
+        // the error points past the last character
+        assert_eq!(
+            e.span.start,
+            source.len(),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn defaults() {
+    assert_error(
+        "message A {\n  optional int32 a = 1 [default = \"s\"];\n}",
+        "mismatched default value: field `a` of type `int32` expects an integer, found a string",
+        2,
+        25,
+    );
+    assert_error(
+        "message A {\n  optional int32 a = 1 [default = 5000000000];\n}",
+        "default value 5000000000 is out of range for field `a` of type `int32`",
+        2,
+        25,
+    );
+    assert_error(
+        "message A {\n  optional uint64 a = 1 [default = -1];\n}",
+        "default value -1 is out of range for field `a` of type `uint64`",
+        2,
+        26,
+    );
+    assert_error(
+        "message A {\n  optional bool a = 1 [default = 1];\n}",
+        "mismatched default value: field `a` of type `bool` expects `true` or `false`, found an integer",
+        2,
+        24,
+    );
+    assert_error(
+        "message A {\n  optional string a = 1 [default = 2.5];\n}",
+        "mismatched default value: field `a` of type `string` expects a string, found a float",
+        2,
+        26,
+    );
+    assert_error(
+        "message A {\n  optional string a = 1 [default = \"\\xff\"];\n}",
+        "default value of field `a` of type `string` is not valid UTF-8",
+        2,
+        26,
+    );
+    assert_error(
+        "syntax = \"proto3\";\nmessage A {\n  int32 a = 1 [default = 1];\n}",
+        "explicit default values are not allowed in proto3",
+        3,
+        16,
+    );
+    parser::parse(
+        "message A {\n  optional double a = 1 [default = 1];\n  optional float b = 2 [default = -inf];\n  optional bytes c = 3 [default = \"\\xff\"];\n}",
+    )
+    .unwrap();
+}
+
+#[test]
+fn fully_qualified_types() {
+    let file = parser::parse(
+        "syntax = \"proto3\";\npackage pkg;\nmessage Outer {\n  message Inner {}\n  .pkg.Outer.Inner n = 8;\n  oneof o {\n    .pkg.Outer.Inner m = 9;\n  }\n}\nextend Outer {\n  .pkg.Outer.Inner e = 10;\n}",
+    )
+    .unwrap();
+    let outer = &file.messages[0];
+    assert_eq!(
+        outer.fields[0].typ,
+        FieldType::Named(".pkg.Outer.Inner".to_string())
+    );
+    assert_eq!(
+        outer.oneofs[0].fields[0].typ,
+        FieldType::Named(".pkg.Outer.Inner".to_string())
+    );
+    assert_eq!(
+        file.extends[0].fields[0].typ,
+        FieldType::Named(".pkg.Outer.Inner".to_string())
+    );
+}
+
+#[test]
+fn render() {
+    let source = "syntax = \"proto3\";\nmessage A {\n  int32 a = 0;\n}\n";
+    let e = error(source);
+    assert_eq!(
+        e.to_string(),
+        "3:9: field number 0 is out of range, must be between 1 and 536870911"
+    );
+    assert_eq!(
+        e.render("a.proto", source),
+        "\
+error: field number 0 is out of range, must be between 1 and 536870911
+ --> a.proto:3:9
+  |
+3 |   int32 a = 0;
+  |         ^
+"
+    );
+}
+
+/// Generates the code of `source`, expecting an error, rendered as the command line does
+fn generate_error(source: &str) -> String {
+    let file = ProtoFile {
+        path: PathBuf::from("a.proto"),
+        source: source.to_string(),
+        descriptor: parser::parse(source).unwrap(),
+    };
+    let set = FileSet { files: vec![file] };
+    match Generator::new().generate_set(&set) {
+        Ok(_) => panic!("expected an error"),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn generator_diagnostics() {
+    assert_eq!(
+        generate_error("message A {\n  optional B b = 1;\n}\n"),
+        "\
+cannot resolve type `B`
+ --> a.proto:2:14
+  |
+2 |   optional B b = 1;
+  |              ^"
+    );
+    assert_eq!(
+        generate_error("enum E { X = 0; }\nmessage A {\n  optional E e = 1 [default = Y];\n}\n"),
+        "\
+default value `Y` of field `e` is not a value of enum `E`
+ --> a.proto:3:21
+  |
+3 |   optional E e = 1 [default = Y];
+  |                     ^^^^^^^^^^^"
+    );
+    assert_eq!(
+        generate_error("enum E { X = 0; }\nmessage A {\n  optional E e = 1 [default = 0];\n}\n"),
+        "\
+mismatched default value: field `e` of type `E` expects an enum value, found an integer
+ --> a.proto:3:21
+  |
+3 |   optional E e = 1 [default = 0];
+  |                     ^^^^^^^^^^^"
+    );
+    assert_eq!(
+        generate_error("message A {\n  optional A a = 1 [default = X];\n}\n"),
+        "\
+default values are only allowed on singular scalar fields
+ --> a.proto:2:21
+  |
+2 |   optional A a = 1 [default = X];
+  |                     ^^^^^^^^^^^"
+    );
+}
+
+#[test]
+fn command_line() {
+    let dir = std::env::temp_dir().join(format!(
+        "quack-protobuf-codegen-{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let cases = [
+        (
+            "syntax.proto",
+            "message A {\n  optional int32 a = x;\n}\n",
+        ),
+        (
+            "type.proto",
+            "message A {\n  optional B b = 1;\n}\n",
+        ),
+        (
+            "default.proto",
+            "message A {\n  optional int32 a = 1 [default = \"s\"];\n}\n",
+        ),
+    ];
+    for (name, source) in cases {
+        let path = dir.join(name);
+        fs::write(&path, source).unwrap();
+        let output = Command::new(env!(
+            "CARGO_BIN_EXE_quack-protobuf-codegen"
+        ))
+        .arg("-o")
+        .arg(&dir)
+        .arg(&path)
+        .output()
+        .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        // every error is reported the same way: a single severity, then the location and the line
+        assert!(
+            stderr.starts_with("error: ") && !stderr.starts_with("error: error"),
+            "{}",
+            stderr
+        );
+        let location = format!("--> {}:2:", path.display());
+        assert!(
+            stderr.contains(&location),
+            "{}",
+            stderr
+        );
+        assert!(
+            stderr.contains("\n2 |   optional "),
+            "{}",
+            stderr
+        );
+        assert!(
+            stderr.trim_end().ends_with('^'),
+            "{}",
+            stderr
+        );
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn command_line_creates_output() {
+    let dir = std::env::temp_dir().join(format!(
+        "quack-protobuf-codegen-out-{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("a.proto");
+    fs::write(
+        &path,
+        "syntax = \"proto3\";\nmessage A {\n  int32 a = 1;\n}\n",
+    )
+    .unwrap();
+    // missing directories of the output are created
+    let output = dir.join("gen").join("nested");
+    let status = Command::new(env!(
+        "CARGO_BIN_EXE_quack-protobuf-codegen"
+    ))
+    .arg("-I")
+    .arg(&dir)
+    .arg("-o")
+    .arg(&output)
+    .arg(&path)
+    .status()
+    .unwrap();
+    assert!(status.success());
+    assert!(output.join("mod.rs").is_file());
+    fs::remove_dir_all(&dir).unwrap();
+}
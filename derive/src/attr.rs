@@ -0,0 +1,230 @@
+//! A module to parse the `#[pb(...)]` attributes
+//!
+//! ```text
+//! #[pb(TAG [, TYPE] [, LABEL])]
+//! ```
+//!
+//! - `TAG` is the field number
+//! - `TYPE` is a protobuf scalar type (`int32`, `sint64`, `string` ...),
+//!   `enumeration` or `message`; inferred from the Rust type when omitted
+//! - `LABEL` is `optional`, `repeated`, `packed`, `packed_fixed`,
+//!   `map` / `map(KEY_TYPE, VALUE_TYPE)` or `oneof`; inferred from the Rust
+//!   type when omitted
+//!
+//! Fields which are not encoded are marked with `#[pb(skip)]`, the
+//! `UnknownFields` collecting the fields of other numbers with `#[pb(unknown)]`
+//! and the `CachedSize` of the message with `#[pb(cached_size)]`.
+
+use proc_macro2::Span;
+use syn::parse::{Parse, ParseStream};
+use syn::{parenthesized, Attribute, Ident, LitInt, Result, Token};
+
+/// A protobuf value type, as written in the attribute
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeName {
+    Int32,
+    Int64,
+    Uint32,
+    Uint64,
+    Sint32,
+    Sint64,
+    Bool,
+    Fixed32,
+    Fixed64,
+    Sfixed32,
+    Sfixed64,
+    Float,
+    Double,
+    String,
+    Bytes,
+    Enumeration,
+    Message,
+}
+
+impl TypeName {
+    fn from_ident(ident: &Ident) -> Option<TypeName> {
+        Some(
+            match ident.to_string().as_str() {
+                "int32" => TypeName::Int32,
+                "int64" => TypeName::Int64,
+                "uint32" => TypeName::Uint32,
+                "uint64" => TypeName::Uint64,
+                "sint32" => TypeName::Sint32,
+                "sint64" => TypeName::Sint64,
+                "bool" => TypeName::Bool,
+                "fixed32" => TypeName::Fixed32,
+                "fixed64" => TypeName::Fixed64,
+                "sfixed32" => TypeName::Sfixed32,
+                "sfixed64" => TypeName::Sfixed64,
+                "float" => TypeName::Float,
+                "double" => TypeName::Double,
+                "string" => TypeName::String,
+                "bytes" => TypeName::Bytes,
+                "enumeration" => TypeName::Enumeration,
+                "message" => TypeName::Message,
+                _ => return None,
+            },
+        )
+    }
+}
+
+/// How a field is stored
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Label {
+    Optional,
+    Repeated,
+    Packed,
+    PackedFixed,
+    Map(
+        Option<TypeName>,
+        Option<TypeName>,
+    ),
+    OneOf,
+    Unknown,
+    CachedSize,
+}
+
+/// A parsed `#[pb(...)]` attribute
+#[derive(Debug, Clone)]
+pub struct PbAttr {
+    pub tag: Option<u32>,
+    pub typ: Option<TypeName>,
+    pub label: Option<Label>,
+    pub skip: bool,
+    pub span: Span,
+}
+
+impl Parse for PbAttr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attr = PbAttr {
+            tag: None,
+            typ: None,
+            label: None,
+            skip: false,
+            span: input.span(),
+        };
+        while !input.is_empty() {
+            if input.peek(LitInt) {
+                let lit: LitInt = input.parse()?;
+                if attr.tag.is_some() {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        "duplicate field number",
+                    ));
+                }
+                let tag = lit.base10_parse::<u32>()?;
+                if !(1..=536_870_911).contains(&tag) {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        "field numbers must be between 1 and 536870911",
+                    ));
+                }
+                attr.tag = Some(tag);
+            } else {
+                let ident: Ident = input.parse()?;
+                let label = match ident.to_string().as_str() {
+                    "skip" => {
+                        attr.skip = true;
+                        None
+                    }
+                    "optional" => Some(Label::Optional),
+                    "repeated" => Some(Label::Repeated),
+                    "packed" => Some(Label::Packed),
+                    "packed_fixed" => Some(Label::PackedFixed),
+                    "oneof" => Some(Label::OneOf),
+                    "unknown" => Some(Label::Unknown),
+                    "cached_size" => Some(Label::CachedSize),
+                    "map" if input.peek(syn::token::Paren) => {
+                        let content;
+                        parenthesized!(content in input);
+                        let key = parse_type(&content)?;
+                        content.parse::<Token![,]>()?;
+                        let value = parse_type(&content)?;
+                        Some(Label::Map(
+                            Some(key),
+                            Some(value),
+                        ))
+                    }
+                    "map" => Some(Label::Map(None, None)),
+                    _ => match TypeName::from_ident(&ident) {
+                        Some(typ) if attr.typ.is_none() => {
+                            attr.typ = Some(typ);
+                            None
+                        }
+                        Some(_) => {
+                            return Err(syn::Error::new(
+                                ident.span(),
+                                "duplicate type",
+                            ))
+                        }
+                        None => {
+                            return Err(syn::Error::new(
+                                ident.span(),
+                                "unknown protobuf type or label",
+                            ))
+                        }
+                    },
+                };
+                if let Some(label) = label {
+                    if attr.label.replace(label).is_some() {
+                        return Err(syn::Error::new(
+                            ident.span(),
+                            "duplicate label",
+                        ));
+                    }
+                }
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        Ok(attr)
+    }
+}
+
+fn parse_type(input: ParseStream) -> Result<TypeName> {
+    let ident: Ident = input.parse()?;
+    TypeName::from_ident(&ident).ok_or_else(|| {
+        syn::Error::new(
+            ident.span(),
+            "unknown protobuf type",
+        )
+    })
+}
+
+/// Finds and parses the `#[pb(...)]` attribute of a field or a variant
+pub fn pb_attr(
+    attrs: &[Attribute],
+    span: Span,
+) -> Result<PbAttr> {
+    let mut found = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("pb")) {
+        if found.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "duplicate #[pb(...)] attribute",
+            ));
+        }
+        let mut parsed: PbAttr = attr.parse_args()?;
+        parsed.span = span;
+        found = Some(parsed);
+    }
+    let attr = found.ok_or_else(|| {
+        syn::Error::new(
+            span,
+            "missing #[pb(...)] attribute",
+        )
+    })?;
+    let untagged = matches!(
+        attr.label,
+        Some(Label::OneOf | Label::Unknown | Label::CachedSize)
+    );
+    if !attr.skip && attr.tag.is_none() && !untagged {
+        return Err(syn::Error::new(
+            span,
+            "missing field number",
+        ));
+    }
+    Ok(attr)
+}
@@ -0,0 +1,780 @@
+//! A module to resolve how each field is encoded and to write the matching
+//! reading, writing and sizing code
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{GenericArgument, Ident, PathArguments, Result, Type};
+
+use crate::attr::{pb_attr, Label, PbAttr, TypeName};
+
+const WIRE_TYPE_VARINT: u32 = 0;
+const WIRE_TYPE_FIXED64: u32 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u32 = 2;
+const WIRE_TYPE_FIXED32: u32 = 5;
+
+/// A single encoded value
+#[derive(Clone)]
+pub enum Value {
+    /// A scalar type, never `Enumeration` nor `Message`
+    Scalar(TypeName),
+    Enumeration,
+    Message {
+        ty: Box<Type>,
+        boxed: bool,
+    },
+}
+
+/// How a struct field is stored and encoded
+pub enum Kind {
+    /// Written unless equal to the default value, messages are always written
+    Single(Value),
+    Optional(Value),
+    Repeated(Value),
+    Packed(Value),
+    PackedFixed(Value),
+    Map(Value, Value),
+    /// `Option<E>` where `E` derives `Oneof`
+    OneOf(Box<Type>),
+    /// `UnknownFields<'a>` collecting the fields of unknown numbers
+    Unknown,
+    /// `CachedSize` keeping the size of the message, neither read nor written
+    CachedSize,
+}
+
+/// An encoded struct field
+pub struct Field {
+    pub ident: Ident,
+    pub ty: Type,
+    pub tag: u32,
+    pub kind: Kind,
+}
+
+impl Field {
+    /// Resolves a struct field, `None` if it is skipped
+    pub fn new(field: &syn::Field) -> Result<Option<Field>> {
+        let ident = field.ident.clone().ok_or_else(|| {
+            syn::Error::new_spanned(
+                field,
+                "only structs with named fields are supported",
+            )
+        })?;
+        let attr = pb_attr(&field.attrs, ident.span())?;
+        if attr.skip {
+            return Ok(None);
+        }
+        let kind = kind(&attr, &field.ty)?;
+        Ok(Some(Field {
+            ident,
+            ty: field.ty.clone(),
+            tag: attr.tag.unwrap_or(0),
+            kind,
+        }))
+    }
+
+    /// Match arms of the read loop, reading into `local`
+    ///
+    /// With `merge`, messages already read are merged with the new ones
+    /// instead of being replaced.
+    pub fn read_arms(
+        &self,
+        local: &TokenStream,
+        merge: bool,
+    ) -> TokenStream {
+        match &self.kind {
+            Kind::Single(Value::Message { boxed, .. }) if merge => {
+                let tag = self.tag << 3 | WIRE_TYPE_LENGTH_DELIMITED;
+                let message = match boxed {
+                    true => quote!(&mut *#local),
+                    false => quote!(&mut #local),
+                };
+                quote!(#tag => r.merge_message(bytes, #message)?,)
+            }
+            Kind::Optional(Value::Message { boxed, .. }) if merge => {
+                let tag = self.tag << 3 | WIRE_TYPE_LENGTH_DELIMITED;
+                let inserted = quote!(#local.get_or_insert_with(::core::default::Default::default));
+                let message = match boxed {
+                    true => quote!(&mut **#inserted),
+                    false => inserted,
+                };
+                quote!(#tag => r.merge_message(bytes, #message)?,)
+            }
+            Kind::Single(v) => {
+                let tag = v.tag(self.tag);
+                let read = v.read();
+                quote!(#tag => #local = #read?,)
+            }
+            Kind::Optional(v) => {
+                let tag = v.tag(self.tag);
+                let read = v.read();
+                quote!(#tag => #local = ::core::option::Option::Some(#read?),)
+            }
+            Kind::Repeated(v) | Kind::Packed(v) => {
+                let tag = v.tag(self.tag);
+                let read = v.read();
+                let packed_tag = self.tag << 3 | WIRE_TYPE_LENGTH_DELIMITED;
+                // parsers must accept both packed and unpacked encodings
+                if v.is_packable() {
+                    quote! {
+                        #tag => {
+                            r.check_repeated_len(#local.len())?;
+                            #local.push(#read?);
+                        }
+                        #packed_tag => r.append_packed(bytes, &mut #local, |r, bytes| #read)?,
+                    }
+                } else {
+                    quote! {
+                        #tag => {
+                            r.check_repeated_len(#local.len())?;
+                            #local.push(#read?);
+                        }
+                    }
+                }
+            }
+            Kind::PackedFixed(v) => {
+                let tag = v.tag(self.tag);
+                let read = v.read();
+                let packed_tag = self.tag << 3 | WIRE_TYPE_LENGTH_DELIMITED;
+                quote! {
+                    #packed_tag => r.append_packed_fixed(bytes, &mut #local)?,
+                    #tag => {
+                        let v = #read?;
+                        #local.own();
+                        if let ::quack_protobuf::PackedFixed::Owned(values) = &mut #local {
+                            r.check_repeated_len(values.len())?;
+                            values.push(v);
+                        }
+                    }
+                }
+            }
+            Kind::Map(k, v) => {
+                let tag = self.tag << 3 | WIRE_TYPE_LENGTH_DELIMITED;
+                let read_key = k.read();
+                let read_value = v.read();
+                quote! {
+                    #tag => {
+                        let (k, v) = r.read_map(bytes, |r, bytes| #read_key, |r, bytes| #read_value)?;
+                        r.check_map_len(#local.len())?;
+                        #local.insert(k, v);
+                    }
+                }
+            }
+            Kind::OneOf(_) | Kind::Unknown | Kind::CachedSize => TokenStream::new(),
+        }
+    }
+
+    /// Statement trying to read a oneof variant of unknown tag `t`
+    ///
+    /// With `merge`, a message variant already read is merged with the new one.
+    pub fn read_oneof(
+        &self,
+        local: &TokenStream,
+        merge: bool,
+    ) -> TokenStream {
+        match &self.kind {
+            Kind::OneOf(ty) if merge => quote! {
+                if <#ty>::merge_variant(&mut #local, t, r, bytes)? {
+                    continue;
+                }
+            },
+            Kind::OneOf(ty) => quote! {
+                if let ::core::option::Option::Some(v) = <#ty>::read_variant(t, r, bytes)? {
+                    #local = ::core::option::Option::Some(v);
+                    continue;
+                }
+            },
+            _ => TokenStream::new(),
+        }
+    }
+
+    /// Statement keeping the unknown field of tag `t`, if the field collects them
+    pub fn read_unknown(
+        &self,
+        local: &TokenStream,
+    ) -> Option<TokenStream> {
+        match self.kind {
+            Kind::Unknown => Some(quote!(#local.read(r, bytes, t)?;)),
+            _ => None,
+        }
+    }
+
+    /// Statement resetting the field, collections and messages keeping their allocations
+    pub fn clear(&self) -> TokenStream {
+        let ident = &self.ident;
+        match &self.kind {
+            Kind::Single(Value::Message { .. })
+            | Kind::Repeated(_)
+            | Kind::Packed(_)
+            | Kind::PackedFixed(_)
+            | Kind::Map(..)
+            | Kind::Unknown => quote!(self.#ident.clear();),
+            Kind::CachedSize => TokenStream::new(),
+            _ => quote!(self.#ident = ::core::default::Default::default();),
+        }
+    }
+
+    /// Expression of the encoded size of the field
+    pub fn size(&self) -> TokenStream {
+        let ident = &self.ident;
+        let tag_size = sizeof_tag(self.tag);
+        match &self.kind {
+            Kind::Single(v) => {
+                let size = v.size(&quote!((&self.#ident)));
+                match v.is_default(&quote!(self.#ident)) {
+                    Some(is_default) => quote!(if #is_default { 0 } else { #tag_size + #size }),
+                    None => quote!(#tag_size + #size),
+                }
+            }
+            Kind::Optional(v) => {
+                let (arg, size) = v.size_closure();
+                quote!(self.#ident.as_ref().map_or(0, |#arg| #tag_size + #size))
+            }
+            Kind::Repeated(v) => match v.fixed_size() {
+                Some(size) => quote!(self.#ident.len() * (#tag_size + #size)),
+                None => {
+                    let size = v.size(&quote!(v));
+                    quote!(self.#ident.iter().map(|v| #tag_size + #size).sum::<usize>())
+                }
+            },
+            Kind::Packed(v) => {
+                let len = match v.fixed_size() {
+                    Some(size) => quote!(self.#ident.len() * #size),
+                    None => {
+                        let size = v.size(&quote!(v));
+                        quote!(self.#ident.iter().map(|v| #size).sum::<usize>())
+                    }
+                };
+                quote! {
+                    if self.#ident.is_empty() {
+                        0
+                    } else {
+                        #tag_size + ::quack_protobuf::sizeofs::sizeof_len(#len)
+                    }
+                }
+            }
+            Kind::PackedFixed(v) => {
+                let size = v.fixed_size().unwrap_or(0);
+                quote! {
+                    if self.#ident.is_empty() {
+                        0
+                    } else {
+                        #tag_size + ::quack_protobuf::sizeofs::sizeof_len(self.#ident.len() * #size)
+                    }
+                }
+            }
+            Kind::Map(k, v) => {
+                let key_size = k.size(&quote!(k));
+                let value_size = v.size(&quote!(v));
+                quote! {
+                    self.#ident
+                        .iter()
+                        .map(|(k, v)| #tag_size + ::quack_protobuf::sizeofs::sizeof_len(2 + #key_size + #value_size))
+                        .sum::<usize>()
+                }
+            }
+            Kind::OneOf(_) => quote!(self.#ident.as_ref().map_or(0, |v| v.variant_size())),
+            Kind::Unknown => quote!(self.#ident.get_size()),
+            Kind::CachedSize => quote!(0),
+        }
+    }
+
+    /// Statement writing the field
+    pub fn write(&self) -> TokenStream {
+        let ident = &self.ident;
+        let packed_tag = self.tag << 3 | WIRE_TYPE_LENGTH_DELIMITED;
+        match &self.kind {
+            Kind::Single(v) => {
+                let tag = v.tag(self.tag);
+                let write = v.write(&quote!((&self.#ident)));
+                match v.is_default(&quote!(self.#ident)) {
+                    Some(is_default) => quote! {
+                        if !(#is_default) {
+                            w.write_with_tag(#tag, |w| #write)?;
+                        }
+                    },
+                    None => quote!(w.write_with_tag(#tag, |w| #write)?;),
+                }
+            }
+            Kind::Optional(v) => {
+                let tag = v.tag(self.tag);
+                let write = v.write(&quote!(v));
+                quote! {
+                    if let ::core::option::Option::Some(v) = &self.#ident {
+                        w.write_with_tag(#tag, |w| #write)?;
+                    }
+                }
+            }
+            Kind::Repeated(v) => {
+                let tag = v.tag(self.tag);
+                let write = v.write(&quote!(v));
+                quote! {
+                    for v in self.#ident.iter() {
+                        w.write_with_tag(#tag, |w| #write)?;
+                    }
+                }
+            }
+            Kind::Packed(v) => {
+                let write = v.write(&quote!(v));
+                let (arg, size) = v.size_closure();
+                quote!(w.write_packed_with_tag(#packed_tag, &self.#ident, |w, v| #write, &|#arg| #size)?;)
+            }
+            Kind::PackedFixed(_) => quote!(w.write_packed_fixed_with_tag(#packed_tag, &self.#ident)?;),
+            Kind::Map(k, v) => {
+                let key_tag = 1 << 3 | k.wire_type();
+                let value_tag = 2 << 3 | v.wire_type();
+                let key_size = k.size(&quote!(k));
+                let value_size = v.size(&quote!(v));
+                let write_key = k.write(&quote!(k));
+                let write_value = v.write(&quote!(v));
+                if let Value::Message { boxed, .. } = v {
+                    // the size of the message is computed once, by the write
+                    let value = match boxed {
+                        true => quote!(&**v),
+                        false => quote!(v),
+                    };
+                    return quote! {
+                        for (k, v) in self.#ident.iter() {
+                            w.write_with_tag(#packed_tag, |w| {
+                                w.write_map_message(#key_size, #key_tag, |w| #write_key, #value_tag, #value)
+                            })?;
+                        }
+                    };
+                }
+                quote! {
+                    for (k, v) in self.#ident.iter() {
+                        w.write_with_tag(#packed_tag, |w| {
+                            w.write_map(
+                                2 + #key_size + #value_size,
+                                #key_tag,
+                                |w| #write_key,
+                                #value_tag,
+                                |w| #write_value,
+                            )
+                        })?;
+                    }
+                }
+            }
+            Kind::OneOf(_) => quote! {
+                if let ::core::option::Option::Some(v) = &self.#ident {
+                    v.write_variant(w)?;
+                }
+            },
+            Kind::Unknown => quote!(w.write_unknown(&self.#ident)?;),
+            Kind::CachedSize => TokenStream::new(),
+        }
+    }
+}
+
+impl Value {
+    /// Resolves a value from its explicit protobuf type, or from its Rust type
+    pub fn new(
+        typ: Option<TypeName>,
+        ty: &Type,
+    ) -> Result<Value> {
+        Ok(match typ {
+            Some(TypeName::Message) => {
+                let (ty, boxed) = match container(ty, "Box") {
+                    Some(args) => (
+                        Box::new(args[0].clone()),
+                        true,
+                    ),
+                    None => (Box::new(ty.clone()), false),
+                };
+                Value::Message { ty, boxed }
+            }
+            Some(TypeName::Enumeration) => Value::Enumeration,
+            Some(typ) => Value::Scalar(typ),
+            None => infer(ty),
+        })
+    }
+
+    /// The tag of a single value
+    pub fn tag(
+        &self,
+        number: u32,
+    ) -> u32 {
+        number << 3 | self.wire_type()
+    }
+
+    pub fn wire_type(&self) -> u32 {
+        match self {
+            Value::Scalar(TypeName::Fixed64 | TypeName::Sfixed64 | TypeName::Double) => WIRE_TYPE_FIXED64,
+            Value::Scalar(TypeName::Fixed32 | TypeName::Sfixed32 | TypeName::Float) => WIRE_TYPE_FIXED32,
+            Value::Scalar(TypeName::String | TypeName::Bytes) | Value::Message { .. } => WIRE_TYPE_LENGTH_DELIMITED,
+            _ => WIRE_TYPE_VARINT,
+        }
+    }
+
+    /// Whether repeated values of this type can be packed
+    pub fn is_packable(&self) -> bool {
+        !matches!(
+            self,
+            Value::Scalar(TypeName::String | TypeName::Bytes) | Value::Message { .. }
+        )
+    }
+
+    /// The binary size of values with a fixed size
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self.wire_type() {
+            WIRE_TYPE_FIXED64 => Some(8),
+            WIRE_TYPE_FIXED32 => Some(4),
+            _ if matches!(
+                self,
+                Value::Scalar(TypeName::Bool)
+            ) =>
+            {
+                Some(1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Expression reading a value, of type `Result<T>`
+    pub fn read(&self) -> TokenStream {
+        match self {
+            Value::Scalar(TypeName::String) => quote!(r
+                .read_string(bytes)
+                .map(::core::convert::Into::into)),
+            Value::Scalar(TypeName::Bytes) => quote!(r
+                .read_bytes(bytes)
+                .map(::core::convert::Into::into)),
+            Value::Scalar(t) => {
+                let read = Ident::new(
+                    &format!("read_{}", scalar_name(*t)),
+                    Span::call_site(),
+                );
+                quote!(r.#read(bytes))
+            }
+            Value::Enumeration => quote!(r.read_enum(bytes)),
+            Value::Message { ty, boxed: false } => quote!(r.read_message::<#ty>(bytes)),
+            Value::Message { ty, boxed: true } => {
+                quote!(r.read_message::<#ty>(bytes).map(::std::boxed::Box::new))
+            }
+        }
+    }
+
+    /// Expression writing `v`, a reference to a value
+    pub fn write(
+        &self,
+        v: &TokenStream,
+    ) -> TokenStream {
+        match self {
+            Value::Scalar(TypeName::String) => quote!(w.write_string(#v)),
+            Value::Scalar(TypeName::Bytes) => quote!(w.write_bytes(#v)),
+            Value::Scalar(t) => {
+                let write = Ident::new(
+                    &format!("write_{}", scalar_name(*t)),
+                    Span::call_site(),
+                );
+                quote!(w.#write(*#v))
+            }
+            Value::Enumeration => quote!(w.write_enum(*#v as i32)),
+            Value::Message { boxed: false, .. } => quote!(w.write_message_with_len_prefix(#v)),
+            Value::Message { boxed: true, .. } => quote!(w.write_message_with_len_prefix(&**#v)),
+        }
+    }
+
+    /// Expression of the encoded size of `v`, a reference to a value, without its tag
+    pub fn size(
+        &self,
+        v: &TokenStream,
+    ) -> TokenStream {
+        if let Some(size) = self.fixed_size() {
+            return quote!(#size);
+        }
+        match self {
+            Value::Scalar(TypeName::String | TypeName::Bytes) => {
+                quote!(::quack_protobuf::sizeofs::sizeof_len(#v.len()))
+            }
+            Value::Scalar(t) => {
+                let sizeof = Ident::new(
+                    &format!("sizeof_{}", scalar_name(*t)),
+                    Span::call_site(),
+                );
+                quote!(::quack_protobuf::sizeofs::#sizeof(*#v))
+            }
+            Value::Enumeration => quote!(::quack_protobuf::sizeofs::sizeof_enum(*#v as i32)),
+            Value::Message { boxed: false, .. } => {
+                quote!(::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(#v)))
+            }
+            Value::Message { boxed: true, .. } => {
+                quote!(::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(&**#v)))
+            }
+        }
+    }
+
+    /// Argument and body of a closure computing the size of a value `v`
+    pub fn size_closure(&self) -> (TokenStream, TokenStream) {
+        let arg = match self.fixed_size() {
+            Some(_) => quote!(_),
+            None => quote!(v),
+        };
+        (arg, self.size(&quote!(v)))
+    }
+
+    /// Condition under which a singular value is not written, none for messages
+    fn is_default(
+        &self,
+        place: &TokenStream,
+    ) -> Option<TokenStream> {
+        match self {
+            Value::Message { .. } => None,
+            Value::Scalar(TypeName::String | TypeName::Bytes) => Some(quote!(#place.is_empty())),
+            _ => Some(quote!(#place == ::core::default::Default::default())),
+        }
+    }
+}
+
+/// Name of a scalar type, as used in `read_*`, `write_*` and `sizeof_*`
+fn scalar_name(typ: TypeName) -> &'static str {
+    match typ {
+        TypeName::Int32 => "int32",
+        TypeName::Int64 => "int64",
+        TypeName::Uint32 => "uint32",
+        TypeName::Uint64 => "uint64",
+        TypeName::Sint32 => "sint32",
+        TypeName::Sint64 => "sint64",
+        TypeName::Bool => "bool",
+        TypeName::Fixed32 => "fixed32",
+        TypeName::Fixed64 => "fixed64",
+        TypeName::Sfixed32 => "sfixed32",
+        TypeName::Sfixed64 => "sfixed64",
+        TypeName::Float => "float",
+        TypeName::Double => "double",
+        TypeName::String => "string",
+        TypeName::Bytes => "bytes",
+        TypeName::Enumeration | TypeName::Message => unreachable!("not a scalar"),
+    }
+}
+
+/// Binary size of the tag of field `number`
+pub fn sizeof_tag(number: u32) -> usize {
+    match number << 3 {
+        0x0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        0x4000..=0x1f_ffff => 3,
+        0x20_0000..=0xfff_ffff => 4,
+        _ => 5,
+    }
+}
+
+/// Name of the last segment of a path type
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(p) => p
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// Generic type arguments of `ty` if its name is `name`, e.g. `T` of `Vec<T>`
+fn container(
+    ty: &Type,
+    name: &str,
+) -> Option<Vec<Type>> {
+    let Type::Path(p) = ty else { return None };
+    let segment = p.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    let types = args
+        .args
+        .iter()
+        .filter_map(|a| match a {
+            GenericArgument::Type(t) => Some(t.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    (!types.is_empty()).then_some(types)
+}
+
+fn is_u8(ty: &Type) -> bool {
+    type_name(ty).as_deref() == Some("u8")
+}
+
+/// Infers the protobuf type of a single value from its Rust type
+fn infer(ty: &Type) -> Value {
+    match ty {
+        Type::Reference(r) => match &*r.elem {
+            Type::Slice(s) if is_u8(&s.elem) => return Value::Scalar(TypeName::Bytes),
+            t if type_name(t).as_deref() == Some("str") => return Value::Scalar(TypeName::String),
+            _ => {}
+        },
+        Type::Group(g) => return infer(&g.elem),
+        Type::Paren(p) => return infer(&p.elem),
+        _ => {}
+    }
+    if let Some(args) = container(ty, "Cow") {
+        return match &args[0] {
+            Type::Slice(s) if is_u8(&s.elem) => Value::Scalar(TypeName::Bytes),
+            _ => Value::Scalar(TypeName::String),
+        };
+    }
+    if let Some(args) = container(ty, "Vec") {
+        if is_u8(&args[0]) {
+            return Value::Scalar(TypeName::Bytes);
+        }
+    }
+    if let Some(args) = container(ty, "Box") {
+        return Value::Message {
+            ty: Box::new(args[0].clone()),
+            boxed: true,
+        };
+    }
+    match type_name(ty).as_deref() {
+        Some("i32") => Value::Scalar(TypeName::Int32),
+        Some("i64") => Value::Scalar(TypeName::Int64),
+        Some("u32") => Value::Scalar(TypeName::Uint32),
+        Some("u64") => Value::Scalar(TypeName::Uint64),
+        Some("bool") => Value::Scalar(TypeName::Bool),
+        Some("f32") => Value::Scalar(TypeName::Float),
+        Some("f64") => Value::Scalar(TypeName::Double),
+        Some("String") => Value::Scalar(TypeName::String),
+        _ => Value::Message {
+            ty: Box::new(ty.clone()),
+            boxed: false,
+        },
+    }
+}
+
+/// Infers the fixed size protobuf type of the values of a `PackedFixed`
+fn infer_fixed(ty: &Type) -> Option<TypeName> {
+    Some(
+        match type_name(ty)?.as_str() {
+            "u32" => TypeName::Fixed32,
+            "i32" => TypeName::Sfixed32,
+            "f32" => TypeName::Float,
+            "u64" => TypeName::Fixed64,
+            "i64" => TypeName::Sfixed64,
+            "f64" => TypeName::Double,
+            _ => return None,
+        },
+    )
+}
+
+/// Resolves the storage of a field from its attribute and Rust type
+fn kind(
+    attr: &PbAttr,
+    ty: &Type,
+) -> Result<Kind> {
+    let single = |name: &str| -> Result<Type> {
+        container(ty, name)
+            .map(|args| args[0].clone())
+            .ok_or_else(|| {
+                syn::Error::new_spanned(
+                    ty,
+                    format!(
+                        "expected a `{}<_>` field",
+                        name
+                    ),
+                )
+            })
+    };
+    let label = match attr.label {
+        Some(label) => label,
+        None if container(ty, "Option").is_some() => Label::Optional,
+        None if container(ty, "PackedFixed").is_some() => Label::PackedFixed,
+        None if container(ty, "HashMap").is_some() || container(ty, "BTreeMap").is_some() => Label::Map(None, None),
+        None => match container(ty, "Vec") {
+            // `Vec<u8>` is `bytes` unless told otherwise
+            Some(args)
+                if !(is_u8(&args[0])
+                    && matches!(
+                        attr.typ,
+                        None | Some(TypeName::Bytes)
+                    )) =>
+            {
+                match Value::new(attr.typ, &args[0])?.is_packable() {
+                    true => Label::Packed,
+                    false => Label::Repeated,
+                }
+            }
+            _ => {
+                return Ok(Kind::Single(Value::new(
+                    attr.typ, ty,
+                )?))
+            }
+        },
+    };
+    Ok(match label {
+        Label::Optional => Kind::Optional(Value::new(
+            attr.typ,
+            &single("Option")?,
+        )?),
+        Label::Repeated => Kind::Repeated(Value::new(
+            attr.typ,
+            &single("Vec")?,
+        )?),
+        Label::Packed => {
+            let value = Value::new(attr.typ, &single("Vec")?)?;
+            if !value.is_packable() {
+                return Err(syn::Error::new(
+                    attr.span,
+                    "only scalar numeric types can be packed",
+                ));
+            }
+            Kind::Packed(value)
+        }
+        Label::PackedFixed => {
+            let inner = single("PackedFixed")?;
+            let value = Value::new(
+                attr.typ.or_else(|| infer_fixed(&inner)),
+                &inner,
+            )?;
+            if value.fixed_size().is_none() || value.wire_type() == WIRE_TYPE_VARINT {
+                return Err(syn::Error::new(
+                    attr.span,
+                    "`packed_fixed` requires a fixed size type (fixed32, sfixed32, float, fixed64, sfixed64 or double)",
+                ));
+            }
+            Kind::PackedFixed(value)
+        }
+        Label::Map(key, value) => {
+            let args = container(ty, "HashMap")
+                .or_else(|| container(ty, "BTreeMap"))
+                .filter(|args| args.len() >= 2)
+                .ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        ty,
+                        "expected a `HashMap<_, _>` or `BTreeMap<_, _>` field",
+                    )
+                })?;
+            let key = Value::new(key, &args[0])?;
+            if matches!(
+                key,
+                Value::Message { .. }
+                    | Value::Enumeration
+                    | Value::Scalar(TypeName::Float | TypeName::Double | TypeName::Bytes)
+            ) {
+                return Err(syn::Error::new(
+                    attr.span,
+                    "map keys must be integral or string types",
+                ));
+            }
+            Kind::Map(
+                key,
+                Value::new(value, &args[1])?,
+            )
+        }
+        Label::OneOf => Kind::OneOf(Box::new(single("Option")?)),
+        Label::Unknown if attr.typ.is_none() => Kind::Unknown,
+        Label::Unknown => {
+            return Err(syn::Error::new(
+                attr.span,
+                "`unknown` fields have no protobuf type",
+            ))
+        }
+        Label::CachedSize if attr.typ.is_none() => Kind::CachedSize,
+        Label::CachedSize => {
+            return Err(syn::Error::new(
+                attr.span,
+                "`cached_size` fields have no protobuf type",
+            ))
+        }
+    })
+}
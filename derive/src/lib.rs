@@ -0,0 +1,517 @@
+//! Derive macros for quack-protobuf
+//!
+//! Implements `MessageRead`, `MessageMerge` and `MessageWrite` for plain Rust
+//! structs, without going through a .proto file. Every field is described by
+//! a `#[pb(...)]` attribute holding its field number and, when it cannot be
+//! inferred from the Rust type, its protobuf type and label:
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//! use quack_protobuf::{MessageMerge, MessageRead, MessageWrite, Oneof, PackedFixed};
+//!
+//! #[derive(Debug, Default, PartialEq, MessageRead, MessageMerge, MessageWrite)]
+//! struct Point {
+//!     #[pb(1, sint32)]
+//!     x: i32,
+//!     #[pb(2, sint32)]
+//!     y: i32,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Oneof)]
+//! enum Shape<'a> {
+//!     #[pb(6)]
+//!     Circle(f32),
+//!     #[pb(7)]
+//!     Label(&'a str),
+//! }
+//!
+//! #[derive(Debug, Default, PartialEq, MessageRead, MessageMerge, MessageWrite)]
+//! struct Foo<'a> {
+//!     #[pb(1, sint64)]
+//!     a: i64,
+//!     #[pb(2, packed_fixed)]
+//!     v: PackedFixed<'a, f32>,
+//!     #[pb(3)]
+//!     name: Option<&'a str>,
+//!     #[pb(4, repeated)]
+//!     points: Vec<Point>,
+//!     #[pb(5, map(string, int32))]
+//!     counts: HashMap<String, i32>,
+//!     #[pb(oneof)]
+//!     shape: Option<Shape<'a>>,
+//! }
+//!
+//! let foo = Foo {
+//!     a: -1,
+//!     v: vec![1.5, 2.5].into(),
+//!     name: Some("foo"),
+//!     points: vec![Point { x: 1, y: -1 }],
+//!     counts: HashMap::from([("a".to_string(), 1)]),
+//!     shape: Some(Shape::Label("bar")),
+//! };
+//! let bytes = quack_protobuf::encode_to_vec(&foo).unwrap();
+//! assert_eq!(quack_protobuf::decode::<Foo>(&bytes).unwrap(), foo);
+//!
+//! let mut reused = Foo::default();
+//! quack_protobuf::decode_into(&mut reused, &bytes).unwrap();
+//! reused.clear();
+//! assert_eq!(reused, Foo::default());
+//! ```
+//!
+//! Types:
+//! - scalars are inferred from `i32`, `i64`, `u32`, `u64`, `bool`, `f32`,
+//!   `f64` and the string (`&'a str`, `String`, `Cow<'a, str>`) and bytes
+//!   (`&'a [u8]`, `Vec<u8>`, `Cow<'a, [u8]>`) types; `sint32`, `fixed64` etc.
+//!   must be explicit
+//! - enums are marked with `enumeration` and must be `Copy`, `PartialEq`,
+//!   `Default` and `From<i32>`
+//! - any other type is a nested message, `Box<M>` can be used for recursion
+//!
+//! Labels:
+//! - `optional` for `Option<T>`
+//! - `repeated` / `packed` for `Vec<T>`, scalar numeric types are packed by default
+//! - `packed_fixed` for `PackedFixed<'a, T>`
+//! - `map` for `HashMap<K, V>` or `BTreeMap<K, V>`, `map(KEY_TYPE, VALUE_TYPE)`
+//!   when the types cannot be inferred
+//! - `oneof` for `Option<E>` where `E` derives `Oneof`
+//!
+//! - `unknown` for an `UnknownFields<'a>` keeping the fields of all the other
+//!   numbers, which are written back after the known fields
+//! - `cached_size` for a `CachedSize` keeping the size computed by `get_size`
+//!   until the message is written with a length prefix
+//!
+//! Singular fields without label are not written when equal to their default
+//! value, like proto3 fields. Fields marked `#[pb(skip)]` are not encoded
+//! and are initialized with their default value when reading.
+//!
+//! `MessageMerge` merges the nested messages of singular fields, which must
+//! implement it too. A oneof field is replaced by the variant read, unless
+//! both are the same message variant: the message read is then merged into
+//! the one already set.
+//! Its `clear` resets skipped fields to their default value.
+
+mod attr;
+mod field;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Generics, Ident, Lifetime, LifetimeParam, Result};
+
+use crate::attr::pb_attr;
+use crate::field::{sizeof_tag, Field, Kind, Value};
+
+/// Implements `MessageRead` for a struct with `#[pb(...)]` fields
+#[proc_macro_derive(MessageRead, attributes(pb))]
+pub fn derive_message_read(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    message_read(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `MessageMerge` for a struct with `#[pb(...)]` fields
+#[proc_macro_derive(MessageMerge, attributes(pb))]
+pub fn derive_message_merge(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    message_merge(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `MessageWrite` for a struct with `#[pb(...)]` fields
+#[proc_macro_derive(MessageWrite, attributes(pb))]
+pub fn derive_message_write(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    message_write(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Makes an enum usable as a `#[pb(oneof)]` field, every variant holds a
+/// single value described by a `#[pb(...)]` attribute
+#[proc_macro_derive(Oneof, attributes(pb))]
+pub fn derive_oneof(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    oneof(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Fields of a struct, `None` for the skipped ones
+fn struct_fields(input: &DeriveInput) -> Result<Vec<(Ident, Option<Field>)>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs can derive MessageRead, MessageMerge and MessageWrite",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs with named fields are supported",
+        ));
+    };
+    let mut out = Vec::new();
+    let mut numbers = Vec::new();
+    let mut unknown = false;
+    let mut cached_size = false;
+    for f in &fields.named {
+        let field = Field::new(f)?;
+        if let Some(field) = field
+            .as_ref()
+            .filter(|f| matches!(f.kind, Kind::Unknown))
+        {
+            if unknown {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    "only one field can collect the unknown fields",
+                ));
+            }
+            unknown = true;
+        }
+        if let Some(field) = field
+            .as_ref()
+            .filter(|f| matches!(f.kind, Kind::CachedSize))
+        {
+            if cached_size {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    "only one field can cache the size",
+                ));
+            }
+            cached_size = true;
+        }
+        if let Some(field) = field.as_ref().filter(|f| f.tag != 0) {
+            if let Some((_, other)) = numbers.iter().find(|(n, _)| *n == field.tag) {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    format!(
+                        "field number {} is already used by `{}`",
+                        field.tag, other
+                    ),
+                ));
+            }
+            numbers.push((field.tag, field.ident.clone()));
+        }
+        out.push((
+            f.ident.clone().unwrap(),
+            field,
+        ));
+    }
+    Ok(out)
+}
+
+/// The lifetime the input bytes are borrowed for, and the generics of the impl
+fn read_generics(generics: &Generics) -> (Lifetime, Generics) {
+    match generics.lifetimes().next() {
+        Some(l) => (
+            l.lifetime.clone(),
+            generics.clone(),
+        ),
+        None => {
+            let lifetime = Lifetime::new("'__pb", Span::call_site());
+            let mut generics = generics.clone();
+            generics.params.insert(
+                0,
+                LifetimeParam::new(lifetime.clone()).into(),
+            );
+            (lifetime, generics)
+        }
+    }
+}
+
+fn message_read(input: &DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = struct_fields(input)?;
+    let name = &input.ident;
+    let (lifetime, generics) = read_generics(&input.generics);
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut locals = Vec::new();
+    let mut arms = Vec::new();
+    let mut oneofs = Vec::new();
+    let mut unknown = quote!(r.read_unknown(bytes, t)?;);
+    let mut init = Vec::new();
+    for (ident, field) in &fields {
+        match field {
+            Some(field) => {
+                let local = format_ident!("__pb_{}", ident);
+                let ty = &field.ty;
+                locals.push(quote!(let mut #local: #ty = ::core::default::Default::default();));
+                let place = quote!(#local);
+                arms.push(field.read_arms(&place, false));
+                oneofs.push(field.read_oneof(&place, false));
+                if let Some(read) = field.read_unknown(&place) {
+                    unknown = read;
+                }
+                init.push(quote!(#ident: #local));
+            }
+            None => init.push(quote!(#ident: ::core::default::Default::default())),
+        }
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::quack_protobuf::MessageRead<#lifetime> for #name #ty_generics #where_clause {
+            fn from_reader(
+                r: &mut ::quack_protobuf::BytesReader,
+                bytes: &#lifetime [u8],
+            ) -> ::quack_protobuf::Result<Self> {
+                #(#locals)*
+                while !r.is_eof() {
+                    match r.next_tag(bytes)? {
+                        #(#arms)*
+                        t => {
+                            #(#oneofs)*
+                            #unknown
+                        }
+                    }
+                }
+                ::core::result::Result::Ok(Self { #(#init),* })
+            }
+        }
+    })
+}
+
+fn message_merge(input: &DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = struct_fields(input)?;
+    let name = &input.ident;
+    let (lifetime, generics) = read_generics(&input.generics);
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut arms = Vec::new();
+    let mut oneofs = Vec::new();
+    let mut unknown = quote!(r.read_unknown(bytes, t)?;);
+    let mut clears = Vec::new();
+    for (ident, field) in &fields {
+        match field {
+            Some(field) => {
+                let place = quote!(self.#ident);
+                arms.push(field.read_arms(&place, true));
+                oneofs.push(field.read_oneof(&place, true));
+                if let Some(read) = field.read_unknown(&place) {
+                    unknown = read;
+                }
+                clears.push(field.clear());
+            }
+            None => clears.push(quote!(self.#ident = ::core::default::Default::default();)),
+        }
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::quack_protobuf::MessageMerge<#lifetime> for #name #ty_generics #where_clause {
+            fn merge_from(
+                &mut self,
+                r: &mut ::quack_protobuf::BytesReader,
+                bytes: &#lifetime [u8],
+            ) -> ::quack_protobuf::Result<()> {
+                while !r.is_eof() {
+                    match r.next_tag(bytes)? {
+                        #(#arms)*
+                        t => {
+                            #(#oneofs)*
+                            #unknown
+                        }
+                    }
+                }
+                ::core::result::Result::Ok(())
+            }
+
+            fn clear(&mut self) {
+                #(#clears)*
+            }
+        }
+    })
+}
+
+fn message_write(input: &DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = struct_fields(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut fields = fields
+        .iter()
+        .filter_map(|(_, f)| f.as_ref())
+        .collect::<Vec<_>>();
+    // unknown fields are written last, whatever their position in the struct
+    fields.sort_by_key(|f| matches!(f.kind, Kind::Unknown));
+    let sizes = fields.iter().map(|f| f.size());
+    let writes = fields.iter().map(|f| f.write());
+    let cached_size = fields
+        .iter()
+        .find(|f| matches!(f.kind, Kind::CachedSize))
+        .map(|f| &f.ident);
+    let get_size = match cached_size {
+        Some(ident) => quote! {
+            fn get_size(&self) -> usize {
+                let size = 0 #(+ #sizes)*;
+                self.#ident.set(size);
+                size
+            }
+
+            fn take_cached_size(&self) -> ::core::option::Option<usize> {
+                self.#ident.take()
+            }
+        },
+        None => quote! {
+            fn get_size(&self) -> usize {
+                0 #(+ #sizes)*
+            }
+        },
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::quack_protobuf::MessageWrite for #name #ty_generics #where_clause {
+            #get_size
+
+            fn write_message<W: ::quack_protobuf::WriterBackend>(
+                &self,
+                w: &mut ::quack_protobuf::Writer<W>,
+            ) -> ::quack_protobuf::Result<()> {
+                #(#writes)*
+                ::core::result::Result::Ok(())
+            }
+        }
+    })
+}
+
+fn oneof(input: &DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "only enums can derive Oneof",
+        ));
+    };
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    // only the read needs a lifetime, added to the method when the enum has none
+    let (lifetime, read_lifetime) = match input.generics.lifetimes().next() {
+        Some(l) => (l.lifetime.clone(), None),
+        None => {
+            let lifetime = Lifetime::new("'__pb", Span::call_site());
+            (
+                lifetime.clone(),
+                Some(quote!(<#lifetime>)),
+            )
+        }
+    };
+
+    let mut reads = Vec::new();
+    let mut merges = Vec::new();
+    let mut merge_bounds = Vec::new();
+    let mut sizes = Vec::new();
+    let mut writes = Vec::new();
+    let mut tags = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let field = match &variant.fields {
+            Fields::Unnamed(f) if f.unnamed.len() == 1 => &f.unnamed[0],
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "oneof variants must hold a single unnamed value",
+                ))
+            }
+        };
+        let attr = pb_attr(&variant.attrs, ident.span())?;
+        let number = match attr.tag {
+            Some(number) if attr.label.is_none() && !attr.skip => number,
+            _ => {
+                return Err(syn::Error::new(
+                    attr.span,
+                    "oneof variants only accept a field number and a type",
+                ))
+            }
+        };
+        if tags.contains(&number) {
+            return Err(syn::Error::new(
+                attr.span,
+                format!(
+                    "field number {} is already used",
+                    number
+                ),
+            ));
+        }
+        tags.push(number);
+
+        let value = Value::new(attr.typ, &field.ty)?;
+        let tag = value.tag(number);
+        let read = value.read();
+        reads.push(quote!(#tag => ::core::option::Option::Some(Self::#ident(#read?)),));
+        let replace = quote!(*oneof = ::core::option::Option::Some(Self::#ident(#read?)));
+        merges.push(match &value {
+            Value::Message { ty, boxed } => {
+                merge_bounds.push(quote!(#ty: ::quack_protobuf::MessageMerge<#lifetime>,));
+                let message = match boxed {
+                    true => quote!(&mut **m),
+                    false => quote!(m),
+                };
+                quote! {
+                    #tag => match oneof {
+                        ::core::option::Option::Some(Self::#ident(m)) => r.merge_message(bytes, #message)?,
+                        _ => #replace,
+                    },
+                }
+            }
+            _ => quote!(#tag => #replace,),
+        });
+        let tag_size = sizeof_tag(number);
+        let (arg, size) = value.size_closure();
+        sizes.push(quote!(Self::#ident(#arg) => #tag_size + #size,));
+        let write = value.write(&quote!(v));
+        writes.push(quote!(Self::#ident(v) => w.write_with_tag(#tag, |w| #write),));
+    }
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Reads the variant of field `tag`, `None` if `tag` is not part of the oneof
+            pub fn read_variant #read_lifetime (
+                tag: u32,
+                r: &mut ::quack_protobuf::BytesReader,
+                bytes: &#lifetime [u8],
+            ) -> ::quack_protobuf::Result<::core::option::Option<Self>> {
+                ::core::result::Result::Ok(match tag {
+                    #(#reads)*
+                    _ => ::core::option::Option::None,
+                })
+            }
+
+            /// Reads the variant of field `tag` into `oneof`, `false` if `tag` is not part of the oneof
+            ///
+            /// A message variant already set is merged with the one read, any other value is replaced.
+            pub fn merge_variant #read_lifetime (
+                oneof: &mut ::core::option::Option<Self>,
+                tag: u32,
+                r: &mut ::quack_protobuf::BytesReader,
+                bytes: &#lifetime [u8],
+            ) -> ::quack_protobuf::Result<bool>
+            where
+                #(#merge_bounds)*
+            {
+                match tag {
+                    #(#merges)*
+                    _ => return ::core::result::Result::Ok(false),
+                }
+                ::core::result::Result::Ok(true)
+            }
+
+            /// Computes the binary size of the variant, tag included
+            pub fn variant_size(&self) -> usize {
+                match self {
+                    #(#sizes)*
+                }
+            }
+
+            /// Writes the variant, tag included
+            pub fn write_variant<W: ::quack_protobuf::WriterBackend>(
+                &self,
+                w: &mut ::quack_protobuf::Writer<W>,
+            ) -> ::quack_protobuf::Result<()> {
+                match self {
+                    #(#writes)*
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,161 @@
+//! A module to frame protobuf messages in `tokio_util::codec`
+//!
+//! `ProtobufCodec` turns a byte stream into a stream of varint length
+//! delimited messages, the framing of `Writer::write_message_with_len_prefix`
+//! and `BytesReader::read_message`, to be used with `Framed`, `FramedRead` or
+//! `FramedWrite`.
+
+use std::marker::PhantomData;
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::errors::{Error, Result};
+use crate::message::{MessageRead, MessageWrite};
+use crate::reader::{BytesReader, DecodeLimits};
+use crate::sizeofs::sizeof_varint;
+use crate::writer::{BufMutWriter, Writer};
+
+/// Default maximum size of a frame, length prefix excluded: 8 MiB
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+
+/// A codec of varint length delimited messages of type `M`
+///
+/// Decoding waits for the whole frame to be buffered, then yields an owned
+/// message. Frames larger than the maximum frame size fail with
+/// `Error::MessageTooLarge` as soon as their length prefix is read, before
+/// anything is buffered for them, and so do messages too large to be encoded.
+///
+/// # Examples
+///
+/// ```rust
+/// use bytes::BytesMut;
+/// use quack_protobuf::{BytesReader, MessageRead, ProtobufCodec};
+/// use tokio_util::codec::Decoder;
+/// # #[derive(Debug, PartialEq)]
+/// # struct Ping(u64);
+/// # impl<'a> MessageRead<'a> for Ping {
+/// #     fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> quack_protobuf::Result<Self> {
+/// #         r.next_tag(bytes)?;
+/// #         Ok(Ping(r.read_uint64(bytes)?))
+/// #     }
+/// # }
+///
+/// let mut codec = ProtobufCodec::<Ping>::new();
+/// // a frame of 3 bytes: field 1 = 150, received in two parts
+/// let mut buf = BytesMut::from(&[0x03, 0x08][..]);
+/// assert_eq!(codec.decode(&mut buf).unwrap(), None);
+/// buf.extend_from_slice(&[0x96, 0x01]);
+/// assert_eq!(codec.decode(&mut buf).unwrap(), Some(Ping(150)));
+/// assert!(buf.is_empty());
+/// ```
+#[derive(Debug)]
+pub struct ProtobufCodec<M> {
+    limits: DecodeLimits,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<M> ProtobufCodec<M> {
+    /// Creates a new codec, with a maximum frame size of `DEFAULT_MAX_FRAME_SIZE`
+    pub fn new() -> Self {
+        ProtobufCodec::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    /// Creates a new codec accepting frames of up to `max_frame_size` bytes, length prefix excluded
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        ProtobufCodec::with_limits(DecodeLimits {
+            max_message_size: max_frame_size,
+            ..DecodeLimits::default()
+        })
+    }
+
+    /// Creates a new codec decoding messages within `limits`
+    ///
+    /// `limits.max_message_size` is the maximum frame size.
+    pub fn with_limits(limits: DecodeLimits) -> Self {
+        ProtobufCodec {
+            limits,
+            _message: PhantomData,
+        }
+    }
+
+    /// Gets the maximum size of a frame, length prefix excluded
+    pub fn max_frame_size(&self) -> usize {
+        self.limits.max_message_size
+    }
+}
+
+impl<M> Default for ProtobufCodec<M> {
+    fn default() -> Self {
+        ProtobufCodec::new()
+    }
+}
+
+impl<M> Clone for ProtobufCodec<M> {
+    fn clone(&self) -> Self {
+        ProtobufCodec::with_limits(self.limits)
+    }
+}
+
+/// Reads the varint length prefix at the start of `src`, without consuming it
+///
+/// Returns the length and the size of the prefix, `None` if the prefix is incomplete
+fn peek_len(src: &[u8]) -> Result<Option<(u64, usize)>> {
+    let mut len = 0;
+    for (i, b) in src.iter().take(10).enumerate() {
+        len |= ((b & 0x7f) as u64) << (7 * i);
+        if *b < 0x80 {
+            return Ok(Some((len, i + 1)));
+        }
+    }
+    if src.len() >= 10 {
+        return Err(Error::Varint);
+    }
+    Ok(None)
+}
+
+impl<M> Decoder for ProtobufCodec<M>
+where
+    M: for<'a> MessageRead<'a>,
+{
+    type Item = M;
+    type Error = Error;
+
+    fn decode(
+        &mut self,
+        src: &mut BytesMut,
+    ) -> Result<Option<M>> {
+        let (len, prefix) = match peek_len(src)? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        let len = usize::try_from(len).map_err(|_| Error::Varint)?;
+        self.limits.check_message_size(len)?;
+        if src.len() < prefix + len {
+            src.reserve(prefix + len - src.len());
+            return Ok(None);
+        }
+        src.advance(prefix);
+        // a shared frame lets `read_shared_bytes` avoid copies
+        let frame = src.split_to(len).freeze();
+        let mut reader = BytesReader::from_shared_with_limits(&frame, self.limits)?;
+        M::from_reader(&mut reader, &frame).map(Some)
+    }
+}
+
+impl<M: MessageWrite> Encoder<M> for ProtobufCodec<M> {
+    type Error = Error;
+
+    fn encode(
+        &mut self,
+        item: M,
+        dst: &mut BytesMut,
+    ) -> Result<()> {
+        let len = item.get_size();
+        self.limits.check_message_size(len)?;
+        dst.reserve(sizeof_varint(len as u64) + len);
+        let mut writer = Writer::new(BufMutWriter::new(dst));
+        writer.write_varint(len as u64)?;
+        item.write_message(&mut writer)
+    }
+}
@@ -0,0 +1,1328 @@
+//! A module to read and write messages whose schema is only known at runtime
+//!
+//! Message types are described by `MessageDescriptor`s, gathered into a
+//! `DescriptorPool` where message fields refer to other types by name. A
+//! `DynamicMessage` keeps the values of its fields by field number, typed
+//! by the descriptor of its message, and implements `MessageMerge` and
+//! `MessageWrite`: it is decoded with `decode_into` and encoded with a
+//! `Writer`, as generated messages are.
+//!
+//! Fields not in the descriptor, groups included, are kept as unknown fields
+//! and written back. Singular fields are written when they are set, whatever
+//! their value.
+//!
+//! ```rust
+//! use quack_protobuf::decode_into;
+//! use quack_protobuf::dynamic::*;
+//!
+//! let mut point = MessageDescriptor::new("Point");
+//! point
+//!     .add_field(FieldDescriptor::new("x", 1, FieldType::Sint32))
+//!     .add_field(FieldDescriptor::new("y", 2, FieldType::Sint32));
+//! let mut path = MessageDescriptor::new("Path");
+//! path.add_field(FieldDescriptor::new("name", 1, FieldType::String))
+//!     .add_field(FieldDescriptor::repeated("points", 2, FieldType::Message("Point".into())));
+//! let pool = DescriptorPool::new([point, path]).unwrap();
+//!
+//! let mut p = DynamicMessage::new(&pool, "Point").unwrap();
+//! p.set_by_name("x", Value::I32(-1)).unwrap();
+//! let mut msg = DynamicMessage::new(&pool, "Path").unwrap();
+//! msg.set(1, Value::String("line".into())).unwrap();
+//! msg.set(2, Value::List(vec![Value::Message(p)])).unwrap();
+//! assert!(msg.set(1, Value::I32(1)).is_err());
+//!
+//! let bytes = quack_protobuf::encode_to_vec(&msg).unwrap();
+//! let mut back = DynamicMessage::new(&pool, "Path").unwrap();
+//! decode_into(&mut back, &bytes).unwrap();
+//! assert_eq!(back, msg);
+//! assert_eq!(back.get_by_name("name"), Some(&Value::String("line".into())));
+//! ```
+
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+// targets without atomic pointers share pools with `Rc`, they are not `Send` there
+#[cfg(not(target_has_atomic = "ptr"))]
+use alloc::rc::Rc as Arc;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+
+use crate::errors::{Error, Result};
+use crate::message::{MessageMerge, MessageWrite};
+use crate::reader::{BytesReader, WIRE_TYPE_FIXED32, WIRE_TYPE_FIXED64, WIRE_TYPE_LENGTH_DELIMITED, WIRE_TYPE_VARINT};
+use crate::sizeofs::*;
+use crate::wire::UnknownFields;
+use crate::writer::{Writer, WriterBackend};
+
+/// Largest field number allowed by protobuf
+const MAX_FIELD_NUMBER: u32 = (1 << 29) - 1;
+
+/// The protobuf type of the values of a field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    /// `double`, as `Value::F64`
+    Double,
+    /// `float`, as `Value::F32`
+    Float,
+    /// `int32`, as `Value::I32`
+    Int32,
+    /// `int64`, as `Value::I64`
+    Int64,
+    /// `uint32`, as `Value::U32`
+    Uint32,
+    /// `uint64`, as `Value::U64`
+    Uint64,
+    /// `sint32`, as `Value::I32`
+    Sint32,
+    /// `sint64`, as `Value::I64`
+    Sint64,
+    /// `fixed32`, as `Value::U32`
+    Fixed32,
+    /// `fixed64`, as `Value::U64`
+    Fixed64,
+    /// `sfixed32`, as `Value::I32`
+    Sfixed32,
+    /// `sfixed64`, as `Value::I64`
+    Sfixed64,
+    /// `bool`, as `Value::Bool`
+    Bool,
+    /// `string`, as `Value::String`
+    String,
+    /// `bytes`, as `Value::Bytes`
+    Bytes,
+    /// An enum, as the `i32` of `Value::Enum`
+    Enum,
+    /// A message of the pool, by its name, as `Value::Message`
+    Message(String),
+}
+
+impl FieldType {
+    /// The wire type of a single value
+    fn wire_type(&self) -> u8 {
+        match self {
+            FieldType::Fixed64 | FieldType::Sfixed64 | FieldType::Double => WIRE_TYPE_FIXED64,
+            FieldType::Fixed32 | FieldType::Sfixed32 | FieldType::Float => WIRE_TYPE_FIXED32,
+            FieldType::String | FieldType::Bytes | FieldType::Message(_) => WIRE_TYPE_LENGTH_DELIMITED,
+            _ => WIRE_TYPE_VARINT,
+        }
+    }
+
+    /// Whether repeated values of this type can be packed
+    fn is_packable(&self) -> bool {
+        self.wire_type() != WIRE_TYPE_LENGTH_DELIMITED
+    }
+
+    /// Whether this type can be the key of a map
+    fn is_map_key(&self) -> bool {
+        !matches!(
+            self,
+            FieldType::Double | FieldType::Float | FieldType::Bytes | FieldType::Enum | FieldType::Message(_)
+        )
+    }
+}
+
+/// How many values a field holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldLabel {
+    /// A single value
+    Singular,
+    /// A `Value::List`, written packed when `packed` and the values are numeric
+    Repeated {
+        /// Whether the values are written packed
+        packed: bool,
+    },
+    /// A `Value::Map` with keys of the given type, the values being of the type of the field
+    Map(FieldType),
+}
+
+/// The description of a field of a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDescriptor {
+    name: String,
+    number: u32,
+    field_type: FieldType,
+    label: FieldLabel,
+    /// index of the message type in the pool, once resolved
+    message: usize,
+}
+
+impl FieldDescriptor {
+    /// Creates a singular field
+    pub fn new<S: Into<String>>(
+        name: S,
+        number: u32,
+        field_type: FieldType,
+    ) -> Self {
+        FieldDescriptor {
+            name: name.into(),
+            number,
+            field_type,
+            label: FieldLabel::Singular,
+            message: 0,
+        }
+    }
+
+    /// Creates a repeated field, packed if its values are numeric as in proto3
+    pub fn repeated<S: Into<String>>(
+        name: S,
+        number: u32,
+        field_type: FieldType,
+    ) -> Self {
+        let packed = field_type.is_packable();
+        FieldDescriptor {
+            label: FieldLabel::Repeated { packed },
+            ..FieldDescriptor::new(name, number, field_type)
+        }
+    }
+
+    /// Creates a map field
+    pub fn map<S: Into<String>>(
+        name: S,
+        number: u32,
+        key: FieldType,
+        value: FieldType,
+    ) -> Self {
+        FieldDescriptor {
+            label: FieldLabel::Map(key),
+            ..FieldDescriptor::new(name, number, value)
+        }
+    }
+
+    /// Sets whether the numeric values of a repeated field are written packed
+    pub fn set_packed(
+        &mut self,
+        packed: bool,
+    ) -> &mut Self {
+        if let FieldLabel::Repeated { packed: p } = &mut self.label {
+            *p = packed;
+        }
+        self
+    }
+
+    /// Gets the name of the field
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the field number
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// Gets the type of the values of the field, the map values for maps
+    pub fn field_type(&self) -> &FieldType {
+        &self.field_type
+    }
+
+    /// Gets how many values the field holds
+    pub fn label(&self) -> &FieldLabel {
+        &self.label
+    }
+
+    /// The tag of a single value
+    fn tag(&self) -> u32 {
+        self.number << 3 | self.field_type.wire_type() as u32
+    }
+
+    /// The tag of a packed field or of a map entry
+    fn len_tag(&self) -> u32 {
+        self.number << 3 | WIRE_TYPE_LENGTH_DELIMITED as u32
+    }
+}
+
+/// The description of a message type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageDescriptor {
+    name: String,
+    fields: Vec<FieldDescriptor>,
+}
+
+impl MessageDescriptor {
+    /// Creates a message type without any field
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        MessageDescriptor {
+            name: name.into(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Adds a field
+    pub fn add_field(
+        &mut self,
+        field: FieldDescriptor,
+    ) -> &mut Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Gets the name of the message type
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the fields, sorted by number once in a pool
+    pub fn fields(&self) -> &[FieldDescriptor] {
+        &self.fields
+    }
+
+    /// Gets the field of number `number`
+    pub fn get_field(
+        &self,
+        number: u32,
+    ) -> Option<&FieldDescriptor> {
+        self.fields.iter().find(|f| f.number == number)
+    }
+
+    /// Gets the field named `name`
+    pub fn get_field_by_name(
+        &self,
+        name: &str,
+    ) -> Option<&FieldDescriptor> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Same as `get_field` for the fields of a pool, sorted by number
+    fn field(
+        &self,
+        number: u32,
+    ) -> Option<&FieldDescriptor> {
+        self.fields
+            .binary_search_by_key(&number, |f| f.number)
+            .ok()
+            .map(|i| &self.fields[i])
+    }
+}
+
+#[derive(Debug)]
+struct Pool {
+    messages: Vec<MessageDescriptor>,
+    names: BTreeMap<String, usize>,
+}
+
+/// A set of message types, whose message fields refer to each other by name
+///
+/// Cloning a pool is cheap, its types are shared.
+#[derive(Debug, Clone)]
+pub struct DescriptorPool {
+    inner: Arc<Pool>,
+}
+
+impl DescriptorPool {
+    /// Creates a pool out of message types, checking that they are consistent
+    ///
+    /// Fails with `Error::InvalidDescriptor` if a name or a field number is
+    /// used twice, a field number is out of range, a message type is not in
+    /// the pool or a map key is not of an integer, `bool` or `string` type.
+    pub fn new<I: IntoIterator<Item = MessageDescriptor>>(messages: I) -> Result<Self> {
+        let mut messages = messages.into_iter().collect::<Vec<_>>();
+        let mut names = BTreeMap::new();
+        for (i, m) in messages.iter().enumerate() {
+            if names.insert(m.name.clone(), i).is_some() {
+                return Err(invalid(format!(
+                    "message {} is defined twice",
+                    m.name
+                )));
+            }
+        }
+        for m in &mut messages {
+            m.fields.sort_by_key(|f| f.number);
+            for i in 0..m.fields.len() {
+                let (before, after) = m.fields.split_at_mut(i);
+                let f = &mut after[0];
+                let at = || {
+                    format!(
+                        "field {} of {}",
+                        f.name, m.name
+                    )
+                };
+                if f.number == 0 || f.number > MAX_FIELD_NUMBER {
+                    return Err(invalid(format!(
+                        "{} has an invalid number {}",
+                        at(),
+                        f.number
+                    )));
+                }
+                if before
+                    .last()
+                    .is_some_and(|p| p.number == f.number)
+                {
+                    return Err(invalid(format!(
+                        "{} reuses number {}",
+                        at(),
+                        f.number
+                    )));
+                }
+                if before.iter().any(|p| p.name == f.name) {
+                    return Err(invalid(format!(
+                        "{} is defined twice",
+                        at()
+                    )));
+                }
+                if let FieldLabel::Map(key) = &f.label {
+                    if !key.is_map_key() {
+                        return Err(invalid(format!(
+                            "{} has an invalid key type",
+                            at()
+                        )));
+                    }
+                }
+                if let FieldType::Message(name) = &f.field_type {
+                    f.message = *names.get(name).ok_or_else(|| {
+                        invalid(format!(
+                            "{} has an unknown type {}",
+                            at(),
+                            name
+                        ))
+                    })?;
+                }
+            }
+        }
+        Ok(DescriptorPool {
+            inner: Arc::new(Pool { messages, names }),
+        })
+    }
+
+    /// Creates a pool out of the messages of an encoded `google.protobuf.FileDescriptorSet`
+    ///
+    /// Such sets are written by `protoc --descriptor_set_out`, or by the
+    /// `descriptor_set` method of the generator of quack-protobuf-codegen,
+    /// straight from .proto files. Messages are named after their full name
+    /// without the leading dot, e.g. `foo.Bar.Baz`. Map fields get the key
+    /// and value types of their entry message, which is left out of the pool.
+    /// Repeated numeric fields are packed as declared, or by default in
+    /// proto3. Group fields are left out too, their values being kept as
+    /// unknown fields.
+    ///
+    /// Fails with `Error::InvalidDescriptor` as `new` does, or if a field
+    /// type is unknown.
+    pub fn from_file_descriptor_set(bytes: &[u8]) -> Result<Self> {
+        let mut messages = Vec::new();
+        read_fields(
+            bytes,
+            |r, bytes, tag| match tag {
+                10 => read_file(
+                    r.read_bytes(bytes)?,
+                    &mut messages,
+                ),
+                t => r.read_unknown(bytes, t),
+            },
+        )?;
+
+        let entries = messages
+            .iter()
+            .filter(|m| m.map_entry)
+            .map(|m| (m.name.as_str(), m))
+            .collect::<BTreeMap<_, _>>();
+        let mut descriptors = Vec::new();
+        for m in messages.iter().filter(|m| !m.map_entry) {
+            let mut descriptor = MessageDescriptor::new(m.name.clone());
+            for f in &m.fields {
+                if f.typ == TYPE_GROUP {
+                    continue;
+                }
+                let entry = entries
+                    .get(f.type_name.trim_start_matches('.'))
+                    .filter(|_| f.typ == TYPE_MESSAGE && f.label == LABEL_REPEATED);
+                let field = match entry {
+                    Some(entry) => {
+                        let entry_field = |number| {
+                            entry
+                                .fields
+                                .iter()
+                                .find(|f| f.number == number)
+                                .and_then(|f| f.field_type().ok())
+                                .ok_or_else(|| {
+                                    invalid(format!(
+                                        "map entry {} has no valid key or value",
+                                        entry.name
+                                    ))
+                                })
+                        };
+                        FieldDescriptor::map(
+                            f.name.clone(),
+                            f.number,
+                            entry_field(1)?,
+                            entry_field(2)?,
+                        )
+                    }
+                    None => {
+                        let field_type = f.field_type().map_err(|typ| {
+                            invalid(format!(
+                                "field {} of {} has an unknown type {}",
+                                f.name, m.name, typ
+                            ))
+                        })?;
+                        if f.label == LABEL_REPEATED {
+                            let mut field = FieldDescriptor::repeated(
+                                f.name.clone(),
+                                f.number,
+                                field_type,
+                            );
+                            let packed = f.packed.unwrap_or(m.proto3) && field.field_type.is_packable();
+                            field.set_packed(packed);
+                            field
+                        } else {
+                            FieldDescriptor::new(
+                                f.name.clone(),
+                                f.number,
+                                field_type,
+                            )
+                        }
+                    }
+                };
+                descriptor.add_field(field);
+            }
+            descriptors.push(descriptor);
+        }
+        DescriptorPool::new(descriptors)
+    }
+
+    /// Gets the message types
+    pub fn messages(&self) -> &[MessageDescriptor] {
+        &self.inner.messages
+    }
+
+    /// Gets the message type named `name`
+    pub fn get_message(
+        &self,
+        name: &str,
+    ) -> Option<&MessageDescriptor> {
+        self.inner
+            .names
+            .get(name)
+            .map(|i| &self.inner.messages[*i])
+    }
+
+    fn message(
+        &self,
+        index: usize,
+    ) -> &MessageDescriptor {
+        &self.inner.messages[index]
+    }
+
+    /// Checks that `value` can be the value of `field`
+    fn accepts(
+        &self,
+        field: &FieldDescriptor,
+        value: &Value,
+    ) -> bool {
+        let accepts = |v| self.accepts_value(field, v);
+        match (&field.label, value) {
+            (FieldLabel::Singular, v) => accepts(v),
+            (FieldLabel::Repeated { .. }, Value::List(values)) => values.iter().all(accepts),
+            (FieldLabel::Map(key), Value::Map(entries)) => entries
+                .iter()
+                .all(|(k, v)| k.is_of(key) && accepts(v)),
+            _ => false,
+        }
+    }
+
+    /// Checks that `value` is a single value of the type of `field`
+    fn accepts_value(
+        &self,
+        field: &FieldDescriptor,
+        value: &Value,
+    ) -> bool {
+        match (&field.field_type, value) {
+            (FieldType::Message(_), Value::Message(m)) => {
+                m.index == field.message && Arc::ptr_eq(&m.pool.inner, &self.inner)
+            }
+            (FieldType::Double, Value::F64(_))
+            | (FieldType::Float, Value::F32(_))
+            | (FieldType::Int32 | FieldType::Sint32 | FieldType::Sfixed32, Value::I32(_))
+            | (FieldType::Int64 | FieldType::Sint64 | FieldType::Sfixed64, Value::I64(_))
+            | (FieldType::Uint32 | FieldType::Fixed32, Value::U32(_))
+            | (FieldType::Uint64 | FieldType::Fixed64, Value::U64(_))
+            | (FieldType::Bool, Value::Bool(_))
+            | (FieldType::String, Value::String(_))
+            | (FieldType::Bytes, Value::Bytes(_))
+            | (FieldType::Enum, Value::Enum(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidDescriptor(reason)
+}
+
+const LABEL_REPEATED: i32 = 3;
+const TYPE_GROUP: i32 = 10;
+const TYPE_MESSAGE: i32 = 11;
+
+/// A `DescriptorProto`, its nested types being read separately
+struct RawMessage {
+    /// full name, without the leading dot
+    name: String,
+    fields: Vec<RawField>,
+    map_entry: bool,
+    proto3: bool,
+}
+
+/// A `FieldDescriptorProto`
+#[derive(Default)]
+struct RawField {
+    name: String,
+    number: u32,
+    label: i32,
+    typ: i32,
+    type_name: String,
+    packed: Option<bool>,
+}
+
+impl RawField {
+    /// The type of the field, or the number of its unknown type
+    fn field_type(&self) -> core::result::Result<FieldType, i32> {
+        Ok(match self.typ {
+            1 => FieldType::Double,
+            2 => FieldType::Float,
+            3 => FieldType::Int64,
+            4 => FieldType::Uint64,
+            5 => FieldType::Int32,
+            6 => FieldType::Fixed64,
+            7 => FieldType::Fixed32,
+            8 => FieldType::Bool,
+            9 => FieldType::String,
+            TYPE_MESSAGE => FieldType::Message(self.type_name.trim_start_matches('.').to_owned()),
+            12 => FieldType::Bytes,
+            13 => FieldType::Uint32,
+            14 => FieldType::Enum,
+            15 => FieldType::Sfixed32,
+            16 => FieldType::Sfixed64,
+            17 => FieldType::Sint32,
+            18 => FieldType::Sint64,
+            t => return Err(t),
+        })
+    }
+}
+
+/// Calls `read` with the tag of every field of the encoded message `bytes`
+fn read_fields<'a>(
+    bytes: &'a [u8],
+    mut read: impl FnMut(&mut BytesReader, &'a [u8], u32) -> Result<()>,
+) -> Result<()> {
+    let mut r = BytesReader::from_bytes(bytes);
+    while !r.is_eof() {
+        let tag = r.next_tag(bytes)?;
+        read(&mut r, bytes, tag)?;
+    }
+    Ok(())
+}
+
+/// Reads the messages of a `FileDescriptorProto`
+fn read_file(
+    bytes: &[u8],
+    messages: &mut Vec<RawMessage>,
+) -> Result<()> {
+    let mut package = "";
+    let mut types = Vec::new();
+    let mut proto3 = false;
+    read_fields(bytes, |r, bytes, tag| {
+        match tag {
+            18 => package = r.read_string(bytes)?,
+            34 => types.push(r.read_bytes(bytes)?),
+            98 => proto3 = r.read_string(bytes)? == "proto3",
+            t => r.read_unknown(bytes, t)?,
+        }
+        Ok(())
+    })?;
+    for t in types {
+        read_message(t, package, proto3, messages)?;
+    }
+    Ok(())
+}
+
+/// Reads a `DescriptorProto` declared in `scope`, and its nested types
+fn read_message(
+    bytes: &[u8],
+    scope: &str,
+    proto3: bool,
+    messages: &mut Vec<RawMessage>,
+) -> Result<()> {
+    let mut message = RawMessage {
+        name: String::new(),
+        fields: Vec::new(),
+        map_entry: false,
+        proto3,
+    };
+    let mut nested = Vec::new();
+    read_fields(bytes, |r, bytes, tag| {
+        match tag {
+            10 => message.name = r.read_string(bytes)?.to_owned(),
+            18 => message.fields.push(read_field(
+                r.read_bytes(bytes)?,
+            )?),
+            26 => nested.push(r.read_bytes(bytes)?),
+            // options { map_entry }
+            58 => read_fields(
+                r.read_bytes(bytes)?,
+                |r, bytes, tag| match tag {
+                    56 => {
+                        message.map_entry = r.read_bool(bytes)?;
+                        Ok(())
+                    }
+                    t => r.read_unknown(bytes, t),
+                },
+            )?,
+            t => r.read_unknown(bytes, t)?,
+        }
+        Ok(())
+    })?;
+    if !scope.is_empty() {
+        message.name = format!("{}.{}", scope, message.name);
+    }
+    let name = message.name.clone();
+    messages.push(message);
+    for n in nested {
+        read_message(n, &name, proto3, messages)?;
+    }
+    Ok(())
+}
+
+/// Reads a `FieldDescriptorProto`
+fn read_field(bytes: &[u8]) -> Result<RawField> {
+    let mut field = RawField::default();
+    read_fields(bytes, |r, bytes, tag| {
+        match tag {
+            10 => field.name = r.read_string(bytes)?.to_owned(),
+            24 => field.number = r.read_int32(bytes)? as u32,
+            32 => field.label = r.read_int32(bytes)?,
+            40 => field.typ = r.read_int32(bytes)?,
+            50 => field.type_name = r.read_string(bytes)?.to_owned(),
+            // options { packed }
+            66 => read_fields(
+                r.read_bytes(bytes)?,
+                |r, bytes, tag| match tag {
+                    16 => {
+                        field.packed = Some(r.read_bool(bytes)?);
+                        Ok(())
+                    }
+                    t => r.read_unknown(bytes, t),
+                },
+            )?,
+            t => r.read_unknown(bytes, t)?,
+        }
+        Ok(())
+    })?;
+    Ok(field)
+}
+
+/// The value of a field of a `DynamicMessage`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// `bool`
+    Bool(bool),
+    /// `int32`, `sint32` or `sfixed32`
+    I32(i32),
+    /// `int64`, `sint64` or `sfixed64`
+    I64(i64),
+    /// `uint32` or `fixed32`
+    U32(u32),
+    /// `uint64` or `fixed64`
+    U64(u64),
+    /// `float`
+    F32(f32),
+    /// `double`
+    F64(f64),
+    /// `string`
+    String(String),
+    /// `bytes`
+    Bytes(Vec<u8>),
+    /// An enum, unknown values included
+    Enum(i32),
+    /// A nested message
+    Message(DynamicMessage),
+    /// The values of a repeated field
+    List(Vec<Value>),
+    /// The entries of a map field
+    Map(BTreeMap<MapKey, Value>),
+}
+
+/// The key of an entry of a map field
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MapKey {
+    /// `bool`
+    Bool(bool),
+    /// `int32`, `sint32` or `sfixed32`
+    I32(i32),
+    /// `int64`, `sint64` or `sfixed64`
+    I64(i64),
+    /// `uint32` or `fixed32`
+    U32(u32),
+    /// `uint64` or `fixed64`
+    U64(u64),
+    /// `string`
+    String(String),
+}
+
+impl MapKey {
+    /// Checks that the key is of type `key`
+    fn is_of(
+        &self,
+        key: &FieldType,
+    ) -> bool {
+        matches!(
+            (key, self),
+            (
+                FieldType::Bool,
+                MapKey::Bool(_)
+            ) | (
+                FieldType::Int32 | FieldType::Sint32 | FieldType::Sfixed32,
+                MapKey::I32(_)
+            ) | (
+                FieldType::Int64 | FieldType::Sint64 | FieldType::Sfixed64,
+                MapKey::I64(_)
+            ) | (
+                FieldType::Uint32 | FieldType::Fixed32,
+                MapKey::U32(_)
+            ) | (
+                FieldType::Uint64 | FieldType::Fixed64,
+                MapKey::U64(_)
+            ) | (
+                FieldType::String,
+                MapKey::String(_)
+            )
+        )
+    }
+
+    /// The default key of type `key`, when an entry has none
+    fn default_of(key: &FieldType) -> MapKey {
+        match key {
+            FieldType::Bool => MapKey::Bool(false),
+            FieldType::Int32 | FieldType::Sint32 | FieldType::Sfixed32 => MapKey::I32(0),
+            FieldType::Int64 | FieldType::Sint64 | FieldType::Sfixed64 => MapKey::I64(0),
+            FieldType::Uint32 | FieldType::Fixed32 => MapKey::U32(0),
+            FieldType::Uint64 | FieldType::Fixed64 => MapKey::U64(0),
+            _ => MapKey::String(String::new()),
+        }
+    }
+
+    fn read(
+        key: &FieldType,
+        r: &mut BytesReader,
+        bytes: &[u8],
+    ) -> Result<MapKey> {
+        Ok(match key {
+            FieldType::Bool => MapKey::Bool(r.read_bool(bytes)?),
+            FieldType::Int32 => MapKey::I32(r.read_int32(bytes)?),
+            FieldType::Sint32 => MapKey::I32(r.read_sint32(bytes)?),
+            FieldType::Sfixed32 => MapKey::I32(r.read_sfixed32(bytes)?),
+            FieldType::Int64 => MapKey::I64(r.read_int64(bytes)?),
+            FieldType::Sint64 => MapKey::I64(r.read_sint64(bytes)?),
+            FieldType::Sfixed64 => MapKey::I64(r.read_sfixed64(bytes)?),
+            FieldType::Uint32 => MapKey::U32(r.read_uint32(bytes)?),
+            FieldType::Fixed32 => MapKey::U32(r.read_fixed32(bytes)?),
+            FieldType::Uint64 => MapKey::U64(r.read_uint64(bytes)?),
+            FieldType::Fixed64 => MapKey::U64(r.read_fixed64(bytes)?),
+            _ => MapKey::String(r.read_string(bytes)?.to_owned()),
+        })
+    }
+
+    fn size(
+        &self,
+        key: &FieldType,
+    ) -> usize {
+        match (key, self) {
+            (FieldType::Int32, MapKey::I32(v)) => sizeof_int32(*v),
+            (FieldType::Sint32, MapKey::I32(v)) => sizeof_sint32(*v),
+            (FieldType::Int64, MapKey::I64(v)) => sizeof_int64(*v),
+            (FieldType::Sint64, MapKey::I64(v)) => sizeof_sint64(*v),
+            (FieldType::Uint32, MapKey::U32(v)) => sizeof_uint32(*v),
+            (FieldType::Uint64, MapKey::U64(v)) => sizeof_uint64(*v),
+            (_, MapKey::String(s)) => sizeof_len(s.len()),
+            (_, MapKey::Bool(_)) => 1,
+            (FieldType::Sfixed32 | FieldType::Fixed32, _) => 4,
+            _ => 8,
+        }
+    }
+
+    fn write<W: WriterBackend>(
+        &self,
+        key: &FieldType,
+        number: u32,
+        w: &mut Writer<W>,
+    ) -> Result<()> {
+        match (key, self) {
+            (FieldType::Bool, MapKey::Bool(v)) => w.write_bool(*v),
+            (FieldType::Int32, MapKey::I32(v)) => w.write_int32(*v),
+            (FieldType::Sint32, MapKey::I32(v)) => w.write_sint32(*v),
+            (FieldType::Sfixed32, MapKey::I32(v)) => w.write_sfixed32(*v),
+            (FieldType::Int64, MapKey::I64(v)) => w.write_int64(*v),
+            (FieldType::Sint64, MapKey::I64(v)) => w.write_sint64(*v),
+            (FieldType::Sfixed64, MapKey::I64(v)) => w.write_sfixed64(*v),
+            (FieldType::Uint32, MapKey::U32(v)) => w.write_uint32(*v),
+            (FieldType::Fixed32, MapKey::U32(v)) => w.write_fixed32(*v),
+            (FieldType::Uint64, MapKey::U64(v)) => w.write_uint64(*v),
+            (FieldType::Fixed64, MapKey::U64(v)) => w.write_fixed64(*v),
+            (FieldType::String, MapKey::String(s)) => w.write_string(s),
+            _ => Err(Error::FieldTypeMismatch(
+                number,
+            )),
+        }
+    }
+}
+
+/// A message of a type described at runtime, keeping its fields by number
+///
+/// Values set are checked against the descriptor of the message, so that
+/// it can always be encoded.
+#[derive(Clone)]
+pub struct DynamicMessage {
+    pool: DescriptorPool,
+    index: usize,
+    fields: BTreeMap<u32, Value>,
+    unknown_fields: UnknownFields<'static>,
+}
+
+impl DynamicMessage {
+    /// Creates an empty message of the type named `name`, `None` if it is not in the pool
+    pub fn new(
+        pool: &DescriptorPool,
+        name: &str,
+    ) -> Option<Self> {
+        let index = *pool.inner.names.get(name)?;
+        Some(DynamicMessage::of(
+            pool, index,
+        ))
+    }
+
+    fn of(
+        pool: &DescriptorPool,
+        index: usize,
+    ) -> Self {
+        DynamicMessage {
+            pool: pool.clone(),
+            index,
+            fields: BTreeMap::new(),
+            unknown_fields: UnknownFields::new(),
+        }
+    }
+
+    /// Gets the descriptor of the type of the message
+    pub fn descriptor(&self) -> &MessageDescriptor {
+        self.pool.message(self.index)
+    }
+
+    /// Gets the pool of the type of the message
+    pub fn pool(&self) -> &DescriptorPool {
+        &self.pool
+    }
+
+    /// Gets the value of field `number`, `None` if it is not set
+    pub fn get(
+        &self,
+        number: u32,
+    ) -> Option<&Value> {
+        self.fields.get(&number)
+    }
+
+    /// Gets the value of the field named `name`, `None` if it is not set
+    pub fn get_by_name(
+        &self,
+        name: &str,
+    ) -> Option<&Value> {
+        let field = self.descriptor().get_field_by_name(name)?;
+        self.fields.get(&field.number)
+    }
+
+    /// Sets the value of field `number`
+    ///
+    /// Fails with `Error::UnknownField` if the message has no such field and
+    /// with `Error::FieldTypeMismatch` if the value is not of its type: a
+    /// `Value::List` for repeated fields, a `Value::Map` for maps.
+    pub fn set(
+        &mut self,
+        number: u32,
+        value: Value,
+    ) -> Result<()> {
+        let field = self
+            .pool
+            .message(self.index)
+            .field(number)
+            .ok_or(Error::UnknownField)?;
+        if !self.pool.accepts(field, &value) {
+            return Err(Error::FieldTypeMismatch(
+                number,
+            ));
+        }
+        self.fields.insert(number, value);
+        Ok(())
+    }
+
+    /// Sets the value of the field named `name`, same as `set`
+    pub fn set_by_name(
+        &mut self,
+        name: &str,
+        value: Value,
+    ) -> Result<()> {
+        let number = self
+            .descriptor()
+            .get_field_by_name(name)
+            .ok_or(Error::UnknownField)?
+            .number;
+        self.set(number, value)
+    }
+
+    /// Removes the value of field `number`, returning it
+    pub fn remove(
+        &mut self,
+        number: u32,
+    ) -> Option<Value> {
+        self.fields.remove(&number)
+    }
+
+    /// Iterates over the fields set, by number
+    pub fn fields(&self) -> impl Iterator<Item = (&FieldDescriptor, &Value)> {
+        let descriptor = self.descriptor();
+        self.fields.iter().filter_map(|(number, value)| {
+            Some((
+                descriptor.field(*number)?,
+                value,
+            ))
+        })
+    }
+
+    /// Gets the fields read which are not in the descriptor
+    pub fn unknown_fields(&self) -> &UnknownFields<'static> {
+        &self.unknown_fields
+    }
+
+    /// Reads a single value of `field`, of type `field_type`
+    fn read_value(
+        &self,
+        field: &FieldDescriptor,
+        field_type: &FieldType,
+        r: &mut BytesReader,
+        bytes: &[u8],
+    ) -> Result<Value> {
+        Ok(match field_type {
+            FieldType::Double => Value::F64(r.read_double(bytes)?),
+            FieldType::Float => Value::F32(r.read_float(bytes)?),
+            FieldType::Int32 => Value::I32(r.read_int32(bytes)?),
+            FieldType::Sint32 => Value::I32(r.read_sint32(bytes)?),
+            FieldType::Sfixed32 => Value::I32(r.read_sfixed32(bytes)?),
+            FieldType::Int64 => Value::I64(r.read_int64(bytes)?),
+            FieldType::Sint64 => Value::I64(r.read_sint64(bytes)?),
+            FieldType::Sfixed64 => Value::I64(r.read_sfixed64(bytes)?),
+            FieldType::Uint32 => Value::U32(r.read_uint32(bytes)?),
+            FieldType::Fixed32 => Value::U32(r.read_fixed32(bytes)?),
+            FieldType::Uint64 => Value::U64(r.read_uint64(bytes)?),
+            FieldType::Fixed64 => Value::U64(r.read_fixed64(bytes)?),
+            FieldType::Bool => Value::Bool(r.read_bool(bytes)?),
+            FieldType::String => Value::String(r.read_string(bytes)?.to_string()),
+            FieldType::Bytes => Value::Bytes(r.read_bytes(bytes)?.to_vec()),
+            FieldType::Enum => Value::Enum(r.read_int32(bytes)?),
+            FieldType::Message(_) => {
+                let mut m = DynamicMessage::of(&self.pool, field.message);
+                r.merge_message(bytes, &mut m)?;
+                Value::Message(m)
+            }
+        })
+    }
+
+    /// The default value of `field`, when a map entry has none
+    fn default_value(
+        &self,
+        field: &FieldDescriptor,
+    ) -> Value {
+        match field.field_type {
+            FieldType::Double => Value::F64(0.),
+            FieldType::Float => Value::F32(0.),
+            FieldType::Int32 | FieldType::Sint32 | FieldType::Sfixed32 => Value::I32(0),
+            FieldType::Int64 | FieldType::Sint64 | FieldType::Sfixed64 => Value::I64(0),
+            FieldType::Uint32 | FieldType::Fixed32 => Value::U32(0),
+            FieldType::Uint64 | FieldType::Fixed64 => Value::U64(0),
+            FieldType::Bool => Value::Bool(false),
+            FieldType::String => Value::String(String::new()),
+            FieldType::Bytes => Value::Bytes(Vec::new()),
+            FieldType::Enum => Value::Enum(0),
+            FieldType::Message(_) => Value::Message(DynamicMessage::of(
+                &self.pool,
+                field.message,
+            )),
+        }
+    }
+
+    /// Reads the value of `field` with tag `tag`, `false` if the wire type does not match the field
+    fn read_field(
+        &mut self,
+        field: &FieldDescriptor,
+        tag: u32,
+        r: &mut BytesReader,
+        bytes: &[u8],
+    ) -> Result<bool> {
+        let wire_type = (tag & 0x7) as u8;
+        let field_type = &field.field_type;
+        match &field.label {
+            FieldLabel::Singular if wire_type == field_type.wire_type() => {
+                // messages read several times are merged
+                if let Some(Value::Message(m)) = self.fields.get_mut(&field.number) {
+                    r.merge_message(bytes, m)?;
+                } else {
+                    let value = self.read_value(field, field_type, r, bytes)?;
+                    self.fields.insert(field.number, value);
+                }
+            }
+            // parsers must accept both packed and unpacked encodings
+            FieldLabel::Repeated { .. } if wire_type == field_type.wire_type() => {
+                let value = self.read_value(field, field_type, r, bytes)?;
+                let values = self.values_mut(field.number);
+                r.check_repeated_len(values.len())?;
+                values.push(value);
+            }
+            FieldLabel::Repeated { .. } if wire_type == WIRE_TYPE_LENGTH_DELIMITED && field_type.is_packable() => {
+                // taken out while reading, the earlier chunks count towards the limit
+                let mut values = core::mem::take(self.values_mut(field.number));
+                let read = r.append_packed(
+                    bytes,
+                    &mut values,
+                    |r, bytes| self.read_value(field, field_type, r, bytes),
+                );
+                *self.values_mut(field.number) = values;
+                read?;
+            }
+            FieldLabel::Map(key) if wire_type == WIRE_TYPE_LENGTH_DELIMITED => {
+                let (k, v) = r.read_map(
+                    bytes,
+                    |r, bytes| MapKey::read(key, r, bytes).map(Some),
+                    |r, bytes| {
+                        self.read_value(field, field_type, r, bytes)
+                            .map(Some)
+                    },
+                )?;
+                let k = k.unwrap_or_else(|| MapKey::default_of(key));
+                let v = v.unwrap_or_else(|| self.default_value(field));
+                let entries = match self
+                    .fields
+                    .entry(field.number)
+                    .or_insert_with(|| Value::Map(BTreeMap::new()))
+                {
+                    Value::Map(entries) => entries,
+                    _ => unreachable!("maps are set as Value::Map"),
+                };
+                r.check_map_len(entries.len())?;
+                entries.insert(k, v);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Gets the values of repeated field `number`
+    fn values_mut(
+        &mut self,
+        number: u32,
+    ) -> &mut Vec<Value> {
+        match self
+            .fields
+            .entry(number)
+            .or_insert_with(|| Value::List(Vec::new()))
+        {
+            Value::List(values) => values,
+            _ => unreachable!("repeated fields are set as Value::List"),
+        }
+    }
+}
+
+/// Computes the size of a single value of type `field_type`
+fn value_size(
+    field_type: &FieldType,
+    value: &Value,
+) -> usize {
+    match (field_type, value) {
+        (FieldType::Int32, Value::I32(v)) => sizeof_int32(*v),
+        (FieldType::Sint32, Value::I32(v)) => sizeof_sint32(*v),
+        (FieldType::Int64, Value::I64(v)) => sizeof_int64(*v),
+        (FieldType::Sint64, Value::I64(v)) => sizeof_sint64(*v),
+        (FieldType::Uint32, Value::U32(v)) => sizeof_uint32(*v),
+        (FieldType::Uint64, Value::U64(v)) => sizeof_uint64(*v),
+        (_, Value::Enum(v)) => sizeof_enum(*v),
+        (_, Value::Bool(_)) => 1,
+        (_, Value::String(s)) => sizeof_len(s.len()),
+        (_, Value::Bytes(b)) => sizeof_len(b.len()),
+        (_, Value::Message(m)) => sizeof_len(m.get_size()),
+        (_, Value::I32(_) | Value::U32(_) | Value::F32(_)) => 4,
+        (_, Value::I64(_) | Value::U64(_) | Value::F64(_)) => 8,
+        (_, Value::List(_) | Value::Map(_)) => 0,
+    }
+}
+
+/// Writes a single value of type `field_type`, of field `number`
+fn write_value<W: WriterBackend>(
+    w: &mut Writer<W>,
+    number: u32,
+    field_type: &FieldType,
+    value: &Value,
+) -> Result<()> {
+    match (field_type, value) {
+        (FieldType::Double, Value::F64(v)) => w.write_double(*v),
+        (FieldType::Float, Value::F32(v)) => w.write_float(*v),
+        (FieldType::Int32, Value::I32(v)) => w.write_int32(*v),
+        (FieldType::Sint32, Value::I32(v)) => w.write_sint32(*v),
+        (FieldType::Sfixed32, Value::I32(v)) => w.write_sfixed32(*v),
+        (FieldType::Int64, Value::I64(v)) => w.write_int64(*v),
+        (FieldType::Sint64, Value::I64(v)) => w.write_sint64(*v),
+        (FieldType::Sfixed64, Value::I64(v)) => w.write_sfixed64(*v),
+        (FieldType::Uint32, Value::U32(v)) => w.write_uint32(*v),
+        (FieldType::Fixed32, Value::U32(v)) => w.write_fixed32(*v),
+        (FieldType::Uint64, Value::U64(v)) => w.write_uint64(*v),
+        (FieldType::Fixed64, Value::U64(v)) => w.write_fixed64(*v),
+        (FieldType::Bool, Value::Bool(v)) => w.write_bool(*v),
+        (FieldType::String, Value::String(s)) => w.write_string(s),
+        (FieldType::Bytes, Value::Bytes(b)) => w.write_bytes(b),
+        (FieldType::Enum, Value::Enum(v)) => w.write_enum(*v),
+        (FieldType::Message(_), Value::Message(m)) => w.write_message_with_len_prefix(m),
+        _ => Err(Error::FieldTypeMismatch(
+            number,
+        )),
+    }
+}
+
+impl<'a> MessageMerge<'a> for DynamicMessage {
+    fn merge_from(
+        &mut self,
+        r: &mut BytesReader,
+        bytes: &'a [u8],
+    ) -> Result<()> {
+        let pool = self.pool.clone();
+        let descriptor = pool.message(self.index);
+        while !r.is_eof() {
+            let tag = r.next_tag(bytes)?;
+            let read = match descriptor.field(tag >> 3) {
+                Some(field) => self.read_field(field, tag, r, bytes)?,
+                None => false,
+            };
+            if !read {
+                let value = r.read_unknown_bytes(bytes, tag)?;
+                self.unknown_fields.push(tag, value.to_vec());
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.fields.clear();
+        self.unknown_fields.clear();
+    }
+}
+
+impl MessageWrite for DynamicMessage {
+    fn get_size(&self) -> usize {
+        let mut size = self.unknown_fields.get_size();
+        for (field, value) in self.fields() {
+            let field_type = &field.field_type;
+            let tag_size = sizeof_varint(field.tag() as u64);
+            size += match (&field.label, value) {
+                (FieldLabel::Repeated { packed: true }, Value::List(values)) if field_type.is_packable() => {
+                    match values.is_empty() {
+                        true => 0,
+                        false => {
+                            tag_size
+                                + sizeof_len(
+                                    values
+                                        .iter()
+                                        .map(|v| value_size(field_type, v))
+                                        .sum(),
+                                )
+                        }
+                    }
+                }
+                (FieldLabel::Repeated { .. }, Value::List(values)) => values
+                    .iter()
+                    .map(|v| tag_size + value_size(field_type, v))
+                    .sum(),
+                (FieldLabel::Map(key), Value::Map(entries)) => entries
+                    .iter()
+                    .map(|(k, v)| tag_size + sizeof_len(2 + k.size(key) + value_size(field_type, v)))
+                    .sum(),
+                (_, v) => tag_size + value_size(field_type, v),
+            };
+        }
+        size
+    }
+
+    fn write_message<W: WriterBackend>(
+        &self,
+        w: &mut Writer<W>,
+    ) -> Result<()> {
+        for (field, value) in self.fields() {
+            let (number, field_type) = (
+                field.number,
+                &field.field_type,
+            );
+            match (&field.label, value) {
+                (FieldLabel::Repeated { packed: true }, Value::List(values)) if field_type.is_packable() => {
+                    w.write_packed_with_tag(
+                        field.len_tag(),
+                        values,
+                        |w, v| write_value(w, number, field_type, v),
+                        &|v| value_size(field_type, v),
+                    )?;
+                }
+                (FieldLabel::Repeated { .. }, Value::List(values)) => {
+                    for v in values {
+                        w.write_with_tag(field.tag(), |w| {
+                            write_value(w, number, field_type, v)
+                        })?;
+                    }
+                }
+                (FieldLabel::Map(key), Value::Map(entries)) => {
+                    for (k, v) in entries {
+                        let size = 2 + k.size(key) + value_size(field_type, v);
+                        w.write_with_tag(field.len_tag(), |w| {
+                            w.write_map(
+                                size,
+                                8 | key.wire_type() as u32,
+                                |w| k.write(key, number, w),
+                                16 | field_type.wire_type() as u32,
+                                |w| write_value(w, number, field_type, v),
+                            )
+                        })?;
+                    }
+                }
+                (_, v) => w.write_with_tag(field.tag(), |w| {
+                    write_value(w, number, field_type, v)
+                })?,
+            }
+        }
+        w.write_unknown(&self.unknown_fields)
+    }
+}
+
+impl PartialEq for DynamicMessage {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        Arc::ptr_eq(
+            &self.pool.inner,
+            &other.pool.inner,
+        ) && self.index == other.index
+            && self.fields == other.fields
+            && self.unknown_fields == other.unknown_fields
+    }
+}
+
+impl core::fmt::Debug for DynamicMessage {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter,
+    ) -> core::fmt::Result {
+        f.debug_struct("DynamicMessage")
+            .field(
+                "name",
+                &self.descriptor().name,
+            )
+            .field("fields", &self.fields)
+            .field(
+                "unknown_fields",
+                &self.unknown_fields,
+            )
+            .finish()
+    }
+}
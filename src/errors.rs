@@ -1,6 +1,31 @@
 //! A module to handle all errors
 
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, vec::Vec};
+
 /// An error enum
+///
+/// # Decoding errors
+///
+/// With the `alloc` feature, errors met while decoding are wrapped in
+/// `Error::Decode` along with their position: a reader fails with
+/// `Error::Decode(DecodeError { kind: Error::Varint, .. })` rather than with a
+/// bare `Error::Varint`. This is a breaking change from previous versions:
+/// patterns such as `Err(Error::Varint)` no longer match decoding errors.
+/// Match on `Error::kind` instead, which sees through the wrapper and works
+/// with or without `alloc`:
+///
+/// ```rust
+/// use quack_protobuf::{BytesReader, Error};
+///
+/// // a varint never ending
+/// let bytes = [0xff; 11];
+/// let mut reader = BytesReader::from_bytes(&bytes);
+/// let err = reader.read_uint64(&bytes).unwrap_err();
+/// assert!(matches!(err.kind(), Error::Varint));
+/// # #[cfg(feature = "alloc")]
+/// assert!(matches!(err, Error::Decode(_)));
+/// ```
 #[derive(Debug)]
 pub enum Error {
     /// Io error
@@ -10,7 +35,7 @@ pub enum Error {
     #[cfg(not(feature = "std"))]
     Io,
     /// Utf8 Error
-    Utf8(::std::str::Utf8Error),
+    Utf8(core::str::Utf8Error),
     /// Deprecated feature (in protocol buffer specification)
     Deprecated(&'static str),
     /// Unknown wire type
@@ -22,14 +47,185 @@ pub enum Error {
     Message(String),
     /// Unexpected map tag
     Map(u8),
+    /// End group tag without a matching start group tag
+    UnexpectedEndGroup(u32),
+    /// Messages, maps or groups are nested deeper than the recursion limit of the reader
+    RecursionLimitExceeded,
+    /// A length prefix exceeds the bytes left in the enclosing message
+    LengthOutOfBounds {
+        /// Position of the first byte after the length prefix
+        offset: usize,
+        /// Length read from the prefix
+        declared: usize,
+        /// Bytes left in the enclosing message
+        available: usize,
+    },
+    /// The message is larger than `DecodeLimits::max_message_size`
+    MessageTooLarge {
+        /// Size of the message
+        size: usize,
+        /// Maximum size allowed
+        limit: usize,
+    },
+    /// A `string` or `bytes` value is longer than `DecodeLimits::max_bytes_len`
+    BytesTooLong {
+        /// Length read from the prefix
+        len: usize,
+        /// Maximum length allowed
+        limit: usize,
+    },
+    /// A repeated field has more elements than `DecodeLimits::max_repeated_len`
+    TooManyElements {
+        /// Maximum number of elements allowed
+        limit: usize,
+    },
+    /// A repeated field has more elements than the storage it is read into can hold
+    CapacityExceeded,
+    /// A map has more entries than `DecodeLimits::max_map_entries`
+    TooManyMapEntries {
+        /// Maximum number of entries allowed
+        limit: usize,
+    },
+    /// A gRPC frame is compressed but no decompressor is available
+    UnsupportedCompression,
+    /// A gRPC-Web trailers frame is not made of `name: value` lines
+    InvalidTrailers,
+    /// A gRPC-Web trailers frame where a message frame was expected
+    UnexpectedTrailers,
     /// Out of data when reading from or writing to a byte buffer
     UnexpectedEndOfBuffer,
     /// The supplied output buffer is not large enough to serialize the message
     OutputBufferTooSmall,
+    /// `WriterBackend::pb_end_len_prefixed` without a matching `pb_begin_len_prefixed`, or the reverse
+    UnbalancedLenPrefix,
+    /// A dynamic message has no field of that name or number
+    UnknownField,
+    /// A value does not match the type of the field of a dynamic message, of the given number
+    FieldTypeMismatch(u32),
+    /// The message types of a descriptor pool are inconsistent
+    #[cfg(feature = "alloc")]
+    InvalidDescriptor(String),
+    /// An error while decoding, with the position of the faulty data
+    ///
+    /// Wraps the errors of the readers, use `Error::kind` to match on them.
+    #[cfg(feature = "alloc")]
+    Decode(Box<DecodeError>),
+}
+
+impl Error {
+    /// The error without its decoding context
+    ///
+    /// The inner error of an `Error::Decode`, `self` for any other error.
+    pub fn kind(&self) -> &Error {
+        match self {
+            #[cfg(feature = "alloc")]
+            Error::Decode(e) => &e.kind,
+            e => e,
+        }
+    }
+}
+
+/// A field on the path to the data which could not be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathSegment {
+    /// Field number
+    pub number: u32,
+    /// Position of the value among the values of the same field, when the
+    /// field occurs several times in its message
+    pub index: Option<usize>,
+}
+
+/// A decoding error, with where it happened
+///
+/// The path lists the fields of the nested messages, maps and groups
+/// containing the faulty data, outermost first, and is displayed as
+/// `3.1[7].2`: field 2 of the 8th value of field 1 of field 3.
+///
+/// Requires the `alloc` feature: without it, errors are returned as is and
+/// only `BytesReader::error_offset` tells where they happened.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct DecodeError {
+    kind: Error,
+    offset: usize,
+    path: Vec<PathSegment>,
+}
+
+#[cfg(feature = "alloc")]
+impl DecodeError {
+    /// Creates a new error at absolute position `offset` of the input bytes
+    pub fn new(
+        kind: Error,
+        offset: usize,
+    ) -> Self {
+        DecodeError {
+            kind,
+            offset,
+            path: Vec::new(),
+        }
+    }
+
+    /// The underlying error
+    pub fn kind(&self) -> &Error {
+        &self.kind
+    }
+
+    /// Absolute position in the input bytes where decoding failed
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Fields containing the faulty data, outermost first
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+
+    /// Adds the field containing all the current path
+    pub(crate) fn push_front(
+        &mut self,
+        segment: PathSegment,
+    ) {
+        self.path.insert(0, segment);
+    }
+}
+
+impl core::fmt::Display for PathSegment {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter,
+    ) -> core::fmt::Result {
+        match self.index {
+            Some(index) => write!(
+                f,
+                "{}[{}]",
+                self.number, index
+            ),
+            None => write!(f, "{}", self.number),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::fmt::Display for DecodeError {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter,
+    ) -> core::fmt::Result {
+        write!(
+            f,
+            "{} at offset {}",
+            self.kind, self.offset
+        )?;
+        for (i, segment) in self.path.iter().enumerate() {
+            let sep = if i == 0 { " in field " } else { "." };
+            write!(f, "{}{}", sep, segment)?;
+        }
+        Ok(())
+    }
 }
 
 /// A wrapper for `Result<T, Error>`
-pub type Result<T> = ::std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 #[cfg(feature = "std")]
 impl From<Error> for std::io::Error {
@@ -40,7 +236,11 @@ impl From<Error> for std::io::Error {
                 std::io::ErrorKind::InvalidData,
                 x,
             ),
-            x => std::io::Error::new(std::io::ErrorKind::Other, x),
+            x @ Error::Decode(_) => std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                x,
+            ),
+            x => std::io::Error::other(x),
         }
     }
 }
@@ -52,8 +252,8 @@ impl From<std::io::Error> for Error {
     }
 }
 
-impl From<::std::str::Utf8Error> for Error {
-    fn from(e: ::std::str::Utf8Error) -> Error {
+impl From<core::str::Utf8Error> for Error {
+    fn from(e: core::str::Utf8Error) -> Error {
         Error::Utf8(e)
     }
 }
@@ -64,6 +264,7 @@ impl std::error::Error for Error {
         match self {
             Error::Io(e) => Some(e),
             Error::Utf8(e) => Some(e),
+            // `Error::Decode` displays its kind, which is not another cause
             _ => None,
         }
     }
@@ -104,8 +305,73 @@ impl core::fmt::Display for Error {
                 "Unexpected map tag: '{}', expecting 1 or 2",
                 tag
             ),
+            Error::UnexpectedEndGroup(number) => write!(
+                f,
+                "Unexpected end group tag for field {}",
+                number
+            ),
+            Error::RecursionLimitExceeded => write!(f, "Recursion limit exceeded"),
+            // the offset is displayed by the `DecodeError` wrapping it
+            Error::LengthOutOfBounds {
+                declared, available, ..
+            } => write!(
+                f,
+                "Length {} exceeds the {} bytes available",
+                declared, available
+            ),
+            Error::MessageTooLarge { size, limit } => write!(
+                f,
+                "Message of {} bytes exceeds the limit of {} bytes",
+                size, limit
+            ),
+            Error::BytesTooLong { len, limit } => write!(
+                f,
+                "Length {} exceeds the limit of {} bytes",
+                len, limit
+            ),
+            Error::TooManyElements { limit } => write!(
+                f,
+                "Repeated field exceeds the limit of {} elements",
+                limit
+            ),
+            Error::CapacityExceeded => write!(
+                f,
+                "Repeated field exceeds the capacity of its storage"
+            ),
+            Error::TooManyMapEntries { limit } => write!(
+                f,
+                "Map exceeds the limit of {} entries",
+                limit
+            ),
+            Error::UnsupportedCompression => write!(
+                f,
+                "Compressed gRPC frame without a decompressor"
+            ),
+            Error::InvalidTrailers => write!(f, "Invalid gRPC-Web trailers"),
+            Error::UnexpectedTrailers => write!(
+                f,
+                "Unexpected gRPC-Web trailers frame"
+            ),
             Error::UnexpectedEndOfBuffer => write!(f, "Unexpected end of buffer"),
             Error::OutputBufferTooSmall => write!(f, "Output buffer too small"),
+            Error::UnbalancedLenPrefix => write!(
+                f,
+                "Length prefixed value ended without being started, or started without being ended"
+            ),
+            Error::UnknownField => write!(f, "Unknown field"),
+            Error::FieldTypeMismatch(number) => write!(
+                f,
+                "Value of the wrong type for field {}",
+                number
+            ),
+            #[cfg(feature = "alloc")]
+            Error::InvalidDescriptor(reason) => write!(
+                f,
+                "Invalid descriptor: {}",
+                reason
+            ),
+            #[cfg(feature = "alloc")]
+            Error::Decode(e) => write!(f, "{}", e),
         }
     }
 }
@@ -0,0 +1,410 @@
+//! A module to frame protobuf messages as gRPC and gRPC-Web do
+//!
+//! On the wire, each message is prefixed with a 5 bytes header: a flags
+//! byte, whose lowest bit tells if the message is compressed, then the length
+//! of the message as a big endian `u32`. gRPC-Web also sends the trailers of
+//! the response as a last frame, flagged with the highest bit, whose content
+//! is made of `name: value` lines.
+//!
+//! - `encode_frame` and `decode_frame` handle single, uncompressed messages
+//! - `FrameDecoder` parses frames out of data received in arbitrary chunks,
+//!   decompressing them with a `Decompressor`
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::errors::{Error, Result};
+use crate::message::{MessageRead, MessageWrite};
+use crate::reader::BytesReader;
+use crate::writer::Writer;
+
+/// Size of the header preceding each frame
+pub const FRAME_HEADER_LEN: usize = 5;
+
+/// Flag of the frames whose content is compressed
+pub const FLAG_COMPRESSED: u8 = 0x01;
+
+/// Flag of the gRPC-Web trailers frame
+pub const FLAG_TRAILERS: u8 = 0x80;
+
+/// Default maximum size of a frame, header excluded: 4 MiB, same as gRPC
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 4 * 1024 * 1024;
+
+/// Appends the header of a frame of `len` bytes to `out`
+fn write_header(
+    flags: u8,
+    len: usize,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let len = u32::try_from(len).map_err(|_| Error::MessageTooLarge {
+        size: len,
+        limit: u32::MAX as usize,
+    })?;
+    out.push(flags);
+    out.extend_from_slice(&len.to_be_bytes());
+    Ok(())
+}
+
+/// Reads the flags and the length of the frame starting `bytes`, `None` if the header is incomplete
+fn read_header(bytes: &[u8]) -> Option<(u8, usize)> {
+    let header = bytes.get(..FRAME_HEADER_LEN)?;
+    let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+    Some((header[0], len as usize))
+}
+
+/// Appends `message` to `out` as an uncompressed frame
+///
+/// # Examples
+///
+/// ```rust
+/// use quack_protobuf::grpc::{decode_frame, encode_frame};
+/// use quack_protobuf::{BytesReader, MessageRead, MessageWrite, Writer, WriterBackend};
+/// # #[derive(Debug, PartialEq)]
+/// # struct Ping(u64);
+/// # impl<'a> MessageRead<'a> for Ping {
+/// #     fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> quack_protobuf::Result<Self> {
+/// #         r.next_tag(bytes)?;
+/// #         Ok(Ping(r.read_uint64(bytes)?))
+/// #     }
+/// # }
+/// # impl MessageWrite for Ping {
+/// #     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> quack_protobuf::Result<()> {
+/// #         w.write_with_tag(8, |w| w.write_uint64(self.0))
+/// #     }
+/// #     fn get_size(&self) -> usize {
+/// #         1 + quack_protobuf::sizeofs::sizeof_varint(self.0)
+/// #     }
+/// # }
+///
+/// let mut out = Vec::new();
+/// encode_frame(&Ping(150), &mut out).unwrap();
+/// encode_frame(&Ping(1), &mut out).unwrap();
+/// assert_eq!(&out[..8], &[0, 0, 0, 0, 3, 0x08, 0x96, 0x01]);
+///
+/// let (first, rest) = decode_frame::<Ping>(&out).unwrap();
+/// let (second, rest) = decode_frame::<Ping>(rest).unwrap();
+/// assert_eq!((first, second), (Ping(150), Ping(1)));
+/// assert!(rest.is_empty());
+/// ```
+pub fn encode_frame<M: MessageWrite>(
+    message: &M,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let len = message.get_size();
+    write_header(0, len, out)?;
+    out.reserve(len);
+    message.write_message(&mut Writer::new(out))
+}
+
+/// Appends `trailers` to `out` as a gRPC-Web trailers frame
+pub fn encode_trailers(
+    trailers: &Trailers,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let len = trailers
+        .iter()
+        .map(|(name, value)| name.len() + value.len() + 4)
+        .sum();
+    write_header(FLAG_TRAILERS, len, out)?;
+    for (name, value) in trailers.iter() {
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(b": ");
+        out.extend_from_slice(value.as_bytes());
+        out.extend_from_slice(b"\r\n");
+    }
+    Ok(())
+}
+
+/// Decodes the uncompressed message frame starting `bytes`, returning it with the bytes left after it
+///
+/// Fails with `Error::UnexpectedEndOfBuffer` if the frame is incomplete, with
+/// `Error::UnsupportedCompression` if it is compressed and with
+/// `Error::UnexpectedTrailers` if it is a gRPC-Web trailers frame: use a
+/// `FrameDecoder` to handle these.
+pub fn decode_frame<'a, M: MessageRead<'a>>(bytes: &'a [u8]) -> Result<(M, &'a [u8])> {
+    let (flags, len) = read_header(bytes).ok_or(Error::UnexpectedEndOfBuffer)?;
+    if flags & (FLAG_COMPRESSED | FLAG_TRAILERS) != 0 {
+        return Err(match flags & FLAG_TRAILERS {
+            0 => Error::UnsupportedCompression,
+            _ => Error::UnexpectedTrailers,
+        });
+    }
+    // a 4 GiB frame does not fit in the address space of 32 bits targets
+    let end = FRAME_HEADER_LEN
+        .checked_add(len)
+        .ok_or(Error::MessageTooLarge {
+            size: len,
+            limit: usize::MAX - FRAME_HEADER_LEN,
+        })?;
+    let frame = bytes
+        .get(FRAME_HEADER_LEN..end)
+        .ok_or(Error::UnexpectedEndOfBuffer)?;
+    let mut reader = BytesReader::from_bytes(frame);
+    let message = M::from_reader(&mut reader, frame)?;
+    Ok((message, &bytes[end..]))
+}
+
+/// The trailers of a gRPC-Web response, as a list of `(name, value)`
+///
+/// Names are lowercase, values are kept as sent: `grpc-message` in
+/// particular is percent-encoded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trailers {
+    entries: Vec<(String, String)>,
+}
+
+impl Trailers {
+    /// Creates empty trailers
+    pub fn new() -> Self {
+        Trailers::default()
+    }
+
+    /// Parses the content of a trailers frame
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let text = core::str::from_utf8(bytes).map_err(|_| Error::InvalidTrailers)?;
+        let mut trailers = Trailers::new();
+        for line in text.split('\n') {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or(Error::InvalidTrailers)?;
+            trailers.insert(name.trim(), value.trim());
+        }
+        Ok(trailers)
+    }
+
+    /// Adds a trailer
+    pub fn insert(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) {
+        self.entries.push((
+            name.to_ascii_lowercase(),
+            value.to_string(),
+        ));
+    }
+
+    /// Gets the value of the first trailer named `name`, case insensitive
+    pub fn get(
+        &self,
+        name: &str,
+    ) -> Option<&str> {
+        self.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Iterates over the trailers, as `(name, value)`
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// Gets the `grpc-status` trailer, the status code of the call
+    pub fn grpc_status(&self) -> Option<u32> {
+        self.get("grpc-status")?.parse().ok()
+    }
+
+    /// Gets the `grpc-message` trailer, the description of the status
+    pub fn grpc_message(&self) -> Option<&str> {
+        self.get("grpc-message")
+    }
+}
+
+/// A frame parsed by a `FrameDecoder`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame<'a> {
+    /// An encoded message, already decompressed
+    Message(&'a [u8]),
+    /// The trailers ending a gRPC-Web response
+    Trailers(Trailers),
+}
+
+/// A hook to decompress the frames flagged as compressed
+///
+/// The algorithm is the one negotiated with the `grpc-encoding` header. Any
+/// `FnMut(&[u8], &mut Vec<u8>, usize) -> Result<()>` closure is a `Decompressor`.
+pub trait Decompressor {
+    /// Decompresses `data` into `out`, which is empty
+    ///
+    /// `out` must not grow past `limit` bytes: decompression should stop and fail
+    /// with `Error::MessageTooLarge` as soon as the output exceeds it, so that a
+    /// small compressed frame cannot expand into an unbounded allocation.
+    fn decompress(
+        &mut self,
+        data: &[u8],
+        out: &mut Vec<u8>,
+        limit: usize,
+    ) -> Result<()>;
+}
+
+impl<F> Decompressor for F
+where
+    F: FnMut(&[u8], &mut Vec<u8>, usize) -> Result<()>,
+{
+    fn decompress(
+        &mut self,
+        data: &[u8],
+        out: &mut Vec<u8>,
+        limit: usize,
+    ) -> Result<()> {
+        self(data, out, limit)
+    }
+}
+
+/// The `Decompressor` of a `FrameDecoder` not expecting compressed frames
+///
+/// It fails with `Error::UnsupportedCompression`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoDecompressor;
+
+impl Decompressor for NoDecompressor {
+    fn decompress(
+        &mut self,
+        _: &[u8],
+        _: &mut Vec<u8>,
+        _: usize,
+    ) -> Result<()> {
+        Err(Error::UnsupportedCompression)
+    }
+}
+
+/// A parser of gRPC frames from data received in chunks of any size
+///
+/// Received data is added with `push` and complete frames are taken out
+/// with `next_frame`. Frames borrow the decoder and must be dropped before
+/// pushing more data or reading the next frame.
+///
+/// # Examples
+///
+/// ```rust
+/// use quack_protobuf::grpc::{encode_trailers, Frame, FrameDecoder, Trailers};
+///
+/// let mut data = vec![0, 0, 0, 0, 3, 0x08, 0x96, 0x01];
+/// let mut trailers = Trailers::new();
+/// trailers.insert("grpc-status", "0");
+/// encode_trailers(&trailers, &mut data).unwrap();
+///
+/// let mut decoder = FrameDecoder::new();
+/// decoder.push(&data[..6]);
+/// assert_eq!(decoder.next_frame().unwrap(), None);
+/// decoder.push(&data[6..]);
+/// assert_eq!(decoder.next_frame().unwrap(), Some(Frame::Message(&[0x08, 0x96, 0x01])));
+/// match decoder.next_frame().unwrap() {
+///     Some(Frame::Trailers(t)) => assert_eq!(t.grpc_status(), Some(0)),
+///     _ => unreachable!(),
+/// }
+/// assert!(decoder.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameDecoder<D = NoDecompressor> {
+    buffer: Vec<u8>,
+    /// start of the data not parsed yet
+    start: usize,
+    decompressed: Vec<u8>,
+    decompressor: D,
+    max_frame_size: usize,
+}
+
+impl FrameDecoder {
+    /// Creates a new decoder, failing on compressed frames
+    pub fn new() -> Self {
+        FrameDecoder::with_decompressor(NoDecompressor)
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        FrameDecoder::new()
+    }
+}
+
+impl<D: Decompressor> FrameDecoder<D> {
+    /// Creates a new decoder, decompressing compressed frames with `decompressor`
+    pub fn with_decompressor(decompressor: D) -> Self {
+        FrameDecoder {
+            buffer: Vec::new(),
+            start: 0,
+            decompressed: Vec::new(),
+            decompressor,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Sets the maximum size of a frame, `DEFAULT_MAX_FRAME_SIZE` by default
+    ///
+    /// Larger frames, before or after decompression, fail with `Error::MessageTooLarge`.
+    /// The limit is handed to the `Decompressor` so it can stop as soon as it is exceeded.
+    pub fn set_max_frame_size(
+        &mut self,
+        max_frame_size: usize,
+    ) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    /// Adds received data
+    pub fn push(
+        &mut self,
+        data: &[u8],
+    ) {
+        if self.start > 0 {
+            self.buffer.drain(..self.start);
+            self.start = 0;
+        }
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Checks if all the received data has been parsed
+    ///
+    /// At the end of the stream, remaining data is a truncated frame.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.buffer.len()
+    }
+
+    /// Takes the next frame out of the received data, `None` if it is not complete yet
+    pub fn next_frame(&mut self) -> Result<Option<Frame<'_>>> {
+        let (flags, len) = match read_header(&self.buffer[self.start..]) {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let too_large = |size| Error::MessageTooLarge {
+            size,
+            limit: self.max_frame_size,
+        };
+        if len > self.max_frame_size {
+            return Err(too_large(len));
+        }
+        let start = self.start + FRAME_HEADER_LEN;
+        if self.buffer.len() - start < len {
+            return Ok(None);
+        }
+        self.start = start + len;
+        let mut data = &self.buffer[start..self.start];
+        if flags & FLAG_COMPRESSED != 0 {
+            self.decompressed.clear();
+            self.decompressor.decompress(
+                data,
+                &mut self.decompressed,
+                self.max_frame_size,
+            )?;
+            // in case the decompressor overlooked the limit
+            if self.decompressed.len() > self.max_frame_size {
+                return Err(too_large(
+                    self.decompressed.len(),
+                ));
+            }
+            data = &self.decompressed;
+        }
+        if flags & FLAG_TRAILERS != 0 {
+            return Trailers::parse(data).map(|t| Some(Frame::Trailers(t)));
+        }
+        Ok(Some(Frame::Message(data)))
+    }
+}
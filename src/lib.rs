@@ -1,24 +1,302 @@
 //! A library to read binary protobuf files
 //!
 //! This reader is developed similarly to a pull reader
+//!
+//! # `no_std`
+//!
+//! Without the default `std` feature, the crate is `no_std`:
+//! - with the `alloc` feature, only the `std::io` based APIs are missing
+//!   (`Reader::from_file`, `MessageWrite::write_file`, the streams ...)
+//! - without it, nothing is allocated: messages are read from a `BytesReader`
+//!   borrowing the input and written with a `BytesWriter` into a fixed size
+//!   buffer. `read_packed`, `PackedFixed::Owned`, `UnknownFields`, `Reader`
+//!   and the path of errors (`DecodeError`) need `alloc`, their offset is
+//!   kept by `BytesReader::error_offset`.
+//!
+//! ```rust
+//! use quack_protobuf::{BytesReader, BytesWriter, Writer};
+//!
+//! let mut buf = [0u8; 16];
+//! let mut writer = Writer::new(BytesWriter::new(&mut buf));
+//! writer.write_with_tag(8, |w| w.write_uint32(150)).unwrap();
+//! writer.write_with_tag(18, |w| w.write_string("hi")).unwrap();
+//!
+//! // field 1: varint 150, field 2: "hi"
+//! let len = writer.into_inner().cursor();
+//! let bytes = &buf[..len];
+//! let mut reader = BytesReader::from_bytes(bytes);
+//! assert_eq!(reader.next_tag(bytes).unwrap(), 8);
+//! assert_eq!(reader.read_uint32(bytes).unwrap(), 150);
+//! assert_eq!(reader.next_tag(bytes).unwrap(), 18);
+//! assert_eq!(reader.read_string(bytes).unwrap(), "hi");
+//! assert!(reader.is_eof());
+//! ```
 
 #![deny(missing_docs)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "tokio-util")]
+pub mod codec;
+#[cfg(feature = "dynamic")]
+pub mod dynamic;
 pub mod errors;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod message;
 pub mod reader;
+#[cfg(feature = "rpc")]
+pub mod rpc;
 pub mod sizeofs;
+pub mod storage;
+#[cfg(feature = "std")]
+pub mod stream;
+pub mod wire;
 pub mod writer;
 
 pub use crate::{
-    errors::{Error, Result},
-    message::{MessageInfo, MessageRead, MessageWrite},
-    reader::{decode, BytesReader, PackedFixed, PackedFixedIntoIter, PackedFixedRefIter},
-    writer::{BytesWriter, Writer, WriterBackend},
+    errors::{Error, PathSegment, Result},
+    message::{CachedSize, MessageInfo, MessageMerge, MessageRead, MessageWrite},
+    reader::{
+        decode, decode_into, decode_length_delimited, BytesReader, DecodeLimits, PackedFixed, PackedFixedIntoIter,
+        PackedFixedRefIter,
+    },
+    storage::{ArrayVec, RepeatedStorage},
+    wire::{FieldIter, WireValue},
+    writer::{encode_length_delimited_to_slice, encode_to_slice, BytesWriter, SizeCounter, Writer, WriterBackend},
 };
 
+#[cfg(feature = "alloc")]
+pub use crate::{
+    errors::DecodeError,
+    reader::Reader,
+    wire::UnknownFields,
+    writer::{encode_length_delimited_to_vec, encode_to_vec, serialize_into_vec, BackPatchWriter},
+};
+
+/// Re-export of the `bytes` crate, used by the generated code
+#[cfg(feature = "bytes")]
+pub use bytes;
+
+#[cfg(feature = "derive")]
+pub use quack_protobuf_derive::{MessageMerge, MessageRead, MessageWrite, Oneof};
+
+#[cfg(feature = "tokio-util")]
+pub use crate::codec::ProtobufCodec;
+#[cfg(feature = "bytes")]
+pub use crate::reader::decode_shared;
 #[cfg(feature = "std")]
-pub use crate::reader::Reader;
-#[cfg(feature = "std")]
-pub use crate::writer::serialize_into_vec;
+pub use crate::stream::DelimitedReader;
+#[cfg(feature = "tokio")]
+pub use crate::stream::{AsyncDelimitedReader, AsyncDelimitedWriter};
+#[cfg(feature = "bytes")]
+pub use crate::writer::BufMutWriter;
+
+/// Reads and writes messages without an allocator, run with `cargo test --no-default-features`
+#[cfg(all(test, not(feature = "alloc")))]
+mod no_alloc_tests {
+    use crate::sizeofs::{sizeof_len, sizeof_varint};
+    use crate::{
+        decode, decode_length_delimited, encode_length_delimited_to_slice, encode_to_slice, ArrayVec, BytesReader,
+        BytesWriter, DecodeLimits, Error, MessageRead, MessageWrite, RepeatedStorage, Result, Writer, WriterBackend,
+    };
+
+    /// `name = 1`, packed `samples = 2`, non packed `flags = 3`
+    #[derive(Debug, Default, PartialEq)]
+    struct Reading<'a> {
+        name: &'a str,
+        samples: ArrayVec<u32, 4>,
+        flags: ArrayVec<bool, 2>,
+    }
+
+    impl<'a> MessageRead<'a> for Reading<'a> {
+        fn from_reader(
+            r: &mut BytesReader,
+            bytes: &'a [u8],
+        ) -> Result<Self> {
+            let mut msg = Reading::default();
+            while !r.is_eof() {
+                match r.next_tag(bytes)? {
+                    10 => msg.name = r.read_string(bytes)?,
+                    18 => {
+                        r.read_packed_into(
+                            bytes,
+                            &mut msg.samples,
+                            |r, b| r.read_uint32(b),
+                        )?;
+                    }
+                    24 => r.read_repeated_into(
+                        bytes,
+                        &mut msg.flags,
+                        |r, b| r.read_bool(b),
+                    )?,
+                    t => r.read_unknown(bytes, t)?,
+                }
+            }
+            Ok(msg)
+        }
+    }
+
+    impl MessageWrite for Reading<'_> {
+        fn write_message<W: WriterBackend>(
+            &self,
+            w: &mut Writer<W>,
+        ) -> Result<()> {
+            w.write_with_tag(10, |w| {
+                w.write_string(self.name)
+            })?;
+            if !self.samples.is_empty() {
+                w.write_with_tag(18, |w| {
+                    w.write_packed(
+                        self.samples.iter(),
+                        |w, v| w.write_uint32(*v),
+                        &|v| sizeof_varint(*v as u64),
+                    )
+                })?;
+            }
+            for f in self.flags.iter() {
+                w.write_with_tag(24, |w| w.write_bool(*f))?;
+            }
+            Ok(())
+        }
+
+        fn get_size(&self) -> usize {
+            let samples = self
+                .samples
+                .iter()
+                .map(|v| sizeof_varint(*v as u64))
+                .sum::<usize>();
+            1 + sizeof_len(self.name.len())
+                + if samples == 0 { 0 } else { 1 + sizeof_len(samples) }
+                + 2 * self.flags.len()
+        }
+    }
+
+    fn reading() -> Reading<'static> {
+        let mut r = Reading {
+            name: "temp",
+            ..Default::default()
+        };
+        for v in [1, 300, 70000] {
+            r.samples.try_push(v).unwrap();
+        }
+        r.flags.try_push(true).unwrap();
+        r.flags.try_push(false).unwrap();
+        r
+    }
+
+    #[test]
+    fn bytes_writer_and_reader() {
+        let mut buf = [0u8; 8];
+        let mut writer = Writer::new(BytesWriter::new(&mut buf));
+        writer
+            .write_with_tag(8, |w| w.write_sint64(-2))
+            .unwrap();
+        writer
+            .write_with_tag(21, |w| w.write_fixed32(7))
+            .unwrap();
+        let w = writer.into_inner();
+        assert_eq!(w.cursor(), 7);
+        assert_eq!(w.remaining(), 1);
+        assert_eq!(
+            buf[..7],
+            [0x08, 0x03, 0x15, 0x07, 0x00, 0x00, 0x00]
+        );
+
+        let bytes = &buf[..7];
+        let mut reader = BytesReader::from_bytes(bytes);
+        assert_eq!(
+            reader.next_tag(bytes).unwrap(),
+            8
+        );
+        assert_eq!(
+            reader.read_sint64(bytes).unwrap(),
+            -2
+        );
+        assert_eq!(
+            reader.next_tag(bytes).unwrap(),
+            21
+        );
+        assert_eq!(
+            reader.read_fixed32(bytes).unwrap(),
+            7
+        );
+        assert!(reader.is_eof());
+        assert!(matches!(
+            reader.next_tag(bytes),
+            Err(Error::UnexpectedEndOfBuffer)
+        ));
+
+        // the buffer is full
+        let mut buf = [0u8; 2];
+        let mut writer = Writer::new(BytesWriter::new(&mut buf));
+        assert!(matches!(
+            writer.write_with_tag(8, |w| w.write_uint32(300)),
+            Err(Error::UnexpectedEndOfBuffer)
+        ));
+    }
+
+    #[test]
+    fn message_roundtrip() {
+        let r = reading();
+        let mut buf = [0u8; 32];
+        let len = encode_to_slice(&r, &mut buf).unwrap();
+        assert_eq!(len, r.get_size());
+        assert_eq!(
+            decode::<Reading>(&buf[..len]).unwrap(),
+            r
+        );
+
+        let len = encode_length_delimited_to_slice(&r, &mut buf).unwrap();
+        assert_eq!(len, sizeof_len(r.get_size()));
+        assert_eq!(
+            decode_length_delimited::<Reading>(&buf[..len]).unwrap(),
+            r
+        );
+
+        let mut small = [0u8; 4];
+        assert!(matches!(
+            encode_to_slice(&r, &mut small),
+            Err(Error::OutputBufferTooSmall)
+        ));
+    }
+
+    #[test]
+    fn array_vec_capacity() {
+        // 5 packed samples do not fit in 4
+        let bytes = [0x12, 0x05, 1, 2, 3, 4, 5];
+        assert!(matches!(
+            decode::<Reading>(&bytes),
+            Err(Error::CapacityExceeded)
+        ));
+        // nor 3 flags in 2
+        let bytes = [0x18, 0x01, 0x18, 0x00, 0x18, 0x01];
+        assert!(matches!(
+            decode::<Reading>(&bytes),
+            Err(Error::CapacityExceeded)
+        ));
+
+        let mut v = ArrayVec::<u8, 1>::new();
+        assert_eq!(v.try_push(1), Ok(()));
+        assert_eq!(v.try_push(2), Err(2));
+        assert_eq!(&v[..], &[1]);
+    }
+
+    #[test]
+    fn limits() {
+        let r = reading();
+        let mut buf = [0u8; 32];
+        let len = encode_to_slice(&r, &mut buf).unwrap();
+        let limits = DecodeLimits {
+            max_bytes_len: 3,
+            ..Default::default()
+        };
+        let mut reader = BytesReader::from_bytes_with_limits(&buf[..len], limits).unwrap();
+        assert!(matches!(
+            Reading::from_reader(&mut reader, &buf[..len]),
+            Err(Error::BytesTooLong { len: 4, limit: 3 })
+        ));
+    }
+}
@@ -9,6 +9,9 @@ use std::io::BufWriter;
 #[cfg(feature = "std")]
 use std::path::Path;
 
+use core::hash::{Hash, Hasher};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::errors::Result;
 use crate::reader::BytesReader;
 use crate::writer::{Writer, WriterBackend};
@@ -28,6 +31,14 @@ pub trait MessageWrite: Sized {
         0
     }
 
+    /// Takes the size cached by the last `get_size`, for messages keeping a `CachedSize`
+    ///
+    /// `Writer::write_message_with_len_prefix` uses it instead of calling
+    /// `get_size` again, so that nested sizes are computed once per encode.
+    fn take_cached_size(&self) -> Option<usize> {
+        None
+    }
+
     /// Writes self into a file
     #[cfg(feature = "std")]
     fn write_file<P: AsRef<Path>>(
@@ -53,8 +64,106 @@ pub trait MessageRead<'a>: Sized {
     ) -> Result<Self>;
 }
 
+/// A trait to decode into an existing message, as protobuf merges concatenated messages
+///
+/// Reading a field sets singular fields, while repeated fields and maps
+/// are extended and nested messages are merged recursively. Along with
+/// `clear`, it allows reusing the allocations of a message across decodes.
+pub trait MessageMerge<'a> {
+    /// Reads the fields in the given bytes into `self`, via the given reader.
+    ///
+    /// Same as `MessageRead::from_reader`, it does NOT read message length first.
+    fn merge_from(
+        &mut self,
+        r: &mut BytesReader,
+        bytes: &'a [u8],
+    ) -> Result<()>;
+
+    /// Resets all the fields to their default value, keeping the allocated capacity of collections
+    fn clear(&mut self);
+}
+
 /// A trait to provide basic information about a given message
 pub trait MessageInfo {
     /// Full message path, in form of Module.Message
     const PATH: &'static str;
 }
+
+/// The size of a message computed by `get_size`, cached until the message is written
+///
+/// Messages opt in by keeping one in a field, set at the end of `get_size`
+/// and taken back in `MessageWrite::take_cached_size`. A `Writer` only uses
+/// the sizes cached by the `get_size` of the message being written, or of
+/// the message enclosing it, which the `encode_*` functions compute first:
+/// the caches left by any other `get_size` are discarded, as the message may
+/// have changed since. A `get_size` must therefore call the `get_size` of
+/// every nested message it writes, as generated code does.
+///
+/// It is ignored by comparisons and not copied by `clone`.
+///
+/// ```rust
+/// use quack_protobuf::CachedSize;
+///
+/// let cache = CachedSize::new();
+/// cache.set(42);
+/// assert_eq!(cache.take(), Some(42));
+/// assert_eq!(cache.take(), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct CachedSize {
+    /// size + 1, 0 if there is no size
+    size: AtomicUsize,
+}
+
+impl CachedSize {
+    /// Creates a new, empty, cache
+    pub const fn new() -> Self {
+        CachedSize {
+            size: AtomicUsize::new(0),
+        }
+    }
+
+    /// Caches `size`
+    pub fn set(
+        &self,
+        size: usize,
+    ) {
+        self.size.store(
+            size.wrapping_add(1),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Takes the cached size, leaving the cache empty
+    pub fn take(&self) -> Option<usize> {
+        // not `swap`, missing on targets without atomic read-modify-write (thumbv6m)
+        let size = self.size.load(Ordering::Relaxed);
+        self.size.store(0, Ordering::Relaxed);
+        size.checked_sub(1)
+    }
+}
+
+impl Clone for CachedSize {
+    fn clone(&self) -> Self {
+        CachedSize::new()
+    }
+}
+
+impl PartialEq for CachedSize {
+    fn eq(
+        &self,
+        _: &Self,
+    ) -> bool {
+        true
+    }
+}
+
+impl Eq for CachedSize {}
+
+impl Hash for CachedSize {
+    fn hash<H: Hasher>(
+        &self,
+        _: &mut H,
+    ) {
+    }
+}
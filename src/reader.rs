@@ -8,38 +8,100 @@
 //!
 //! It is advised, for convenience, to directly work with a `Reader`.
 
-#[cfg(feature = "std")]
-use std::borrow::ToOwned;
 #[cfg(feature = "std")]
 use std::fs::File;
 #[cfg(feature = "std")]
 use std::io::Read;
-use std::iter::FusedIterator;
 #[cfg(feature = "std")]
 use std::path::Path;
 
-use std::convert::TryFrom;
+use core::cell::Cell;
+use core::convert::TryFrom;
+use core::iter::FusedIterator;
+use core::ops::Range;
 
-#[cfg(not(feature = "std"))]
-extern crate alloc;
-#[cfg(not(feature = "std"))]
-use alloc::borrow::Cow;
-#[cfg(not(feature = "std"))]
-use alloc::borrow::ToOwned;
-#[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
 use byteorder_lite::ByteOrder;
 use byteorder_lite::LE;
+#[cfg(feature = "bytes")]
+use bytes::Bytes;
 
+#[cfg(feature = "alloc")]
+use crate::errors::{DecodeError, PathSegment};
 use crate::errors::{Error, Result};
-use crate::message::MessageRead;
+use crate::message::{MessageMerge, MessageRead};
+use crate::storage::RepeatedStorage;
+
+pub(crate) const WIRE_TYPE_VARINT: u8 = 0;
+pub(crate) const WIRE_TYPE_FIXED64: u8 = 1;
+pub(crate) const WIRE_TYPE_LENGTH_DELIMITED: u8 = 2;
+pub(crate) const WIRE_TYPE_START_GROUP: u8 = 3;
+pub(crate) const WIRE_TYPE_END_GROUP: u8 = 4;
+pub(crate) const WIRE_TYPE_FIXED32: u8 = 5;
+
+/// Default maximum nesting depth of messages, maps and groups, same as the reference implementation
+pub const DEFAULT_RECURSION_LIMIT: usize = 100;
 
-const WIRE_TYPE_VARINT: u8 = 0;
-const WIRE_TYPE_FIXED64: u8 = 1;
-const WIRE_TYPE_LENGTH_DELIMITED: u8 = 2;
-const WIRE_TYPE_START_GROUP: u8 = 3;
-const WIRE_TYPE_END_GROUP: u8 = 4;
-const WIRE_TYPE_FIXED32: u8 = 5;
+/// Limits on the data a reader accepts, to decode untrusted input safely
+///
+/// Exceeding a limit fails with a dedicated error before anything is
+/// allocated for the offending data. All limits are disabled by default.
+///
+/// # Examples
+///
+/// ```rust
+/// use quack_protobuf::{BytesReader, DecodeLimits, Error};
+///
+/// let limits = DecodeLimits {
+///     max_bytes_len: 4,
+///     ..DecodeLimits::default()
+/// };
+/// // field 1: "hello"
+/// let bytes = [0x0a, 0x05, b'h', b'e', b'l', b'l', b'o'];
+/// let mut reader = BytesReader::from_bytes_with_limits(&bytes, limits).unwrap();
+/// reader.next_tag(&bytes).unwrap();
+/// let err = reader.read_string(&bytes).unwrap_err();
+/// assert!(matches!(err.kind(), Error::BytesTooLong { len: 5, limit: 4 }));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum size of the whole encoded message
+    pub max_message_size: usize,
+    /// Maximum length of a `string` or `bytes` value
+    pub max_bytes_len: usize,
+    /// Maximum number of elements of a repeated field
+    pub max_repeated_len: usize,
+    /// Maximum number of entries of a map
+    pub max_map_entries: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        DecodeLimits {
+            max_message_size: usize::MAX,
+            max_bytes_len: usize::MAX,
+            max_repeated_len: usize::MAX,
+            max_map_entries: usize::MAX,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// Checks that a message of `size` bytes can be decoded
+    pub fn check_message_size(
+        &self,
+        size: usize,
+    ) -> Result<()> {
+        if size > self.max_message_size {
+            return Err(Error::MessageTooLarge {
+                size,
+                limit: self.max_message_size,
+            });
+        }
+        Ok(())
+    }
+}
 
 /// A struct to read protocol binary files
 ///
@@ -47,7 +109,7 @@ const WIRE_TYPE_FIXED32: u8 = 5;
 ///
 /// ```rust
 /// # mod foo_bar {
-/// #     use quick_protobuf::{MessageRead, BytesReader, Result};
+/// #     use quack_protobuf::{MessageRead, BytesReader, Result};
 /// #     pub struct Foo {}
 /// #     pub struct Bar {}
 /// #     pub struct FooBar { pub foos: Vec<Foo>, pub bars: Vec<Bar>, }
@@ -61,7 +123,7 @@ const WIRE_TYPE_FIXED32: u8 = 5;
 /// // FooBar is a message generated from a proto file
 /// // in parcicular it contains a `from_reader` function
 /// use foo_bar::FooBar;
-/// use quick_protobuf::{BytesReader, MessageRead};
+/// use quack_protobuf::{BytesReader, MessageRead};
 ///
 /// fn main() {
 ///     // bytes is a buffer on the data we want to deserialize
@@ -92,6 +154,18 @@ const WIRE_TYPE_FIXED32: u8 = 5;
 pub struct BytesReader {
     start: usize,
     end: usize,
+    /// nesting levels still allowed below the current one
+    depth: usize,
+    /// last tag read, to locate errors in nested messages
+    tag: u32,
+    /// start of the message being read
+    message_start: usize,
+    limits: DecodeLimits,
+    /// position of the first error, kept without `alloc` too
+    error_offset: Cell<Option<usize>>,
+    /// buffer being read, to share rather than copy in `read_shared_bytes`
+    #[cfg(feature = "bytes")]
+    shared: Option<Bytes>,
 }
 
 impl BytesReader {
@@ -100,16 +174,212 @@ impl BytesReader {
         Self {
             start: 0,
             end: bytes.len(),
+            depth: DEFAULT_RECURSION_LIMIT,
+            tag: 0,
+            message_start: 0,
+            limits: DecodeLimits::default(),
+            error_offset: Cell::new(None),
+            #[cfg(feature = "bytes")]
+            shared: None,
+        }
+    }
+
+    /// Creates a new reader from chunks of data, enforcing `limits`
+    ///
+    /// Fails with `Error::MessageTooLarge` if `bytes` exceeds `limits.max_message_size`.
+    pub fn from_bytes_with_limits(
+        bytes: &[u8],
+        limits: DecodeLimits,
+    ) -> Result<Self> {
+        limits.check_message_size(bytes.len())?;
+        let mut reader = BytesReader::from_bytes(bytes);
+        reader.limits = limits;
+        Ok(reader)
+    }
+
+    /// Creates a new reader from a refcounted buffer
+    ///
+    /// Bytes and strings read with `read_shared_bytes` and
+    /// `read_shared_string` are then slices of `bytes` instead of copies,
+    /// so the decoded messages can outlive the borrow of the buffer.
+    ///
+    /// ```rust
+    /// use bytes::Bytes;
+    /// use quack_protobuf::BytesReader;
+    ///
+    /// let buf = Bytes::from(vec![0x0a, 0x03, b'a', b'b', b'c']);
+    /// let mut reader = BytesReader::from_shared(&buf);
+    /// assert_eq!(reader.next_tag(&buf).unwrap(), 0x0a);
+    /// let abc = reader.read_shared_bytes(&buf).unwrap();
+    /// assert_eq!(abc, &b"abc"[..]);
+    /// assert_eq!(abc.as_ptr(), buf[2..].as_ptr());
+    /// ```
+    #[cfg(feature = "bytes")]
+    pub fn from_shared(bytes: &Bytes) -> Self {
+        let mut reader = BytesReader::from_bytes(bytes);
+        reader.shared = Some(bytes.clone());
+        reader
+    }
+
+    /// Creates a new reader from a refcounted buffer, enforcing `limits`
+    ///
+    /// Fails with `Error::MessageTooLarge` if `bytes` exceeds `limits.max_message_size`.
+    #[cfg(feature = "bytes")]
+    pub fn from_shared_with_limits(
+        bytes: &Bytes,
+        limits: DecodeLimits,
+    ) -> Result<Self> {
+        let mut reader = BytesReader::from_bytes_with_limits(bytes, limits)?;
+        reader.shared = Some(bytes.clone());
+        Ok(reader)
+    }
+
+    /// Gets the limits enforced by this reader
+    pub fn limits(&self) -> &DecodeLimits {
+        &self.limits
+    }
+
+    /// Checks that a repeated field holding `len` elements can take one more
+    ///
+    /// Called by generated code before pushing an element, so that the
+    /// number of elements stays within `DecodeLimits::max_repeated_len`.
+    #[cfg_attr(feature = "std", inline(always))]
+    pub fn check_repeated_len(
+        &self,
+        len: usize,
+    ) -> Result<()> {
+        if len >= self.limits.max_repeated_len {
+            return Err(
+                self.error(Error::TooManyElements {
+                    limit: self.limits.max_repeated_len,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks that a map holding `len` entries can take one more
+    ///
+    /// Called by generated code before inserting an entry, so that the
+    /// number of entries stays within `DecodeLimits::max_map_entries`.
+    #[cfg_attr(feature = "std", inline(always))]
+    pub fn check_map_len(
+        &self,
+        len: usize,
+    ) -> Result<()> {
+        if len >= self.limits.max_map_entries {
+            return Err(
+                self.error(Error::TooManyMapEntries {
+                    limit: self.limits.max_map_entries,
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets the maximum nesting depth of messages, maps and groups, `DEFAULT_RECURSION_LIMIT` by default
+    ///
+    /// Reading deeper nested data fails with `Error::RecursionLimitExceeded`
+    /// instead of risking a stack overflow on untrusted input.
+    pub fn set_recursion_limit(
+        &mut self,
+        limit: usize,
+    ) {
+        self.depth = limit;
+    }
+
+    /// Absolute position in the input bytes of the first error met by this reader
+    ///
+    /// The same as `DecodeError::offset`, and also available without the
+    /// `alloc` feature, where errors are not wrapped in a `DecodeError`.
+    ///
+    /// ```rust
+    /// use quack_protobuf::{BytesReader, Error};
+    ///
+    /// // field 1, then a varint never ending
+    /// let bytes = [0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    /// let mut reader = BytesReader::from_bytes(&bytes);
+    /// assert_eq!(reader.error_offset(), None);
+    /// assert_eq!(reader.next_tag(&bytes).unwrap(), 8);
+    /// let err = reader.read_uint64(&bytes).unwrap_err();
+    /// assert!(matches!(err.kind(), Error::Varint));
+    /// // the reader gives up after the 10 bytes of the longest varint
+    /// assert_eq!(reader.error_offset(), Some(11));
+    /// ```
+    pub fn error_offset(&self) -> Option<usize> {
+        self.error_offset.get()
+    }
+
+    /// Adds the current position to `error`, unless it already has one
+    ///
+    /// Without the `alloc` feature, errors are returned as is, their position
+    /// is only kept in `error_offset`.
+    pub(crate) fn error(
+        &self,
+        error: Error,
+    ) -> Error {
+        if self.error_offset.get().is_none() {
+            self.error_offset.set(Some(self.start));
+        }
+        match error {
+            #[cfg(feature = "alloc")]
+            Error::Decode(_) => error,
+            #[cfg(feature = "alloc")]
+            e => Error::Decode(Box::new(DecodeError::new(
+                e, self.start,
+            ))),
+            #[cfg(not(feature = "alloc"))]
+            e => e,
         }
     }
 
+    /// Runs `read` one nesting level deeper, failing if the recursion limit is reached
+    ///
+    /// `number` is the field being read, added to the path of the errors.
+    #[cfg_attr(feature = "std", inline(always))]
+    fn nested<M, F>(
+        &mut self,
+        bytes: &[u8],
+        number: u32,
+        read: F,
+    ) -> Result<M>
+    where
+        F: FnOnce(&mut BytesReader) -> Result<M>,
+    {
+        let (tag, message_start, message_end, field_start) = (
+            self.tag,
+            self.message_start,
+            self.end,
+            self.start,
+        );
+        let v = if self.depth == 0 {
+            Err(self.error(Error::RecursionLimitExceeded))
+        } else {
+            self.depth -= 1;
+            let v = read(self);
+            self.depth += 1;
+            v
+        };
+        self.tag = tag;
+        v.map_err(|e| {
+            in_field(
+                self.error(e),
+                bytes,
+                number,
+                message_start..message_end,
+                field_start,
+            )
+        })
+    }
+
     /// Reads next tag, `None` if all bytes have been read
     #[cfg_attr(feature = "std", inline(always))]
     pub fn next_tag(
         &mut self,
         bytes: &[u8],
     ) -> Result<u32> {
-        self.read_varint32(bytes)
+        self.tag = self.read_varint32(bytes)?;
+        Ok(self.tag)
     }
 
     /// Reads the next byte
@@ -120,7 +390,8 @@ impl BytesReader {
     ) -> Result<u8> {
         let b = bytes
             .get(self.start)
-            .ok_or(Error::UnexpectedEndOfBuffer)?;
+            .filter(|_| self.start < self.end)
+            .ok_or_else(|| self.error(Error::UnexpectedEndOfBuffer))?;
         self.start += 1;
         Ok(*b)
     }
@@ -213,7 +484,7 @@ impl BytesReader {
         }
 
         // cannot read more than 10 bytes
-        Err(Error::Varint)
+        Err(self.error(Error::Varint))
     }
 
     /// Reads the next varint encoded u64
@@ -301,7 +572,7 @@ impl BytesReader {
         }
 
         // cannot read more than 10 bytes
-        Err(Error::Varint)
+        Err(self.error(Error::Varint))
     }
 
     /// Reads int32 (varint)
@@ -373,7 +644,8 @@ impl BytesReader {
         let v = read(
             bytes
                 .get(self.start..self.start + len)
-                .ok_or(Error::UnexpectedEndOfBuffer)?,
+                .filter(|_| len <= self.len())
+                .ok_or_else(|| self.error(Error::UnexpectedEndOfBuffer))?,
         );
         self.start += len;
         Ok(v)
@@ -465,7 +737,28 @@ impl BytesReader {
         self.read_len(bytes, read, len)
     }
 
+    /// Checks that a chunk of `len` bytes fits in what remains of the current message
+    #[cfg_attr(feature = "std", inline(always))]
+    fn check_len(
+        &self,
+        len: usize,
+    ) -> Result<()> {
+        if len > self.len() {
+            return Err(
+                self.error(Error::LengthOutOfBounds {
+                    offset: self.start,
+                    declared: len,
+                    available: self.len(),
+                }),
+            );
+        }
+        Ok(())
+    }
+
     /// Reads a certain number of bytes specified by len
+    ///
+    /// The bytes must fit in the current message, so that a nested message
+    /// never reads the data of its parent or of its siblings
     #[cfg_attr(feature = "std", inline(always))]
     fn read_len<'a, M, F>(
         &mut self,
@@ -476,12 +769,35 @@ impl BytesReader {
     where
         F: FnMut(&mut BytesReader, &'a [u8]) -> Result<M>,
     {
-        let cur_end = self.end;
+        self.check_len(len)?;
+        let (cur_start, cur_end) = (self.message_start, self.end);
+        self.message_start = self.start;
         self.end = self.start + len;
-        let v = read(self, bytes)?;
-        self.start = self.end;
+        let v = read(self, bytes);
+        if v.is_ok() {
+            self.start = self.end;
+        }
+        self.message_start = cur_start;
         self.end = cur_end;
-        Ok(v)
+        v
+    }
+
+    /// Reads the length of a `string` or `bytes` value, within `DecodeLimits::max_bytes_len`
+    #[cfg_attr(feature = "std", inline(always))]
+    fn read_bytes_len(
+        &mut self,
+        bytes: &[u8],
+    ) -> Result<usize> {
+        let len = self.read_varint32(bytes)? as usize;
+        if len > self.limits.max_bytes_len {
+            return Err(
+                self.error(Error::BytesTooLong {
+                    len,
+                    limit: self.limits.max_bytes_len,
+                }),
+            );
+        }
+        Ok(len)
     }
 
     /// Reads bytes (Vec<u8>)
@@ -490,10 +806,15 @@ impl BytesReader {
         &mut self,
         bytes: &'a [u8],
     ) -> Result<&'a [u8]> {
-        self.read_len_varint(bytes, |r, b| {
-            b.get(r.start..r.end)
-                .ok_or(Error::UnexpectedEndOfBuffer)
-        })
+        let len = self.read_bytes_len(bytes)?;
+        self.read_len(
+            bytes,
+            |r, b| {
+                b.get(r.start..r.end)
+                    .ok_or_else(|| r.error(Error::UnexpectedEndOfBuffer))
+            },
+            len,
+        )
     }
 
     /// Reads string (String)
@@ -502,17 +823,66 @@ impl BytesReader {
         &mut self,
         bytes: &'a [u8],
     ) -> Result<&'a str> {
-        self.read_len_varint(bytes, |r, b| {
-            b.get(r.start..r.end)
-                .ok_or(Error::UnexpectedEndOfBuffer)
-                .and_then(|x| ::std::str::from_utf8(x).map_err(|e| e.into()))
-        })
+        let len = self.read_bytes_len(bytes)?;
+        self.read_len(
+            bytes,
+            |r, b| {
+                b.get(r.start..r.end)
+                    .ok_or_else(|| r.error(Error::UnexpectedEndOfBuffer))
+                    .and_then(|x| core::str::from_utf8(x).map_err(|e| r.error(e.into())))
+            },
+            len,
+        )
+    }
+
+    /// Reads bytes (Bytes), sharing the buffer of `from_shared` instead of copying it
+    ///
+    /// The bytes are copied if the reader was not created with `from_shared`.
+    #[cfg(feature = "bytes")]
+    #[cfg_attr(feature = "std", inline)]
+    pub fn read_shared_bytes(
+        &mut self,
+        bytes: &[u8],
+    ) -> Result<Bytes> {
+        self.read_bytes(bytes).map(|b| self.share(b))
+    }
+
+    /// Reads string (Bytes holding valid UTF-8), sharing the buffer of `from_shared` instead of copying it
+    ///
+    /// The string is copied if the reader was not created with `from_shared`.
+    #[cfg(feature = "bytes")]
+    #[cfg_attr(feature = "std", inline)]
+    pub fn read_shared_string(
+        &mut self,
+        bytes: &[u8],
+    ) -> Result<Bytes> {
+        self.read_string(bytes)
+            .map(|s| self.share(s.as_bytes()))
+    }
+
+    /// Gets `slice` out of the shared buffer, or copies it if it is not part of it
+    #[cfg(feature = "bytes")]
+    fn share(
+        &self,
+        slice: &[u8],
+    ) -> Bytes {
+        match &self.shared {
+            Some(shared)
+                if shared.as_ptr_range().start <= slice.as_ptr_range().start
+                    && slice.as_ptr_range().end <= shared.as_ptr_range().end =>
+            {
+                shared.slice_ref(slice)
+            }
+            _ => Bytes::copy_from_slice(slice),
+        }
     }
 
     /// Reads packed repeated field (Vec<M>)
     ///
     /// Note: packed fields are stored as a variable length chunk of data,
-    /// while regular repeated fields behave like an iterator, yielding their tag everytime
+    /// while regular repeated fields behave like an iterator, yielding their tag everytime.
+    /// `DecodeLimits::max_repeated_len` only applies to this chunk, see `append_packed`.
+    #[cfg(feature = "alloc")]
     #[cfg_attr(feature = "std", inline)]
     pub fn read_packed<'a, M, F>(
         &mut self,
@@ -525,33 +895,152 @@ impl BytesReader {
         self.read_len_varint(bytes, |r, b| {
             let mut v = Vec::new();
             while !r.is_eof() {
+                r.check_repeated_len(v.len())?;
                 v.push(read(r, b)?);
             }
             Ok(v)
         })
     }
 
+    /// Reads packed repeated field, appending its elements to `values`
+    ///
+    /// A field may be split into several packed chunks: `values` holds the
+    /// elements of the previous ones, and all of them count towards
+    /// `DecodeLimits::max_repeated_len`.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "std", inline)]
+    pub fn append_packed<'a, M, F>(
+        &mut self,
+        bytes: &'a [u8],
+        values: &mut Vec<M>,
+        mut read: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut BytesReader, &'a [u8]) -> Result<M>,
+    {
+        self.read_len_varint(bytes, |r, b| {
+            while !r.is_eof() {
+                r.check_repeated_len(values.len())?;
+                values.push(read(r, b)?);
+            }
+            Ok(())
+        })
+    }
+
+    /// Reads packed repeated field into `storage`, returning the number of elements read
+    ///
+    /// Fails with `Error::CapacityExceeded` when `storage` is full before
+    /// the end of the field.
+    ///
+    /// ```rust
+    /// use quack_protobuf::{ArrayVec, BytesReader};
+    ///
+    /// // field 4: packed [3, 270]
+    /// let bytes = [0x22, 0x03, 0x03, 0x8e, 0x02];
+    /// let mut reader = BytesReader::from_bytes(&bytes);
+    /// reader.next_tag(&bytes).unwrap();
+    /// let mut values = ArrayVec::<u32, 4>::new();
+    /// let n = reader.read_packed_into(&bytes, &mut values, |r, b| r.read_uint32(b)).unwrap();
+    /// assert_eq!(n, 2);
+    /// assert_eq!(&values[..], &[3, 270]);
+    /// ```
+    #[cfg_attr(feature = "std", inline)]
+    pub fn read_packed_into<'a, M, S, F>(
+        &mut self,
+        bytes: &'a [u8],
+        storage: &mut S,
+        mut read: F,
+    ) -> Result<usize>
+    where
+        S: RepeatedStorage<M> + ?Sized,
+        F: FnMut(&mut BytesReader, &'a [u8]) -> Result<M>,
+    {
+        self.read_len_varint(bytes, |r, b| {
+            let mut n = 0;
+            while !r.is_eof() {
+                r.check_repeated_len(n)?;
+                let v = read(r, b)?;
+                storage
+                    .try_push(v)
+                    .map_err(|_| r.error(Error::CapacityExceeded))?;
+                n += 1;
+            }
+            Ok(n)
+        })
+    }
+
+    /// Reads an element of a non packed repeated field into `storage`
+    ///
+    /// Each element comes with its own tag, this is called every time the
+    /// tag of the field is read. Fails with `Error::CapacityExceeded` when
+    /// `storage` is full.
+    #[cfg_attr(feature = "std", inline)]
+    pub fn read_repeated_into<'a, M, S, F>(
+        &mut self,
+        bytes: &'a [u8],
+        storage: &mut S,
+        mut read: F,
+    ) -> Result<()>
+    where
+        S: RepeatedStorage<M> + ?Sized,
+        F: FnMut(&mut BytesReader, &'a [u8]) -> Result<M>,
+    {
+        let v = read(self, bytes)?;
+        storage
+            .try_push(v)
+            .map_err(|_| self.error(Error::CapacityExceeded))
+    }
+
     /// Reads packed repeated field where M can directly be transmutted from raw bytes
     ///
     /// Note: packed fields are stored as a variable length chunk of data,
-    /// while regular repeated fields behave like an iterator, yielding their tag everytime
+    /// while regular repeated fields behave like an iterator, yielding their tag everytime.
+    /// `DecodeLimits::max_repeated_len` only applies to this chunk, see `append_packed_fixed`.
     #[cfg_attr(feature = "std", inline)]
     pub fn read_packed_fixed<'a, M: Copy + PartialEq>(
         &mut self,
         bytes: &'a [u8],
-    ) -> Result<PackedFixed<'a, M>>
-    where
-        [M]: ToOwned,
-    {
+    ) -> Result<PackedFixed<'a, M>> {
+        self.packed_fixed(bytes, 0)
+    }
+
+    /// Reads packed repeated field where M can directly be transmutted from raw bytes,
+    /// appending its elements to `values`
+    ///
+    /// The elements of `values`, read from the previous chunks of the field,
+    /// count towards `DecodeLimits::max_repeated_len`.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "std", inline)]
+    pub fn append_packed_fixed<'a, M: Copy + PartialEq>(
+        &mut self,
+        bytes: &'a [u8],
+        values: &mut PackedFixed<'a, M>,
+    ) -> Result<()> {
+        let packed = self.packed_fixed(bytes, values.len())?;
+        values.append(packed);
+        Ok(())
+    }
+
+    /// Reads a packed chunk of fixed size values, following `previous` values
+    fn packed_fixed<'a, M: Copy + PartialEq>(
+        &mut self,
+        bytes: &'a [u8],
+        previous: usize,
+    ) -> Result<PackedFixed<'a, M>> {
         let len = self.read_varint32(bytes)? as usize;
-        if self.len() < len {
-            return Err(Error::UnexpectedEndOfBuffer);
-        }
+        self.check_len(len)?;
 
         // Note the floor divide; we rely on this to guarantee
         // correctness in the rest of this function
-        let n = len / ::std::mem::size_of::<M>();
-        let target = &bytes[self.start..self.start + n * ::std::mem::size_of::<M>()];
+        let n = len / core::mem::size_of::<M>();
+        if previous.saturating_add(n) > self.limits.max_repeated_len {
+            return Err(
+                self.error(Error::TooManyElements {
+                    limit: self.limits.max_repeated_len,
+                }),
+            );
+        }
+        let target = &bytes[self.start..self.start + n * core::mem::size_of::<M>()];
 
         self.start += len;
         Ok(PackedFixed::from(target))
@@ -568,7 +1057,28 @@ impl BytesReader {
     where
         M: MessageRead<'a>,
     {
-        self.read_len_varint(bytes, M::from_reader)
+        self.nested(bytes, self.tag >> 3, |r| {
+            r.read_len_varint(bytes, M::from_reader)
+        })
+    }
+
+    /// Reads a nested message into `message`, merging it with the fields already set
+    ///
+    /// First reads a varint and interprets it as the length of the message
+    #[cfg_attr(feature = "std", inline)]
+    pub fn merge_message<'a, M>(
+        &mut self,
+        bytes: &'a [u8],
+        message: &mut M,
+    ) -> Result<()>
+    where
+        M: MessageMerge<'a>,
+    {
+        self.nested(bytes, self.tag >> 3, |r| {
+            r.read_len_varint(bytes, |r, bytes| {
+                message.merge_from(r, bytes)
+            })
+        })
     }
 
     /// Reads a nested message
@@ -612,27 +1122,30 @@ impl BytesReader {
     where
         F: FnMut(&mut BytesReader, &'a [u8]) -> Result<K>,
         G: FnMut(&mut BytesReader, &'a [u8]) -> Result<V>,
-        K: ::std::fmt::Debug + Default,
-        V: ::std::fmt::Debug + Default,
+        K: core::fmt::Debug + Default,
+        V: core::fmt::Debug + Default,
     {
-        self.read_len_varint(bytes, |r, bytes| {
-            let mut k = K::default();
-            let mut v = V::default();
-            while !r.is_eof() {
-                let t = r.read_u8(bytes)?;
-                match t >> 3 {
-                    1 => {
-                        k = read_key(r, bytes)?;
-                    }
-                    2 => {
-                        v = read_val(r, bytes)?;
-                    }
-                    t => {
-                        return Err(Error::Map(t));
+        self.nested(bytes, self.tag >> 3, |r| {
+            r.read_len_varint(bytes, |r, bytes| {
+                let mut k = K::default();
+                let mut v = V::default();
+                while !r.is_eof() {
+                    let t = r.read_u8(bytes)?;
+                    r.tag = t as u32;
+                    match t >> 3 {
+                        1 => {
+                            k = read_key(r, bytes)?;
+                        }
+                        2 => {
+                            v = read_val(r, bytes)?;
+                        }
+                        t => {
+                            return Err(r.error(Error::Map(t)));
+                        }
                     }
                 }
-            }
-            Ok((k, v))
+                Ok((k, v))
+            })
         })
     }
 
@@ -653,12 +1166,24 @@ impl BytesReader {
             }
             WIRE_TYPE_FIXED64 => 8,
             WIRE_TYPE_FIXED32 => 4,
-            WIRE_TYPE_LENGTH_DELIMITED => usize::try_from(self.read_varint64(bytes)?).map_err(|_| Error::Varint)?,
-            WIRE_TYPE_START_GROUP | WIRE_TYPE_END_GROUP => {
-                return Err(Error::Deprecated("group"));
+            WIRE_TYPE_LENGTH_DELIMITED => {
+                let len = usize::try_from(self.read_varint64(bytes)?).map_err(|_| self.error(Error::Varint))?;
+                self.check_len(len)?;
+                len
+            }
+            WIRE_TYPE_START_GROUP => {
+                self.read_group_bytes(bytes, tag_value)?;
+                return Ok(());
+            }
+            WIRE_TYPE_END_GROUP => {
+                return Err(
+                    self.error(Error::UnexpectedEndGroup(
+                        tag_value >> 3,
+                    )),
+                );
             }
             t => {
-                return Err(Error::UnknownWireType(t));
+                return Err(self.error(Error::UnknownWireType(t)));
             }
         };
 
@@ -666,13 +1191,129 @@ impl BytesReader {
         // since `self.end` is given by `len()`; i.e. `self.end` is 1 more than
         // highest index
         if self.end - self.start < offset {
-            Err(Error::Varint)
+            Err(self.error(Error::UnexpectedEndOfBuffer))
         } else {
             self.start += offset;
             Ok(())
         }
     }
 
+    /// Reads the raw fields of a group, up to its end group tag
+    ///
+    /// `tag_value` is the start group tag, which has just been read. The end
+    /// group tag is consumed but not part of the returned bytes. Nested groups
+    /// are skipped as a whole.
+    pub fn read_group_bytes<'a>(
+        &mut self,
+        bytes: &'a [u8],
+        tag_value: u32,
+    ) -> Result<&'a [u8]> {
+        let start = self.start;
+        let end = self.skip_group(bytes, tag_value >> 3, 1)?;
+        Ok(&bytes[start..end])
+    }
+
+    /// Skips the fields of group `number` and its end group tag, returning where that tag starts
+    ///
+    /// `open` is the number of groups being skipped which do not count towards
+    /// the recursion limit yet, this one included unless it is being read.
+    fn skip_group(
+        &mut self,
+        bytes: &[u8],
+        number: u32,
+        open: usize,
+    ) -> Result<usize> {
+        if open > self.depth {
+            return Err(self.error(Error::RecursionLimitExceeded));
+        }
+        loop {
+            if self.is_eof() {
+                return Err(self.error(Error::UnexpectedEndOfBuffer));
+            }
+            let end = self.start;
+            let tag = self.next_tag(bytes)?;
+            match (tag & 0x7) as u8 {
+                WIRE_TYPE_START_GROUP => {
+                    self.skip_group(bytes, tag >> 3, open + 1)?;
+                }
+                WIRE_TYPE_END_GROUP if tag >> 3 == number => return Ok(end),
+                WIRE_TYPE_END_GROUP => {
+                    return Err(
+                        self.error(Error::UnexpectedEndGroup(
+                            tag >> 3,
+                        )),
+                    )
+                }
+                _ => self.read_unknown(bytes, tag)?,
+            }
+        }
+    }
+
+    /// Reads a group (proto2), a nested message delimited by an end group tag
+    ///
+    /// `tag_value` is the start group tag, which has just been read
+    #[cfg_attr(feature = "std", inline)]
+    pub fn read_group<'a, M>(
+        &mut self,
+        bytes: &'a [u8],
+        tag_value: u32,
+    ) -> Result<M>
+    where
+        M: MessageRead<'a>,
+    {
+        self.nested(bytes, tag_value >> 3, |r| {
+            let start = r.start;
+            // this group already counts towards the recursion limit
+            let len = r.skip_group(bytes, tag_value >> 3, 0)? - start;
+            let end = r.start;
+            r.start = start;
+            let m = r.read_len(bytes, M::from_reader, len)?;
+            r.start = end;
+            Ok(m)
+        })
+    }
+
+    /// Reads a group (proto2) into `message`, merging it with the fields already set
+    ///
+    /// `tag_value` is the start group tag, which has just been read
+    #[cfg_attr(feature = "std", inline)]
+    pub fn merge_group<'a, M>(
+        &mut self,
+        bytes: &'a [u8],
+        tag_value: u32,
+        message: &mut M,
+    ) -> Result<()>
+    where
+        M: MessageMerge<'a>,
+    {
+        self.nested(bytes, tag_value >> 3, |r| {
+            let start = r.start;
+            // this group already counts towards the recursion limit
+            let len = r.skip_group(bytes, tag_value >> 3, 0)? - start;
+            let end = r.start;
+            r.start = start;
+            r.read_len(
+                bytes,
+                |r, bytes| message.merge_from(r, bytes),
+                len,
+            )?;
+            r.start = end;
+            Ok(())
+        })
+    }
+
+    /// Reads unknown data like `read_unknown`, returning its raw encoding, tag excluded
+    #[cfg_attr(feature = "std", inline)]
+    pub fn read_unknown_bytes<'a>(
+        &mut self,
+        bytes: &'a [u8],
+        tag_value: u32,
+    ) -> Result<&'a [u8]> {
+        let start = self.start;
+        self.read_unknown(bytes, tag_value)?;
+        Ok(&bytes[start..self.start])
+    }
+
     /// Gets the remaining length of bytes not read yet
     #[cfg_attr(feature = "std", inline(always))]
     #[allow(clippy::len_without_is_empty)]
@@ -692,15 +1333,81 @@ impl BytesReader {
     }
 }
 
+/// Adds the field `number` of `message`, whose value starts at `field_start`, to the path of `error`
+#[cfg(feature = "alloc")]
+fn in_field(
+    mut error: Error,
+    bytes: &[u8],
+    number: u32,
+    message: Range<usize>,
+    field_start: usize,
+) -> Error {
+    if let (Error::Decode(d), 1..) = (&mut error, number) {
+        d.push_front(PathSegment {
+            number,
+            index: field_index(
+                bytes,
+                message,
+                number,
+                field_start,
+            ),
+        });
+    }
+    error
+}
+
+/// Errors have no path without the `alloc` feature
+#[cfg(not(feature = "alloc"))]
+fn in_field(
+    error: Error,
+    _: &[u8],
+    _: u32,
+    _: Range<usize>,
+    _: usize,
+) -> Error {
+    error
+}
+
+/// Position of the field `number` whose value starts at `field_start` among
+/// the fields of the same number of `message`, if there are several
+#[cfg(feature = "alloc")]
+fn field_index(
+    bytes: &[u8],
+    message: Range<usize>,
+    number: u32,
+    field_start: usize,
+) -> Option<usize> {
+    let mut r = BytesReader::from_bytes(bytes);
+    r.start = message.start;
+    r.end = message.end;
+    r.message_start = message.start;
+    let (mut index, mut count) = (0, 0);
+    // the message is malformed, stop at the first error
+    while let Ok(tag) = r.next_tag(bytes) {
+        if tag >> 3 == number {
+            count += 1;
+            if r.start < field_start {
+                index += 1;
+            }
+        }
+        if r.read_unknown(bytes, tag).is_err() || r.is_eof() {
+            break;
+        }
+    }
+    (count > 1).then_some(index)
+}
+
 /// A struct to read protobuf data
 ///
 /// Contrary to `BytesReader`, this struct will own a buffer
 ///
+/// Requires the `alloc` feature.
+///
 /// # Examples
 ///
 /// ```rust,should_panic
 /// # mod foo_bar {
-/// #     use quick_protobuf::{MessageRead, BytesReader, Result};
+/// #     use quack_protobuf::{MessageRead, BytesReader, Result};
 /// #     pub struct Foo {}
 /// #     pub struct Bar {}
 /// #     pub struct FooBar { pub foos: Vec<Foo>, pub bars: Vec<Bar>, }
@@ -714,8 +1421,11 @@ impl BytesReader {
 /// // FooBar is a message generated from a proto file
 /// // In particular it implements the `MessageRead` trait, containing a `from_reader` function.
 /// use foo_bar::FooBar;
-/// use quick_protobuf::Reader;
+/// use quack_protobuf::Reader;
 ///
+/// # #[cfg(not(feature = "std"))]
+/// # fn main() { panic!("`Reader::from_file` requires the `std` feature") }
+/// # #[cfg(feature = "std")]
 /// fn main() {
 ///     // create a reader, which will parse the protobuf binary file and pop events
 ///     // this reader will read the entire file into an internal buffer
@@ -745,26 +1455,45 @@ impl BytesReader {
 ///     );
 /// }
 /// ```
+#[cfg(feature = "alloc")]
 pub struct Reader {
     buffer: Vec<u8>,
     inner: BytesReader,
 }
 
+#[cfg(feature = "alloc")]
 impl Reader {
-    /// Creates a new `Reader`
+    /// Creates a new `Reader` out of the next `capacity` bytes of `r`
     #[cfg(feature = "std")]
-    #[allow(clippy::uninit_vec)]
     pub fn from_reader<R: Read>(
-        mut r: R,
+        r: R,
+        capacity: usize,
+    ) -> Result<Reader> {
+        Reader::from_reader_with_limits(
+            r,
+            capacity,
+            DecodeLimits::default(),
+        )
+    }
+
+    /// Creates a new `Reader` out of the next `capacity` bytes of `r`, enforcing `limits`
+    ///
+    /// `capacity` is checked against `limits.max_message_size` before reading
+    /// anything, and the buffer only grows as data is actually read, so that a
+    /// bogus `capacity` cannot trigger a large allocation.
+    #[cfg(feature = "std")]
+    pub fn from_reader_with_limits<R: Read>(
+        r: R,
         capacity: usize,
+        limits: DecodeLimits,
     ) -> Result<Reader> {
-        let mut buf = Vec::with_capacity(capacity);
-        unsafe {
-            buf.set_len(capacity);
+        limits.check_message_size(capacity)?;
+        let mut buf = Vec::new();
+        r.take(capacity as u64).read_to_end(&mut buf)?;
+        if buf.len() < capacity {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
         }
-        buf.shrink_to_fit();
-        r.read_exact(&mut buf)?;
-        Ok(Reader::from_bytes(buf))
+        Reader::from_bytes_with_limits(buf, limits)
     }
 
     /// Creates a new `Reader` out of a file path
@@ -777,16 +1506,25 @@ impl Reader {
 
     /// Creates a new reader consuming the bytes
     pub fn from_bytes(bytes: Vec<u8>) -> Reader {
-        let reader = BytesReader {
-            start: 0,
-            end: bytes.len(),
-        };
+        let reader = BytesReader::from_bytes(&bytes);
         Reader {
             buffer: bytes,
             inner: reader,
         }
     }
 
+    /// Creates a new reader consuming the bytes, enforcing `limits`
+    pub fn from_bytes_with_limits(
+        bytes: Vec<u8>,
+        limits: DecodeLimits,
+    ) -> Result<Reader> {
+        let reader = BytesReader::from_bytes_with_limits(&bytes, limits)?;
+        Ok(Reader {
+            buffer: bytes,
+            inner: reader,
+        })
+    }
+
     /// Run a `BytesReader` dependent function
     #[cfg_attr(feature = "std", inline)]
     pub fn read<'a, M, F>(
@@ -810,16 +1548,75 @@ impl Reader {
     }
 }
 
-// /// Deserialize a `MessageRead from a `&[u8]`
-// pub fn deserialize_from_slice_with_len_prefix<'a, M: MessageRead<'a>>(bytes: &'a [u8]) -> Result<M> {
-//     let mut reader = BytesReader::from_bytes(bytes);
-//     reader.read_message::<M>(bytes)
-// }
-
-/// Deserialize a `MessageRead from a `&[u8]` without a length prefix
+/// Deserialize a `MessageRead` from a `&[u8]` without a length prefix
 pub fn decode<'a, M: MessageRead<'a>>(bytes: &'a [u8]) -> Result<M> {
-    let mut reader = BytesReader::from_bytes(&bytes);
-    reader.read_message_without_len::<M>(&bytes)
+    let mut reader = BytesReader::from_bytes(bytes);
+    reader.read_message_without_len::<M>(bytes)
+}
+
+/// Decodes a message without a length prefix into `message`, merging it with the fields already set
+///
+/// Call `MessageMerge::clear` first to decode a new message while reusing
+/// the allocations of the previous one.
+///
+/// ```rust
+/// use quack_protobuf::{decode_into, BytesReader, MessageMerge, Result};
+///
+/// #[derive(Default)]
+/// struct Ids { ids: Vec<u32> }
+///
+/// impl<'a> MessageMerge<'a> for Ids {
+///     fn merge_from(&mut self, r: &mut BytesReader, bytes: &'a [u8]) -> Result<()> {
+///         while !r.is_eof() {
+///             match r.next_tag(bytes)? {
+///                 8 => self.ids.push(r.read_uint32(bytes)?),
+///                 t => r.read_unknown(bytes, t)?,
+///             }
+///         }
+///         Ok(())
+///     }
+///
+///     fn clear(&mut self) {
+///         self.ids.clear();
+///     }
+/// }
+///
+/// let mut msg = Ids::default();
+/// decode_into(&mut msg, &[0x08, 0x01]).unwrap();
+/// decode_into(&mut msg, &[0x08, 0x02]).unwrap();
+/// assert_eq!(msg.ids, [1, 2]);
+///
+/// msg.clear();
+/// decode_into(&mut msg, &[0x08, 0x03]).unwrap();
+/// assert_eq!(msg.ids, [3]);
+/// ```
+pub fn decode_into<'a, M: MessageMerge<'a>>(
+    message: &mut M,
+    bytes: &'a [u8],
+) -> Result<()> {
+    let mut reader = BytesReader::from_bytes(bytes);
+    reader.read_len(
+        bytes,
+        |r, bytes| message.merge_from(r, bytes),
+        bytes.len(),
+    )
+}
+
+/// Deserialize a `MessageRead` from a `&[u8]` starting with its length
+///
+/// The bytes following the message are ignored.
+pub fn decode_length_delimited<'a, M: MessageRead<'a>>(bytes: &'a [u8]) -> Result<M> {
+    let mut reader = BytesReader::from_bytes(bytes);
+    reader.read_message::<M>(bytes)
+}
+
+/// Deserialize a `MessageRead` from a refcounted buffer without a length prefix
+///
+/// Fields read with `read_shared_bytes` share `bytes` instead of copying it.
+#[cfg(feature = "bytes")]
+pub fn decode_shared<M: for<'a> MessageRead<'a>>(bytes: &Bytes) -> Result<M> {
+    let mut reader = BytesReader::from_shared(bytes);
+    reader.read_message_without_len::<M>(bytes)
 }
 
 /// Wrapper enum over packed fixed data, similar to `Cow`.
@@ -834,7 +1631,7 @@ pub fn decode<'a, M: MessageRead<'a>>(bytes: &'a [u8]) -> Result<M> {
 /// However, it is also convenient for the user to be able to use a
 /// `PackedFixed` variant that owns its own data (perhaps when setting the data
 /// themselves). It is mainly for this reason that the `Owned` variant is
-/// provided, which owns a `Vec<T>`.
+/// provided, which owns a `Vec<T>`, with the `alloc` feature.
 ///
 /// One implementation detail is that the `Owned` variant is always aligned, so
 /// no use of `read_unaligned` is necessary. Methods are provided to convert
@@ -856,21 +1653,33 @@ pub enum PackedFixed<'a, T: Copy + PartialEq> {
     /// coincidentally aligned.
     Borrowed(&'a [u8]),
     /// Variant that contains an owned vector of numbers.
+    #[cfg(feature = "alloc")]
     Owned(Vec<T>),
+    /// Uninhabited, makes use of `T` when there is no `Owned` variant
+    #[cfg(not(feature = "alloc"))]
+    #[doc(hidden)]
+    _Phantom(
+        core::convert::Infallible,
+        core::marker::PhantomData<T>,
+    ),
 }
 
 impl<'a, T: Copy + PartialEq> PackedFixed<'a, T> {
     /// Return the length of the DATA (not the bytes).
     pub fn len(&self) -> usize {
         match self {
-            PackedFixed::Borrowed(bytes) => bytes.len() / ::std::mem::size_of::<T>(),
+            PackedFixed::Borrowed(bytes) => bytes.len() / core::mem::size_of::<T>(),
+            #[cfg(feature = "alloc")]
             PackedFixed::Owned(v) => v.len(),
+            #[cfg(not(feature = "alloc"))]
+            PackedFixed::_Phantom(never, _) => match *never {},
             PackedFixed::NoDataYet => 0,
         }
     }
 
     /// Mutate in place to `Owned` variant. In the case of `Borrowed`, this
     /// performs a bitwise copy of the entire slice.
+    #[cfg(feature = "alloc")]
     pub fn own(&mut self) {
         match self {
             PackedFixed::NoDataYet => {
@@ -892,6 +1701,7 @@ impl<'a, T: Copy + PartialEq> PackedFixed<'a, T> {
     /// moving `self`, but we can't do this for the `Borrowed` variant, so
     /// we have no such method on `PackedFixed` as a whole. And anyway, this is
     /// what `at()` on `Borrowed` is for.
+    #[cfg(feature = "alloc")]
     pub fn into_vec(self) -> Vec<T> {
         match self {
             PackedFixed::NoDataYet => Vec::new(),
@@ -922,12 +1732,16 @@ impl<'a, T: Copy + PartialEq> PackedFixed<'a, T> {
                     (ptr as *const T).read_unaligned()
                 }
             }
+            #[cfg(feature = "alloc")]
             PackedFixed::Owned(v) => v[index],
+            #[cfg(not(feature = "alloc"))]
+            PackedFixed::_Phantom(never, _) => match *never {},
             PackedFixed::NoDataYet => panic!("Cannot call at() on PackedFixed::NoDataYet!"),
         }
     }
 
     /// Mutate `self` to `Owned` variant before returning immutable slice
+    #[cfg(feature = "alloc")]
     pub fn to_slice(&mut self) -> &[T] {
         self.own();
         if let PackedFixed::Owned(ref contents) = *self {
@@ -938,6 +1752,7 @@ impl<'a, T: Copy + PartialEq> PackedFixed<'a, T> {
     }
 
     /// Mutate `self` to `Owned` variant before returning mutable slice
+    #[cfg(feature = "alloc")]
     pub fn to_mut_slice(&mut self) -> &mut [T] {
         self.own();
         if let PackedFixed::Owned(ref mut contents) = *self {
@@ -947,23 +1762,52 @@ impl<'a, T: Copy + PartialEq> PackedFixed<'a, T> {
         }
     }
 
+    /// Appends the elements of `other`, keeping it borrowed if `self` is empty
+    #[cfg(feature = "alloc")]
+    pub fn append(
+        &mut self,
+        other: PackedFixed<'a, T>,
+    ) {
+        if self.is_empty() {
+            *self = other;
+        } else if !other.is_empty() {
+            self.own();
+            if let PackedFixed::Owned(ref mut contents) = *self {
+                contents.extend(other);
+            }
+        }
+    }
+
+    /// Removes all the elements, keeping the capacity of the `Owned` variant
+    pub fn clear(&mut self) {
+        match self {
+            #[cfg(feature = "alloc")]
+            PackedFixed::Owned(contents) => contents.clear(),
+            _ => *self = PackedFixed::NoDataYet,
+        }
+    }
+
     /// Returns `true` if no data is contained in the enum.
     pub fn is_empty(&self) -> bool {
         match self {
             PackedFixed::NoDataYet => true,
             PackedFixed::Borrowed(bytes) => bytes.is_empty(),
+            #[cfg(feature = "alloc")]
             PackedFixed::Owned(contents) => contents.is_empty(),
+            #[cfg(not(feature = "alloc"))]
+            PackedFixed::_Phantom(never, _) => match *never {},
         }
     }
 
     // This method is private and mainly to avoid repetition in code.
+    #[cfg(feature = "alloc")]
     fn make_vec_from_unaligned_buf(&self) -> Vec<T> {
         match &self {
             PackedFixed::Borrowed(bytes) => unsafe {
                 let src = bytes.as_ptr();
                 let mut buf = Vec::<T>::with_capacity(self.len());
                 let dst = buf.as_mut_ptr() as *mut u8;
-                ::std::ptr::copy(src, dst, bytes.len()); // careful to use length in bytes here
+                core::ptr::copy(src, dst, bytes.len()); // careful to use length in bytes here
                 buf.set_len(self.len());
                 buf
             },
@@ -972,6 +1816,7 @@ impl<'a, T: Copy + PartialEq> PackedFixed<'a, T> {
     }
 
     // This method is private and mainly to avoid repetition in code.
+    #[cfg(feature = "alloc")]
     fn make_owned_variant_from_unaligned_buf(&self) -> Self {
         match &self {
             PackedFixed::Borrowed(_) => PackedFixed::Owned(self.make_vec_from_unaligned_buf()),
@@ -1071,12 +1916,14 @@ impl<'a, T: Copy + PartialEq> From<&'a [u8]> for PackedFixed<'a, T> {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<'a, T: Copy + PartialEq> From<&'a Vec<u8>> for PackedFixed<'a, T> {
     fn from(value: &'a Vec<u8>) -> Self {
         Self::Borrowed(value)
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<'a, T: Copy + PartialEq> From<Vec<T>> for PackedFixed<'a, T> {
     fn from(value: Vec<T>) -> Self {
         Self::Owned(value)
@@ -0,0 +1,129 @@
+//! A module to make gRPC calls over HTTP/2
+
+use bytes::Bytes;
+use h2::client::SendRequest;
+use http::header::{HeaderValue, CONTENT_TYPE, TE};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+use crate::errors::Result;
+use crate::message::{MessageRead, MessageWrite};
+use crate::rpc::streaming::GRPC_CONTENT_TYPE;
+use crate::rpc::{Metadata, Request, Response, Sender, Status, Streaming};
+
+/// A connection to a gRPC server over HTTP/2, without TLS
+///
+/// Cloning a channel is cheap: calls of all the clones are multiplexed on
+/// the same connection. `path` arguments are the `/package.Service/Method`
+/// of the calls, as the generated clients give them.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    sender: SendRequest<Bytes>,
+    authority: String,
+}
+
+impl Channel {
+    /// Connects to the server listening at `addr`
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<Channel> {
+        let socket = TcpStream::connect(addr).await?;
+        let _ = socket.set_nodelay(true);
+        let authority = socket.peer_addr()?.to_string();
+        Channel::from_io(socket, authority).await
+    }
+
+    /// Makes the calls over an already established connection to `authority`
+    ///
+    /// The connection is driven by a new tokio task.
+    pub async fn from_io<IO>(
+        io: IO,
+        authority: String,
+    ) -> Result<Channel>
+    where
+        IO: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (sender, connection) = h2::client::handshake(io)
+            .await
+            .map_err(std::io::Error::other)?;
+        tokio::spawn(connection);
+        Ok(Channel { sender, authority })
+    }
+
+    /// Makes a call with a single request message and a single response message
+    pub async fn unary<Req, Resp>(
+        &self,
+        path: &str,
+        request: Request<Req>,
+    ) -> std::result::Result<Response<Resp>, Status>
+    where
+        Req: MessageWrite,
+        Resp: for<'a> MessageRead<'a>,
+    {
+        self.server_streaming(path, request)
+            .await?
+            .into_response()
+            .await
+    }
+
+    /// Makes a call with a single request message and a stream of response messages
+    pub async fn server_streaming<Req, Resp>(
+        &self,
+        path: &str,
+        request: Request<Req>,
+    ) -> std::result::Result<Streaming<Resp>, Status>
+    where
+        Req: MessageWrite,
+    {
+        let (metadata, message) = request.into_parts();
+        let (mut sender, responses) = self.streaming(path, metadata).await?;
+        // a server answering early resets the request, its status is in the response
+        if sender.send(&message).await.is_ok() {
+            let _ = sender.close().await;
+        }
+        Ok(responses)
+    }
+
+    /// Starts a call with a stream of request messages, for client and bidirectional streaming
+    ///
+    /// The request ends when `Sender::close` is called. For client
+    /// streaming, the response is then received with `Streaming::into_response`.
+    pub async fn streaming<Req, Resp>(
+        &self,
+        path: &str,
+        metadata: Metadata,
+    ) -> std::result::Result<(Sender<Req>, Streaming<Resp>), Status> {
+        let mut request = http::Request::post(format!(
+            "http://{}{}",
+            self.authority, path
+        ))
+        .body(())
+        .map_err(|e| {
+            Status::new(
+                crate::rpc::Code::Internal,
+                e.to_string(),
+            )
+        })?;
+        let headers = request.headers_mut();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(GRPC_CONTENT_TYPE),
+        );
+        headers.insert(
+            TE,
+            HeaderValue::from_static("trailers"),
+        );
+        metadata.to_headers(headers)?;
+        let mut sender = self
+            .sender
+            .clone()
+            .ready()
+            .await
+            .map_err(Status::from_h2)?;
+        let (response, stream) = sender
+            .send_request(request, false)
+            .map_err(Status::from_h2)?;
+        Ok((
+            Sender::request(stream),
+            Streaming::response(response),
+        ))
+    }
+}
@@ -0,0 +1,111 @@
+//! A module to handle the custom metadata of calls
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::rpc::{Code, Status};
+
+/// The custom metadata of a call, sent as HTTP/2 headers or trailers
+///
+/// Names are lowercase. Reserved headers (`content-type`, `te` and the
+/// `grpc-` ones) are not part of the metadata, and binary values (`-bin`
+/// names) are kept base64 encoded, as sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    entries: Vec<(String, String)>,
+}
+
+impl Metadata {
+    /// Creates empty metadata
+    pub fn new() -> Self {
+        Metadata::default()
+    }
+
+    /// Adds an entry
+    pub fn insert(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) {
+        self.entries.push((
+            name.to_ascii_lowercase(),
+            value.to_string(),
+        ));
+    }
+
+    /// Gets the value of the first entry named `name`, case insensitive
+    pub fn get(
+        &self,
+        name: &str,
+    ) -> Option<&str> {
+        self.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Iterates over the entries, as `(name, value)`
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// Gets the number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if there is no entry
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds all the entries of `other`
+    pub fn extend(
+        &mut self,
+        other: Metadata,
+    ) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Collects the metadata out of received headers
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        let mut metadata = Metadata::new();
+        for (name, value) in headers {
+            if is_reserved(name.as_str()) {
+                continue;
+            }
+            if let Ok(value) = value.to_str() {
+                metadata.insert(name.as_str(), value);
+            }
+        }
+        metadata
+    }
+
+    /// Adds the metadata to headers to be sent
+    pub(crate) fn to_headers(
+        &self,
+        headers: &mut HeaderMap,
+    ) -> Result<(), Status> {
+        for (name, value) in self.iter() {
+            let invalid = || {
+                Status::new(
+                    Code::Internal,
+                    format!("invalid metadata `{}`", name),
+                )
+            };
+            if is_reserved(name) {
+                return Err(invalid());
+            }
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+                HeaderValue::from_str(value).map_err(|_| invalid())?,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Whether a header is used by the protocol itself
+fn is_reserved(name: &str) -> bool {
+    name == "content-type" || name == "te" || name.starts_with("grpc-")
+}
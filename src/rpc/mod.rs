@@ -0,0 +1,139 @@
+//! A module to make and serve gRPC calls over HTTP/2
+//!
+//! The code generated for a `service` of a .proto file (see the `services`
+//! option of the code generator) contains:
+//! - a trait with one method per `rpc`, implemented by the server
+//! - a `...Server` wrapper turning it into a `Service`, served by a `Server`
+//! - a `...Client` making the calls over a `Channel`
+//!
+//! Unary, client streaming, server streaming and bidirectional streaming
+//! calls are supported, with custom metadata and `Status` codes. Messages
+//! are sent uncompressed and must not borrow their input (use owned
+//! strings and bytes).
+//!
+//! `tests/rpc.rs` runs a complete server and client over a loopback socket.
+//!
+//! ```rust,ignore
+//! struct MyGreeter;
+//!
+//! impl Greeter for MyGreeter {
+//!     async fn say_hello(&self, request: Request<HelloRequest>) -> Result<Response<HelloReply>, Status> {
+//!         let message = format!("Hello {}!", request.get_ref().name);
+//!         Ok(Response::new(HelloReply { message }))
+//!     }
+//! }
+//!
+//! let listener = TcpListener::bind("127.0.0.1:50051").await?;
+//! let mut server = Server::new();
+//! server.add_service(GreeterServer::new(MyGreeter));
+//! tokio::spawn(async move { server.serve(listener).await });
+//!
+//! let client = GreeterClient::new(Channel::connect("127.0.0.1:50051").await?);
+//! let reply = client.say_hello(Request::new(HelloRequest { name: "you".into() })).await?;
+//! ```
+
+mod client;
+mod metadata;
+mod server;
+mod status;
+mod streaming;
+
+pub use self::client::Channel;
+pub use self::metadata::Metadata;
+pub use self::server::{BoxFuture, Call, Server, Service};
+pub use self::status::{Code, Status};
+pub use self::streaming::{Sender, Streaming};
+
+/// A request: a message, or a `Streaming` of messages, along with its metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Request<T> {
+    metadata: Metadata,
+    message: T,
+}
+
+impl<T> Request<T> {
+    /// Creates a new request without metadata
+    pub fn new(message: T) -> Self {
+        Request {
+            metadata: Metadata::new(),
+            message,
+        }
+    }
+
+    /// Gets the metadata of the request
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Gets a mutable reference to the metadata of the request
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Gets a reference to the message
+    pub fn get_ref(&self) -> &T {
+        &self.message
+    }
+
+    /// Gets a mutable reference to the message
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.message
+    }
+
+    /// Unwraps the message
+    pub fn into_inner(self) -> T {
+        self.message
+    }
+
+    /// Splits the request into its metadata and its message
+    pub fn into_parts(self) -> (Metadata, T) {
+        (self.metadata, self.message)
+    }
+}
+
+/// A response: a message, or a `Streaming` of messages, along with its metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Response<T> {
+    metadata: Metadata,
+    message: T,
+}
+
+impl<T> Response<T> {
+    /// Creates a new response without metadata
+    pub fn new(message: T) -> Self {
+        Response {
+            metadata: Metadata::new(),
+            message,
+        }
+    }
+
+    /// Gets the metadata of the response
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Gets a mutable reference to the metadata of the response
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Gets a reference to the message
+    pub fn get_ref(&self) -> &T {
+        &self.message
+    }
+
+    /// Gets a mutable reference to the message
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.message
+    }
+
+    /// Unwraps the message
+    pub fn into_inner(self) -> T {
+        self.message
+    }
+
+    /// Splits the response into its metadata and its message
+    pub fn into_parts(self) -> (Metadata, T) {
+        (self.metadata, self.message)
+    }
+}
@@ -0,0 +1,196 @@
+//! A module to serve gRPC services over HTTP/2
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use h2::server::SendResponse;
+use h2::RecvStream;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+
+use crate::errors::Result;
+use crate::message::MessageRead;
+use crate::rpc::{Code, Metadata, Request, Sender, Status, Streaming};
+
+/// A boxed future, as returned by `Service::call`
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A service served by a `Server`, implemented by the generated `...Server` wrappers
+pub trait Service: Send + Sync + 'static {
+    /// Full name of the service, `package.Service`
+    fn name(&self) -> &'static str;
+
+    /// Handles a call of `method`, up to sending its status
+    fn call<'a>(
+        &'a self,
+        method: &'a str,
+        call: Call,
+    ) -> BoxFuture<'a, ()>;
+}
+
+/// An incoming call, handed to a `Service`
+#[derive(Debug)]
+pub struct Call {
+    request: http::Request<RecvStream>,
+    respond: SendResponse<Bytes>,
+}
+
+impl Call {
+    /// Starts a call with a single request message, `None` if it could not be received
+    ///
+    /// The client is answered with an error status in that case.
+    pub async fn unary<Req, Resp>(self) -> Option<(Request<Req>, Sender<Resp>)>
+    where
+        Req: for<'a> MessageRead<'a>,
+    {
+        let (request, sender) = self.streaming::<Req, Resp>();
+        let (metadata, mut messages) = request.into_parts();
+        let message = match messages.message().await {
+            Ok(Some(message)) => match messages.message().await {
+                Ok(None) => Ok(message),
+                Ok(Some(_)) => Err(Status::new(
+                    Code::InvalidArgument,
+                    "several request messages",
+                )),
+                Err(status) => Err(status),
+            },
+            Ok(None) => Err(Status::new(
+                Code::InvalidArgument,
+                "missing request message",
+            )),
+            Err(status) => Err(status),
+        };
+        match message {
+            Ok(message) => {
+                let mut request = Request::new(message);
+                *request.metadata_mut() = metadata;
+                Some((request, sender))
+            }
+            Err(status) => {
+                sender.finish(Err(status)).await;
+                None
+            }
+        }
+    }
+
+    /// Starts a call with a stream of request messages
+    pub fn streaming<Req, Resp>(
+        self
+    ) -> (
+        Request<Streaming<Req>>,
+        Sender<Resp>,
+    ) {
+        let (parts, body) = self.request.into_parts();
+        let mut request = Request::new(Streaming::request(body));
+        *request.metadata_mut() = Metadata::from_headers(&parts.headers);
+        (
+            request,
+            Sender::response(self.respond),
+        )
+    }
+
+    /// Answers a call of an unknown method
+    pub async fn unimplemented(self) {
+        let status = Status::new(
+            Code::Unimplemented,
+            format!(
+                "unknown method {}",
+                self.request.uri().path()
+            ),
+        );
+        Sender::<()>::response(self.respond)
+            .finish(Err(status))
+            .await
+    }
+}
+
+/// A server of gRPC services over HTTP/2, without TLS
+///
+/// Each call is handled in its own tokio task.
+#[derive(Clone, Default)]
+pub struct Server {
+    services: HashMap<&'static str, Arc<dyn Service>>,
+}
+
+impl std::fmt::Debug for Server {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field(
+                "services",
+                &self.services.keys(),
+            )
+            .finish()
+    }
+}
+
+impl Server {
+    /// Creates a new server without any service
+    pub fn new() -> Self {
+        Server::default()
+    }
+
+    /// Adds a service, replacing any service of the same name
+    pub fn add_service<S: Service>(
+        &mut self,
+        service: S,
+    ) -> &mut Self {
+        self.services.insert(
+            service.name(),
+            Arc::new(service),
+        );
+        self
+    }
+
+    /// Serves the connections accepted by `listener`, each in its own task
+    pub async fn serve(
+        &self,
+        listener: TcpListener,
+    ) -> Result<()> {
+        loop {
+            let (socket, _) = listener.accept().await?;
+            let _ = socket.set_nodelay(true);
+            let server = self.clone();
+            tokio::spawn(async move { server.serve_connection(socket).await });
+        }
+    }
+
+    /// Serves the calls of a single connection, until it is closed
+    pub async fn serve_connection<IO>(
+        &self,
+        io: IO,
+    ) -> Result<()>
+    where
+        IO: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut connection = h2::server::handshake(io)
+            .await
+            .map_err(std::io::Error::other)?;
+        while let Some(call) = connection.accept().await {
+            let (request, respond) = call.map_err(std::io::Error::other)?;
+            let call = Call { request, respond };
+            let path = call.request.uri().path().to_string();
+            let service = path
+                .strip_prefix('/')
+                .and_then(|p| p.split_once('/'))
+                .and_then(|(service, method)| {
+                    Some((
+                        self.services.get(service)?.clone(),
+                        method.to_string(),
+                    ))
+                });
+            tokio::spawn(async move {
+                match service {
+                    Some((service, method)) => service.call(&method, call).await,
+                    None => call.unimplemented().await,
+                }
+            });
+        }
+        Ok(())
+    }
+}
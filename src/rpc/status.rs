@@ -0,0 +1,224 @@
+//! A module to handle the status of calls
+
+use std::fmt;
+
+use http::header::{HeaderMap, HeaderValue};
+
+use crate::errors::Error;
+use crate::rpc::Metadata;
+
+/// The status code of a call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// The call succeeded
+    Ok = 0,
+    /// The call was cancelled, typically by the caller
+    Cancelled = 1,
+    /// Unknown error
+    Unknown = 2,
+    /// The client specified an invalid argument
+    InvalidArgument = 3,
+    /// The deadline expired before the call could complete
+    DeadlineExceeded = 4,
+    /// Some requested entity was not found
+    NotFound = 5,
+    /// Some entity the client attempted to create already exists
+    AlreadyExists = 6,
+    /// The caller does not have permission to execute the call
+    PermissionDenied = 7,
+    /// Some resource has been exhausted
+    ResourceExhausted = 8,
+    /// The system is not in a state required for the call
+    FailedPrecondition = 9,
+    /// The call was aborted, typically due to a concurrency issue
+    Aborted = 10,
+    /// The call was attempted past the valid range
+    OutOfRange = 11,
+    /// The call is not implemented or supported by the server
+    Unimplemented = 12,
+    /// Internal error
+    Internal = 13,
+    /// The service is currently unavailable
+    Unavailable = 14,
+    /// Unrecoverable data loss or corruption
+    DataLoss = 15,
+    /// The request does not have valid authentication credentials
+    Unauthenticated = 16,
+}
+
+impl From<u32> for Code {
+    fn from(code: u32) -> Self {
+        match code {
+            0 => Code::Ok,
+            1 => Code::Cancelled,
+            3 => Code::InvalidArgument,
+            4 => Code::DeadlineExceeded,
+            5 => Code::NotFound,
+            6 => Code::AlreadyExists,
+            7 => Code::PermissionDenied,
+            8 => Code::ResourceExhausted,
+            9 => Code::FailedPrecondition,
+            10 => Code::Aborted,
+            11 => Code::OutOfRange,
+            12 => Code::Unimplemented,
+            13 => Code::Internal,
+            14 => Code::Unavailable,
+            15 => Code::DataLoss,
+            16 => Code::Unauthenticated,
+            _ => Code::Unknown,
+        }
+    }
+}
+
+/// The status a call ends with: a code, a message and trailing metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    code: Code,
+    message: String,
+    metadata: Metadata,
+}
+
+impl Status {
+    /// Creates a new status
+    pub fn new<S: Into<String>>(
+        code: Code,
+        message: S,
+    ) -> Self {
+        Status {
+            code,
+            message: message.into(),
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// Gets the status code
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    /// Gets the description of the status
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Gets the metadata sent in the trailers along with the status
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Gets a mutable reference to the metadata sent in the trailers
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Reads the status out of received trailers, `None` without a `grpc-status`
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Status> {
+        let code = headers
+            .get("grpc-status")?
+            .to_str()
+            .ok()
+            .and_then(|c| c.parse::<u32>().ok())
+            .map_or(Code::Unknown, Code::from);
+        let message = headers
+            .get("grpc-message")
+            .map(|m| percent_decode(m.as_bytes()))
+            .unwrap_or_default();
+        Some(Status {
+            code,
+            message,
+            metadata: Metadata::from_headers(headers),
+        })
+    }
+
+    /// Adds the status and its metadata to trailers to be sent
+    pub(crate) fn to_headers(
+        &self,
+        headers: &mut HeaderMap,
+    ) {
+        // invalid metadata is dropped rather than hiding the status
+        if self.metadata.to_headers(headers).is_err() {
+            headers.clear();
+        }
+        headers.insert(
+            "grpc-status",
+            HeaderValue::from(self.code as u32),
+        );
+        if !self.message.is_empty() {
+            // percent encoding only leaves visible ASCII characters
+            let message = percent_encode(&self.message);
+            headers.insert(
+                "grpc-message",
+                HeaderValue::from_str(&message).expect("percent encoded"),
+            );
+        }
+    }
+
+    /// Converts a transport error
+    pub(crate) fn from_h2(e: h2::Error) -> Status {
+        let code = if e.is_io() || e.is_go_away() {
+            Code::Unavailable
+        } else if e.reason() == Some(h2::Reason::CANCEL) {
+            Code::Cancelled
+        } else {
+            Code::Internal
+        };
+        Status::new(code, e.to_string())
+    }
+}
+
+impl From<Error> for Status {
+    fn from(e: Error) -> Self {
+        Status::new(Code::Internal, e.to_string())
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {}",
+            self.code, self.message
+        )
+    }
+}
+
+impl std::error::Error for Status {}
+
+/// Encodes the bytes of `s` outside of visible ASCII as `%XX`, as `grpc-message` requires
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b' '..=b'~' if b != b'%' => out.push(b as char),
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Decodes the `%XX` sequences of `grpc-message`, leaving invalid ones as is
+fn percent_decode(s: &[u8]) -> String {
+    let mut out = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        let hex = s
+            .get(i + 1..i + 3)
+            .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (s[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
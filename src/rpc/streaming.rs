@@ -0,0 +1,386 @@
+//! A module to send and receive the messages of a call over an HTTP/2 stream
+
+use std::future::poll_fn;
+use std::marker::PhantomData;
+
+use bytes::Bytes;
+use h2::{client::ResponseFuture, server::SendResponse, RecvStream, SendStream};
+use http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+
+use crate::grpc::{encode_frame, Frame, FrameDecoder};
+use crate::message::{MessageRead, MessageWrite};
+use crate::reader::BytesReader;
+use crate::rpc::{Code, Metadata, Response, Status};
+
+/// Content type of gRPC requests and responses
+pub(crate) const GRPC_CONTENT_TYPE: &str = "application/grpc";
+
+/// Where the messages of a `Streaming` come from
+enum Source {
+    /// Response whose headers have not been received yet
+    Pending(ResponseFuture),
+    /// Body of a request or a response
+    Body {
+        body: RecvStream,
+        /// whether the body ends with the status of the call, as responses do
+        status_trailers: bool,
+    },
+    Done,
+}
+
+/// A stream of messages received during a call
+///
+/// On the client side, the status of the call is received after the last
+/// message: an error status is returned by `message`.
+pub struct Streaming<M> {
+    source: Source,
+    decoder: FrameDecoder,
+    metadata: Metadata,
+    trailers: Metadata,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<M> std::fmt::Debug for Streaming<M> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        f.debug_struct("Streaming")
+            .field("metadata", &self.metadata)
+            .field("trailers", &self.trailers)
+            .finish()
+    }
+}
+
+impl<M> Streaming<M> {
+    /// Creates the stream of the messages of a request
+    pub(crate) fn request(body: RecvStream) -> Self {
+        Streaming::new(Source::Body {
+            body,
+            status_trailers: false,
+        })
+    }
+
+    /// Creates the stream of the messages of a response not received yet
+    pub(crate) fn response(response: ResponseFuture) -> Self {
+        Streaming::new(Source::Pending(response))
+    }
+
+    fn new(source: Source) -> Self {
+        Streaming {
+            source,
+            decoder: FrameDecoder::new(),
+            metadata: Metadata::new(),
+            trailers: Metadata::new(),
+            _message: PhantomData,
+        }
+    }
+
+    /// Gets the metadata received in the headers
+    ///
+    /// The headers of a response are only received along with the first message.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Gets the metadata received in the trailers of a response, after the last message
+    pub fn trailers(&self) -> &Metadata {
+        &self.trailers
+    }
+
+    /// Receives the headers of a response
+    async fn receive_headers(
+        &mut self,
+        response: ResponseFuture,
+    ) -> Result<(), Status> {
+        let response = response.await.map_err(Status::from_h2)?;
+        if response.status() != http::StatusCode::OK {
+            return Err(Status::new(
+                Code::Unknown,
+                format!(
+                    "unexpected HTTP status {}",
+                    response.status()
+                ),
+            ));
+        }
+        // a response without message has its status in the headers
+        if let Some(status) = Status::from_headers(response.headers()) {
+            self.source = Source::Done;
+            self.trailers = status.metadata().clone();
+            return match status.code() {
+                Code::Ok => Ok(()),
+                _ => Err(status),
+            };
+        }
+        self.metadata = Metadata::from_headers(response.headers());
+        self.source = Source::Body {
+            body: response.into_body(),
+            status_trailers: true,
+        };
+        Ok(())
+    }
+}
+
+impl<M: for<'a> MessageRead<'a>> Streaming<M> {
+    /// Receives the next message, `None` once all have been received
+    pub async fn message(&mut self) -> Result<Option<M>, Status> {
+        loop {
+            match self.decoder.next_frame()? {
+                Some(Frame::Message(bytes)) => {
+                    let mut reader = BytesReader::from_bytes(bytes);
+                    return Ok(Some(M::from_reader(
+                        &mut reader,
+                        bytes,
+                    )?));
+                }
+                Some(Frame::Trailers(_)) => {
+                    return Err(Status::new(
+                        Code::Internal,
+                        "unexpected trailers frame",
+                    ))
+                }
+                None => {}
+            }
+            let (body, status_trailers) = match &mut self.source {
+                Source::Pending(_) => {
+                    let Source::Pending(response) = std::mem::replace(&mut self.source, Source::Done) else {
+                        unreachable!()
+                    };
+                    self.receive_headers(response).await?;
+                    continue;
+                }
+                Source::Body { body, status_trailers } => (body, *status_trailers),
+                Source::Done => return Ok(None),
+            };
+            match body.data().await {
+                Some(Ok(data)) => {
+                    let _ = body.flow_control().release_capacity(data.len());
+                    self.decoder.push(&data);
+                }
+                Some(Err(e)) => return Err(Status::from_h2(e)),
+                None => {
+                    if !self.decoder.is_empty() {
+                        return Err(Status::new(
+                            Code::Internal,
+                            "truncated message",
+                        ));
+                    }
+                    let trailers = match status_trailers {
+                        true => body.trailers().await.map_err(Status::from_h2)?,
+                        false => None,
+                    };
+                    self.source = Source::Done;
+                    if status_trailers {
+                        let status = trailers
+                            .as_ref()
+                            .and_then(Status::from_headers)
+                            .unwrap_or_else(|| {
+                                Status::new(
+                                    Code::Internal,
+                                    "missing status",
+                                )
+                            });
+                        self.trailers = status.metadata().clone();
+                        if status.code() != Code::Ok {
+                            return Err(status);
+                        }
+                    }
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Receives the only message of a unary response, along with the metadata of the response
+    pub async fn into_response(mut self) -> Result<Response<M>, Status> {
+        let message = self.message().await?.ok_or_else(|| {
+            Status::new(
+                Code::Internal,
+                "missing response message",
+            )
+        })?;
+        if self.message().await?.is_some() {
+            return Err(Status::new(
+                Code::Internal,
+                "several response messages",
+            ));
+        }
+        let mut response = Response::new(message);
+        *response.metadata_mut() = self.metadata;
+        Ok(response)
+    }
+}
+
+/// A sender of the messages of a call
+///
+/// On the server side, the headers of the response, with the metadata of
+/// `metadata_mut`, are sent along with the first message. On the client
+/// side, sending fails once the server ended the call, whose status is
+/// then returned by the `Streaming` of the responses.
+pub struct Sender<M> {
+    /// server side, until the headers are sent
+    respond: Option<SendResponse<Bytes>>,
+    stream: Option<SendStream<Bytes>>,
+    metadata: Metadata,
+    _message: PhantomData<fn(M)>,
+}
+
+impl<M> std::fmt::Debug for Sender<M> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        f.debug_struct("Sender")
+            .field("metadata", &self.metadata)
+            .finish()
+    }
+}
+
+impl<M> Sender<M> {
+    /// Creates the sender of the messages of a response
+    pub(crate) fn response(respond: SendResponse<Bytes>) -> Self {
+        Sender {
+            respond: Some(respond),
+            stream: None,
+            metadata: Metadata::new(),
+            _message: PhantomData,
+        }
+    }
+
+    /// Creates the sender of the messages of a request
+    pub(crate) fn request(stream: SendStream<Bytes>) -> Self {
+        Sender {
+            respond: None,
+            stream: Some(stream),
+            metadata: Metadata::new(),
+            _message: PhantomData,
+        }
+    }
+
+    /// Gets a mutable reference to the metadata of the response headers
+    ///
+    /// Changes are ignored once the first message has been sent.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Gets the stream, sending the response headers first if needed
+    fn stream(&mut self) -> Result<&mut SendStream<Bytes>, Status> {
+        if let Some(respond) = self.respond.as_mut() {
+            let mut response = http::Response::new(());
+            response.headers_mut().insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static(GRPC_CONTENT_TYPE),
+            );
+            self.metadata.to_headers(response.headers_mut())?;
+            let stream = respond
+                .send_response(response, false)
+                .map_err(Status::from_h2)?;
+            self.stream = Some(stream);
+            self.respond = None;
+        }
+        self.stream.as_mut().ok_or_else(|| {
+            Status::new(
+                Code::Internal,
+                "stream closed",
+            )
+        })
+    }
+
+    /// Ends the stream of a request
+    pub async fn close(&mut self) -> Result<(), Status> {
+        if self.respond.is_some() {
+            return Err(Status::new(
+                Code::Internal,
+                "responses end with a status",
+            ));
+        }
+        if let Some(mut stream) = self.stream.take() {
+            stream
+                .send_data(Bytes::new(), true)
+                .map_err(Status::from_h2)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the call with the status of `result`, sending the trailers of the response
+    ///
+    /// Used by the generated services once the handler of a call returned.
+    pub async fn finish(
+        mut self,
+        result: Result<(), Status>,
+    ) {
+        let status = result
+            .err()
+            .unwrap_or_else(|| Status::new(Code::Ok, ""));
+        let mut trailers = HeaderMap::new();
+        status.to_headers(&mut trailers);
+        // errors mean the client is gone, there is no one left to tell
+        if let Some(stream) = self.stream.as_mut() {
+            let _ = stream.send_trailers(trailers);
+        } else if let Some(respond) = self.respond.as_mut() {
+            let mut response = http::Response::new(());
+            *response.headers_mut() = trailers;
+            response.headers_mut().insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static(GRPC_CONTENT_TYPE),
+            );
+            let _ = respond.send_response(response, true);
+        }
+    }
+}
+
+impl<M: MessageWrite> Sender<M> {
+    /// Sends a message
+    pub async fn send(
+        &mut self,
+        message: &M,
+    ) -> Result<(), Status> {
+        let mut frame = Vec::new();
+        encode_frame(message, &mut frame)?;
+        let stream = self.stream()?;
+        send_data(stream, Bytes::from(frame)).await
+    }
+
+    /// Ends the call with the only message of a unary response, or with an error status
+    ///
+    /// Used by the generated services once the handler of a call returned.
+    pub async fn respond(
+        mut self,
+        result: Result<Response<M>, Status>,
+    ) {
+        let result = match result {
+            Ok(response) => {
+                let (metadata, message) = response.into_parts();
+                self.metadata.extend(metadata);
+                self.send(&message).await
+            }
+            Err(status) => Err(status),
+        };
+        self.finish(result).await
+    }
+}
+
+/// Sends `data` as soon as the flow control of the stream allows it
+async fn send_data(
+    stream: &mut SendStream<Bytes>,
+    mut data: Bytes,
+) -> Result<(), Status> {
+    while !data.is_empty() {
+        stream.reserve_capacity(data.len());
+        let capacity = match poll_fn(|cx| stream.poll_capacity(cx)).await {
+            Some(capacity) => capacity.map_err(Status::from_h2)?,
+            None => {
+                return Err(Status::new(
+                    Code::Unavailable,
+                    "stream closed",
+                ))
+            }
+        };
+        let chunk = data.split_to(capacity.min(data.len()));
+        stream
+            .send_data(chunk, false)
+            .map_err(Status::from_h2)?;
+    }
+    Ok(())
+}
@@ -28,6 +28,17 @@ pub fn sizeof_len(len: usize) -> usize {
     sizeof_varint(len as u64) + len
 }
 
+/// Computes the binary size of a group (wire type 3) of field `number`
+///
+/// The total size is the size of the message plus the size of its end group
+/// tag, the start group tag is counted with the other tags
+pub fn sizeof_group(
+    number: u32,
+    size: usize,
+) -> usize {
+    size + sizeof_varint((number << 3 | 4) as u64)
+}
+
 /// Computes the binary size of the varint encoded i32
 pub fn sizeof_int32(v: i32) -> usize {
     sizeof_varint(v as u64)
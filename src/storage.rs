@@ -0,0 +1,199 @@
+//! A module to read repeated fields into storage provided by the caller
+//!
+//! `BytesReader::read_packed_into` and `BytesReader::read_repeated_into`
+//! push the elements they read into a `RepeatedStorage`, failing with
+//! `Error::CapacityExceeded` once it is full. Storage of a fixed capacity
+//! needs no allocator:
+//! - `&mut [T]`, whose elements are overwritten from the start, the slice
+//!   being shortened as they are
+//! - `ArrayVec<T, N>`, defined here
+//! - `heapless::Vec<T, N>`, with the `heapless` feature
+
+use core::ops::{Deref, DerefMut};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Storage the elements of a repeated field are pushed into
+pub trait RepeatedStorage<T> {
+    /// Appends `value`, giving it back if the storage is full
+    fn try_push(
+        &mut self,
+        value: T,
+    ) -> Result<(), T>;
+}
+
+/// A slice is filled from its start, and shortened to the elements not filled yet
+///
+/// ```rust
+/// use quack_protobuf::RepeatedStorage;
+///
+/// let mut buf = [0u32; 2];
+/// let mut slots = &mut buf[..];
+/// assert_eq!(slots.try_push(1), Ok(()));
+/// assert_eq!(slots.len(), 1);
+/// assert_eq!(slots.try_push(2), Ok(()));
+/// assert_eq!(slots.try_push(3), Err(3));
+/// assert_eq!(buf, [1, 2]);
+/// ```
+impl<T> RepeatedStorage<T> for &mut [T] {
+    fn try_push(
+        &mut self,
+        value: T,
+    ) -> Result<(), T> {
+        match core::mem::take(self).split_first_mut() {
+            Some((first, rest)) => {
+                *first = value;
+                *self = rest;
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> RepeatedStorage<T> for Vec<T> {
+    fn try_push(
+        &mut self,
+        value: T,
+    ) -> Result<(), T> {
+        self.push(value);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<T, const N: usize> RepeatedStorage<T> for heapless::Vec<T, N> {
+    fn try_push(
+        &mut self,
+        value: T,
+    ) -> Result<(), T> {
+        self.push(value)
+    }
+}
+
+/// A vector of at most `N` elements, stored inline
+///
+/// Elements not pushed yet hold their default value.
+///
+/// ```rust
+/// use quack_protobuf::{ArrayVec, RepeatedStorage};
+///
+/// let mut v = ArrayVec::<u32, 2>::new();
+/// assert_eq!(v.try_push(1), Ok(()));
+/// assert_eq!(v.try_push(2), Ok(()));
+/// assert_eq!(v.try_push(3), Err(3));
+/// assert_eq!(&v[..], &[1, 2]);
+/// ```
+#[derive(Clone)]
+pub struct ArrayVec<T, const N: usize> {
+    items: [T; N],
+    len: usize,
+}
+
+impl<T: Default, const N: usize> ArrayVec<T, N> {
+    /// Creates a new, empty, vector
+    pub fn new() -> Self {
+        ArrayVec {
+            items: core::array::from_fn(|_| T::default()),
+            len: 0,
+        }
+    }
+
+    /// Removes all the elements
+    pub fn clear(&mut self) {
+        for item in &mut self.items[..self.len] {
+            *item = T::default();
+        }
+        self.len = 0;
+    }
+}
+
+impl<T, const N: usize> ArrayVec<T, N> {
+    /// Gets the maximum number of elements, `N`
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Checks if `N` elements have been pushed
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Gets the elements as a slice
+    pub fn as_slice(&self) -> &[T] {
+        &self.items[..self.len]
+    }
+
+    /// Gets the elements as a mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.items[..self.len]
+    }
+}
+
+impl<T, const N: usize> RepeatedStorage<T> for ArrayVec<T, N> {
+    fn try_push(
+        &mut self,
+        value: T,
+    ) -> Result<(), T> {
+        match self.items.get_mut(self.len) {
+            Some(item) => {
+                *item = value;
+                self.len += 1;
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+}
+
+impl<T: Default, const N: usize> Default for ArrayVec<T, N> {
+    fn default() -> Self {
+        ArrayVec::new()
+    }
+}
+
+impl<T, const N: usize> Deref for ArrayVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArrayVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T: core::fmt::Debug, const N: usize> core::fmt::Debug for ArrayVec<T, N> {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter,
+    ) -> core::fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for ArrayVec<T, N> {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, const N: usize> Eq for ArrayVec<T, N> {}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ArrayVec<T, N> {
+    type Item = &'a T;
+
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
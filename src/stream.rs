@@ -0,0 +1,392 @@
+//! A module to read sequences of length delimited messages from a stream
+//!
+//! Each message is prefixed with its length encoded as a varint, as written by
+//! `Writer::write_message_with_len_prefix`. Contrary to `Reader`, the stream
+//! is never held in memory as a whole: only the current message is.
+//!
+//! With the `tokio` feature, `AsyncDelimitedReader` and `AsyncDelimitedWriter`
+//! do the same over tokio's `AsyncRead` and `AsyncWrite`.
+
+use std::io::{ErrorKind, Read};
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::errors::{Error, Result};
+use crate::message::MessageRead;
+#[cfg(feature = "tokio")]
+use crate::message::MessageWrite;
+use crate::reader::{BytesReader, DecodeLimits};
+#[cfg(feature = "tokio")]
+use crate::writer::Writer;
+
+/// Adds the byte `b`, the `i`th of a varint, to `value`, returning if it was the last one
+fn varint_byte(
+    value: &mut u64,
+    i: usize,
+    b: u8,
+) -> bool {
+    *value |= ((b & 0x7f) as u64) << (7 * i);
+    b < 0x80
+}
+
+/// Checks the length prefix of a message against `limits`
+fn frame_len(
+    len: u64,
+    limits: &DecodeLimits,
+) -> Result<usize> {
+    let len = usize::try_from(len).map_err(|_| Error::Varint)?;
+    limits.check_message_size(len)?;
+    Ok(len)
+}
+
+/// A reader of varint length delimited messages from a `Read`
+///
+/// Messages are read one at a time into an internal buffer, reused from one
+/// message to the next. The returned messages borrow this buffer, so that
+/// their `&str`, `&[u8]` or `Cow` fields need no copy, and must be dropped
+/// before reading the next one.
+///
+/// The length prefix is read byte by byte: unless `R` is already buffered,
+/// wrap it into a `std::io::BufReader`.
+///
+/// # Examples
+///
+/// ```rust
+/// use quack_protobuf::DelimitedReader;
+///
+/// // two messages: field 1 = 150, then field 1 = 1
+/// let data: &[u8] = &[0x03, 0x08, 0x96, 0x01, 0x02, 0x08, 0x01];
+/// let mut reader = DelimitedReader::new(data);
+/// assert_eq!(reader.next_frame().unwrap(), Some(&[0x08, 0x96, 0x01][..]));
+/// assert_eq!(reader.next_frame().unwrap(), Some(&[0x08, 0x01][..]));
+/// assert_eq!(reader.next_frame().unwrap(), None);
+/// ```
+///
+/// Messages are read with `next_message`, typically in a loop:
+///
+/// ```rust,no_run
+/// # use quack_protobuf::{BytesReader, MessageRead};
+/// # struct LogEntry<'a> { text: &'a str }
+/// # impl<'a> MessageRead<'a> for LogEntry<'a> {
+/// #     fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> quack_protobuf::Result<Self> {
+/// #         r.next_tag(bytes)?;
+/// #         Ok(LogEntry { text: r.read_string(bytes)? })
+/// #     }
+/// # }
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// use quack_protobuf::DelimitedReader;
+///
+/// fn main() -> quack_protobuf::Result<()> {
+///     let mut reader = DelimitedReader::new(BufReader::new(File::open("log.bin")?));
+///     // `entry` borrows the reader until the next iteration
+///     while let Some(entry) = reader.next_message::<LogEntry>()? {
+///         println!("{}", entry.text);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DelimitedReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+    limits: DecodeLimits,
+}
+
+impl<R: Read> DelimitedReader<R> {
+    /// Creates a new reader of the messages of `inner`
+    pub fn new(inner: R) -> Self {
+        DelimitedReader::with_limits(inner, DecodeLimits::default())
+    }
+
+    /// Creates a new reader of the messages of `inner`, each of them decoded within `limits`
+    ///
+    /// A length prefix above `limits.max_message_size` fails with
+    /// `Error::MessageTooLarge` before the message is read.
+    pub fn with_limits(
+        inner: R,
+        limits: DecodeLimits,
+    ) -> Self {
+        DelimitedReader {
+            inner,
+            buffer: Vec::new(),
+            limits,
+        }
+    }
+
+    /// Reads the length prefix of the next message, `None` at the end of the stream
+    fn read_len(&mut self) -> Result<Option<u64>> {
+        let mut len = 0;
+        for i in 0..10 {
+            let mut b = [0];
+            if let Err(e) = self.inner.read_exact(&mut b) {
+                return match e.kind() {
+                    ErrorKind::UnexpectedEof if i == 0 => Ok(None),
+                    _ => Err(e.into()),
+                };
+            }
+            if varint_byte(&mut len, i, b[0]) {
+                return Ok(Some(len));
+            }
+        }
+        Err(Error::Varint)
+    }
+
+    /// Reads the raw bytes of the next message, `None` at the end of the stream
+    ///
+    /// The stream ending within a message fails with an `UnexpectedEof` io error.
+    pub fn next_frame(&mut self) -> Result<Option<&[u8]>> {
+        let len = match self.read_len()? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        let len = frame_len(len, &self.limits)?;
+        self.buffer.clear();
+        // the buffer only grows with the data actually read, whatever the prefix says
+        (&mut self.inner)
+            .take(len as u64)
+            .read_to_end(&mut self.buffer)?;
+        if self.buffer.len() < len {
+            return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+        Ok(Some(&self.buffer))
+    }
+
+    /// Reads and decodes the next message, `None` at the end of the stream
+    pub fn next_message<'a, M: MessageRead<'a>>(&'a mut self) -> Result<Option<M>> {
+        let limits = self.limits;
+        match self.next_frame()? {
+            Some(bytes) => {
+                let mut reader = BytesReader::from_bytes_with_limits(bytes, limits)?;
+                M::from_reader(&mut reader, bytes).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Gets a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps the underlying reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// An asynchronous reader of varint length delimited messages from an `AsyncRead`
+///
+/// The asynchronous counterpart of `DelimitedReader`: messages borrow the
+/// internal buffer and must be dropped before reading the next one.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> quack_protobuf::Result<()> {
+/// use quack_protobuf::{AsyncDelimitedReader, AsyncDelimitedWriter, BytesReader, MessageRead};
+/// # use quack_protobuf::{MessageWrite, Writer, WriterBackend};
+/// # struct Ping(u64);
+/// # impl<'a> MessageRead<'a> for Ping {
+/// #     fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> quack_protobuf::Result<Self> {
+/// #         r.next_tag(bytes)?;
+/// #         Ok(Ping(r.read_uint64(bytes)?))
+/// #     }
+/// # }
+/// # impl MessageWrite for Ping {
+/// #     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> quack_protobuf::Result<()> {
+/// #         w.write_with_tag(8, |w| w.write_uint64(self.0))
+/// #     }
+/// #     fn get_size(&self) -> usize {
+/// #         1 + quack_protobuf::sizeofs::sizeof_varint(self.0)
+/// #     }
+/// # }
+///
+/// let (client, server) = tokio::io::duplex(64);
+/// let mut writer = AsyncDelimitedWriter::new(client);
+/// writer.write_message(&Ping(1)).await?;
+/// writer.write_message(&Ping(2)).await?;
+/// drop(writer);
+///
+/// let mut reader = AsyncDelimitedReader::new(server);
+/// assert_eq!(reader.next_message::<Ping>().await?.unwrap().0, 1);
+/// assert_eq!(reader.next_message::<Ping>().await?.unwrap().0, 2);
+/// assert!(reader.next_message::<Ping>().await?.is_none());
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct AsyncDelimitedReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+    limits: DecodeLimits,
+}
+
+#[cfg(feature = "tokio")]
+impl<R: AsyncRead + Unpin> AsyncDelimitedReader<R> {
+    /// Creates a new reader of the messages of `inner`
+    pub fn new(inner: R) -> Self {
+        AsyncDelimitedReader::with_limits(inner, DecodeLimits::default())
+    }
+
+    /// Creates a new reader of the messages of `inner`, each of them decoded within `limits`
+    ///
+    /// A length prefix above `limits.max_message_size` fails with
+    /// `Error::MessageTooLarge` before the message is read.
+    pub fn with_limits(
+        inner: R,
+        limits: DecodeLimits,
+    ) -> Self {
+        AsyncDelimitedReader {
+            inner,
+            buffer: Vec::new(),
+            limits,
+        }
+    }
+
+    /// Reads the length prefix of the next message, `None` at the end of the stream
+    ///
+    /// Not cancellation safe: the bytes of the prefix already read are lost
+    /// if the future is dropped before it completes.
+    async fn read_len(&mut self) -> Result<Option<u64>> {
+        let mut len = 0;
+        for i in 0..10 {
+            let b = match self.inner.read_u8().await {
+                Ok(b) => b,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            if varint_byte(&mut len, i, b) {
+                return Ok(Some(len));
+            }
+        }
+        Err(Error::Varint)
+    }
+
+    /// Reads the raw bytes of the next message, `None` at the end of the stream
+    ///
+    /// The stream ending within a message fails with an `UnexpectedEof` io error.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is not cancellation safe. If the future is dropped before
+    /// it completes, for instance when another branch of `tokio::select!`
+    /// completes first, the part of the length prefix or of the message
+    /// already read is lost and the stream is left in the middle of a message.
+    pub async fn next_frame(&mut self) -> Result<Option<&[u8]>> {
+        let len = match self.read_len().await? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        let len = frame_len(len, &self.limits)?;
+        self.buffer.clear();
+        // the buffer only grows with the data actually read, whatever the prefix says
+        (&mut self.inner)
+            .take(len as u64)
+            .read_to_end(&mut self.buffer)
+            .await?;
+        if self.buffer.len() < len {
+            return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+        Ok(Some(&self.buffer))
+    }
+
+    /// Reads and decodes the next message, `None` at the end of the stream
+    ///
+    /// Like `next_frame`, this method is not cancellation safe.
+    pub async fn next_message<'a, M: MessageRead<'a>>(&'a mut self) -> Result<Option<M>> {
+        let limits = self.limits;
+        match self.next_frame().await? {
+            Some(bytes) => {
+                let mut reader = BytesReader::from_bytes_with_limits(bytes, limits)?;
+                M::from_reader(&mut reader, bytes).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Gets a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps the underlying reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// An asynchronous writer of varint length delimited messages to an `AsyncWrite`
+///
+/// Each message is encoded into an internal buffer, reused from one message
+/// to the next, then written at once. The framing is the same as
+/// `Writer::write_message_with_len_prefix`.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct AsyncDelimitedWriter<W> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "tokio")]
+impl<W: AsyncWrite + Unpin> AsyncDelimitedWriter<W> {
+    /// Creates a new writer of messages into `inner`
+    pub fn new(inner: W) -> Self {
+        AsyncDelimitedWriter {
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Writes `message` prefixed with its length
+    pub async fn write_message<M: MessageWrite>(
+        &mut self,
+        message: &M,
+    ) -> Result<()> {
+        self.buffer.clear();
+        Writer::new(&mut self.buffer).write_message_with_len_prefix(message)?;
+        self.inner.write_all(&self.buffer).await?;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer
+    pub async fn flush(&mut self) -> Result<()> {
+        self.inner.flush().await?;
+        Ok(())
+    }
+
+    /// Flushes then shuts down the underlying writer, see `AsyncWriteExt::shutdown`
+    pub async fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown().await?;
+        Ok(())
+    }
+
+    /// Gets a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps the underlying writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
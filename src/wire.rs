@@ -0,0 +1,230 @@
+//! A module to walk protobuf messages at the wire level, without their schema
+//!
+//! A `FieldIter` yields every field of a message as its field number and its
+//! raw `WireValue`, borrowed from the input bytes. Nested messages, strings
+//! and packed fields are all `LengthDelimited` and can be walked again with
+//! another `FieldIter` when they are known to be messages.
+//!
+//! `UnknownFields` keeps the raw fields a message does not know about, so that
+//! they can be written back and decoding then encoding a message is lossless.
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, vec::Vec};
+use core::iter::FusedIterator;
+
+use crate::errors::{Error, Result};
+use crate::reader::{
+    BytesReader, WIRE_TYPE_END_GROUP, WIRE_TYPE_FIXED32, WIRE_TYPE_FIXED64, WIRE_TYPE_LENGTH_DELIMITED,
+    WIRE_TYPE_START_GROUP, WIRE_TYPE_VARINT,
+};
+#[cfg(feature = "alloc")]
+use crate::sizeofs::sizeof_varint;
+
+/// The raw value of a field, as encoded on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireValue<'a> {
+    /// A `varint` (wire type 0), `int32`, `uint64`, `sint32`, `bool`, enums ...
+    Varint(u64),
+    /// A little endian 64 bits value (wire type 1), `fixed64`, `sfixed64` or `double`
+    Fixed64(u64),
+    /// A length prefixed chunk (wire type 2), strings, bytes, messages or packed fields
+    LengthDelimited(&'a [u8]),
+    /// The fields enclosed between a start group and its end group tags (wire types 3 and 4)
+    Group(&'a [u8]),
+    /// A little endian 32 bits value (wire type 5), `fixed32`, `sfixed32` or `float`
+    Fixed32(u32),
+}
+
+impl<'a> WireValue<'a> {
+    /// The wire type of the value
+    pub fn wire_type(&self) -> u8 {
+        match self {
+            WireValue::Varint(_) => WIRE_TYPE_VARINT,
+            WireValue::Fixed64(_) => WIRE_TYPE_FIXED64,
+            WireValue::LengthDelimited(_) => WIRE_TYPE_LENGTH_DELIMITED,
+            WireValue::Group(_) => WIRE_TYPE_START_GROUP,
+            WireValue::Fixed32(_) => WIRE_TYPE_FIXED32,
+        }
+    }
+}
+
+/// An iterator over the fields of a message, yielding `(field_number, WireValue)`
+///
+/// Iteration stops after the first error.
+///
+/// # Examples
+///
+/// ```rust
+/// use quack_protobuf::{FieldIter, WireValue};
+///
+/// // field 1: varint 150, field 2: "hi"
+/// let bytes = [0x08, 0x96, 0x01, 0x12, 0x02, b'h', b'i'];
+/// let fields = FieldIter::new(&bytes).collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(
+///     fields,
+///     vec![(1, WireValue::Varint(150)), (2, WireValue::LengthDelimited(b"hi"))]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct FieldIter<'a> {
+    reader: BytesReader,
+    bytes: &'a [u8],
+    failed: bool,
+}
+
+impl<'a> FieldIter<'a> {
+    /// Creates an iterator over all the fields encoded in `bytes`
+    pub fn new(bytes: &'a [u8]) -> Self {
+        FieldIter {
+            reader: BytesReader::from_bytes(bytes),
+            bytes,
+            failed: false,
+        }
+    }
+
+    /// Reads the next field, the reader must not be at the end
+    fn read_field(&mut self) -> Result<(u32, WireValue<'a>)> {
+        let tag = self.reader.next_tag(self.bytes)?;
+        let number = tag >> 3;
+        let value = match (tag & 0x7) as u8 {
+            WIRE_TYPE_VARINT => WireValue::Varint(self.reader.read_varint64(self.bytes)?),
+            WIRE_TYPE_FIXED64 => WireValue::Fixed64(self.reader.read_fixed64(self.bytes)?),
+            WIRE_TYPE_LENGTH_DELIMITED => WireValue::LengthDelimited(self.reader.read_bytes(self.bytes)?),
+            WIRE_TYPE_START_GROUP => WireValue::Group(self.reader.read_group_bytes(self.bytes, tag)?),
+            WIRE_TYPE_END_GROUP => {
+                return Err(
+                    self.reader.error(Error::UnexpectedEndGroup(
+                        number,
+                    )),
+                )
+            }
+            WIRE_TYPE_FIXED32 => WireValue::Fixed32(self.reader.read_fixed32(self.bytes)?),
+            t => return Err(self.reader.error(Error::UnknownWireType(t))),
+        };
+        Ok((number, value))
+    }
+}
+
+impl<'a> Iterator for FieldIter<'a> {
+    type Item = Result<(u32, WireValue<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.reader.is_eof() {
+            return None;
+        }
+        let field = self.read_field();
+        self.failed = field.is_err();
+        Some(field)
+    }
+}
+
+impl<'a> FusedIterator for FieldIter<'a> {}
+
+/// The fields of a message which are not part of its schema, kept as they were read
+///
+/// Readers collect the fields they do not know with `UnknownFields::read`
+/// instead of `BytesReader::read_unknown`, and writers emit them back with
+/// `Writer::write_unknown`, after the known fields. Fields borrow from the
+/// input bytes until `into_owned` is called.
+///
+/// Requires the `alloc` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// use quack_protobuf::{BytesReader, UnknownFields, Writer};
+///
+/// // field 1: varint 150, field 2: "hi"
+/// let bytes = [0x08, 0x96, 0x01, 0x12, 0x02, b'h', b'i'];
+/// let mut reader = BytesReader::from_bytes(&bytes);
+/// let mut unknown = UnknownFields::new();
+/// while !reader.is_eof() {
+///     let tag = reader.next_tag(&bytes).unwrap();
+///     unknown.read(&mut reader, &bytes, tag).unwrap();
+/// }
+/// assert_eq!(unknown.get_size(), bytes.len());
+///
+/// let mut out = Vec::new();
+/// Writer::new(&mut out).write_unknown(&unknown).unwrap();
+/// assert_eq!(out, bytes);
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct UnknownFields<'a> {
+    fields: Vec<(u32, Cow<'a, [u8]>)>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> UnknownFields<'a> {
+    /// Creates an empty set of unknown fields
+    pub fn new() -> Self {
+        UnknownFields::default()
+    }
+
+    /// Reads the value of the field of tag `tag`, which has just been read, and keeps it
+    pub fn read(
+        &mut self,
+        r: &mut BytesReader,
+        bytes: &'a [u8],
+        tag: u32,
+    ) -> Result<()> {
+        let value = r.read_unknown_bytes(bytes, tag)?;
+        self.fields.push((tag, Cow::Borrowed(value)));
+        Ok(())
+    }
+
+    /// Adds a field from its tag and its raw encoded value
+    pub fn push<V: Into<Cow<'a, [u8]>>>(
+        &mut self,
+        tag: u32,
+        value: V,
+    ) {
+        self.fields.push((tag, value.into()));
+    }
+
+    /// Iterates over the fields, as their tag and their raw encoded value
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &[u8])> {
+        self.fields
+            .iter()
+            .map(|(tag, value)| (*tag, &**value))
+    }
+
+    /// Gets the number of fields
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Checks if there is no unknown field
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Removes all the fields
+    pub fn clear(&mut self) {
+        self.fields.clear();
+    }
+
+    /// Copies the borrowed fields so that they no longer depend on the input bytes
+    pub fn into_owned(self) -> UnknownFields<'static> {
+        UnknownFields {
+            fields: self
+                .fields
+                .into_iter()
+                .map(|(tag, value)| {
+                    (
+                        tag,
+                        Cow::Owned(value.into_owned()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Computes the binary size of the fields, tags included
+    pub fn get_size(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|(tag, value)| sizeof_varint(*tag as u64) + value.len())
+            .sum()
+    }
+}
@@ -1,9 +1,17 @@
 //! A module to manage protobuf serialization
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 use crate::errors::{Error, Result};
 use crate::message::MessageWrite;
+use crate::sizeofs::{sizeof_len, sizeof_varint};
+#[cfg(feature = "alloc")]
+use crate::wire::UnknownFields;
 use crate::PackedFixed;
 use byteorder_lite::{ByteOrder, LittleEndian as LE};
+#[cfg(feature = "bytes")]
+use bytes::BufMut;
 
 #[cfg(feature = "std")]
 use byteorder_lite::WriteBytesExt;
@@ -15,7 +23,7 @@ use byteorder_lite::WriteBytesExt;
 /// ```rust
 /// // an automatically generated module which is in a separate file in general
 /// mod foo_bar {
-///     # use quick_protobuf::{MessageWrite, Writer, WriterBackend, Result};
+///     # use quack_protobuf::{MessageWrite, Writer, WriterBackend, Result};
 ///     # use std::borrow::Cow;
 ///     pub struct Foo<'a> { pub name: Option<Cow<'a, str>>, }
 ///     pub struct Bar { pub id: Option<u32> }
@@ -33,13 +41,13 @@ use byteorder_lite::WriteBytesExt;
 /// // in particular it contains a `write_message` function
 /// use foo_bar::{FooBar, Foo, Bar};
 /// use std::borrow::Cow;
-/// use quick_protobuf::Writer;
+/// use quack_protobuf::{BytesWriter, Writer};
 ///
 /// fn main() {
 ///     // let mut r = File::create("...").expect("Cannot create file");
-///     // for the sake of example, we'll use a simpler struct which impl `Write`
-///     let mut r = Vec::new();
-///     let mut writer = Writer::new(&mut r);
+///     // for the sake of example, we'll write into a fixed size buffer, which needs no allocation
+///     let mut r = [0u8; 64];
+///     let mut writer = Writer::new(BytesWriter::new(&mut r));
 ///
 ///     // manually generates a FooBar for the example
 ///     let foobar = FooBar {
@@ -53,12 +61,44 @@ use byteorder_lite::WriteBytesExt;
 /// ```
 pub struct Writer<W: WriterBackend> {
     inner: W,
+    /// whether nested messages are checked against their `get_size`
+    check_sizes: bool,
+    /// whether the sizes cached by nested messages were computed by this encode
+    cached_sizes: bool,
 }
 
 impl<W: WriterBackend> Writer<W> {
     /// Creates a new `ProtobufWriter`
     pub fn new(w: W) -> Writer<W> {
-        Writer { inner: w }
+        Writer {
+            inner: w,
+            check_sizes: false,
+            cached_sizes: false,
+        }
+    }
+
+    /// Checks that the `get_size` of every message written with a length
+    /// prefix matches the bytes its `write_message` actually writes, disabled by default
+    ///
+    /// A mismatch is a bug in a hand written `MessageWrite` which would
+    /// corrupt the length prefix, so it panics. Each nested message is
+    /// written twice, once into a `SizeCounter`, so this is meant for tests
+    /// and debug builds, e.g. `set_check_sizes(cfg!(debug_assertions))`.
+    pub fn set_check_sizes(
+        &mut self,
+        check: bool,
+    ) {
+        self.check_sizes = check;
+    }
+
+    /// Gets a reference to the underlying backend
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Unwraps the underlying backend
+    pub fn into_inner(self) -> W {
+        self.inner
     }
 
     /// Writes a byte which is NOT internally coded as a `varint`
@@ -237,20 +277,26 @@ impl<W: WriterBackend> Writer<W> {
     }
 
     /// Writes packed repeated field: length first then the chunk of data
-    pub fn write_packed<M, F, S>(
+    ///
+    /// `v` is a slice, a collection or any iterator which can be cloned: it
+    /// is walked once to compute the length, then once to write the elements.
+    pub fn write_packed<I, F, S>(
         &mut self,
-        v: &[M],
+        v: I,
         mut write: F,
         size: &S,
     ) -> Result<()>
     where
-        F: FnMut(&mut Self, &M) -> Result<()>,
-        S: Fn(&M) -> usize,
+        I: IntoIterator,
+        I::IntoIter: Clone,
+        F: FnMut(&mut Self, I::Item) -> Result<()>,
+        S: Fn(I::Item) -> usize,
     {
-        if v.is_empty() {
+        let v = v.into_iter();
+        if v.clone().next().is_none() {
             return Ok(());
         }
-        let len: usize = v.iter().map(size).sum();
+        let len: usize = v.clone().map(size).sum();
         self.write_varint(len as u64)?;
         for m in v {
             write(self, m)?;
@@ -271,28 +317,96 @@ impl<W: WriterBackend> Writer<W> {
         let bytes = match pf {
             PackedFixed::NoDataYet => unreachable!(),
             PackedFixed::Borrowed(bytes) => bytes,
+            #[cfg(feature = "alloc")]
             PackedFixed::Owned(contents) => {
-                let len = ::std::mem::size_of::<M>() * contents.len();
+                let len = core::mem::size_of::<M>() * contents.len();
                 unsafe {
-                    ::std::slice::from_raw_parts(
+                    core::slice::from_raw_parts(
                         contents.as_ptr() as *const u8,
                         len,
                     )
                 }
             }
+            #[cfg(not(feature = "alloc"))]
+            PackedFixed::_Phantom(never, _) => match *never {},
         };
         self.write_bytes(bytes)
     }
 
     /// Writes a message which implements `MessageWrite`
+    ///
+    /// Sizes cached by `get_size` are only used once the enclosing message
+    /// had its `get_size` computed by the same encode: a cache left by an
+    /// earlier `get_size` may be stale and is discarded.
     #[cfg_attr(feature = "std", inline)]
     pub fn write_message_with_len_prefix<M: MessageWrite>(
         &mut self,
         m: &M,
     ) -> Result<()> {
-        let len = m.get_size();
+        if !self.check_sizes && self.inner.pb_begin_len_prefixed() {
+            m.take_cached_size();
+            return self.end_len_prefixed(|w| m.write_message(w));
+        }
+        let len = self.message_len(m);
+        self.write_len_prefixed(len, m)
+    }
+
+    /// Gets the size of `m`, cached if this encode computed it already
+    fn message_len<M: MessageWrite>(
+        &self,
+        m: &M,
+    ) -> usize {
+        match m.take_cached_size() {
+            Some(len) if self.cached_sizes && !self.check_sizes => len,
+            _ => {
+                // only the caches of the nested messages, written next, are still needed
+                let len = m.get_size();
+                m.take_cached_size();
+                len
+            }
+        }
+    }
+
+    /// Writes the value started by `pb_begin_len_prefixed` with `write`, then ends it
+    ///
+    /// A failed write is aborted, leaving the backend ready for the next value.
+    fn end_len_prefixed<F>(
+        &mut self,
+        write: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(&mut Self) -> Result<()>,
+    {
+        match write(self) {
+            Ok(()) => self.inner.pb_end_len_prefixed(),
+            Err(e) => {
+                self.inner.pb_abort_len_prefixed();
+                Err(e)
+            }
+        }
+    }
+
+    /// Writes `m` prefixed with `len`, its size just computed
+    fn write_len_prefixed<M: MessageWrite>(
+        &mut self,
+        len: usize,
+        m: &M,
+    ) -> Result<()> {
+        if self.check_sizes {
+            let written = SizeCounter::message_size(m)?;
+            assert_eq!(
+                len,
+                written,
+                "`get_size` of `{}` does not match the bytes written by `write_message`",
+                core::any::type_name::<M>()
+            );
+        }
         self.write_varint(len as u64)?;
-        m.write_message(self)
+        // the `get_size` of `m` cached the sizes of the nested messages
+        let cached_sizes = core::mem::replace(&mut self.cached_sizes, true);
+        let written = m.write_message(self);
+        self.cached_sizes = cached_sizes;
+        written
     }
 
     /// Writes a message which implements `MessageWrite` without adding the length prefix
@@ -304,6 +418,32 @@ impl<W: WriterBackend> Writer<W> {
         m.write_message(self)
     }
 
+    /// Writes a group (proto2) followed by its end group tag
+    ///
+    /// The start group tag must have been written already, e.g. with `write_with_tag`
+    #[cfg_attr(feature = "std", inline)]
+    pub fn write_group<M: MessageWrite>(
+        &mut self,
+        number: u32,
+        m: &M,
+    ) -> Result<()> {
+        m.write_message(self)?;
+        self.write_tag(number << 3 | 4)
+    }
+
+    /// Writes back the unknown fields collected while reading a message, verbatim
+    #[cfg(feature = "alloc")]
+    pub fn write_unknown(
+        &mut self,
+        unknown: &UnknownFields,
+    ) -> Result<()> {
+        for (tag, value) in unknown.iter() {
+            self.write_tag(tag)?;
+            self.inner.pb_write_all(value)?;
+        }
+        Ok(())
+    }
+
     /// Writes another item prefixed with tag
     #[cfg_attr(feature = "std", inline)]
     pub fn write_with_tag<F>(
@@ -321,23 +461,34 @@ impl<W: WriterBackend> Writer<W> {
     /// Writes tag then repeated field
     ///
     /// If array is empty, then do nothing (do not even write the tag)
-    pub fn write_packed_with_tag<M, F, S>(
+    pub fn write_packed_with_tag<I, F, S>(
         &mut self,
         tag: u32,
-        v: &[M],
+        v: I,
         mut write: F,
         size: &S,
     ) -> Result<()>
     where
-        F: FnMut(&mut Self, &M) -> Result<()>,
-        S: Fn(&M) -> usize,
+        I: IntoIterator,
+        I::IntoIter: Clone,
+        F: FnMut(&mut Self, I::Item) -> Result<()>,
+        S: Fn(I::Item) -> usize,
     {
-        if v.is_empty() {
+        let v = v.into_iter();
+        if v.clone().next().is_none() {
             return Ok(());
         }
 
         self.write_tag(tag)?;
-        let len: usize = v.iter().map(size).sum();
+        if self.inner.pb_begin_len_prefixed() {
+            return self.end_len_prefixed(|w| {
+                for m in v {
+                    write(w, m)?;
+                }
+                Ok(())
+            });
+        }
+        let len: usize = v.clone().map(size).sum();
         self.write_varint(len as u64)?;
         for m in v {
             write(self, m)?;
@@ -376,16 +527,19 @@ impl<W: WriterBackend> Writer<W> {
 
         self.write_tag(tag)?;
 
-        let len = ::std::mem::size_of::<M>() * item_size;
+        let len = core::mem::size_of::<M>() * item_size;
         let bytes = match pf {
             PackedFixed::NoDataYet => unreachable!(),
             PackedFixed::Borrowed(bytes) => &bytes[0..len],
+            #[cfg(feature = "alloc")]
             PackedFixed::Owned(contents) => unsafe {
-                ::std::slice::from_raw_parts(
+                core::slice::from_raw_parts(
                     contents.as_ptr() as *const u8,
                     len,
                 )
             },
+            #[cfg(not(feature = "alloc"))]
+            PackedFixed::_Phantom(never, _) => match *never {},
         };
         self.write_bytes(bytes)
     }
@@ -403,61 +557,160 @@ impl<W: WriterBackend> Writer<W> {
         FK: FnMut(&mut Self) -> Result<()>,
         FV: FnMut(&mut Self) -> Result<()>,
     {
+        if self.inner.pb_begin_len_prefixed() {
+            return self.end_len_prefixed(|w| {
+                w.write_tag(tag_key)?;
+                write_key(w)?;
+                w.write_tag(tag_val)?;
+                write_val(w)
+            });
+        }
         self.write_varint(size as u64)?;
         self.write_tag(tag_key)?;
         write_key(self)?;
         self.write_tag(tag_val)?;
         write_val(self)
     }
+
+    /// Write entire map entry whose value is a message, computing the size of the message once
+    pub fn write_map_message<FK, M>(
+        &mut self,
+        key_size: usize,
+        tag_key: u32,
+        mut write_key: FK,
+        tag_val: u32,
+        m: &M,
+    ) -> Result<()>
+    where
+        FK: FnMut(&mut Self) -> Result<()>,
+        M: MessageWrite,
+    {
+        if self.inner.pb_begin_len_prefixed() {
+            return self.end_len_prefixed(|w| {
+                w.write_tag(tag_key)?;
+                write_key(w)?;
+                w.write_tag(tag_val)?;
+                w.write_message_with_len_prefix(m)
+            });
+        }
+        let len = self.message_len(m);
+        let size = sizeof_varint(tag_key as u64) + key_size + sizeof_varint(tag_val as u64) + sizeof_len(len);
+        self.write_varint(size as u64)?;
+        self.write_tag(tag_key)?;
+        write_key(self)?;
+        self.write_tag(tag_val)?;
+        self.write_len_prefixed(len, m)
+    }
+
+    /// Writes a message without length prefix, using the sizes cached by its `get_size` just computed
+    fn write_sized_message<M: MessageWrite>(
+        &mut self,
+        m: &M,
+    ) -> Result<()> {
+        m.take_cached_size();
+        self.cached_sizes = true;
+        let written = m.write_message(self);
+        self.cached_sizes = false;
+        written
+    }
 }
 
-/// Serialize a `MessageWrite` into a `Vec`
-#[cfg(feature = "std")]
-pub fn serialize_into_vec<M: MessageWrite>(message: &M) -> Result<Vec<u8>> {
+/// Encodes a message into a `Vec`, without a length prefix
+#[cfg(feature = "alloc")]
+pub fn encode_to_vec<M: MessageWrite>(message: &M) -> Result<Vec<u8>> {
     let len = message.get_size();
-    let mut v = Vec::with_capacity(crate::sizeofs::sizeof_len(
-        len,
+    let mut writer = Writer::new(Vec::with_capacity(len));
+    writer.write_sized_message(message)?;
+    Ok(writer.into_inner())
+}
+
+/// Encodes a message into a `Vec`, prefixed with its length
+#[cfg(feature = "alloc")]
+pub fn encode_length_delimited_to_vec<M: MessageWrite>(message: &M) -> Result<Vec<u8>> {
+    let len = message.get_size();
+    let mut writer = Writer::new(Vec::with_capacity(
+        sizeof_len(len),
     ));
-    {
-        let mut writer = Writer::new(&mut v);
-        writer.write_message_with_len_prefix(message)?;
+    writer.cached_sizes = true;
+    writer.write_message_with_len_prefix(message)?;
+    Ok(writer.into_inner())
+}
+
+/// Serialize a `MessageWrite` into a `Vec`, same as `encode_length_delimited_to_vec`
+#[cfg(feature = "alloc")]
+pub fn serialize_into_vec<M: MessageWrite>(message: &M) -> Result<Vec<u8>> {
+    encode_length_delimited_to_vec(message)
+}
+
+/// Encodes a message at the start of `out`, without a length prefix, returning the number of bytes written
+///
+/// Fails with `Error::OutputBufferTooSmall`, before writing anything, if
+/// the message does not fit.
+///
+/// ```rust
+/// use quack_protobuf::{decode, encode_to_slice, Error};
+/// # use quack_protobuf::{BytesReader, MessageRead, MessageWrite, Result, Writer, WriterBackend};
+/// # #[derive(Debug, PartialEq)]
+/// # struct Point { x: i32 }
+/// # impl<'a> MessageRead<'a> for Point {
+/// #     fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+/// #         let mut x = 0;
+/// #         while !r.is_eof() {
+/// #             match r.next_tag(bytes)? {
+/// #                 8 => x = r.read_int32(bytes)?,
+/// #                 t => r.read_unknown(bytes, t)?,
+/// #             }
+/// #         }
+/// #         Ok(Point { x })
+/// #     }
+/// # }
+/// # impl MessageWrite for Point {
+/// #     fn get_size(&self) -> usize { 1 + quack_protobuf::sizeofs::sizeof_int32(self.x) }
+/// #     fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+/// #         w.write_with_tag(8, |w| w.write_int32(self.x))
+/// #     }
+/// # }
+///
+/// let mut buf = [0u8; 8];
+/// let len = encode_to_slice(&Point { x: 150 }, &mut buf).unwrap();
+/// assert_eq!(&buf[..len], &[0x08, 0x96, 0x01]);
+/// assert_eq!(decode::<Point>(&buf[..len]).unwrap(), Point { x: 150 });
+///
+/// let err = encode_to_slice(&Point { x: 150 }, &mut buf[..2]).unwrap_err();
+/// assert!(matches!(err, Error::OutputBufferTooSmall));
+/// ```
+pub fn encode_to_slice<M: MessageWrite>(
+    message: &M,
+    out: &mut [u8],
+) -> Result<usize> {
+    let len = message.get_size();
+    if out.len() < len {
+        message.take_cached_size();
+        return Err(Error::OutputBufferTooSmall);
     }
-    Ok(v)
+    let mut writer = Writer::new(BytesWriter::new(out));
+    writer.write_sized_message(message)?;
+    Ok(writer.into_inner().cursor())
 }
 
-// /// Serialize a `MessageWrite` into a byte slice
-// pub fn serialize_into_slice_with_len_prefix<M: MessageWrite>(
-//     message: &M,
-//     out: &mut [u8],
-// ) -> Result<()> {
-//     let len = message.get_size();
-//     if out.len() < crate::sizeofs::sizeof_len(len) {
-//         return Err(Error::OutputBufferTooSmall);
-//     }
-//     {
-//         let mut writer = Writer::new(BytesWriter::new(out));
-//         writer.write_message_with_len_prefix(message)?;
-//     }
-
-//     Ok(())
-// }
-
-// /// Serialize a `MessageWrite` into a byte slice without a length prefix
-// pub fn serialize_into_slice<M: MessageWrite>(
-//     message: &M,
-//     out: &mut [u8],
-// ) -> Result<usize> {
-//     let len = message.get_size();
-//     if out.len() < len {
-//         return Err(Error::OutputBufferTooSmall);
-//     }
-//     {
-//         let mut writer = Writer::new(BytesWriter::new(out));
-//         writer.write_message(message)?;
-//     }
-
-//     Ok(len)
-// }
+/// Encodes a message at the start of `out`, prefixed with its length, returning the number of bytes written
+///
+/// Fails with `Error::OutputBufferTooSmall`, before writing anything, if
+/// the message does not fit.
+pub fn encode_length_delimited_to_slice<M: MessageWrite>(
+    message: &M,
+    out: &mut [u8],
+) -> Result<usize> {
+    let len = message.get_size();
+    if out.len() < sizeof_len(len) {
+        message.take_cached_size();
+        return Err(Error::OutputBufferTooSmall);
+    }
+    let mut writer = Writer::new(BytesWriter::new(out));
+    writer.cached_sizes = true;
+    writer.write_message_with_len_prefix(message)?;
+    Ok(writer.into_inner().cursor())
+}
 
 /// Writer backend abstraction
 pub trait WriterBackend {
@@ -508,6 +761,311 @@ pub trait WriterBackend {
         &mut self,
         buf: &[u8],
     ) -> Result<()>;
+
+    /// Starts a length delimited value whose length prefix is written once it is complete
+    ///
+    /// Backends able to back-patch their output return `true`, so that
+    /// `Writer` skips computing sizes upfront with `get_size`. The default
+    /// returns `false`: the caller writes the prefix itself.
+    fn pb_begin_len_prefixed(&mut self) -> bool {
+        false
+    }
+
+    /// Ends the value started by the last successful `pb_begin_len_prefixed`, writing its length prefix
+    ///
+    /// Fails with `Error::UnbalancedLenPrefix` if no value was started.
+    fn pb_end_len_prefixed(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Drops the value started by the last successful `pb_begin_len_prefixed`, whose writing failed
+    fn pb_abort_len_prefixed(&mut self) {}
+}
+
+/// A writer backend into a `Vec` which back-patches length prefixes, encoding in a single pass
+///
+/// `Writer::write_message_with_len_prefix` then writes nested messages
+/// without calling their `get_size`, which otherwise walks every message
+/// once per level of nesting. Prefixes are reserved while writing and
+/// spliced in by `into_vec`, in a single copy of the output.
+///
+/// ```rust
+/// use quack_protobuf::{BackPatchWriter, Writer};
+///
+/// let mut writer = Writer::new(BackPatchWriter::new());
+/// writer.write_with_tag(10, |w| w.write_string("abc")).unwrap();
+/// assert_eq!(writer.into_inner().into_vec().unwrap(), b"\x0a\x03abc");
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct BackPatchWriter {
+    buf: Vec<u8>,
+    /// reserved prefixes, position in `buf` then length, in the order they start
+    prefixes: Vec<(usize, usize)>,
+    /// values not ended yet: index in `prefixes` then `prefix_len` when started
+    open: Vec<(usize, usize)>,
+    /// total size of the prefixes ended so far
+    prefix_len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl BackPatchWriter {
+    /// Create a new, empty, BackPatchWriter
+    pub fn new() -> BackPatchWriter {
+        BackPatchWriter::default()
+    }
+
+    /// Create a new BackPatchWriter with room for `capacity` bytes
+    pub fn with_capacity(capacity: usize) -> BackPatchWriter {
+        BackPatchWriter {
+            buf: Vec::with_capacity(capacity),
+            ..BackPatchWriter::default()
+        }
+    }
+
+    /// Gets the output, length prefixes included
+    ///
+    /// Fails with `Error::UnbalancedLenPrefix` if a value was started and
+    /// never ended, as its prefix would be missing.
+    pub fn into_vec(self) -> Result<Vec<u8>> {
+        if !self.open.is_empty() {
+            return Err(Error::UnbalancedLenPrefix);
+        }
+        if self.prefixes.is_empty() {
+            return Ok(self.buf);
+        }
+        let mut out = Vec::with_capacity(self.buf.len() + self.prefix_len);
+        let mut last = 0;
+        for (pos, mut len) in self.prefixes {
+            out.extend_from_slice(&self.buf[last..pos]);
+            while len > 0x7f {
+                out.push(((len as u8) & 0x7f) | 0x80);
+                len >>= 7;
+            }
+            out.push(len as u8);
+            last = pos;
+        }
+        out.extend_from_slice(&self.buf[last..]);
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl WriterBackend for BackPatchWriter {
+    #[inline(always)]
+    fn pb_write_u8(
+        &mut self,
+        x: u8,
+    ) -> Result<()> {
+        self.buf.push(x);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_u32(
+        &mut self,
+        x: u32,
+    ) -> Result<()> {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_i32(
+        &mut self,
+        x: i32,
+    ) -> Result<()> {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_f32(
+        &mut self,
+        x: f32,
+    ) -> Result<()> {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_u64(
+        &mut self,
+        x: u64,
+    ) -> Result<()> {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_i64(
+        &mut self,
+        x: i64,
+    ) -> Result<()> {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_f64(
+        &mut self,
+        x: f64,
+    ) -> Result<()> {
+        self.buf.extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_all(
+        &mut self,
+        buf: &[u8],
+    ) -> Result<()> {
+        self.buf.extend_from_slice(buf);
+        Ok(())
+    }
+
+    #[inline]
+    fn pb_begin_len_prefixed(&mut self) -> bool {
+        self.open.push((
+            self.prefixes.len(),
+            self.prefix_len,
+        ));
+        self.prefixes.push((self.buf.len(), 0));
+        true
+    }
+
+    #[inline]
+    fn pb_end_len_prefixed(&mut self) -> Result<()> {
+        let (index, prefix_len) = self
+            .open
+            .pop()
+            .ok_or(Error::UnbalancedLenPrefix)?;
+        let (pos, len) = &mut self.prefixes[index];
+        // nested prefixes are part of the length
+        *len = self.buf.len() - *pos + self.prefix_len - prefix_len;
+        self.prefix_len += sizeof_varint(*len as u64);
+        Ok(())
+    }
+
+    fn pb_abort_len_prefixed(&mut self) {
+        // the value and the prefixes it reserved are dropped, as if it had not been started
+        if let Some((index, prefix_len)) = self.open.pop() {
+            self.buf.truncate(self.prefixes[index].0);
+            self.prefixes.truncate(index);
+            self.prefix_len = prefix_len;
+        }
+    }
+}
+
+/// A writer backend counting the bytes written instead of storing them
+///
+/// Writing a message into it gives its encoded size, e.g. to implement or
+/// check `MessageWrite::get_size`.
+///
+/// ```rust
+/// use quack_protobuf::{SizeCounter, Writer};
+///
+/// let mut writer = Writer::new(SizeCounter::new());
+/// writer.write_string("abc").unwrap();
+/// writer.write_fixed64(7).unwrap();
+/// assert_eq!(writer.get_ref().size(), 12);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeCounter {
+    size: usize,
+}
+
+impl SizeCounter {
+    /// Create a new SizeCounter, at 0 bytes
+    pub fn new() -> SizeCounter {
+        SizeCounter::default()
+    }
+
+    /// Gets the number of bytes written so far
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Computes the size of `m`, without length prefix, by running its `write_message`
+    pub fn message_size<M: MessageWrite>(m: &M) -> Result<usize> {
+        let mut writer = Writer::new(SizeCounter::new());
+        m.write_message(&mut writer)?;
+        Ok(writer.inner.size)
+    }
+}
+
+impl WriterBackend for SizeCounter {
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_u8(
+        &mut self,
+        _: u8,
+    ) -> Result<()> {
+        self.size += 1;
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_u32(
+        &mut self,
+        _: u32,
+    ) -> Result<()> {
+        self.size += 4;
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_i32(
+        &mut self,
+        _: i32,
+    ) -> Result<()> {
+        self.size += 4;
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_f32(
+        &mut self,
+        _: f32,
+    ) -> Result<()> {
+        self.size += 4;
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_u64(
+        &mut self,
+        _: u64,
+    ) -> Result<()> {
+        self.size += 8;
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_i64(
+        &mut self,
+        _: i64,
+    ) -> Result<()> {
+        self.size += 8;
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_f64(
+        &mut self,
+        _: f64,
+    ) -> Result<()> {
+        self.size += 8;
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_all(
+        &mut self,
+        buf: &[u8],
+    ) -> Result<()> {
+        self.size += buf.len();
+        Ok(())
+    }
 }
 
 /// A writer backend for byte buffers
@@ -521,6 +1079,16 @@ impl<'a> BytesWriter<'a> {
     pub fn new(buf: &'a mut [u8]) -> BytesWriter<'a> {
         BytesWriter { buf, cursor: 0 }
     }
+
+    /// Gets the number of bytes written, at the start of the buffer
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Gets the number of bytes which can still be written
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.cursor
+    }
 }
 
 impl<'a> WriterBackend for BytesWriter<'a> {
@@ -655,6 +1223,142 @@ impl<'a> WriterBackend for BytesWriter<'a> {
     }
 }
 
+/// A writer backend for any `bytes::BufMut`, e.g. a `BytesMut` growing as needed
+///
+/// ```rust
+/// use bytes::BytesMut;
+/// use quack_protobuf::{BufMutWriter, Writer};
+///
+/// let mut buf = BytesMut::new();
+/// let mut writer = Writer::new(BufMutWriter::new(&mut buf));
+/// writer.write_string("abc").unwrap();
+/// assert_eq!(&buf[..], b"\x03abc");
+/// ```
+#[cfg(feature = "bytes")]
+#[derive(Debug)]
+pub struct BufMutWriter<B: BufMut> {
+    buf: B,
+}
+
+#[cfg(feature = "bytes")]
+impl<B: BufMut> BufMutWriter<B> {
+    /// Create a new BufMutWriter to write into `buf`
+    pub fn new(buf: B) -> BufMutWriter<B> {
+        BufMutWriter { buf }
+    }
+
+    /// Gets a reference to the underlying buffer
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// Gets a mutable reference to the underlying buffer
+    pub fn get_mut(&mut self) -> &mut B {
+        &mut self.buf
+    }
+
+    /// Unwraps the underlying buffer
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+
+    /// Checks that `len` more bytes can be written, as `BufMut` panics otherwise
+    #[cfg_attr(feature = "std", inline(always))]
+    fn reserve(
+        &self,
+        len: usize,
+    ) -> Result<()> {
+        if self.buf.remaining_mut() < len {
+            Err(Error::UnexpectedEndOfBuffer)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: BufMut> WriterBackend for BufMutWriter<B> {
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_u8(
+        &mut self,
+        x: u8,
+    ) -> Result<()> {
+        self.reserve(1)?;
+        self.buf.put_u8(x);
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_u32(
+        &mut self,
+        x: u32,
+    ) -> Result<()> {
+        self.reserve(4)?;
+        self.buf.put_u32_le(x);
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_i32(
+        &mut self,
+        x: i32,
+    ) -> Result<()> {
+        self.reserve(4)?;
+        self.buf.put_i32_le(x);
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_f32(
+        &mut self,
+        x: f32,
+    ) -> Result<()> {
+        self.reserve(4)?;
+        self.buf.put_f32_le(x);
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_u64(
+        &mut self,
+        x: u64,
+    ) -> Result<()> {
+        self.reserve(8)?;
+        self.buf.put_u64_le(x);
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_i64(
+        &mut self,
+        x: i64,
+    ) -> Result<()> {
+        self.reserve(8)?;
+        self.buf.put_i64_le(x);
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_f64(
+        &mut self,
+        x: f64,
+    ) -> Result<()> {
+        self.reserve(8)?;
+        self.buf.put_f64_le(x);
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "std", inline(always))]
+    fn pb_write_all(
+        &mut self,
+        buf: &[u8],
+    ) -> Result<()> {
+        self.reserve(buf.len())?;
+        self.buf.put_slice(buf);
+        Ok(())
+    }
+}
+
 #[cfg(feature = "std")]
 impl<W: std::io::Write> WriterBackend for W {
     #[inline(always)]
@@ -721,3 +1425,79 @@ impl<W: std::io::Write> WriterBackend for W {
         self.write_all(buf).map_err(|e| e.into())
     }
 }
+
+/// Without `std`, `Vec<u8>` and `&mut Vec<u8>` are backends as they are with `std::io::Write`
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+impl<V: AsMut<Vec<u8>>> WriterBackend for V {
+    #[inline(always)]
+    fn pb_write_u8(
+        &mut self,
+        x: u8,
+    ) -> Result<()> {
+        self.as_mut().push(x);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_u32(
+        &mut self,
+        x: u32,
+    ) -> Result<()> {
+        self.as_mut().extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_i32(
+        &mut self,
+        x: i32,
+    ) -> Result<()> {
+        self.as_mut().extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_f32(
+        &mut self,
+        x: f32,
+    ) -> Result<()> {
+        self.as_mut().extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_u64(
+        &mut self,
+        x: u64,
+    ) -> Result<()> {
+        self.as_mut().extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_i64(
+        &mut self,
+        x: i64,
+    ) -> Result<()> {
+        self.as_mut().extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_f64(
+        &mut self,
+        x: f64,
+    ) -> Result<()> {
+        self.as_mut().extend_from_slice(&x.to_le_bytes());
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn pb_write_all(
+        &mut self,
+        buf: &[u8],
+    ) -> Result<()> {
+        self.as_mut().extend_from_slice(buf);
+        Ok(())
+    }
+}
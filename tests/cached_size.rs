@@ -0,0 +1,177 @@
+#![cfg(feature = "std")]
+
+use std::collections::BTreeMap;
+
+use quack_protobuf::{
+    decode, encode_length_delimited_to_vec, encode_to_vec, BackPatchWriter, BytesReader, CachedSize, Error,
+    MessageWrite, Result, Writer, WriterBackend,
+};
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Child {
+    #[pb(1)]
+    s: String,
+    #[pb(cached_size)]
+    cached_size: CachedSize,
+}
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Parent {
+    #[pb(1)]
+    id: u32,
+    #[pb(3)]
+    child: Child,
+    #[pb(4)]
+    children: BTreeMap<u32, Child>,
+    #[pb(cached_size)]
+    cached_size: CachedSize,
+}
+
+fn child(s: &str) -> Child {
+    Child {
+        s: s.to_string(),
+        ..Default::default()
+    }
+}
+
+fn parent() -> Parent {
+    Parent {
+        id: 7,
+        child: child("a"),
+        children: BTreeMap::from([(1, child("b"))]),
+        ..Default::default()
+    }
+}
+
+fn write(parent: &Parent) -> Vec<u8> {
+    let mut writer = Writer::new(Vec::new());
+    writer.write_message(parent).unwrap();
+    writer.into_inner()
+}
+
+#[test]
+fn mutate_after_get_size() {
+    let mut p = parent();
+    p.child.get_size();
+    p.children[&1].get_size();
+    p.child.s = "x".repeat(300);
+    p.children.get_mut(&1).unwrap().s = "y".repeat(200);
+
+    let bytes = write(&p);
+    assert_eq!(bytes.len(), p.get_size());
+    assert_eq!(
+        decode::<Parent>(&bytes).unwrap(),
+        p
+    );
+}
+
+#[test]
+fn mutate_after_top_level_get_size() {
+    let mut p = parent();
+    p.get_size();
+    p.child.s = "x".repeat(300);
+
+    for bytes in [write(&p), encode_to_vec(&p).unwrap()] {
+        assert_eq!(
+            decode::<Parent>(&bytes).unwrap(),
+            p
+        );
+    }
+
+    p.get_size();
+    p.children.get_mut(&1).unwrap().s.clear();
+    let bytes = encode_length_delimited_to_vec(&p).unwrap();
+    let mut r = BytesReader::from_bytes(&bytes);
+    let back = r.read_message::<Parent>(&bytes).unwrap();
+    assert_eq!(back, p);
+}
+
+#[test]
+fn caches_match_get_size() {
+    let p = parent();
+    let bytes = encode_to_vec(&p).unwrap();
+    assert_eq!(bytes.len(), p.get_size());
+    assert_eq!(
+        decode::<Parent>(&bytes).unwrap(),
+        p
+    );
+
+    let mut writer = Writer::new(Vec::new());
+    writer.set_check_sizes(true);
+    writer.write_message(&p).unwrap();
+    assert_eq!(writer.into_inner(), bytes);
+
+    let mut writer = Writer::new(BackPatchWriter::new());
+    writer.write_message(&p).unwrap();
+    assert_eq!(
+        writer.into_inner().into_vec().unwrap(),
+        bytes
+    );
+}
+
+/// A message failing halfway through its writing
+struct Failing;
+
+impl MessageWrite for Failing {
+    fn get_size(&self) -> usize {
+        4
+    }
+
+    fn write_message<W: WriterBackend>(
+        &self,
+        w: &mut Writer<W>,
+    ) -> Result<()> {
+        w.write_with_tag(10, |w| w.write_string("ab"))?;
+        Err(Error::UnknownField)
+    }
+}
+
+#[test]
+fn back_patch_after_failed_write() {
+    let mut writer = Writer::new(BackPatchWriter::new());
+    writer
+        .write_with_tag(10, |w| w.write_string("a"))
+        .unwrap();
+    assert!(matches!(
+        writer.write_with_tag(18, |w| w
+            .write_message_with_len_prefix(&Failing)),
+        Err(Error::UnknownField)
+    ));
+    // the failed message is dropped, its tag aside, and the next prefixes are not shifted
+    writer
+        .write_with_tag(26, |w| {
+            w.write_message_with_len_prefix(&child("b"))
+        })
+        .unwrap();
+    assert_eq!(
+        writer.into_inner().into_vec().unwrap(),
+        b"\x0a\x01a\x12\x1a\x03\x0a\x01b"
+    );
+
+    let mut backend = BackPatchWriter::new();
+    assert!(matches!(
+        backend.pb_end_len_prefixed(),
+        Err(Error::UnbalancedLenPrefix)
+    ));
+    assert!(backend.pb_begin_len_prefixed());
+    backend.pb_end_len_prefixed().unwrap();
+    assert!(matches!(
+        backend.pb_end_len_prefixed(),
+        Err(Error::UnbalancedLenPrefix)
+    ));
+    assert_eq!(
+        backend.into_vec().unwrap(),
+        [0]
+    );
+
+    // a value never ended has no prefix to write
+    let mut backend = BackPatchWriter::new();
+    assert!(backend.pb_begin_len_prefixed());
+    backend.pb_write_u8(1).unwrap();
+    assert!(backend.pb_begin_len_prefixed());
+    backend.pb_end_len_prefixed().unwrap();
+    assert!(matches!(
+        backend.into_vec(),
+        Err(Error::UnbalancedLenPrefix)
+    ));
+}
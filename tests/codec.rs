@@ -0,0 +1,153 @@
+//! Frames messages with `ProtobufCodec`, through `FramedRead` and `FramedWrite`
+
+#![cfg(feature = "tokio-util")]
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::BytesMut;
+use futures_util::{SinkExt, StreamExt};
+use quack_protobuf::{encode_to_vec, Error, ProtobufCodec};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio_util::codec::{Decoder, Encoder, FramedRead, FramedWrite};
+
+#[derive(Debug, Default, Clone, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Log {
+    #[pb(1)]
+    id: u32,
+    #[pb(2)]
+    text: String,
+}
+
+fn log(
+    id: u32,
+    text: &str,
+) -> Log {
+    Log {
+        id,
+        text: text.to_string(),
+    }
+}
+
+/// Varint length delimited encoding of `logs`
+fn frames(logs: &[Log]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for l in logs {
+        let message = encode_to_vec(l).unwrap();
+        assert!(message.len() < 0x80);
+        bytes.push(message.len() as u8);
+        bytes.extend_from_slice(&message);
+    }
+    bytes
+}
+
+/// A reader yielding its bytes one at a time, so that every frame is split
+struct Trickle<'a>(&'a [u8]);
+
+impl AsyncRead for Trickle<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if let Some((first, rest)) = self.0.split_first() {
+            buf.put_slice(&[*first]);
+            self.0 = rest;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn split_frames() {
+    let logs = [log(1, "start"), Log::default(), log(150, &"x".repeat(100))];
+    let bytes = frames(&logs);
+    let mut read = FramedRead::new(
+        Trickle(&bytes),
+        ProtobufCodec::<Log>::new(),
+    );
+    for l in &logs {
+        assert_eq!(
+            &read.next().await.unwrap().unwrap(),
+            l
+        );
+    }
+    assert!(read.next().await.is_none());
+}
+
+#[tokio::test]
+async fn end_of_stream_in_a_frame() {
+    let bytes = frames(&[log(1, "start"), log(2, "end")]);
+    let mut read = FramedRead::new(
+        &bytes[..bytes.len() - 1],
+        ProtobufCodec::<Log>::new(),
+    );
+    assert_eq!(
+        read.next().await.unwrap().unwrap(),
+        log(1, "start")
+    );
+    assert!(matches!(
+        read.next().await,
+        Some(Err(Error::Io(_)))
+    ));
+}
+
+#[tokio::test]
+async fn encoding() {
+    let logs = [log(1, "start"), log(2, "end")];
+    let mut write = FramedWrite::new(
+        Vec::new(),
+        ProtobufCodec::<Log>::new(),
+    );
+    for l in &logs {
+        write.send(l.clone()).await.unwrap();
+    }
+    let bytes = write.into_inner();
+    assert_eq!(bytes, frames(&logs));
+
+    // and back
+    let read = FramedRead::new(
+        &bytes[..],
+        ProtobufCodec::<Log>::new(),
+    );
+    let decoded = read.map(Result::unwrap).collect::<Vec<_>>().await;
+    assert_eq!(decoded, logs);
+}
+
+#[tokio::test]
+async fn max_frame_size() {
+    let bytes = frames(&[log(1, "a"), log(2, "too long")]);
+    let mut read = FramedRead::new(
+        &bytes[..],
+        ProtobufCodec::<Log>::with_max_frame_size(5),
+    );
+    assert_eq!(
+        read.next().await.unwrap().unwrap(),
+        log(1, "a")
+    );
+    assert!(matches!(
+        read.next().await,
+        Some(Err(Error::MessageTooLarge {
+            size: 12,
+            limit: 5
+        }))
+    ));
+
+    // the length prefix is enough to reject a frame, before its content arrives
+    let mut codec = ProtobufCodec::<Log>::with_max_frame_size(5);
+    assert_eq!(codec.max_frame_size(), 5);
+    let mut buf = BytesMut::from(&[0x0c][..]);
+    assert!(matches!(
+        codec.decode(&mut buf),
+        Err(Error::MessageTooLarge { size: 12, limit: 5 })
+    ));
+
+    // messages too large are not encoded
+    let mut buf = BytesMut::new();
+    assert!(matches!(
+        codec.encode(log(2, "too long"), &mut buf),
+        Err(Error::MessageTooLarge { size: 12, limit: 5 })
+    ));
+    assert!(buf.is_empty());
+}
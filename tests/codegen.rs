@@ -0,0 +1,313 @@
+//! Compiles and exercises the code generated for `tests/protos/fixtures`
+
+#![cfg(feature = "std")]
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use quack_protobuf::{
+    decode, decode_into, encode_to_vec, BytesReader, DecodeLimits, Error, MessageMerge, MessageRead, MessageWrite,
+};
+
+mod generated {
+    include!("generated/fixtures/mod.rs");
+}
+
+use generated::fixtures::{p2, p3};
+
+mod generated_collisions {
+    include!("generated/collisions/mod.rs");
+}
+
+use generated_collisions::collisions as c;
+
+fn point(
+    x: i32,
+    y: i32,
+) -> p3::Point {
+    p3::Point {
+        x,
+        y,
+        ..Default::default()
+    }
+}
+
+fn shape() -> p3::Shape<'static> {
+    p3::Shape {
+        name: "square".into(),
+        points: vec![point(1, -1), point(0, 0)],
+        color: p3::Color::GREEN,
+        ids: vec![1, -2, 300],
+        weights: vec![0.5, 2.0].into(),
+        tags: [("a".into(), -1), ("b".into(), 1 << 40)].into(),
+        by_id: [(3, point(3, 3)), (-4, point(-4, 4))].into(),
+        colors: [("sky".into(), p3::Color::RED)].into(),
+        kind: p3::mod_Shape::OneOfkind::circle(p3::mod_Shape::Circle { radius: 1.5 }),
+        maybe: Some(0),
+        data: Cow::Borrowed(b"\x00\xff"),
+        child: Some(Box::new(p3::Shape {
+            name: "child".into(),
+            ..Default::default()
+        })),
+        children: vec![p3::Shape::default()],
+        flags: vec![true, false],
+        f32: 7,
+        sf64: -7,
+        inner: p3::mod_Shape::Inner::B,
+        status: p2::Status::RUNNING,
+    }
+}
+
+#[test]
+fn proto3_roundtrip() {
+    let shape = shape();
+    let bytes = encode_to_vec(&shape).unwrap();
+    assert_eq!(bytes.len(), shape.get_size());
+    assert_eq!(
+        decode::<p3::Shape>(&bytes).unwrap(),
+        shape
+    );
+
+    // default values are not written, except for explicitly optional fields
+    let empty = p3::Shape {
+        maybe: Some(0),
+        ..Default::default()
+    };
+    assert_eq!(
+        encode_to_vec(&empty).unwrap(),
+        [0x60, 0x00]
+    );
+}
+
+#[test]
+fn proto2_defaults() {
+    let d = decode::<p2::Defaults>(&[0x08, 0x01]).unwrap();
+    assert_eq!(d.id, 1);
+    assert_eq!(d.name, "bob");
+    assert_eq!(d.status, p2::Status::STARTED);
+    assert_eq!(d.ratio, 1.5);
+    assert_eq!(&*d.blob, b"a\x00\"b");
+    assert_eq!(d.f, f32::INFINITY);
+    assert_eq!(d.big, i64::MIN);
+    assert!(d.on);
+    assert_eq!(d.neg, -3);
+
+    let d = p2::Defaults {
+        id: 2,
+        unpacked: vec![1, 2],
+        packed: vec![3, 4],
+        next: Some(Box::default()),
+        ..Default::default()
+    };
+    let bytes = encode_to_vec(&d).unwrap();
+    assert_eq!(
+        decode::<p2::Defaults>(&bytes).unwrap(),
+        d
+    );
+}
+
+#[test]
+fn nan_defaults() {
+    // a NaN default is not written, nor counted
+    let d = p2::NanDefaults::default();
+    assert!(d.d.is_nan() && d.f.is_nan());
+    assert_eq!(d.get_size(), 0);
+    assert!(encode_to_vec(&d).unwrap().is_empty());
+
+    // any number is
+    let d = p2::NanDefaults {
+        d: 0.,
+        f: f32::INFINITY,
+    };
+    let bytes = encode_to_vec(&d).unwrap();
+    assert_eq!(bytes.len(), 9 + 5);
+    assert_eq!(
+        decode::<p2::NanDefaults>(&bytes).unwrap(),
+        d
+    );
+}
+
+#[test]
+fn groups_roundtrip() {
+    use p2::mod_WithGroups::{mod_Res, Item, Res};
+
+    let g = p2::WithGroups {
+        a: Some(1),
+        res: Some(Res {
+            url: Some("u".into()),
+            inner: vec![mod_Res::Inner { x: 5 }],
+        }),
+        item: vec![Item { id: Some(7) }, Item { id: None }],
+    };
+    let bytes = encode_to_vec(&g).unwrap();
+    assert_eq!(
+        decode::<p2::WithGroups>(&bytes).unwrap(),
+        g
+    );
+}
+
+#[test]
+fn keyword_names() {
+    use p2::mod_Self_pb::{match_pb, OneOfmod};
+
+    let s = p2::Self_pb {
+        type_pb: Some(1),
+        fn_pb: Some("f".into()),
+        self_pb: Some(true),
+        loop_pb: Some(match_pb::in_pb),
+        mod_pb: OneOfmod::use_pb("u".into()),
+    };
+    let t = p2::type_pb {
+        Self_pb: Some(s.clone()),
+        struct_pb: vec![s.clone(), p2::Self_pb::default()],
+        impl_pb: [("k".to_string(), s)].into(),
+    };
+    let bytes = encode_to_vec(&t).unwrap();
+    assert_eq!(
+        decode::<p2::type_pb>(&bytes).unwrap(),
+        t
+    );
+}
+
+#[test]
+fn colliding_names() {
+    let result = c::Result { ok: Some("ok".into()) };
+    let cow = c::Cow {
+        result: result.clone(),
+        options: vec![c::Option { some: 1 }, c::Option::default()],
+    };
+    let b = c::Box {
+        next: Some(Box::new(c::Box {
+            kind: c::mod_Box::OneOfkind::cow(cow),
+            ..Default::default()
+        })),
+        by_name: [(
+            "a".into(),
+            c::Option::default(),
+        )]
+        .into(),
+        kind: c::mod_Box::OneOfkind::name("b".into()),
+    };
+    assert_eq!(c::Option::default().some, 3);
+    let bytes = encode_to_vec(&b).unwrap();
+    assert_eq!(
+        decode::<c::Box>(&bytes).unwrap(),
+        b
+    );
+
+    let d = c::Default {
+        result: Some(c::mod_Default::Result {
+            writer: Some(c::Writer {
+                data: vec![Cow::Borrowed(b"w")],
+                packed: vec![1, 2],
+            }),
+        }),
+        top: Some(result),
+    };
+    let bytes = encode_to_vec(&d).unwrap();
+    assert_eq!(
+        decode::<c::Default>(&bytes).unwrap(),
+        d
+    );
+}
+
+#[test]
+fn unknown_fields_roundtrip() {
+    let shape = shape();
+    let bytes = encode_to_vec(&shape).unwrap();
+    let partial = decode::<p3::Partial>(&bytes).unwrap();
+    assert_eq!(partial.name, "square");
+    // unknown fields can be hashed along with the known ones
+    let set = HashSet::from([partial.clone(), p3::Partial::default()]);
+    assert!(set.contains(&partial));
+    assert_eq!(
+        decode::<p3::Shape>(&encode_to_vec(&partial).unwrap()).unwrap(),
+        shape
+    );
+}
+
+#[test]
+fn merge_and_cached_sizes() {
+    let mut counted = p3::Counted {
+        points: vec![point(1, 2)],
+        by_id: [(1, point(-1, -2))].into(),
+        ..Default::default()
+    };
+    let bytes = encode_to_vec(&counted).unwrap();
+    assert_eq!(
+        bytes.len(),
+        counted.get_size()
+    );
+
+    // a second message appends to repeated fields and replaces map entries
+    let other = p3::Counted {
+        points: vec![point(3, 4)],
+        by_id: [(1, point(5, 6))].into(),
+        ..Default::default()
+    };
+    let other_bytes = encode_to_vec(&other).unwrap();
+    decode_into(&mut counted, &other_bytes).unwrap();
+    assert_eq!(
+        counted.points,
+        [point(1, 2), point(3, 4)]
+    );
+    assert_eq!(counted.by_id[&1], point(5, 6));
+
+    counted.clear();
+    assert_eq!(
+        counted,
+        p3::Counted::default()
+    );
+}
+
+/// Decodes a `Shape` with at most 3 elements per repeated field
+fn decode_3(bytes: &[u8]) -> quack_protobuf::Result<p3::Shape<'_>> {
+    let limits = DecodeLimits {
+        max_repeated_len: 3,
+        ..Default::default()
+    };
+    let mut reader = BytesReader::from_bytes_with_limits(bytes, limits)?;
+    p3::Shape::from_reader(&mut reader, bytes)
+}
+
+#[test]
+fn repeated_len_across_packed_chunks() {
+    // ids split into 2 packed chunks of 2 elements, each one within the limit
+    let ids = [0x22, 0x02, 0x01, 0x02, 0x22, 0x02, 0x03, 0x04];
+    assert!(matches!(
+        decode_3(&ids).unwrap_err().kind(),
+        Error::TooManyElements { limit: 3 }
+    ));
+    let ids = [0x22, 0x02, 0x01, 0x02, 0x22, 0x01, 0x03];
+    assert_eq!(
+        decode_3(&ids).unwrap().ids,
+        [1, 2, 3]
+    );
+
+    // weights are fixed size values, read as `PackedFixed`
+    let mut weights = Vec::new();
+    for chunk in [[1.0, 2.0], [3.0, 4.0]] {
+        weights.extend_from_slice(&[0x2a, 0x10]);
+        for w in chunk {
+            weights.extend_from_slice(&f64::to_le_bytes(w));
+        }
+    }
+    assert!(matches!(
+        decode_3(&weights).unwrap_err().kind(),
+        Error::TooManyElements { limit: 3 }
+    ));
+    // unpacked values are counted along with the packed ones
+    weights.truncate(18);
+    weights.push(0x29);
+    weights.extend_from_slice(&f64::to_le_bytes(3.0));
+    assert_eq!(
+        decode_3(&weights).unwrap().weights,
+        vec![1.0, 2.0, 3.0].into()
+    );
+    weights.push(0x29);
+    weights.extend_from_slice(&f64::to_le_bytes(4.0));
+    assert!(matches!(
+        decode_3(&weights).unwrap_err().kind(),
+        Error::TooManyElements { limit: 3 }
+    ));
+}
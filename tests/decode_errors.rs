@@ -0,0 +1,208 @@
+//! Locates decoding errors by their byte offset and the path of the fields containing them
+
+#![cfg(feature = "std")]
+
+use quack_protobuf::{decode, encode_to_vec, BytesReader, Error, MessageRead, MessageWrite, PathSegment};
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Leaf {
+    #[pb(1)]
+    x: u64,
+}
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Inner {
+    #[pb(2)]
+    leaf: Leaf,
+}
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Middle {
+    #[pb(1, repeated)]
+    inners: Vec<Inner>,
+}
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Outer {
+    #[pb(3)]
+    middle: Middle,
+}
+
+fn inner(x: u64) -> Inner {
+    Inner { leaf: Leaf { x } }
+}
+
+/// Encodes `message` then breaks the varint of the last `u64::MAX`
+///
+/// Returns the bytes and the offset of the error: the reader gives up after
+/// the 10 bytes of the longest varint.
+fn corrupt<M: MessageWrite>(message: &M) -> (Vec<u8>, usize) {
+    let mut bytes = encode_to_vec(message).unwrap();
+    let max = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+    let start = (0..=bytes.len() - max.len())
+        .rev()
+        .find(|&i| bytes[i..].starts_with(&max))
+        .unwrap();
+    // a continuation bit on the 10th byte
+    bytes[start + 9] = 0x80;
+    (bytes, start + max.len())
+}
+
+fn segment(
+    number: u32,
+    index: Option<usize>,
+) -> PathSegment {
+    PathSegment { number, index }
+}
+
+#[test]
+fn offset_and_path() {
+    // the 8th of 8 repeated values is broken
+    let mut inners = (0..7).map(inner).collect::<Vec<_>>();
+    inners.push(inner(u64::MAX));
+    let outer = Outer {
+        middle: Middle { inners },
+    };
+    let (bytes, offset) = corrupt(&outer);
+
+    let err = decode::<Outer>(&bytes).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        Error::Varint
+    ));
+    let Error::Decode(e) = &err else {
+        panic!(
+            "expected a decode error, got {:?}",
+            err
+        )
+    };
+    assert!(matches!(
+        e.kind(),
+        Error::Varint
+    ));
+    assert_eq!(e.offset(), offset);
+    assert_eq!(
+        e.path(),
+        [segment(3, None), segment(1, Some(7)), segment(2, None)]
+    );
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Cannot decode varint at offset {} in field 3.1[7].2",
+            offset
+        )
+    );
+}
+
+#[test]
+fn reader_keeps_the_offset() {
+    let mut inners = (0..3).map(inner).collect::<Vec<_>>();
+    inners.push(inner(u64::MAX));
+    let (bytes, offset) = corrupt(&Outer {
+        middle: Middle { inners },
+    });
+    let mut reader = BytesReader::from_bytes(&bytes);
+    let err = Outer::from_reader(&mut reader, &bytes).unwrap_err();
+    // the offset of the innermost error, as in the error itself
+    assert_eq!(
+        reader.error_offset(),
+        Some(offset)
+    );
+    let Error::Decode(e) = &err else {
+        panic!(
+            "expected a decode error, got {:?}",
+            err
+        )
+    };
+    assert_eq!(e.offset(), offset);
+
+    // the kind is displayed along with the position, it is not a source
+    assert!(std::error::Error::source(&err).is_none());
+}
+
+#[test]
+fn single_values_have_no_index() {
+    let outer = Outer {
+        middle: Middle {
+            inners: vec![inner(u64::MAX)],
+        },
+    };
+    let (bytes, offset) = corrupt(&outer);
+    let err = decode::<Outer>(&bytes).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Cannot decode varint at offset {} in field 3.1.2",
+            offset
+        )
+    );
+}
+
+#[test]
+fn top_level_errors_have_no_path() {
+    let (bytes, offset) = corrupt(&Leaf { x: u64::MAX });
+    assert_eq!(offset, 11);
+    let err = decode::<Leaf>(&bytes).unwrap_err();
+    let Error::Decode(e) = &err else {
+        panic!(
+            "expected a decode error, got {:?}",
+            err
+        )
+    };
+    assert_eq!(e.offset(), offset);
+    assert!(e.path().is_empty());
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Cannot decode varint at offset {}",
+            offset
+        )
+    );
+}
+
+#[test]
+fn length_out_of_bounds() {
+    // the leaf declares 5 bytes, within the buffer but past the 2 left in its `Inner`
+    let bytes = [
+        0x1a, 0x06, // Outer.middle
+        0x0a, 0x04, // Middle.inners
+        0x12, 0x05, // Inner.leaf
+        0x08, 0x01, // Leaf.x
+        0x20, 0x01, 0x20, 0x01, // unknown fields of Outer
+    ];
+    let err = decode::<Outer>(&bytes).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        Error::LengthOutOfBounds {
+            offset: 6,
+            declared: 5,
+            available: 2,
+        }
+    ));
+    let Error::Decode(e) = &err else {
+        panic!(
+            "expected a decode error, got {:?}",
+            err
+        )
+    };
+    assert_eq!(e.offset(), 6);
+    assert_eq!(
+        e.path(),
+        [segment(3, None), segment(1, None), segment(2, None)]
+    );
+
+    // the prefix of a top level field is checked against the whole buffer
+    let err = decode::<Outer>(&[0x1a, 0x0a, 0x0a]).unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        Error::LengthOutOfBounds {
+            offset: 2,
+            declared: 10,
+            available: 1,
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Length 10 exceeds the 1 bytes available at offset 2 in field 3"
+    );
+}
@@ -0,0 +1,149 @@
+//! Reads length delimited messages from a `std::io::Read` with `DelimitedReader`
+
+#![cfg(feature = "std")]
+
+use std::io::{BufReader, ErrorKind, Read};
+
+use quack_protobuf::writer::encode_length_delimited_to_vec;
+use quack_protobuf::{DecodeLimits, DelimitedReader, Error};
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Entry<'a> {
+    #[pb(1)]
+    id: u32,
+    #[pb(2)]
+    text: &'a str,
+}
+
+/// The length delimited encoding of `entries`
+fn stream(entries: &[Entry]) -> Vec<u8> {
+    entries
+        .iter()
+        .flat_map(|e| encode_length_delimited_to_vec(e).unwrap())
+        .collect()
+}
+
+fn assert_unexpected_eof<T: std::fmt::Debug>(result: quack_protobuf::Result<T>) {
+    match result {
+        Err(Error::Io(e)) => assert_eq!(
+            e.kind(),
+            ErrorKind::UnexpectedEof
+        ),
+        r => panic!(
+            "expected an UnexpectedEof io error, got {:?}",
+            r
+        ),
+    }
+}
+
+#[test]
+fn several_messages() {
+    let long = "b".repeat(300);
+    let entries = [
+        Entry { id: 1, text: "a" },
+        Entry::default(),
+        Entry { id: 3, text: &long },
+    ];
+    let bytes = stream(&entries);
+    // the default entry is empty, and the prefix of the last one takes 2 bytes
+    assert_eq!(
+        bytes[6..9],
+        [0x00, 0xb1, 0x02]
+    );
+
+    let mut reader = DelimitedReader::new(BufReader::new(&bytes[..]));
+    for expected in &entries {
+        // the text borrows the buffer of the reader
+        let entry = reader.next_message::<Entry>().unwrap().unwrap();
+        assert_eq!(&entry, expected);
+    }
+    assert_eq!(
+        reader.next_message::<Entry>().unwrap(),
+        None
+    );
+    // and keeps returning `None`
+    assert_eq!(
+        reader.next_frame().unwrap(),
+        None
+    );
+}
+
+#[test]
+fn clean_eof() {
+    let mut reader = DelimitedReader::new(&[][..]);
+    assert_eq!(
+        reader.next_frame().unwrap(),
+        None
+    );
+
+    // an empty message is not the end of the stream
+    let mut reader = DelimitedReader::new(&[0x00][..]);
+    assert_eq!(
+        reader.next_frame().unwrap(),
+        Some(&[][..])
+    );
+    assert_eq!(
+        reader.next_frame().unwrap(),
+        None
+    );
+}
+
+#[test]
+fn eof_mid_message() {
+    let bytes = stream(&[Entry { id: 1, text: "abc" }]);
+
+    // within the message
+    for end in 2..bytes.len() {
+        let mut reader = DelimitedReader::new(&bytes[..end]);
+        assert_unexpected_eof(reader.next_frame());
+    }
+    // within a prefix of 2 bytes
+    let mut reader = DelimitedReader::new(&[0x80][..]);
+    assert_unexpected_eof(reader.next_frame());
+
+    // after a complete message
+    let mut input = bytes.clone();
+    input.push(0x05);
+    let mut reader = DelimitedReader::new(&input[..]);
+    assert!(reader.next_frame().unwrap().is_some());
+    assert_unexpected_eof(reader.next_message::<Entry>());
+}
+
+#[test]
+fn size_limit() {
+    let limits = DecodeLimits {
+        max_message_size: 5,
+        ..Default::default()
+    };
+    let bytes = stream(&[
+        Entry { id: 1, text: "a" },
+        Entry {
+            id: 2,
+            text: "too long",
+        },
+        Entry { id: 3, text: "c" },
+    ]);
+    let mut reader = DelimitedReader::with_limits(&bytes[..], limits);
+    assert_eq!(
+        reader.next_message::<Entry>().unwrap(),
+        Some(Entry { id: 1, text: "a" })
+    );
+    assert!(matches!(
+        reader.next_frame(),
+        Err(Error::MessageTooLarge { size: 12, limit: 5 })
+    ));
+    // the message is rejected from its prefix, nothing else is read
+    let mut rest = Vec::new();
+    reader
+        .into_inner()
+        .read_to_end(&mut rest)
+        .unwrap();
+    assert_eq!(rest.len(), 12 + 6);
+
+    // a prefix too long to be a varint
+    let mut reader = DelimitedReader::new(&[0xff; 11][..]);
+    assert!(matches!(
+        reader.next_frame(),
+        Err(Error::Varint)
+    ));
+}
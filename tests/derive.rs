@@ -0,0 +1,144 @@
+//! Merges messages deriving `MessageMerge` into messages already read
+
+#![cfg(feature = "std")]
+
+use quack_protobuf::{decode_into, encode_to_vec, MessageMerge};
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    quack_protobuf_derive::MessageRead,
+    quack_protobuf_derive::MessageMerge,
+    quack_protobuf_derive::MessageWrite,
+)]
+struct Circle {
+    #[pb(1)]
+    radius: f32,
+    #[pb(2, repeated)]
+    labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, quack_protobuf_derive::Oneof)]
+enum Kind {
+    #[pb(1)]
+    Circle(Circle),
+    #[pb(2)]
+    Boxed(Box<Circle>),
+    #[pb(3)]
+    Side(f64),
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    quack_protobuf_derive::MessageRead,
+    quack_protobuf_derive::MessageMerge,
+    quack_protobuf_derive::MessageWrite,
+)]
+struct Shape {
+    #[pb(oneof)]
+    kind: Option<Kind>,
+}
+
+fn shape(kind: Kind) -> Shape {
+    Shape { kind: Some(kind) }
+}
+
+fn circle(
+    radius: f32,
+    label: &str,
+) -> Circle {
+    Circle {
+        radius,
+        labels: vec![label.to_string()],
+    }
+}
+
+/// Merges the encoding of `other` into `shape`
+fn merge(
+    mut shape: Shape,
+    other: Shape,
+) -> Shape {
+    decode_into(
+        &mut shape,
+        &encode_to_vec(&other).unwrap(),
+    )
+    .unwrap();
+    shape
+}
+
+#[test]
+fn oneof_message_is_merged() {
+    // the same message variant: the repeated labels are appended, the radius set again
+    assert_eq!(
+        merge(
+            shape(Kind::Circle(circle(1.0, "a"))),
+            shape(Kind::Circle(circle(2.0, "b")))
+        ),
+        shape(Kind::Circle(Circle {
+            radius: 2.0,
+            labels: vec!["a".to_string(), "b".to_string()],
+        }))
+    );
+    assert_eq!(
+        merge(
+            shape(Kind::Boxed(Box::new(circle(
+                1.0, "a"
+            )))),
+            shape(Kind::Boxed(Box::new(circle(
+                0.0, "b"
+            ))))
+        ),
+        shape(Kind::Boxed(Box::new(
+            Circle {
+                radius: 1.0,
+                labels: vec!["a".to_string(), "b".to_string()],
+            }
+        )))
+    );
+}
+
+#[test]
+fn oneof_variant_is_replaced() {
+    // another variant, even holding the same message type, replaces the current one
+    assert_eq!(
+        merge(
+            shape(Kind::Circle(circle(1.0, "a"))),
+            shape(Kind::Boxed(Box::new(circle(
+                2.0, "b"
+            ))))
+        ),
+        shape(Kind::Boxed(Box::new(circle(
+            2.0, "b"
+        ))))
+    );
+    assert_eq!(
+        merge(
+            shape(Kind::Circle(circle(1.0, "a"))),
+            shape(Kind::Side(3.0))
+        ),
+        shape(Kind::Side(3.0))
+    );
+    assert_eq!(
+        merge(
+            shape(Kind::Side(3.0)),
+            shape(Kind::Circle(circle(1.0, "a")))
+        ),
+        shape(Kind::Circle(circle(1.0, "a")))
+    );
+    assert_eq!(
+        merge(
+            Shape::default(),
+            shape(Kind::Side(3.0))
+        ),
+        shape(Kind::Side(3.0))
+    );
+
+    let mut s = shape(Kind::Side(3.0));
+    s.clear();
+    assert_eq!(s, Shape::default());
+}
@@ -0,0 +1,452 @@
+//! Reads and writes the messages of `tests/protos/fixtures` with a pool built
+//! from the .proto files, against the code generated for them
+
+#![cfg(all(feature = "std", feature = "dynamic"))]
+
+use std::borrow::Cow;
+
+use quack_protobuf::dynamic::{DescriptorPool, DynamicMessage, FieldLabel, FieldType, MapKey, Value};
+use quack_protobuf::{decode, decode_into, encode_to_vec, BytesReader, DecodeLimits, Error, MessageMerge};
+use quack_protobuf_codegen::generator::Generator;
+
+#[allow(dead_code)]
+mod generated {
+    include!("generated/fixtures/mod.rs");
+}
+
+use generated::fixtures::{p2, p3};
+
+fn pool() -> DescriptorPool {
+    let set = Generator::new()
+        .include("tests/protos")
+        .descriptor_set(&["tests/protos/fixtures/p3.proto"])
+        .unwrap();
+    DescriptorPool::from_file_descriptor_set(&set).unwrap()
+}
+
+fn point(
+    x: i32,
+    y: i32,
+) -> p3::Point {
+    p3::Point {
+        x,
+        y,
+        ..Default::default()
+    }
+}
+
+fn shape() -> p3::Shape<'static> {
+    p3::Shape {
+        name: "square".into(),
+        points: vec![point(1, -1), point(0, 0)],
+        color: p3::Color::GREEN,
+        ids: vec![1, -2, 300],
+        weights: vec![0.5, 2.0].into(),
+        tags: [("a".into(), -1), ("b".into(), 1 << 40)].into(),
+        by_id: [(3, point(3, 3)), (-4, point(-4, 4))].into(),
+        colors: [("sky".into(), p3::Color::RED)].into(),
+        kind: p3::mod_Shape::OneOfkind::circle(p3::mod_Shape::Circle { radius: 1.5 }),
+        maybe: Some(0),
+        data: Cow::Borrowed(b"\x00\xff"),
+        child: Some(Box::new(p3::Shape {
+            name: "child".into(),
+            ..Default::default()
+        })),
+        children: vec![p3::Shape::default()],
+        flags: vec![true, false],
+        f32: 7,
+        sf64: -7,
+        inner: p3::mod_Shape::Inner::B,
+        status: p2::Status::RUNNING,
+    }
+}
+
+/// Decodes `bytes` as a dynamic message of type `name`
+fn dynamic(
+    pool: &DescriptorPool,
+    name: &str,
+    bytes: &[u8],
+) -> DynamicMessage {
+    let mut msg = DynamicMessage::new(pool, name).unwrap();
+    decode_into(&mut msg, bytes).unwrap();
+    msg
+}
+
+/// Gets the nested message of a `Value::Message`
+fn message(value: Option<&Value>) -> &DynamicMessage {
+    match value {
+        Some(Value::Message(m)) => m,
+        v => panic!(
+            "expected a message, got {:?}",
+            v
+        ),
+    }
+}
+
+#[test]
+fn descriptors() {
+    let pool = pool();
+    let shape = pool.get_message("fixtures.p3.Shape").unwrap();
+    let field = |name| shape.get_field_by_name(name).unwrap();
+
+    assert_eq!(field("name").number(), 1);
+    assert_eq!(
+        field("color").field_type(),
+        &FieldType::Enum
+    );
+    assert_eq!(
+        field("child").field_type(),
+        &FieldType::Message("fixtures.p3.Shape".into())
+    );
+    assert_eq!(
+        field("circle").field_type(),
+        &FieldType::Message("fixtures.p3.Shape.Circle".into())
+    );
+    // proto3 packs repeated numbers by default
+    assert_eq!(
+        field("ids").label(),
+        &FieldLabel::Repeated { packed: true }
+    );
+    assert_eq!(
+        field("points").label(),
+        &FieldLabel::Repeated { packed: false }
+    );
+    assert_eq!(
+        field("tags").label(),
+        &FieldLabel::Map(FieldType::String)
+    );
+    assert_eq!(
+        field("tags").field_type(),
+        &FieldType::Int64
+    );
+    assert_eq!(
+        field("by_id").field_type(),
+        &FieldType::Message("fixtures.p3.Point".into())
+    );
+    // map entries are not messages of their own
+    assert!(pool
+        .get_message("fixtures.p3.Shape.TagsEntry")
+        .is_none());
+
+    // proto2 only packs when asked to
+    let defaults = pool.get_message("fixtures.p2.Defaults").unwrap();
+    assert_eq!(
+        defaults
+            .get_field_by_name("unpacked")
+            .unwrap()
+            .label(),
+        &FieldLabel::Repeated { packed: false }
+    );
+    assert_eq!(
+        defaults
+            .get_field_by_name("packed")
+            .unwrap()
+            .label(),
+        &FieldLabel::Repeated { packed: true }
+    );
+    // groups are kept as unknown fields
+    let groups = pool
+        .get_message("fixtures.p2.WithGroups")
+        .unwrap();
+    assert_eq!(groups.fields().len(), 1);
+}
+
+#[test]
+fn static_to_dynamic() {
+    let pool = pool();
+    let shape = shape();
+    let msg = dynamic(
+        &pool,
+        "fixtures.p3.Shape",
+        &encode_to_vec(&shape).unwrap(),
+    );
+    let get = |name| msg.get_by_name(name);
+
+    // scalars
+    assert_eq!(
+        get("name"),
+        Some(&Value::String(
+            "square".into()
+        ))
+    );
+    assert_eq!(
+        get("color"),
+        Some(&Value::Enum(2))
+    );
+    assert_eq!(
+        get("maybe"),
+        Some(&Value::U64(0))
+    );
+    assert_eq!(
+        get("data"),
+        Some(&Value::Bytes(vec![0, 0xff]))
+    );
+    assert_eq!(
+        get("f32"),
+        Some(&Value::U32(7))
+    );
+    assert_eq!(
+        get("sf64"),
+        Some(&Value::I64(-7))
+    );
+    assert_eq!(
+        get("status"),
+        Some(&Value::Enum(2))
+    );
+    // unset proto3 fields are not written, hence not set
+    assert_eq!(get("side"), None);
+
+    // repeated and packed
+    assert_eq!(
+        get("ids"),
+        Some(&Value::List(vec![
+            Value::I32(1),
+            Value::I32(-2),
+            Value::I32(300)
+        ]))
+    );
+    assert_eq!(
+        get("weights"),
+        Some(&Value::List(vec![
+            Value::F64(0.5),
+            Value::F64(2.0)
+        ]))
+    );
+    assert_eq!(
+        get("flags"),
+        Some(&Value::List(vec![
+            Value::Bool(true),
+            Value::Bool(false)
+        ]))
+    );
+
+    // maps
+    assert_eq!(
+        get("tags"),
+        Some(&Value::Map(
+            [
+                (
+                    MapKey::String("a".into()),
+                    Value::I64(-1)
+                ),
+                (
+                    MapKey::String("b".into()),
+                    Value::I64(1 << 40)
+                ),
+            ]
+            .into()
+        ))
+    );
+    let Some(Value::Map(by_id)) = get("by_id") else {
+        panic!("by_id is not a map")
+    };
+    let p = message(by_id.get(&MapKey::I32(-4)));
+    assert_eq!(
+        p.get_by_name("x"),
+        Some(&Value::I32(-4))
+    );
+    assert_eq!(
+        p.get_by_name("y"),
+        Some(&Value::I32(4))
+    );
+
+    // nested messages
+    let child = message(get("child"));
+    assert_eq!(
+        child.get_by_name("name"),
+        Some(&Value::String("child".into()))
+    );
+    let circle = message(get("circle"));
+    assert_eq!(
+        circle.get_by_name("radius"),
+        Some(&Value::F32(1.5))
+    );
+
+    // and back
+    let bytes = encode_to_vec(&msg).unwrap();
+    assert_eq!(
+        decode::<p3::Shape>(&bytes).unwrap(),
+        shape
+    );
+}
+
+#[test]
+fn dynamic_to_static() {
+    let pool = pool();
+    let point_msg = |x, y| {
+        let mut p = DynamicMessage::new(&pool, "fixtures.p3.Point").unwrap();
+        p.set_by_name("x", Value::I32(x)).unwrap();
+        p.set_by_name("y", Value::I32(y)).unwrap();
+        Value::Message(p)
+    };
+    let mut msg = DynamicMessage::new(&pool, "fixtures.p3.Shape").unwrap();
+    msg.set_by_name(
+        "name",
+        Value::String("dyn".into()),
+    )
+    .unwrap();
+    msg.set_by_name(
+        "points",
+        Value::List(vec![
+            point_msg(1, 2),
+            point_msg(-3, 4),
+        ]),
+    )
+    .unwrap();
+    msg.set_by_name(
+        "ids",
+        Value::List(vec![
+            Value::I32(-1),
+            Value::I32(1 << 20),
+        ]),
+    )
+    .unwrap();
+    msg.set_by_name(
+        "by_id",
+        Value::Map(
+            [(
+                MapKey::I32(5),
+                point_msg(5, 5),
+            )]
+            .into(),
+        ),
+    )
+    .unwrap();
+    msg.set_by_name(
+        "colors",
+        Value::Map(
+            [(
+                MapKey::String("c".into()),
+                Value::Enum(1),
+            )]
+            .into(),
+        ),
+    )
+    .unwrap();
+    msg.set_by_name("side", Value::F64(2.5)).unwrap();
+    msg.set_by_name("inner", Value::Enum(1)).unwrap();
+    // a type mismatch is refused
+    assert!(msg.set_by_name("ids", Value::I32(1)).is_err());
+
+    let bytes = encode_to_vec(&msg).unwrap();
+    assert_eq!(
+        decode::<p3::Shape>(&bytes).unwrap(),
+        p3::Shape {
+            name: "dyn".into(),
+            points: vec![point(1, 2), point(-3, 4)],
+            ids: vec![-1, 1 << 20],
+            by_id: [(5, point(5, 5))].into(),
+            colors: [("c".into(), p3::Color::RED)].into(),
+            kind: p3::mod_Shape::OneOfkind::side(2.5),
+            inner: p3::mod_Shape::Inner::B,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn packed_and_unpacked() {
+    let pool = pool();
+    let defaults = p2::Defaults {
+        id: 2,
+        unpacked: vec![1, 2],
+        packed: vec![3, 4],
+        next: Some(Box::new(p2::Defaults {
+            id: 3,
+            ..Default::default()
+        })),
+        ..Default::default()
+    };
+    let bytes = encode_to_vec(&defaults).unwrap();
+    let msg = dynamic(
+        &pool,
+        "fixtures.p2.Defaults",
+        &bytes,
+    );
+    assert_eq!(
+        msg.get_by_name("packed"),
+        Some(&Value::List(vec![
+            Value::I32(3),
+            Value::I32(4)
+        ]))
+    );
+    // fields are written in the same order and with the same packing
+    assert_eq!(
+        encode_to_vec(&msg).unwrap(),
+        bytes
+    );
+}
+
+#[test]
+fn unknown_fields() {
+    let pool = pool();
+    let shape = shape();
+    let bytes = encode_to_vec(&shape).unwrap();
+
+    // `Partial` only knows the name of a `Shape`
+    let partial = dynamic(
+        &pool,
+        "fixtures.p3.Partial",
+        &bytes,
+    );
+    assert_eq!(
+        partial.get_by_name("name"),
+        Some(&Value::String(
+            "square".into()
+        ))
+    );
+    assert_eq!(partial.fields().count(), 1);
+    assert!(!partial.unknown_fields().is_empty());
+    assert_eq!(
+        decode::<p3::Shape>(&encode_to_vec(&partial).unwrap()).unwrap(),
+        shape
+    );
+
+    // and the static `Partial` reads the same fields back
+    let bytes = encode_to_vec(&partial).unwrap();
+    let static_partial = decode::<p3::Partial>(&bytes).unwrap();
+    assert_eq!(
+        encode_to_vec(&static_partial).unwrap(),
+        bytes
+    );
+
+    // groups are unknown to dynamic messages
+    let groups = p2::WithGroups {
+        a: Some(1),
+        item: vec![p2::mod_WithGroups::Item { id: Some(7) }],
+        ..Default::default()
+    };
+    let bytes = encode_to_vec(&groups).unwrap();
+    let msg = dynamic(
+        &pool,
+        "fixtures.p2.WithGroups",
+        &bytes,
+    );
+    assert_eq!(
+        msg.get_by_name("a"),
+        Some(&Value::I32(1))
+    );
+    assert_eq!(
+        decode::<p2::WithGroups>(&encode_to_vec(&msg).unwrap()).unwrap(),
+        groups
+    );
+}
+
+#[test]
+fn repeated_len_across_packed_chunks() {
+    let pool = pool();
+    let limits = DecodeLimits {
+        max_repeated_len: 3,
+        ..Default::default()
+    };
+    // ids split into 2 packed chunks of 2 elements, each one within the limit
+    let bytes = [0x22, 0x02, 0x01, 0x02, 0x22, 0x02, 0x03, 0x04];
+    let mut msg = DynamicMessage::new(&pool, "fixtures.p3.Shape").unwrap();
+    let mut reader = BytesReader::from_bytes_with_limits(&bytes, limits).unwrap();
+    assert!(matches!(
+        msg.merge_from(&mut reader, &bytes)
+            .unwrap_err()
+            .kind(),
+        Error::TooManyElements { limit: 3 }
+    ));
+}
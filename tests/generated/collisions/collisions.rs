@@ -0,0 +1,418 @@
+// Automatically generated by quack-protobuf-codegen, do not edit.
+// source: tests/protos/collisions.proto
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Result<'a> {
+    pub ok: ::core::option::Option<::std::borrow::Cow<'a, str>>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for Result<'a> {
+    const PATH: &'static str = "collisions.Result";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Result<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Result<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => self.ok = ::core::option::Option::Some(r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?),
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.ok = ::core::default::Default::default();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for Result<'a> {
+    fn get_size(&self) -> usize {
+        self.ok.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_len(m.len()))
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if let ::core::option::Option::Some(ref m) = self.ok {
+            w.write_with_tag(10, |w| w.write_string(m))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Option {
+    pub some: i32,
+}
+
+impl ::core::default::Default for Option {
+    fn default() -> Self {
+        Option {
+            some: 3,
+        }
+    }
+}
+
+impl ::quack_protobuf::MessageInfo for Option {
+    const PATH: &'static str = "collisions.Option";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Option {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Option {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                8 => self.some = r.read_int32(bytes)?,
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.some = 3;
+    }
+}
+
+impl ::quack_protobuf::MessageWrite for Option {
+    fn get_size(&self) -> usize {
+        if self.some != 3 { 1 + ::quack_protobuf::sizeofs::sizeof_int32(self.some) } else { 0 }
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if self.some != 3 {
+            w.write_with_tag(8, |w| w.write_int32(self.some))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Writer<'a> {
+    pub data: ::std::vec::Vec<::std::borrow::Cow<'a, [u8]>>,
+    pub packed: ::std::vec::Vec<i32>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for Writer<'a> {
+    const PATH: &'static str = "collisions.Writer";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Writer<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Writer<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => {
+                    r.check_repeated_len(self.data.len())?;
+                    self.data.push(r.read_bytes(bytes).map(::std::borrow::Cow::Borrowed)?);
+                }
+                16 => {
+                    r.check_repeated_len(self.packed.len())?;
+                    self.packed.push(r.read_int32(bytes)?);
+                }
+                18 => r.append_packed(bytes, &mut self.packed, |r, bytes| r.read_int32(bytes))?,
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.data.clear();
+        self.packed.clear();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for Writer<'a> {
+    fn get_size(&self) -> usize {
+        self.data.iter().map(|m| 1 + ::quack_protobuf::sizeofs::sizeof_len(m.len())).sum::<usize>()
+            + if self.packed.is_empty() { 0 } else { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.packed.iter().map(|m| ::quack_protobuf::sizeofs::sizeof_int32(*m)).sum::<usize>()) }
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        for m in self.data.iter() {
+            w.write_with_tag(10, |w| w.write_bytes(m))?;
+        }
+        w.write_packed_with_tag(18, &self.packed, |w, m| w.write_int32(*m), &|m| ::quack_protobuf::sizeofs::sizeof_int32(*m))?;
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Cow<'a> {
+    pub result: Result<'a>,
+    pub options: ::std::vec::Vec<Option>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for Cow<'a> {
+    const PATH: &'static str = "collisions.Cow";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Cow<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Cow<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => r.merge_message(bytes, &mut self.result)?,
+                18 => {
+                    r.check_repeated_len(self.options.len())?;
+                    self.options.push(r.read_message(bytes)?);
+                }
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        ::quack_protobuf::MessageMerge::clear(&mut self.result);
+        self.options.clear();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for Cow<'a> {
+    fn get_size(&self) -> usize {
+        1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(&self.result))
+            + self.options.iter().map(|m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m))).sum::<usize>()
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        w.write_with_tag(10, |w| w.write_message_with_len_prefix(&self.result))?;
+        for m in self.options.iter() {
+            w.write_with_tag(18, |w| w.write_message_with_len_prefix(m))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Box<'a> {
+    pub next: ::core::option::Option<::std::boxed::Box<Box<'a>>>,
+    pub by_name: ::std::collections::HashMap<::std::borrow::Cow<'a, str>, Option>,
+    pub kind: mod_Box::OneOfkind<'a>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for Box<'a> {
+    const PATH: &'static str = "collisions.Box";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Box<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Box<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => r.merge_message(bytes, &mut **self.next.get_or_insert_with(::core::default::Default::default))?,
+                18 => {
+                    let (key, value) = r.read_map(bytes, |r, bytes| r.read_string(bytes).map(::std::borrow::Cow::Borrowed), |r, bytes| r.read_message(bytes))?;
+                    r.check_map_len(self.by_name.len())?;
+                    self.by_name.insert(key, value);
+                }
+                26 => match &mut self.kind {
+                    mod_Box::OneOfkind::cow(m) => r.merge_message(bytes, m)?,
+                    _ => self.kind = mod_Box::OneOfkind::cow(r.read_message(bytes)?),
+                },
+                34 => self.kind = mod_Box::OneOfkind::name(r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?),
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.next = ::core::default::Default::default();
+        self.by_name.clear();
+        self.kind = ::core::default::Default::default();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for Box<'a> {
+    fn get_size(&self) -> usize {
+        self.next.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(&**m)))
+            + self.by_name.iter().map(|(k, v)| 1 + ::quack_protobuf::sizeofs::sizeof_len(2 + ::quack_protobuf::sizeofs::sizeof_len(k.len()) + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(v)))).sum::<usize>()
+            + match self.kind {
+                mod_Box::OneOfkind::cow(ref m) => 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m)),
+                mod_Box::OneOfkind::name(ref m) => 1 + ::quack_protobuf::sizeofs::sizeof_len(m.len()),
+                mod_Box::OneOfkind::None => 0,
+            }
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if let ::core::option::Option::Some(ref m) = self.next {
+            w.write_with_tag(10, |w| w.write_message_with_len_prefix(&**m))?;
+        }
+        for (k, v) in self.by_name.iter() {
+            w.write_with_tag(18, |w| w.write_map_message(::quack_protobuf::sizeofs::sizeof_len(k.len()), 10, |w| w.write_string(k), 18, v))?;
+        }
+        match self.kind {
+            mod_Box::OneOfkind::cow(ref m) => w.write_with_tag(26, |w| w.write_message_with_len_prefix(m))?,
+            mod_Box::OneOfkind::name(ref m) => w.write_with_tag(34, |w| w.write_string(m))?,
+            mod_Box::OneOfkind::None => {}
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod mod_Box {
+
+    #[derive(Debug, Default, PartialEq, Clone)]
+    #[allow(non_camel_case_types)]
+    pub enum OneOfkind<'a> {
+        cow(super::Cow<'a>),
+        name(::std::borrow::Cow<'a, str>),
+        #[default]
+        None,
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Default<'a> {
+    pub result: ::core::option::Option<mod_Default::Result<'a>>,
+    pub top: ::core::option::Option<Result<'a>>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for Default<'a> {
+    const PATH: &'static str = "collisions.Default";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Default<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Default<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                59 => r.merge_group(bytes, 59, self.result.get_or_insert_with(::core::default::Default::default))?,
+                66 => r.merge_message(bytes, self.top.get_or_insert_with(::core::default::Default::default))?,
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.result = ::core::default::Default::default();
+        self.top = ::core::default::Default::default();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for Default<'a> {
+    fn get_size(&self) -> usize {
+        self.result.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_group(7, ::quack_protobuf::MessageWrite::get_size(m)))
+            + self.top.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m)))
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if let ::core::option::Option::Some(ref m) = self.result {
+            w.write_with_tag(59, |w| w.write_group(7, m))?;
+        }
+        if let ::core::option::Option::Some(ref m) = self.top {
+            w.write_with_tag(66, |w| w.write_message_with_len_prefix(m))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod mod_Default {
+
+    #[derive(Debug, Default, PartialEq, Clone)]
+    pub struct Result<'a> {
+        pub writer: ::core::option::Option<super::Writer<'a>>,
+    }
+
+    impl<'a> ::quack_protobuf::MessageInfo for Result<'a> {
+        const PATH: &'static str = "collisions.Default.Result";
+    }
+
+    impl<'a> ::quack_protobuf::MessageRead<'a> for Result<'a> {
+        fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+            let mut msg = <Self as ::core::default::Default>::default();
+            ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+            ::core::result::Result::Ok(msg)
+        }
+    }
+
+    impl<'a> ::quack_protobuf::MessageMerge<'a> for Result<'a> {
+        fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+            while !r.is_eof() {
+                match r.next_tag(bytes)? {
+                    10 => r.merge_message(bytes, self.writer.get_or_insert_with(::core::default::Default::default))?,
+                    t => {
+                        r.read_unknown(bytes, t)?;
+                    }
+                }
+            }
+            ::core::result::Result::Ok(())
+        }
+
+        fn clear(&mut self) {
+            self.writer = ::core::default::Default::default();
+        }
+    }
+
+    impl<'a> ::quack_protobuf::MessageWrite for Result<'a> {
+        fn get_size(&self) -> usize {
+            self.writer.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m)))
+        }
+
+        fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+            if let ::core::option::Option::Some(ref m) = self.writer {
+                w.write_with_tag(10, |w| w.write_message_with_len_prefix(m))?;
+            }
+            ::core::result::Result::Ok(())
+        }
+    }
+}
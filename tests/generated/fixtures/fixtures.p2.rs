@@ -0,0 +1,679 @@
+// Automatically generated by quack-protobuf-codegen, do not edit.
+// source: tests/protos/fixtures/p2.proto
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+pub enum Status {
+    #[default]
+    UNKNOWN = 1,
+    STARTED = 2,
+}
+
+#[allow(non_upper_case_globals)]
+impl Status {
+    pub const RUNNING: Status = Status::STARTED;
+}
+
+impl ::core::convert::From<i32> for Status {
+    fn from(i: i32) -> Self {
+        match i {
+            1 => Status::UNKNOWN,
+            2 => Status::STARTED,
+            _ => <Self as ::core::default::Default>::default(),
+        }
+    }
+}
+
+impl ::core::convert::From<Status> for i32 {
+    fn from(e: Status) -> i32 {
+        e as i32
+    }
+}
+
+impl<'a> ::core::convert::From<&'a str> for Status {
+    fn from(s: &'a str) -> Self {
+        match s {
+            "UNKNOWN" => Status::UNKNOWN,
+            "STARTED" => Status::STARTED,
+            "RUNNING" => Status::STARTED,
+            _ => <Self as ::core::default::Default>::default(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Defaults<'a> {
+    pub id: i32,
+    pub name: ::std::borrow::Cow<'a, str>,
+    pub status: Status,
+    pub ratio: f64,
+    pub blob: ::std::borrow::Cow<'a, [u8]>,
+    pub f: f32,
+    pub big: i64,
+    pub on: bool,
+    pub neg: i32,
+    pub unpacked: ::std::vec::Vec<i32>,
+    pub packed: ::std::vec::Vec<i32>,
+    pub next: ::core::option::Option<::std::boxed::Box<Defaults<'a>>>,
+}
+
+impl<'a> ::core::default::Default for Defaults<'a> {
+    fn default() -> Self {
+        Defaults {
+            id: ::core::default::Default::default(),
+            name: ::std::borrow::Cow::Borrowed("bob"),
+            status: Status::RUNNING,
+            ratio: 1.5f64,
+            blob: ::std::borrow::Cow::Borrowed(&b"a\x00\"b"[..]),
+            f: f32::INFINITY,
+            big: -9223372036854775808,
+            on: true,
+            neg: -3,
+            unpacked: ::core::default::Default::default(),
+            packed: ::core::default::Default::default(),
+            next: ::core::default::Default::default(),
+        }
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for Defaults<'a> {
+    const PATH: &'static str = "fixtures.p2.Defaults";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Defaults<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Defaults<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                8 => self.id = r.read_int32(bytes)?,
+                18 => self.name = r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?,
+                24 => self.status = r.read_enum(bytes)?,
+                33 => self.ratio = r.read_double(bytes)?,
+                42 => self.blob = r.read_bytes(bytes).map(::std::borrow::Cow::Borrowed)?,
+                53 => self.f = r.read_float(bytes)?,
+                56 => self.big = r.read_int64(bytes)?,
+                64 => self.on = r.read_bool(bytes)?,
+                72 => self.neg = r.read_sint32(bytes)?,
+                80 => {
+                    r.check_repeated_len(self.unpacked.len())?;
+                    self.unpacked.push(r.read_int32(bytes)?);
+                }
+                82 => r.append_packed(bytes, &mut self.unpacked, |r, bytes| r.read_int32(bytes))?,
+                88 => {
+                    r.check_repeated_len(self.packed.len())?;
+                    self.packed.push(r.read_int32(bytes)?);
+                }
+                90 => r.append_packed(bytes, &mut self.packed, |r, bytes| r.read_int32(bytes))?,
+                98 => r.merge_message(bytes, &mut **self.next.get_or_insert_with(::core::default::Default::default))?,
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.id = ::core::default::Default::default();
+        self.name = ::std::borrow::Cow::Borrowed("bob");
+        self.status = Status::RUNNING;
+        self.ratio = 1.5f64;
+        self.blob = ::std::borrow::Cow::Borrowed(&b"a\x00\"b"[..]);
+        self.f = f32::INFINITY;
+        self.big = -9223372036854775808;
+        self.on = true;
+        self.neg = -3;
+        self.unpacked.clear();
+        self.packed.clear();
+        self.next = ::core::default::Default::default();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for Defaults<'a> {
+    fn get_size(&self) -> usize {
+        1 + ::quack_protobuf::sizeofs::sizeof_int32(self.id)
+            + if self.name != "bob" { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.name.len()) } else { 0 }
+            + if self.status != Status::RUNNING { 1 + ::quack_protobuf::sizeofs::sizeof_enum(self.status as i32) } else { 0 }
+            + if self.ratio != 1.5f64 { 1 + 8 } else { 0 }
+            + if self.blob != &b"a\x00\"b"[..] { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.blob.len()) } else { 0 }
+            + if self.f != f32::INFINITY { 1 + 4 } else { 0 }
+            + if self.big != -9223372036854775808 { 1 + ::quack_protobuf::sizeofs::sizeof_int64(self.big) } else { 0 }
+            + if !self.on { 1 + 1 } else { 0 }
+            + if self.neg != -3 { 1 + ::quack_protobuf::sizeofs::sizeof_sint32(self.neg) } else { 0 }
+            + self.unpacked.iter().map(|m| 1 + ::quack_protobuf::sizeofs::sizeof_int32(*m)).sum::<usize>()
+            + if self.packed.is_empty() { 0 } else { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.packed.iter().map(|m| ::quack_protobuf::sizeofs::sizeof_int32(*m)).sum::<usize>()) }
+            + self.next.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(&**m)))
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        w.write_with_tag(8, |w| w.write_int32(self.id))?;
+        if self.name != "bob" {
+            w.write_with_tag(18, |w| w.write_string(&self.name))?;
+        }
+        if self.status != Status::RUNNING {
+            w.write_with_tag(24, |w| w.write_enum(self.status as i32))?;
+        }
+        if self.ratio != 1.5f64 {
+            w.write_with_tag(33, |w| w.write_double(self.ratio))?;
+        }
+        if self.blob != &b"a\x00\"b"[..] {
+            w.write_with_tag(42, |w| w.write_bytes(&self.blob))?;
+        }
+        if self.f != f32::INFINITY {
+            w.write_with_tag(53, |w| w.write_float(self.f))?;
+        }
+        if self.big != -9223372036854775808 {
+            w.write_with_tag(56, |w| w.write_int64(self.big))?;
+        }
+        if !self.on {
+            w.write_with_tag(64, |w| w.write_bool(self.on))?;
+        }
+        if self.neg != -3 {
+            w.write_with_tag(72, |w| w.write_sint32(self.neg))?;
+        }
+        for m in self.unpacked.iter() {
+            w.write_with_tag(80, |w| w.write_int32(*m))?;
+        }
+        w.write_packed_with_tag(90, &self.packed, |w, m| w.write_int32(*m), &|m| ::quack_protobuf::sizeofs::sizeof_int32(*m))?;
+        if let ::core::option::Option::Some(ref m) = self.next {
+            w.write_with_tag(98, |w| w.write_message_with_len_prefix(&**m))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct NanDefaults {
+    pub d: f64,
+    pub f: f32,
+}
+
+impl ::core::default::Default for NanDefaults {
+    fn default() -> Self {
+        NanDefaults {
+            d: f64::NAN,
+            f: f32::NAN,
+        }
+    }
+}
+
+impl ::quack_protobuf::MessageInfo for NanDefaults {
+    const PATH: &'static str = "fixtures.p2.NanDefaults";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for NanDefaults {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for NanDefaults {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                9 => self.d = r.read_double(bytes)?,
+                21 => self.f = r.read_float(bytes)?,
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.d = f64::NAN;
+        self.f = f32::NAN;
+    }
+}
+
+impl ::quack_protobuf::MessageWrite for NanDefaults {
+    fn get_size(&self) -> usize {
+        (if !self.d.is_nan() { 1 + 8 } else { 0 })
+            + if !self.f.is_nan() { 1 + 4 } else { 0 }
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if !self.d.is_nan() {
+            w.write_with_tag(9, |w| w.write_double(self.d))?;
+        }
+        if !self.f.is_nan() {
+            w.write_with_tag(21, |w| w.write_float(self.f))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct WithGroups<'a> {
+    pub a: ::core::option::Option<i32>,
+    pub res: ::core::option::Option<mod_WithGroups::Res<'a>>,
+    pub item: ::std::vec::Vec<mod_WithGroups::Item>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for WithGroups<'a> {
+    const PATH: &'static str = "fixtures.p2.WithGroups";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for WithGroups<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for WithGroups<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                8 => self.a = ::core::option::Option::Some(r.read_int32(bytes)?),
+                19 => r.merge_group(bytes, 19, self.res.get_or_insert_with(::core::default::Default::default))?,
+                51 => {
+                    r.check_repeated_len(self.item.len())?;
+                    self.item.push(r.read_group(bytes, 51)?);
+                }
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.a = ::core::default::Default::default();
+        self.res = ::core::default::Default::default();
+        self.item.clear();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for WithGroups<'a> {
+    fn get_size(&self) -> usize {
+        self.a.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_int32(*m))
+            + self.res.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_group(2, ::quack_protobuf::MessageWrite::get_size(m)))
+            + self.item.iter().map(|m| 1 + ::quack_protobuf::sizeofs::sizeof_group(6, ::quack_protobuf::MessageWrite::get_size(m))).sum::<usize>()
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if let ::core::option::Option::Some(ref m) = self.a {
+            w.write_with_tag(8, |w| w.write_int32(*m))?;
+        }
+        if let ::core::option::Option::Some(ref m) = self.res {
+            w.write_with_tag(19, |w| w.write_group(2, m))?;
+        }
+        for m in self.item.iter() {
+            w.write_with_tag(51, |w| w.write_group(6, m))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod mod_WithGroups {
+
+    #[derive(Debug, Default, PartialEq, Clone)]
+    pub struct Res<'a> {
+        pub url: ::core::option::Option<::std::borrow::Cow<'a, str>>,
+        pub inner: ::std::vec::Vec<mod_Res::Inner>,
+    }
+
+    impl<'a> ::quack_protobuf::MessageInfo for Res<'a> {
+        const PATH: &'static str = "fixtures.p2.WithGroups.Res";
+    }
+
+    impl<'a> ::quack_protobuf::MessageRead<'a> for Res<'a> {
+        fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+            let mut msg = <Self as ::core::default::Default>::default();
+            ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+            ::core::result::Result::Ok(msg)
+        }
+    }
+
+    impl<'a> ::quack_protobuf::MessageMerge<'a> for Res<'a> {
+        fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+            while !r.is_eof() {
+                match r.next_tag(bytes)? {
+                    26 => self.url = ::core::option::Option::Some(r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?),
+                    35 => {
+                        r.check_repeated_len(self.inner.len())?;
+                        self.inner.push(r.read_group(bytes, 35)?);
+                    }
+                    t => {
+                        r.read_unknown(bytes, t)?;
+                    }
+                }
+            }
+            ::core::result::Result::Ok(())
+        }
+
+        fn clear(&mut self) {
+            self.url = ::core::default::Default::default();
+            self.inner.clear();
+        }
+    }
+
+    impl<'a> ::quack_protobuf::MessageWrite for Res<'a> {
+        fn get_size(&self) -> usize {
+            self.url.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_len(m.len()))
+                + self.inner.iter().map(|m| 1 + ::quack_protobuf::sizeofs::sizeof_group(4, ::quack_protobuf::MessageWrite::get_size(m))).sum::<usize>()
+        }
+
+        fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+            if let ::core::option::Option::Some(ref m) = self.url {
+                w.write_with_tag(26, |w| w.write_string(m))?;
+            }
+            for m in self.inner.iter() {
+                w.write_with_tag(35, |w| w.write_group(4, m))?;
+            }
+            ::core::result::Result::Ok(())
+        }
+    }
+
+    #[allow(non_snake_case)]
+    pub mod mod_Res {
+
+        #[derive(Debug, Default, PartialEq, Clone)]
+        pub struct Inner {
+            pub x: i32,
+        }
+
+        impl ::quack_protobuf::MessageInfo for Inner {
+            const PATH: &'static str = "fixtures.p2.WithGroups.Res.Inner";
+        }
+
+        impl<'a> ::quack_protobuf::MessageRead<'a> for Inner {
+            fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+                let mut msg = <Self as ::core::default::Default>::default();
+                ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+                ::core::result::Result::Ok(msg)
+            }
+        }
+
+        impl<'a> ::quack_protobuf::MessageMerge<'a> for Inner {
+            fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+                while !r.is_eof() {
+                    match r.next_tag(bytes)? {
+                        40 => self.x = r.read_int32(bytes)?,
+                        t => {
+                            r.read_unknown(bytes, t)?;
+                        }
+                    }
+                }
+                ::core::result::Result::Ok(())
+            }
+
+            fn clear(&mut self) {
+                self.x = ::core::default::Default::default();
+            }
+        }
+
+        impl ::quack_protobuf::MessageWrite for Inner {
+            fn get_size(&self) -> usize {
+                1 + ::quack_protobuf::sizeofs::sizeof_int32(self.x)
+            }
+
+            fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+                w.write_with_tag(40, |w| w.write_int32(self.x))?;
+                ::core::result::Result::Ok(())
+            }
+        }
+    }
+
+    #[derive(Debug, Default, PartialEq, Clone)]
+    pub struct Item {
+        pub id: ::core::option::Option<u32>,
+    }
+
+    impl ::quack_protobuf::MessageInfo for Item {
+        const PATH: &'static str = "fixtures.p2.WithGroups.Item";
+    }
+
+    impl<'a> ::quack_protobuf::MessageRead<'a> for Item {
+        fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+            let mut msg = <Self as ::core::default::Default>::default();
+            ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+            ::core::result::Result::Ok(msg)
+        }
+    }
+
+    impl<'a> ::quack_protobuf::MessageMerge<'a> for Item {
+        fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+            while !r.is_eof() {
+                match r.next_tag(bytes)? {
+                    56 => self.id = ::core::option::Option::Some(r.read_uint32(bytes)?),
+                    t => {
+                        r.read_unknown(bytes, t)?;
+                    }
+                }
+            }
+            ::core::result::Result::Ok(())
+        }
+
+        fn clear(&mut self) {
+            self.id = ::core::default::Default::default();
+        }
+    }
+
+    impl ::quack_protobuf::MessageWrite for Item {
+        fn get_size(&self) -> usize {
+            self.id.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_uint32(*m))
+        }
+
+        fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+            if let ::core::option::Option::Some(ref m) = self.id {
+                w.write_with_tag(56, |w| w.write_uint32(*m))?;
+            }
+            ::core::result::Result::Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Self_pb<'a> {
+    pub type_pb: ::core::option::Option<i32>,
+    pub fn_pb: ::core::option::Option<::std::borrow::Cow<'a, str>>,
+    pub self_pb: ::core::option::Option<bool>,
+    pub loop_pb: ::core::option::Option<mod_Self_pb::match_pb>,
+    pub mod_pb: mod_Self_pb::OneOfmod<'a>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for Self_pb<'a> {
+    const PATH: &'static str = "fixtures.p2.Self";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Self_pb<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Self_pb<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                8 => self.type_pb = ::core::option::Option::Some(r.read_int32(bytes)?),
+                18 => self.fn_pb = ::core::option::Option::Some(r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?),
+                24 => self.self_pb = ::core::option::Option::Some(r.read_bool(bytes)?),
+                32 => self.loop_pb = ::core::option::Option::Some(r.read_enum(bytes)?),
+                40 => self.mod_pb = mod_Self_pb::OneOfmod::ref_pb(r.read_int32(bytes)?),
+                50 => self.mod_pb = mod_Self_pb::OneOfmod::use_pb(r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?),
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.type_pb = ::core::default::Default::default();
+        self.fn_pb = ::core::default::Default::default();
+        self.self_pb = ::core::default::Default::default();
+        self.loop_pb = ::core::default::Default::default();
+        self.mod_pb = ::core::default::Default::default();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for Self_pb<'a> {
+    fn get_size(&self) -> usize {
+        self.type_pb.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_int32(*m))
+            + self.fn_pb.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_len(m.len()))
+            + self.self_pb.as_ref().map_or(0, |_| 1 + 1)
+            + self.loop_pb.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_enum(*m as i32))
+            + match self.mod_pb {
+                mod_Self_pb::OneOfmod::ref_pb(ref m) => 1 + ::quack_protobuf::sizeofs::sizeof_int32(*m),
+                mod_Self_pb::OneOfmod::use_pb(ref m) => 1 + ::quack_protobuf::sizeofs::sizeof_len(m.len()),
+                mod_Self_pb::OneOfmod::None => 0,
+            }
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if let ::core::option::Option::Some(ref m) = self.type_pb {
+            w.write_with_tag(8, |w| w.write_int32(*m))?;
+        }
+        if let ::core::option::Option::Some(ref m) = self.fn_pb {
+            w.write_with_tag(18, |w| w.write_string(m))?;
+        }
+        if let ::core::option::Option::Some(ref m) = self.self_pb {
+            w.write_with_tag(24, |w| w.write_bool(*m))?;
+        }
+        if let ::core::option::Option::Some(ref m) = self.loop_pb {
+            w.write_with_tag(32, |w| w.write_enum(*m as i32))?;
+        }
+        match self.mod_pb {
+            mod_Self_pb::OneOfmod::ref_pb(ref m) => w.write_with_tag(40, |w| w.write_int32(*m))?,
+            mod_Self_pb::OneOfmod::use_pb(ref m) => w.write_with_tag(50, |w| w.write_string(m))?,
+            mod_Self_pb::OneOfmod::None => {}
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod mod_Self_pb {
+
+    #[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+    #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+    pub enum match_pb {
+        #[default]
+        as_pb = 0,
+        in_pb = 1,
+    }
+
+    impl ::core::convert::From<i32> for match_pb {
+        fn from(i: i32) -> Self {
+            match i {
+                0 => match_pb::as_pb,
+                1 => match_pb::in_pb,
+                _ => <Self as ::core::default::Default>::default(),
+            }
+        }
+    }
+
+    impl ::core::convert::From<match_pb> for i32 {
+        fn from(e: match_pb) -> i32 {
+            e as i32
+        }
+    }
+
+    impl<'a> ::core::convert::From<&'a str> for match_pb {
+        fn from(s: &'a str) -> Self {
+            match s {
+                "as" => match_pb::as_pb,
+                "in" => match_pb::in_pb,
+                _ => <Self as ::core::default::Default>::default(),
+            }
+        }
+    }
+
+    #[derive(Debug, Default, PartialEq, Clone)]
+    #[allow(non_camel_case_types)]
+    pub enum OneOfmod<'a> {
+        ref_pb(i32),
+        use_pb(::std::borrow::Cow<'a, str>),
+        #[default]
+        None,
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+#[allow(non_snake_case)]
+pub struct type_pb<'a> {
+    pub Self_pb: ::core::option::Option<Self_pb<'a>>,
+    pub struct_pb: ::std::vec::Vec<Self_pb<'a>>,
+    pub impl_pb: ::std::collections::HashMap<::std::string::String, Self_pb<'a>>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for type_pb<'a> {
+    const PATH: &'static str = "fixtures.p2.type";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for type_pb<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for type_pb<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => r.merge_message(bytes, self.Self_pb.get_or_insert_with(::core::default::Default::default))?,
+                18 => {
+                    r.check_repeated_len(self.struct_pb.len())?;
+                    self.struct_pb.push(r.read_message(bytes)?);
+                }
+                26 => {
+                    let (key, value) = r.read_map(bytes, |r, bytes| r.read_string(bytes).map(str::to_owned), |r, bytes| r.read_message(bytes))?;
+                    r.check_map_len(self.impl_pb.len())?;
+                    self.impl_pb.insert(key, value);
+                }
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.Self_pb = ::core::default::Default::default();
+        self.struct_pb.clear();
+        self.impl_pb.clear();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for type_pb<'a> {
+    fn get_size(&self) -> usize {
+        self.Self_pb.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m)))
+            + self.struct_pb.iter().map(|m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m))).sum::<usize>()
+            + self.impl_pb.iter().map(|(k, v)| 1 + ::quack_protobuf::sizeofs::sizeof_len(2 + ::quack_protobuf::sizeofs::sizeof_len(k.len()) + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(v)))).sum::<usize>()
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if let ::core::option::Option::Some(ref m) = self.Self_pb {
+            w.write_with_tag(10, |w| w.write_message_with_len_prefix(m))?;
+        }
+        for m in self.struct_pb.iter() {
+            w.write_with_tag(18, |w| w.write_message_with_len_prefix(m))?;
+        }
+        for (k, v) in self.impl_pb.iter() {
+            w.write_with_tag(26, |w| w.write_map_message(::quack_protobuf::sizeofs::sizeof_len(k.len()), 10, |w| w.write_string(k), 18, v))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
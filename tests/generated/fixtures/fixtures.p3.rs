@@ -0,0 +1,528 @@
+// Automatically generated by quack-protobuf-codegen, do not edit.
+// source: tests/protos/fixtures/p3.proto
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+pub enum Color {
+    #[default]
+    COLOR_UNSPECIFIED = 0,
+    RED = 1,
+    GREEN = 2,
+}
+
+impl ::core::convert::From<i32> for Color {
+    fn from(i: i32) -> Self {
+        match i {
+            0 => Color::COLOR_UNSPECIFIED,
+            1 => Color::RED,
+            2 => Color::GREEN,
+            _ => <Self as ::core::default::Default>::default(),
+        }
+    }
+}
+
+impl ::core::convert::From<Color> for i32 {
+    fn from(e: Color) -> i32 {
+        e as i32
+    }
+}
+
+impl<'a> ::core::convert::From<&'a str> for Color {
+    fn from(s: &'a str) -> Self {
+        match s {
+            "COLOR_UNSPECIFIED" => Color::COLOR_UNSPECIFIED,
+            "RED" => Color::RED,
+            "GREEN" => Color::GREEN,
+            _ => <Self as ::core::default::Default>::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Eq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+    pub cached_size: ::quack_protobuf::CachedSize,
+}
+
+impl ::quack_protobuf::MessageInfo for Point {
+    const PATH: &'static str = "fixtures.p3.Point";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Point {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Point {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                8 => self.x = r.read_sint32(bytes)?,
+                16 => self.y = r.read_sint32(bytes)?,
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.x = ::core::default::Default::default();
+        self.y = ::core::default::Default::default();
+    }
+}
+
+impl ::quack_protobuf::MessageWrite for Point {
+    fn get_size(&self) -> usize {
+        let size = {
+            (if self.x != 0 { 1 + ::quack_protobuf::sizeofs::sizeof_sint32(self.x) } else { 0 })
+                + if self.y != 0 { 1 + ::quack_protobuf::sizeofs::sizeof_sint32(self.y) } else { 0 }
+        };
+        self.cached_size.set(size);
+        size
+    }
+
+    fn take_cached_size(&self) -> ::core::option::Option<usize> {
+        self.cached_size.take()
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if self.x != 0 {
+            w.write_with_tag(8, |w| w.write_sint32(self.x))?;
+        }
+        if self.y != 0 {
+            w.write_with_tag(16, |w| w.write_sint32(self.y))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Shape<'a> {
+    pub name: ::std::borrow::Cow<'a, str>,
+    pub points: ::std::vec::Vec<Point>,
+    pub color: Color,
+    pub ids: ::std::vec::Vec<i32>,
+    pub weights: ::quack_protobuf::PackedFixed<'a, f64>,
+    pub tags: ::std::collections::HashMap<::std::borrow::Cow<'a, str>, i64>,
+    pub by_id: ::std::collections::HashMap<i32, Point>,
+    pub colors: ::std::collections::HashMap<::std::borrow::Cow<'a, str>, Color>,
+    pub maybe: ::core::option::Option<u64>,
+    pub data: ::std::borrow::Cow<'a, [u8]>,
+    pub child: ::core::option::Option<::std::boxed::Box<Shape<'a>>>,
+    pub children: ::std::vec::Vec<Shape<'a>>,
+    pub flags: ::std::vec::Vec<bool>,
+    pub f32: u32,
+    pub sf64: i64,
+    pub inner: mod_Shape::Inner,
+    pub status: super::p2::Status,
+    pub kind: mod_Shape::OneOfkind<'a>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for Shape<'a> {
+    const PATH: &'static str = "fixtures.p3.Shape";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Shape<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Shape<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => self.name = r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?,
+                18 => {
+                    r.check_repeated_len(self.points.len())?;
+                    self.points.push(r.read_message(bytes)?);
+                }
+                24 => self.color = r.read_enum(bytes)?,
+                32 => {
+                    r.check_repeated_len(self.ids.len())?;
+                    self.ids.push(r.read_int32(bytes)?);
+                }
+                34 => r.append_packed(bytes, &mut self.ids, |r, bytes| r.read_int32(bytes))?,
+                42 => r.append_packed_fixed(bytes, &mut self.weights)?,
+                41 => {
+                    let v = r.read_double(bytes)?;
+                    self.weights.own();
+                    if let ::quack_protobuf::PackedFixed::Owned(ref mut values) = self.weights {
+                        r.check_repeated_len(values.len())?;
+                        values.push(v);
+                    }
+                }
+                50 => {
+                    let (key, value) = r.read_map(bytes, |r, bytes| r.read_string(bytes).map(::std::borrow::Cow::Borrowed), |r, bytes| r.read_int64(bytes))?;
+                    r.check_map_len(self.tags.len())?;
+                    self.tags.insert(key, value);
+                }
+                58 => {
+                    let (key, value) = r.read_map(bytes, |r, bytes| r.read_int32(bytes), |r, bytes| r.read_message(bytes))?;
+                    r.check_map_len(self.by_id.len())?;
+                    self.by_id.insert(key, value);
+                }
+                66 => {
+                    let (key, value) = r.read_map(bytes, |r, bytes| r.read_string(bytes).map(::std::borrow::Cow::Borrowed), |r, bytes| r.read_enum(bytes))?;
+                    r.check_map_len(self.colors.len())?;
+                    self.colors.insert(key, value);
+                }
+                96 => self.maybe = ::core::option::Option::Some(r.read_uint64(bytes)?),
+                106 => self.data = r.read_bytes(bytes).map(::std::borrow::Cow::Borrowed)?,
+                114 => r.merge_message(bytes, &mut **self.child.get_or_insert_with(::core::default::Default::default))?,
+                122 => {
+                    r.check_repeated_len(self.children.len())?;
+                    self.children.push(r.read_message(bytes)?);
+                }
+                128 => {
+                    r.check_repeated_len(self.flags.len())?;
+                    self.flags.push(r.read_bool(bytes)?);
+                }
+                130 => r.append_packed(bytes, &mut self.flags, |r, bytes| r.read_bool(bytes))?,
+                141 => self.f32 = r.read_fixed32(bytes)?,
+                145 => self.sf64 = r.read_sfixed64(bytes)?,
+                152 => self.inner = r.read_enum(bytes)?,
+                160 => self.status = r.read_enum(bytes)?,
+                74 => match &mut self.kind {
+                    mod_Shape::OneOfkind::circle(m) => r.merge_message(bytes, m)?,
+                    _ => self.kind = mod_Shape::OneOfkind::circle(r.read_message(bytes)?),
+                },
+                81 => self.kind = mod_Shape::OneOfkind::side(r.read_double(bytes)?),
+                90 => self.kind = mod_Shape::OneOfkind::label(r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?),
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.name = ::core::default::Default::default();
+        self.points.clear();
+        self.color = ::core::default::Default::default();
+        self.ids.clear();
+        self.weights.clear();
+        self.tags.clear();
+        self.by_id.clear();
+        self.colors.clear();
+        self.maybe = ::core::default::Default::default();
+        self.data = ::core::default::Default::default();
+        self.child = ::core::default::Default::default();
+        self.children.clear();
+        self.flags.clear();
+        self.f32 = ::core::default::Default::default();
+        self.sf64 = ::core::default::Default::default();
+        self.inner = ::core::default::Default::default();
+        self.status = ::core::default::Default::default();
+        self.kind = ::core::default::Default::default();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for Shape<'a> {
+    fn get_size(&self) -> usize {
+        (if !self.name.is_empty() { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.name.len()) } else { 0 })
+            + self.points.iter().map(|m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m))).sum::<usize>()
+            + if self.color != <Color as ::core::default::Default>::default() { 1 + ::quack_protobuf::sizeofs::sizeof_enum(self.color as i32) } else { 0 }
+            + if self.ids.is_empty() { 0 } else { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.ids.iter().map(|m| ::quack_protobuf::sizeofs::sizeof_int32(*m)).sum::<usize>()) }
+            + if self.weights.is_empty() { 0 } else { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.weights.len() * 8) }
+            + self.tags.iter().map(|(k, v)| 1 + ::quack_protobuf::sizeofs::sizeof_len(2 + ::quack_protobuf::sizeofs::sizeof_len(k.len()) + ::quack_protobuf::sizeofs::sizeof_int64(*v))).sum::<usize>()
+            + self.by_id.iter().map(|(k, v)| 1 + ::quack_protobuf::sizeofs::sizeof_len(2 + ::quack_protobuf::sizeofs::sizeof_int32(*k) + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(v)))).sum::<usize>()
+            + self.colors.iter().map(|(k, v)| 1 + ::quack_protobuf::sizeofs::sizeof_len(2 + ::quack_protobuf::sizeofs::sizeof_len(k.len()) + ::quack_protobuf::sizeofs::sizeof_enum(*v as i32))).sum::<usize>()
+            + self.maybe.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_uint64(*m))
+            + if !self.data.is_empty() { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.data.len()) } else { 0 }
+            + self.child.as_ref().map_or(0, |m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(&**m)))
+            + self.children.iter().map(|m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m))).sum::<usize>()
+            + if self.flags.is_empty() { 0 } else { 2 + ::quack_protobuf::sizeofs::sizeof_len(self.flags.len()) }
+            + if self.f32 != 0 { 2 + 4 } else { 0 }
+            + if self.sf64 != 0 { 2 + 8 } else { 0 }
+            + if self.inner != <mod_Shape::Inner as ::core::default::Default>::default() { 2 + ::quack_protobuf::sizeofs::sizeof_enum(self.inner as i32) } else { 0 }
+            + if self.status != <super::p2::Status as ::core::default::Default>::default() { 2 + ::quack_protobuf::sizeofs::sizeof_enum(self.status as i32) } else { 0 }
+            + match self.kind {
+                mod_Shape::OneOfkind::circle(ref m) => 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m)),
+                mod_Shape::OneOfkind::side(_) => 1 + 8,
+                mod_Shape::OneOfkind::label(ref m) => 1 + ::quack_protobuf::sizeofs::sizeof_len(m.len()),
+                mod_Shape::OneOfkind::None => 0,
+            }
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if !self.name.is_empty() {
+            w.write_with_tag(10, |w| w.write_string(&self.name))?;
+        }
+        for m in self.points.iter() {
+            w.write_with_tag(18, |w| w.write_message_with_len_prefix(m))?;
+        }
+        if self.color != <Color as ::core::default::Default>::default() {
+            w.write_with_tag(24, |w| w.write_enum(self.color as i32))?;
+        }
+        w.write_packed_with_tag(34, &self.ids, |w, m| w.write_int32(*m), &|m| ::quack_protobuf::sizeofs::sizeof_int32(*m))?;
+        w.write_packed_fixed_with_tag(42, &self.weights)?;
+        for (k, v) in self.tags.iter() {
+            w.write_with_tag(50, |w| w.write_map(2 + ::quack_protobuf::sizeofs::sizeof_len(k.len()) + ::quack_protobuf::sizeofs::sizeof_int64(*v), 10, |w| w.write_string(k), 16, |w| w.write_int64(*v)))?;
+        }
+        for (k, v) in self.by_id.iter() {
+            w.write_with_tag(58, |w| w.write_map_message(::quack_protobuf::sizeofs::sizeof_int32(*k), 8, |w| w.write_int32(*k), 18, v))?;
+        }
+        for (k, v) in self.colors.iter() {
+            w.write_with_tag(66, |w| w.write_map(2 + ::quack_protobuf::sizeofs::sizeof_len(k.len()) + ::quack_protobuf::sizeofs::sizeof_enum(*v as i32), 10, |w| w.write_string(k), 16, |w| w.write_enum(*v as i32)))?;
+        }
+        if let ::core::option::Option::Some(ref m) = self.maybe {
+            w.write_with_tag(96, |w| w.write_uint64(*m))?;
+        }
+        if !self.data.is_empty() {
+            w.write_with_tag(106, |w| w.write_bytes(&self.data))?;
+        }
+        if let ::core::option::Option::Some(ref m) = self.child {
+            w.write_with_tag(114, |w| w.write_message_with_len_prefix(&**m))?;
+        }
+        for m in self.children.iter() {
+            w.write_with_tag(122, |w| w.write_message_with_len_prefix(m))?;
+        }
+        w.write_packed_with_tag(130, &self.flags, |w, m| w.write_bool(*m), &|_| 1)?;
+        if self.f32 != 0 {
+            w.write_with_tag(141, |w| w.write_fixed32(self.f32))?;
+        }
+        if self.sf64 != 0 {
+            w.write_with_tag(145, |w| w.write_sfixed64(self.sf64))?;
+        }
+        if self.inner != <mod_Shape::Inner as ::core::default::Default>::default() {
+            w.write_with_tag(152, |w| w.write_enum(self.inner as i32))?;
+        }
+        if self.status != <super::p2::Status as ::core::default::Default>::default() {
+            w.write_with_tag(160, |w| w.write_enum(self.status as i32))?;
+        }
+        match self.kind {
+            mod_Shape::OneOfkind::circle(ref m) => w.write_with_tag(74, |w| w.write_message_with_len_prefix(m))?,
+            mod_Shape::OneOfkind::side(ref m) => w.write_with_tag(81, |w| w.write_double(*m))?,
+            mod_Shape::OneOfkind::label(ref m) => w.write_with_tag(90, |w| w.write_string(m))?,
+            mod_Shape::OneOfkind::None => {}
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[allow(non_snake_case)]
+pub mod mod_Shape {
+
+    #[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+    #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+    pub enum Inner {
+        #[default]
+        INNER_UNSPECIFIED = 0,
+        B = 1,
+    }
+
+    impl ::core::convert::From<i32> for Inner {
+        fn from(i: i32) -> Self {
+            match i {
+                0 => Inner::INNER_UNSPECIFIED,
+                1 => Inner::B,
+                _ => <Self as ::core::default::Default>::default(),
+            }
+        }
+    }
+
+    impl ::core::convert::From<Inner> for i32 {
+        fn from(e: Inner) -> i32 {
+            e as i32
+        }
+    }
+
+    impl<'a> ::core::convert::From<&'a str> for Inner {
+        fn from(s: &'a str) -> Self {
+            match s {
+                "INNER_UNSPECIFIED" => Inner::INNER_UNSPECIFIED,
+                "B" => Inner::B,
+                _ => <Self as ::core::default::Default>::default(),
+            }
+        }
+    }
+
+    #[derive(Debug, Default, PartialEq, Clone)]
+    pub struct Circle {
+        pub radius: f32,
+    }
+
+    impl ::quack_protobuf::MessageInfo for Circle {
+        const PATH: &'static str = "fixtures.p3.Shape.Circle";
+    }
+
+    impl<'a> ::quack_protobuf::MessageRead<'a> for Circle {
+        fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+            let mut msg = <Self as ::core::default::Default>::default();
+            ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+            ::core::result::Result::Ok(msg)
+        }
+    }
+
+    impl<'a> ::quack_protobuf::MessageMerge<'a> for Circle {
+        fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+            while !r.is_eof() {
+                match r.next_tag(bytes)? {
+                    13 => self.radius = r.read_float(bytes)?,
+                    t => {
+                        r.read_unknown(bytes, t)?;
+                    }
+                }
+            }
+            ::core::result::Result::Ok(())
+        }
+
+        fn clear(&mut self) {
+            self.radius = ::core::default::Default::default();
+        }
+    }
+
+    impl ::quack_protobuf::MessageWrite for Circle {
+        fn get_size(&self) -> usize {
+            if self.radius != 0. { 1 + 4 } else { 0 }
+        }
+
+        fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+            if self.radius != 0. {
+                w.write_with_tag(13, |w| w.write_float(self.radius))?;
+            }
+            ::core::result::Result::Ok(())
+        }
+    }
+
+    #[derive(Debug, Default, PartialEq, Clone)]
+    #[allow(non_camel_case_types)]
+    pub enum OneOfkind<'a> {
+        circle(Circle),
+        side(f64),
+        label(::std::borrow::Cow<'a, str>),
+        #[default]
+        None,
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Eq, Hash)]
+pub struct Partial<'a> {
+    pub name: ::std::borrow::Cow<'a, str>,
+    pub unknown_fields: ::quack_protobuf::UnknownFields<'a>,
+}
+
+impl<'a> ::quack_protobuf::MessageInfo for Partial<'a> {
+    const PATH: &'static str = "fixtures.p3.Partial";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Partial<'a> {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Partial<'a> {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => self.name = r.read_string(bytes).map(::std::borrow::Cow::Borrowed)?,
+                t => self.unknown_fields.read(r, bytes, t)?,
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.name = ::core::default::Default::default();
+        self.unknown_fields.clear();
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageWrite for Partial<'a> {
+    fn get_size(&self) -> usize {
+        (if !self.name.is_empty() { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.name.len()) } else { 0 })
+            + self.unknown_fields.get_size()
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if !self.name.is_empty() {
+            w.write_with_tag(10, |w| w.write_string(&self.name))?;
+        }
+        w.write_unknown(&self.unknown_fields)?;
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Counted {
+    pub points: ::std::vec::Vec<Point>,
+    pub by_id: ::std::collections::HashMap<u32, Point>,
+    pub cached_size: ::quack_protobuf::CachedSize,
+}
+
+impl ::quack_protobuf::MessageInfo for Counted {
+    const PATH: &'static str = "fixtures.p3.Counted";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for Counted {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for Counted {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => {
+                    r.check_repeated_len(self.points.len())?;
+                    self.points.push(r.read_message(bytes)?);
+                }
+                18 => {
+                    let (key, value) = r.read_map(bytes, |r, bytes| r.read_uint32(bytes), |r, bytes| r.read_message(bytes))?;
+                    r.check_map_len(self.by_id.len())?;
+                    self.by_id.insert(key, value);
+                }
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.points.clear();
+        self.by_id.clear();
+    }
+}
+
+impl ::quack_protobuf::MessageWrite for Counted {
+    fn get_size(&self) -> usize {
+        let size = {
+            self.points.iter().map(|m| 1 + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(m))).sum::<usize>()
+                + self.by_id.iter().map(|(k, v)| 1 + ::quack_protobuf::sizeofs::sizeof_len(2 + ::quack_protobuf::sizeofs::sizeof_uint32(*k) + ::quack_protobuf::sizeofs::sizeof_len(::quack_protobuf::MessageWrite::get_size(v)))).sum::<usize>()
+        };
+        self.cached_size.set(size);
+        size
+    }
+
+    fn take_cached_size(&self) -> ::core::option::Option<usize> {
+        self.cached_size.take()
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        for m in self.points.iter() {
+            w.write_with_tag(10, |w| w.write_message_with_len_prefix(m))?;
+        }
+        for (k, v) in self.by_id.iter() {
+            w.write_with_tag(18, |w| w.write_map_message(::quack_protobuf::sizeofs::sizeof_uint32(*k), 8, |w| w.write_uint32(*k), 18, v))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
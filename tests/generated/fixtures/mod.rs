@@ -0,0 +1,12 @@
+// Automatically generated by quack-protobuf-codegen, do not edit.
+pub mod fixtures {
+    #![allow(non_snake_case, non_camel_case_types, non_upper_case_globals)]
+    pub mod p2 {
+        #![allow(non_snake_case, non_camel_case_types, non_upper_case_globals)]
+        include!("fixtures.p2.rs");
+    }
+    pub mod p3 {
+        #![allow(non_snake_case, non_camel_case_types, non_upper_case_globals)]
+        include!("fixtures.p3.rs");
+    }
+}
@@ -0,0 +1,183 @@
+// Automatically generated by quack-protobuf-codegen, do not edit.
+// source: tests/protos/greeter.proto
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct HelloRequest {
+    pub name: ::std::string::String,
+    pub times: u32,
+}
+
+impl ::quack_protobuf::MessageInfo for HelloRequest {
+    const PATH: &'static str = "helloworld.HelloRequest";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for HelloRequest {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for HelloRequest {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => self.name = r.read_string(bytes).map(str::to_owned)?,
+                16 => self.times = r.read_uint32(bytes)?,
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.name.clear();
+        self.times = ::core::default::Default::default();
+    }
+}
+
+impl ::quack_protobuf::MessageWrite for HelloRequest {
+    fn get_size(&self) -> usize {
+        (if !self.name.is_empty() { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.name.len()) } else { 0 })
+            + if self.times != 0 { 1 + ::quack_protobuf::sizeofs::sizeof_uint32(self.times) } else { 0 }
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if !self.name.is_empty() {
+            w.write_with_tag(10, |w| w.write_string(&self.name))?;
+        }
+        if self.times != 0 {
+            w.write_with_tag(16, |w| w.write_uint32(self.times))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct HelloReply {
+    pub message: ::std::string::String,
+}
+
+impl ::quack_protobuf::MessageInfo for HelloReply {
+    const PATH: &'static str = "helloworld.HelloReply";
+}
+
+impl<'a> ::quack_protobuf::MessageRead<'a> for HelloReply {
+    fn from_reader(r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<Self> {
+        let mut msg = <Self as ::core::default::Default>::default();
+        ::quack_protobuf::MessageMerge::merge_from(&mut msg, r, bytes)?;
+        ::core::result::Result::Ok(msg)
+    }
+}
+
+impl<'a> ::quack_protobuf::MessageMerge<'a> for HelloReply {
+    fn merge_from(&mut self, r: &mut ::quack_protobuf::BytesReader, bytes: &'a [u8]) -> ::quack_protobuf::Result<()> {
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => self.message = r.read_string(bytes).map(str::to_owned)?,
+                t => {
+                    r.read_unknown(bytes, t)?;
+                }
+            }
+        }
+        ::core::result::Result::Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.message.clear();
+    }
+}
+
+impl ::quack_protobuf::MessageWrite for HelloReply {
+    fn get_size(&self) -> usize {
+        if !self.message.is_empty() { 1 + ::quack_protobuf::sizeofs::sizeof_len(self.message.len()) } else { 0 }
+    }
+
+    fn write_message<W: ::quack_protobuf::WriterBackend>(&self, w: &mut ::quack_protobuf::Writer<W>) -> ::quack_protobuf::Result<()> {
+        if !self.message.is_empty() {
+            w.write_with_tag(10, |w| w.write_string(&self.message))?;
+        }
+        ::core::result::Result::Ok(())
+    }
+}
+
+pub trait Greeter: ::core::marker::Send + ::core::marker::Sync + 'static {
+    fn say_hello(&self, request: ::quack_protobuf::rpc::Request<HelloRequest>) -> impl ::core::future::Future<Output = ::core::result::Result<::quack_protobuf::rpc::Response<HelloReply>, ::quack_protobuf::rpc::Status>> + ::core::marker::Send;
+    fn lots_of_replies(&self, request: ::quack_protobuf::rpc::Request<HelloRequest>, sender: &mut ::quack_protobuf::rpc::Sender<HelloReply>) -> impl ::core::future::Future<Output = ::core::result::Result<(), ::quack_protobuf::rpc::Status>> + ::core::marker::Send;
+    fn lots_of_greetings(&self, request: ::quack_protobuf::rpc::Request<::quack_protobuf::rpc::Streaming<HelloRequest>>) -> impl ::core::future::Future<Output = ::core::result::Result<::quack_protobuf::rpc::Response<HelloReply>, ::quack_protobuf::rpc::Status>> + ::core::marker::Send;
+    fn bidi_hello(&self, request: ::quack_protobuf::rpc::Request<::quack_protobuf::rpc::Streaming<HelloRequest>>, sender: &mut ::quack_protobuf::rpc::Sender<HelloReply>) -> impl ::core::future::Future<Output = ::core::result::Result<(), ::quack_protobuf::rpc::Status>> + ::core::marker::Send;
+}
+
+pub struct GreeterServer<S: Greeter> {
+    inner: S,
+}
+
+impl<S: Greeter> GreeterServer<S> {
+    pub fn new(inner: S) -> Self {
+        GreeterServer { inner }
+    }
+}
+
+impl<S: Greeter> ::quack_protobuf::rpc::Service for GreeterServer<S> {
+    fn name(&self) -> &'static str {
+        "helloworld.Greeter"
+    }
+
+    fn call<'a>(&'a self, method: &'a str, call: ::quack_protobuf::rpc::Call) -> ::quack_protobuf::rpc::BoxFuture<'a, ()> {
+        ::std::boxed::Box::pin(async move {
+            match method {
+                "SayHello" => {
+                    let ::core::option::Option::Some((request, sender)) = call.unary::<HelloRequest, HelloReply>().await else { return };
+                    let result = self.inner.say_hello(request).await;
+                    sender.respond(result).await;
+                }
+                "LotsOfReplies" => {
+                    let ::core::option::Option::Some((request, mut sender)) = call.unary::<HelloRequest, HelloReply>().await else { return };
+                    let result = self.inner.lots_of_replies(request, &mut sender).await;
+                    sender.finish(result).await;
+                }
+                "LotsOfGreetings" => {
+                    let (request, sender) = call.streaming::<HelloRequest, HelloReply>();
+                    let result = self.inner.lots_of_greetings(request).await;
+                    sender.respond(result).await;
+                }
+                "BidiHello" => {
+                    let (request, mut sender) = call.streaming::<HelloRequest, HelloReply>();
+                    let result = self.inner.bidi_hello(request, &mut sender).await;
+                    sender.finish(result).await;
+                }
+                _ => call.unimplemented().await,
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GreeterClient {
+    channel: ::quack_protobuf::rpc::Channel,
+}
+
+impl GreeterClient {
+    pub fn new(channel: ::quack_protobuf::rpc::Channel) -> Self {
+        GreeterClient { channel }
+    }
+
+    pub async fn say_hello(&self, request: ::quack_protobuf::rpc::Request<HelloRequest>) -> ::core::result::Result<::quack_protobuf::rpc::Response<HelloReply>, ::quack_protobuf::rpc::Status> {
+        self.channel.unary("/helloworld.Greeter/SayHello", request).await
+    }
+
+    pub async fn lots_of_replies(&self, request: ::quack_protobuf::rpc::Request<HelloRequest>) -> ::core::result::Result<::quack_protobuf::rpc::Streaming<HelloReply>, ::quack_protobuf::rpc::Status> {
+        self.channel.server_streaming("/helloworld.Greeter/LotsOfReplies", request).await
+    }
+
+    pub async fn lots_of_greetings(&self, metadata: ::quack_protobuf::rpc::Metadata) -> ::core::result::Result<(::quack_protobuf::rpc::Sender<HelloRequest>, ::quack_protobuf::rpc::Streaming<HelloReply>), ::quack_protobuf::rpc::Status> {
+        self.channel.streaming("/helloworld.Greeter/LotsOfGreetings", metadata).await
+    }
+
+    pub async fn bidi_hello(&self, metadata: ::quack_protobuf::rpc::Metadata) -> ::core::result::Result<(::quack_protobuf::rpc::Sender<HelloRequest>, ::quack_protobuf::rpc::Streaming<HelloReply>), ::quack_protobuf::rpc::Status> {
+        self.channel.streaming("/helloworld.Greeter/BidiHello", metadata).await
+    }
+}
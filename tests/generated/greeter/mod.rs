@@ -0,0 +1,5 @@
+// Automatically generated by quack-protobuf-codegen, do not edit.
+pub mod helloworld {
+    #![allow(non_snake_case, non_camel_case_types, non_upper_case_globals)]
+    include!("helloworld.rs");
+}
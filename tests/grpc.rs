@@ -0,0 +1,108 @@
+//! Parses gRPC frames, checking the size limits of compressed and oversized frames
+
+#![cfg(feature = "grpc")]
+
+use quack_protobuf::grpc::{decode_frame, Frame, FrameDecoder, FLAG_COMPRESSED};
+use quack_protobuf::{Error, Result};
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Ping {
+    #[pb(1)]
+    id: u32,
+}
+
+/// A frame of `data` flagged as compressed
+fn compressed(data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![FLAG_COMPRESSED];
+    frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    frame.extend_from_slice(data);
+    frame
+}
+
+/// A run-length "decompressor": each `(count, byte)` pair expands to `count` bytes,
+/// failing as soon as the output grows past `limit`
+fn expand(
+    data: &[u8],
+    out: &mut Vec<u8>,
+    limit: usize,
+) -> Result<()> {
+    for pair in data.chunks(2) {
+        let size = out.len() + pair[0] as usize;
+        if size > limit {
+            return Err(Error::MessageTooLarge { size, limit });
+        }
+        out.resize(size, pair[1]);
+    }
+    Ok(())
+}
+
+#[test]
+fn decompressed_frame() {
+    let mut decoder = FrameDecoder::with_decompressor(expand);
+    decoder.push(&compressed(&[
+        2, 0x08, 1, 0x01,
+    ]));
+    assert_eq!(
+        decoder.next_frame().unwrap(),
+        Some(Frame::Message(&[
+            0x08, 0x08, 0x01
+        ]))
+    );
+    assert!(decoder.is_empty());
+}
+
+#[test]
+fn decompression_bomb() {
+    // 100 bytes expanding to 12750 bytes
+    let bomb = compressed(&[255; 100]);
+    let mut calls = 0;
+    let mut decoder = FrameDecoder::with_decompressor(
+        |data: &[u8], out: &mut Vec<u8>, limit| {
+            calls += 1;
+            assert_eq!(limit, 1000);
+            expand(data, out, limit)
+        },
+    );
+    decoder.set_max_frame_size(1000);
+    decoder.push(&bomb);
+    match decoder.next_frame() {
+        // stopped at the fourth pair, well before expanding everything
+        Err(Error::MessageTooLarge {
+            size: 1020,
+            limit: 1000,
+        }) => {}
+        r => panic!(
+            "expected MessageTooLarge, got {:?}",
+            r
+        ),
+    }
+    drop(decoder);
+    assert_eq!(calls, 1);
+
+    // a decompressor ignoring the limit is still caught afterwards
+    let mut decoder =
+        FrameDecoder::with_decompressor(|data: &[u8], out: &mut Vec<u8>, _| expand(data, out, usize::MAX));
+    decoder.set_max_frame_size(1000);
+    decoder.push(&bomb);
+    assert!(matches!(
+        decoder.next_frame(),
+        Err(Error::MessageTooLarge {
+            size: 12750,
+            limit: 1000
+        })
+    ));
+}
+
+#[test]
+fn frame_length_past_the_buffer() {
+    // the largest length a header can hold must not overflow the end of the frame
+    let mut frame = vec![0, 0xff, 0xff, 0xff, 0xff];
+    frame.extend_from_slice(&[0x08, 0x01]);
+    match decode_frame::<Ping>(&frame) {
+        Err(Error::UnexpectedEndOfBuffer) | Err(Error::MessageTooLarge { .. }) => {}
+        r => panic!(
+            "expected an error, got {:?}",
+            r.map(|(m, _)| m)
+        ),
+    }
+}
@@ -0,0 +1,153 @@
+//! Checks the recursion limit of readers on nested messages, maps and groups
+
+#![cfg(feature = "std")]
+
+use std::collections::BTreeMap;
+
+use quack_protobuf::reader::DEFAULT_RECURSION_LIMIT;
+use quack_protobuf::{encode_to_vec, BytesReader, Error, MessageRead};
+
+#[allow(dead_code)]
+mod generated {
+    include!("generated/fixtures/mod.rs");
+}
+
+use generated::fixtures::p2;
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Node {
+    #[pb(1)]
+    child: Option<Box<Node>>,
+    #[pb(2, map)]
+    children: BTreeMap<u32, Node>,
+}
+
+/// `depth` messages nested in one another, below the top level one
+fn chain(depth: usize) -> Node {
+    (0..depth).fold(Node::default(), |child, _| {
+        Node {
+            child: Some(Box::new(child)),
+            ..Default::default()
+        }
+    })
+}
+
+/// `depth` maps nested in one another: each entry and its value take 2 levels
+fn tree(depth: usize) -> Node {
+    (0..depth).fold(Node::default(), |child, i| {
+        Node {
+            children: [(i as u32, child)].into(),
+            ..Default::default()
+        }
+    })
+}
+
+/// Decodes `bytes` as `M` with a recursion limit of `limit`
+fn decode<'a, M: MessageRead<'a>>(
+    bytes: &'a [u8],
+    limit: usize,
+) -> quack_protobuf::Result<M> {
+    let mut reader = BytesReader::from_bytes(bytes);
+    reader.set_recursion_limit(limit);
+    M::from_reader(&mut reader, bytes)
+}
+
+fn assert_exceeded<T: std::fmt::Debug>(r: quack_protobuf::Result<T>) {
+    match r {
+        Err(e)
+            if matches!(
+                e.kind(),
+                Error::RecursionLimitExceeded
+            ) => {}
+        r => panic!(
+            "expected RecursionLimitExceeded, got {:?}",
+            r
+        ),
+    }
+}
+
+#[test]
+fn nested_messages() {
+    let bytes = encode_to_vec(&chain(
+        DEFAULT_RECURSION_LIMIT,
+    ))
+    .unwrap();
+    assert_eq!(
+        quack_protobuf::decode::<Node>(&bytes).unwrap(),
+        chain(DEFAULT_RECURSION_LIMIT)
+    );
+    let bytes = encode_to_vec(&chain(
+        DEFAULT_RECURSION_LIMIT + 1,
+    ))
+    .unwrap();
+    assert_exceeded(quack_protobuf::decode::<Node>(&bytes));
+}
+
+#[test]
+fn custom_limit() {
+    let bytes = encode_to_vec(&chain(5)).unwrap();
+    assert_eq!(
+        decode::<Node>(&bytes, 5).unwrap(),
+        chain(5)
+    );
+    assert_exceeded(decode::<Node>(&bytes, 4));
+    // a limit of 0 only accepts flat messages
+    assert_exceeded(decode::<Node>(&bytes, 0));
+    let flat = encode_to_vec(&chain(0)).unwrap();
+    assert_eq!(
+        decode::<Node>(&flat, 0).unwrap(),
+        chain(0)
+    );
+}
+
+#[test]
+fn nested_maps() {
+    let bytes = encode_to_vec(&tree(3)).unwrap();
+    assert_eq!(
+        decode::<Node>(&bytes, 6).unwrap(),
+        tree(3)
+    );
+    assert_exceeded(decode::<Node>(&bytes, 5));
+}
+
+#[test]
+fn nested_groups() {
+    // `Res` is a group holding `Inner` groups
+    let groups = p2::WithGroups {
+        res: Some(p2::mod_WithGroups::Res {
+            inner: vec![p2::mod_WithGroups::mod_Res::Inner { x: 1 }],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let bytes = encode_to_vec(&groups).unwrap();
+    assert_eq!(
+        decode::<p2::WithGroups>(&bytes, 2).unwrap(),
+        groups
+    );
+    assert_exceeded(decode::<p2::WithGroups>(
+        &bytes, 1,
+    ));
+}
+
+#[test]
+fn nested_unknown_groups() {
+    // groups of field 15, unknown to `Node`, are skipped as a whole
+    let groups = |depth| {
+        let mut bytes = vec![0x7b; depth];
+        bytes.extend(std::iter::repeat_n(
+            0x7c, depth,
+        ));
+        bytes
+    };
+    assert_eq!(
+        decode::<Node>(&groups(3), 3).unwrap(),
+        Node::default()
+    );
+    assert_exceeded(decode::<Node>(&groups(4), 3));
+    assert_exceeded(
+        quack_protobuf::decode::<Node>(&groups(
+            DEFAULT_RECURSION_LIMIT + 1,
+        )),
+    );
+}
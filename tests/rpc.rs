@@ -0,0 +1,273 @@
+//! Runs the generated Greeter server and client over a loopback socket
+
+#![cfg(feature = "rpc")]
+
+use quack_protobuf::rpc::{Channel, Code, Metadata, Request, Response, Sender, Server, Status, Streaming};
+use tokio::net::TcpListener;
+
+mod generated {
+    include!("generated/greeter/mod.rs");
+}
+
+use generated::helloworld::{Greeter, GreeterClient, GreeterServer, HelloReply, HelloRequest};
+
+struct MyGreeter;
+
+impl Greeter for MyGreeter {
+    async fn say_hello(
+        &self,
+        request: Request<HelloRequest>,
+    ) -> Result<Response<HelloReply>, Status> {
+        let name = &request.get_ref().name;
+        if name.is_empty() {
+            let mut status = Status::new(
+                Code::InvalidArgument,
+                "missing name: é",
+            );
+            status.metadata_mut().insert("x-field", "name");
+            return Err(status);
+        }
+        let mut response = Response::new(HelloReply {
+            message: format!("Hello {}!", name),
+        });
+        let trace = request
+            .metadata()
+            .get("x-trace")
+            .unwrap_or_default();
+        response.metadata_mut().insert("x-trace", trace);
+        Ok(response)
+    }
+
+    async fn lots_of_replies(
+        &self,
+        request: Request<HelloRequest>,
+        sender: &mut Sender<HelloReply>,
+    ) -> Result<(), Status> {
+        let request = request.into_inner();
+        for i in 0..request.times {
+            sender
+                .send(&HelloReply {
+                    message: format!("{} {}", request.name, i),
+                })
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn lots_of_greetings(
+        &self,
+        request: Request<Streaming<HelloRequest>>,
+    ) -> Result<Response<HelloReply>, Status> {
+        let mut requests = request.into_inner();
+        let mut names = Vec::new();
+        while let Some(request) = requests.message().await? {
+            names.push(request.name);
+        }
+        Ok(Response::new(HelloReply {
+            message: names.join(", "),
+        }))
+    }
+
+    async fn bidi_hello(
+        &self,
+        request: Request<Streaming<HelloRequest>>,
+        sender: &mut Sender<HelloReply>,
+    ) -> Result<(), Status> {
+        let mut requests = request.into_inner();
+        while let Some(request) = requests.message().await? {
+            if request.name == "stop" {
+                return Err(Status::new(
+                    Code::Aborted,
+                    "stopped",
+                ));
+            }
+            sender
+                .send(&HelloReply {
+                    message: format!("Hi {}", request.name),
+                })
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Serves a `MyGreeter` on a loopback socket, returning a channel to it
+async fn serve() -> Channel {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut server = Server::new();
+    server.add_service(GreeterServer::new(MyGreeter));
+    tokio::spawn(async move { server.serve(listener).await });
+    Channel::connect(addr).await.unwrap()
+}
+
+fn hello(name: &str) -> HelloRequest {
+    HelloRequest {
+        name: name.to_string(),
+        times: 0,
+    }
+}
+
+#[tokio::test]
+async fn unary() {
+    let client = GreeterClient::new(serve().await);
+    let mut request = Request::new(hello("you"));
+    request.metadata_mut().insert("x-trace", "42");
+    let response = client.say_hello(request).await.unwrap();
+    assert_eq!(
+        response.get_ref().message,
+        "Hello you!"
+    );
+    assert_eq!(
+        response.metadata().get("x-trace"),
+        Some("42")
+    );
+
+    // calls are multiplexed on the same connection
+    let calls = (0..10).map(|i| {
+        let client = client.clone();
+        async move {
+            client
+                .say_hello(Request::new(hello(
+                    &i.to_string(),
+                )))
+                .await
+        }
+    });
+    for (i, response) in join_all(calls).await.into_iter().enumerate() {
+        assert_eq!(
+            response.unwrap().into_inner().message,
+            format!("Hello {}!", i)
+        );
+    }
+}
+
+/// Runs the futures concurrently, in order
+async fn join_all<F: std::future::Future + Send + 'static>(futures: impl Iterator<Item = F>) -> Vec<F::Output>
+where
+    F::Output: Send,
+{
+    let handles = futures.map(tokio::spawn).collect::<Vec<_>>();
+    let mut outputs = Vec::new();
+    for handle in handles {
+        outputs.push(handle.await.unwrap());
+    }
+    outputs
+}
+
+#[tokio::test]
+async fn error_status() {
+    let client = GreeterClient::new(serve().await);
+    let status = client
+        .say_hello(Request::new(hello("")))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        status.code(),
+        Code::InvalidArgument
+    );
+    assert_eq!(
+        status.message(),
+        "missing name: é"
+    );
+    assert_eq!(
+        status.metadata().get("x-field"),
+        Some("name")
+    );
+}
+
+#[tokio::test]
+async fn server_streaming() {
+    let client = GreeterClient::new(serve().await);
+    let mut replies = client
+        .lots_of_replies(Request::new(HelloRequest {
+            name: "n".to_string(),
+            times: 3,
+        }))
+        .await
+        .unwrap();
+    let mut messages = Vec::new();
+    while let Some(reply) = replies.message().await.unwrap() {
+        messages.push(reply.message);
+    }
+    assert_eq!(
+        messages,
+        ["n 0", "n 1", "n 2"]
+    );
+
+    // no message at all
+    let mut replies = client
+        .lots_of_replies(Request::new(hello("n")))
+        .await
+        .unwrap();
+    assert_eq!(
+        replies.message().await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn client_streaming() {
+    let client = GreeterClient::new(serve().await);
+    let (mut sender, responses) = client
+        .lots_of_greetings(Metadata::new())
+        .await
+        .unwrap();
+    for name in ["a", "b", "c"] {
+        sender.send(&hello(name)).await.unwrap();
+    }
+    sender.close().await.unwrap();
+    let response = responses.into_response().await.unwrap();
+    assert_eq!(
+        response.get_ref().message,
+        "a, b, c"
+    );
+}
+
+#[tokio::test]
+async fn bidirectional_streaming() {
+    let client = GreeterClient::new(serve().await);
+    let (mut sender, mut responses) = client.bidi_hello(Metadata::new()).await.unwrap();
+    for name in ["a", "b"] {
+        sender.send(&hello(name)).await.unwrap();
+        let reply = responses.message().await.unwrap().unwrap();
+        assert_eq!(
+            reply.message,
+            format!("Hi {}", name)
+        );
+    }
+    sender.close().await.unwrap();
+    assert_eq!(
+        responses.message().await.unwrap(),
+        None
+    );
+
+    // the status ends the stream of replies
+    let (mut sender, mut responses) = client.bidi_hello(Metadata::new()).await.unwrap();
+    sender.send(&hello("stop")).await.unwrap();
+    let status = responses.message().await.unwrap_err();
+    assert_eq!(status.code(), Code::Aborted);
+    assert_eq!(status.message(), "stopped");
+}
+
+#[tokio::test]
+async fn unknown_method() {
+    let channel = serve().await;
+    for path in [
+        "/helloworld.Greeter/Unknown",
+        "/helloworld.Unknown/SayHello",
+    ] {
+        let status = channel
+            .unary::<HelloRequest, HelloReply>(
+                path,
+                Request::new(hello("you")),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            status.code(),
+            Code::Unimplemented
+        );
+        assert!(status.message().contains(path));
+    }
+}
@@ -0,0 +1,176 @@
+//! Reads `Bytes` sharing the decoded buffer, and writes into `BufMut` buffers
+
+#![cfg(all(feature = "std", feature = "bytes"))]
+
+use std::ops::Range;
+
+use bytes::{Bytes, BytesMut};
+use quack_protobuf::{
+    decode, decode_shared, encode_to_vec, BufMutWriter, BytesReader, Error, MessageRead, MessageWrite, Result, Writer,
+};
+
+/// A message whose fields share the buffer they are read from
+#[derive(Debug, Default, PartialEq)]
+struct Blob {
+    data: Bytes,
+    name: Bytes,
+}
+
+impl<'a> MessageRead<'a> for Blob {
+    fn from_reader(
+        r: &mut BytesReader,
+        bytes: &'a [u8],
+    ) -> Result<Self> {
+        let mut msg = Blob::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes)? {
+                10 => msg.data = r.read_shared_bytes(bytes)?,
+                18 => msg.name = r.read_shared_string(bytes)?,
+                t => r.read_unknown(bytes, t)?,
+            }
+        }
+        Ok(msg)
+    }
+}
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Entry {
+    #[pb(1)]
+    id: u64,
+    #[pb(2)]
+    text: String,
+    #[pb(3, repeated)]
+    values: Vec<f64>,
+    #[pb(4, sfixed32)]
+    delta: i32,
+}
+
+fn range(b: &[u8]) -> Range<*const u8> {
+    b.as_ptr_range()
+}
+
+/// field 1: [1, 2, 3], field 3: 7, field 2: "name"
+fn blob() -> Bytes {
+    Bytes::from_static(&[
+        0x0a, 0x03, 1, 2, 3, 0x18, 0x07, 0x12, 0x04, b'n', b'a', b'm', b'e',
+    ])
+}
+
+#[test]
+fn shared_reads_borrow_the_source() {
+    let buf = blob();
+    let msg = decode_shared::<Blob>(&buf).unwrap();
+    assert_eq!(msg.data, &[1, 2, 3][..]);
+    assert_eq!(msg.name, "name");
+    // the very bytes of `buf`, not a copy
+    assert_eq!(
+        range(&msg.data),
+        range(&buf[2..5])
+    );
+    assert_eq!(
+        range(&msg.name),
+        range(&buf[9..])
+    );
+
+    // `buf` may be dropped, the fields keep the buffer alive
+    drop(buf);
+    assert_eq!(msg.name, "name");
+
+    // a slice of a larger buffer is shared the same way
+    let mut framed = BytesMut::from(&[0xff, 0xff][..]);
+    framed.extend_from_slice(&blob());
+    let framed = framed.freeze();
+    let inner = framed.slice(2..);
+    let msg = decode_shared::<Blob>(&inner).unwrap();
+    assert_eq!(
+        range(&msg.data),
+        range(&framed[4..7])
+    );
+}
+
+#[test]
+fn unshared_reads_copy() {
+    // a reader from a plain slice has nothing to share
+    let buf = blob();
+    let msg = decode::<Blob>(&buf).unwrap();
+    assert_eq!(msg.data, &[1, 2, 3][..]);
+    let shared = range(&buf);
+    assert!(!shared.contains(&msg.data.as_ptr()));
+    assert!(!shared.contains(&msg.name.as_ptr()));
+}
+
+#[test]
+fn buf_mut_writer_roundtrip() {
+    let entry = Entry {
+        id: 1 << 40,
+        text: "entry".to_string(),
+        values: vec![0.5, -2.0],
+        delta: -3,
+    };
+    let mut buf = BytesMut::new();
+    entry
+        .write_message(&mut Writer::new(
+            BufMutWriter::new(&mut buf),
+        ))
+        .unwrap();
+    assert_eq!(
+        buf,
+        encode_to_vec(&entry).unwrap()
+    );
+    assert_eq!(
+        decode::<Entry>(&buf).unwrap(),
+        entry
+    );
+
+    // several messages append to the same buffer
+    let mut writer = Writer::new(BufMutWriter::new(
+        BytesMut::new(),
+    ));
+    writer
+        .write_message_with_len_prefix(&entry)
+        .unwrap();
+    writer
+        .write_message_with_len_prefix(&Entry::default())
+        .unwrap();
+    let mut buf = writer.into_inner().into_inner().freeze();
+    let len = buf[0] as usize;
+    assert_eq!(
+        decode::<Entry>(&buf[1..1 + len]).unwrap(),
+        entry
+    );
+    assert_eq!(
+        &buf.split_off(1 + len)[..],
+        [0x00]
+    );
+}
+
+#[test]
+fn buf_mut_writer_full() {
+    let entry = Entry {
+        text: "too long".to_string(),
+        ..Default::default()
+    };
+    // a slice cannot grow: writing fails instead of panicking
+    let mut slice = [0u8; 4];
+    let mut writer = Writer::new(BufMutWriter::new(
+        &mut slice[..],
+    ));
+    assert!(matches!(
+        entry.write_message(&mut writer),
+        Err(Error::UnexpectedEndOfBuffer)
+    ));
+
+    let mut slice = [0u8; 10];
+    let mut writer = Writer::new(BufMutWriter::new(
+        &mut slice[..],
+    ));
+    entry.write_message(&mut writer).unwrap();
+    assert_eq!(
+        writer.into_inner().into_inner().len(),
+        0
+    );
+    assert_eq!(
+        decode::<Entry>(&slice).unwrap(),
+        entry
+    );
+}
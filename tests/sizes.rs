@@ -0,0 +1,148 @@
+//! Counts encoded sizes with `SizeCounter`, and checks `get_size` while writing
+
+#![cfg(feature = "std")]
+
+use std::borrow::Cow;
+
+use quack_protobuf::{
+    encode_length_delimited_to_vec, encode_to_vec, MessageWrite, Result, SizeCounter, Writer, WriterBackend,
+};
+
+#[allow(dead_code)]
+mod generated {
+    include!("generated/fixtures/mod.rs");
+}
+
+use generated::fixtures::{p2, p3};
+
+fn point(
+    x: i32,
+    y: i32,
+) -> p3::Point {
+    p3::Point {
+        x,
+        y,
+        ..Default::default()
+    }
+}
+
+fn shape() -> p3::Shape<'static> {
+    p3::Shape {
+        name: "square".into(),
+        points: vec![point(1, -1), point(0, 0)],
+        color: p3::Color::GREEN,
+        ids: vec![1, -2, 300],
+        weights: vec![0.5, 2.0].into(),
+        tags: [("a".into(), -1), ("b".into(), 1 << 40)].into(),
+        by_id: [(3, point(3, 3)), (-4, point(-4, 4))].into(),
+        kind: p3::mod_Shape::OneOfkind::circle(p3::mod_Shape::Circle { radius: 1.5 }),
+        maybe: Some(0),
+        data: Cow::Borrowed(b"\x00\xff"),
+        child: Some(Box::new(p3::Shape {
+            name: "child".into(),
+            ..Default::default()
+        })),
+        flags: vec![true, false],
+        sf64: -7,
+        ..Default::default()
+    }
+}
+
+/// Checks `SizeCounter` against `get_size` and the encoding of `m`
+fn assert_sizes<M: MessageWrite>(m: &M) {
+    let encoded = encode_to_vec(m).unwrap();
+    assert_eq!(m.get_size(), encoded.len());
+    assert_eq!(
+        SizeCounter::message_size(m).unwrap(),
+        encoded.len()
+    );
+
+    // with the length prefix
+    let mut writer = Writer::new(SizeCounter::new());
+    writer.write_message_with_len_prefix(m).unwrap();
+    assert_eq!(
+        writer.into_inner().size(),
+        encode_length_delimited_to_vec(m).unwrap().len()
+    );
+}
+
+#[test]
+fn size_counter_matches_get_size() {
+    assert_sizes(&shape());
+    assert_sizes(&p3::Shape::default());
+    assert_sizes(&p2::Defaults {
+        id: -1,
+        name: "".into(),
+        big: 0,
+        packed: vec![1, 1 << 20],
+        next: Some(Box::new(
+            p2::Defaults::default(),
+        )),
+        ..Default::default()
+    });
+    assert_sizes(&p2::WithGroups {
+        a: Some(1),
+        item: vec![p2::mod_WithGroups::Item { id: Some(7) }],
+        ..Default::default()
+    });
+
+    // the counter adds up everything written into it
+    let mut writer = Writer::new(SizeCounter::new());
+    writer.write_varint(300).unwrap();
+    writer.write_string("abc").unwrap();
+    writer.write_fixed64(1).unwrap();
+    assert_eq!(
+        writer.into_inner().size(),
+        2 + 4 + 8
+    );
+}
+
+/// A message whose `get_size` misses the last byte it writes
+struct Miscounted;
+
+impl MessageWrite for Miscounted {
+    fn get_size(&self) -> usize {
+        2
+    }
+
+    fn write_message<W: WriterBackend>(
+        &self,
+        w: &mut Writer<W>,
+    ) -> Result<()> {
+        w.write_with_tag(8, |w| w.write_varint(300))
+    }
+}
+
+#[test]
+fn unchecked_sizes_corrupt_the_prefix() {
+    let mut writer = Writer::new(Vec::new());
+    writer
+        .write_message_with_len_prefix(&Miscounted)
+        .unwrap();
+    // the prefix says 2, 3 bytes follow
+    assert_eq!(
+        writer.into_inner(),
+        [0x02, 0x08, 0xac, 0x02]
+    );
+}
+
+#[test]
+#[should_panic(expected = "`get_size` of `sizes::Miscounted` does not match the bytes written by `write_message`")]
+fn checked_sizes_panic_on_mismatch() {
+    let mut writer = Writer::new(Vec::new());
+    writer.set_check_sizes(true);
+    let _ = writer.write_message_with_len_prefix(&Miscounted);
+}
+
+#[test]
+fn checked_sizes_of_nested_messages() {
+    // every nested message is checked, and correct sizes write the same bytes
+    let shape = shape();
+    let mut writer = Writer::new(Vec::new());
+    writer.set_check_sizes(true);
+    writer.write_message(&shape).unwrap();
+    assert_eq!(
+        writer.into_inner(),
+        encode_to_vec(&shape).unwrap()
+    );
+}
@@ -0,0 +1,146 @@
+//! Reads and writes length delimited messages over an in-memory tokio pipe
+
+#![cfg(feature = "tokio")]
+
+use std::io::ErrorKind;
+
+use quack_protobuf::{AsyncDelimitedReader, AsyncDelimitedWriter, DecodeLimits, Error};
+use tokio::io::{duplex, AsyncWriteExt, DuplexStream};
+
+#[derive(Debug, Default, PartialEq, quack_protobuf_derive::MessageRead, quack_protobuf_derive::MessageWrite)]
+struct Log {
+    #[pb(1)]
+    id: u32,
+    #[pb(2)]
+    text: String,
+}
+
+fn log(
+    id: u32,
+    text: &str,
+) -> Log {
+    Log {
+        id,
+        text: text.to_string(),
+    }
+}
+
+/// Writes `bytes` then closes the pipe, returning its other end
+async fn pipe(bytes: &[u8]) -> DuplexStream {
+    let (mut client, server) = duplex(64);
+    client.write_all(bytes).await.unwrap();
+    client.shutdown().await.unwrap();
+    server
+}
+
+fn assert_unexpected_eof(result: quack_protobuf::Result<Option<&[u8]>>) {
+    match result {
+        Err(Error::Io(e)) => assert_eq!(
+            e.kind(),
+            ErrorKind::UnexpectedEof
+        ),
+        r => panic!(
+            "expected an UnexpectedEof io error, got {:?}",
+            r
+        ),
+    }
+}
+
+#[tokio::test]
+async fn reads_split_across_writes() {
+    // a one byte pipe hands the prefixes and the messages over byte by byte
+    let (client, server) = duplex(1);
+    let messages = vec![log(1, "a"), log(2, &"b".repeat(200)), log(3, "")];
+    let expected = messages
+        .iter()
+        .map(|m| m.text.clone())
+        .collect::<Vec<_>>();
+    let writing = tokio::spawn(async move {
+        let mut writer = AsyncDelimitedWriter::new(client);
+        for m in &messages {
+            writer.write_message(m).await.unwrap();
+        }
+        writer.shutdown().await.unwrap();
+    });
+
+    let mut reader = AsyncDelimitedReader::new(server);
+    let mut read = Vec::new();
+    while let Some(m) = reader.next_message::<Log>().await.unwrap() {
+        assert_eq!(m.id as usize, read.len() + 1);
+        read.push(m.text);
+    }
+    assert_eq!(read, expected);
+    writing.await.unwrap();
+}
+
+#[tokio::test]
+async fn clean_eof() {
+    let mut reader = AsyncDelimitedReader::new(pipe(&[0x02, 0x08, 0x01]).await);
+    assert_eq!(
+        reader.next_frame().await.unwrap(),
+        Some(&[0x08, 0x01][..])
+    );
+    assert_eq!(
+        reader.next_frame().await.unwrap(),
+        None
+    );
+    assert_eq!(
+        reader.next_frame().await.unwrap(),
+        None
+    );
+
+    let mut reader = AsyncDelimitedReader::new(pipe(&[]).await);
+    assert_eq!(
+        reader.next_frame().await.unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn eof_within_prefix() {
+    // the first byte of a two byte varint
+    let mut reader = AsyncDelimitedReader::new(pipe(&[0x02, 0x08, 0x01, 0x96]).await);
+    assert!(reader.next_frame().await.unwrap().is_some());
+    assert_unexpected_eof(reader.next_frame().await);
+}
+
+#[tokio::test]
+async fn truncated_body() {
+    let mut reader = AsyncDelimitedReader::new(pipe(&[0x03, 0x08, 0x01]).await);
+    assert_unexpected_eof(reader.next_frame().await);
+
+    let mut reader = AsyncDelimitedReader::new(pipe(&[0x80, 0x01, 0x00]).await);
+    assert_unexpected_eof(reader.next_frame().await);
+}
+
+#[tokio::test]
+async fn message_too_large() {
+    let limits = DecodeLimits {
+        max_message_size: 2,
+        ..Default::default()
+    };
+    let mut reader = AsyncDelimitedReader::with_limits(
+        pipe(&[0x02, 0x08, 0x01, 0x03, 0x08, 0x96, 0x01]).await,
+        limits,
+    );
+    assert!(reader.next_frame().await.unwrap().is_some());
+    match reader.next_frame().await {
+        Err(Error::MessageTooLarge { size: 3, limit: 2 }) => {}
+        r => panic!(
+            "expected MessageTooLarge, got {:?}",
+            r
+        ),
+    }
+
+    // the prefix is checked before the message is read
+    let (mut client, server) = duplex(64);
+    client
+        .write_all(&[0xff, 0xff, 0xff, 0x7f])
+        .await
+        .unwrap();
+    let mut reader = AsyncDelimitedReader::with_limits(server, limits);
+    assert!(matches!(
+        reader.next_frame().await,
+        Err(Error::MessageTooLarge { .. })
+    ));
+}
@@ -0,0 +1,187 @@
+//! Walks messages at the wire level with `FieldIter`, malformed ones included
+
+#![cfg(feature = "std")]
+
+use quack_protobuf::{Error, FieldIter, WireValue};
+
+/// Collects the fields of `bytes` up to the first error, and the error if any
+fn walk(
+    bytes: &[u8]
+) -> (
+    Vec<(u32, WireValue<'_>)>,
+    Option<Error>,
+) {
+    let mut fields = Vec::new();
+    let mut iter = FieldIter::new(bytes);
+    for field in &mut iter {
+        match field {
+            Ok(f) => fields.push(f),
+            Err(e) => {
+                // iteration stops after the first error
+                assert!(iter.next().is_none());
+                return (fields, Some(e));
+            }
+        }
+    }
+    (fields, None)
+}
+
+#[test]
+fn every_wire_type() {
+    let bytes = [
+        0x08, 0x96, 0x01, // 1: varint 150
+        0x11, 1, 2, 3, 4, 5, 6, 7, 8, // 2: fixed64
+        0x1a, 0x02, b'h', b'i', // 3: "hi"
+        0x23, 0x08, 0x05, 0x24, // 4: group { 1: 5 }
+        0x2d, 0xff, 0xff, 0xff, 0xff, // 5: fixed32
+        0xf8, 0xff, 0xff, 0xff, 0x0f, 0x00, // 536870911: varint 0
+    ];
+    let (fields, err) = walk(&bytes);
+    assert!(err.is_none());
+    assert_eq!(
+        fields,
+        [
+            (1, WireValue::Varint(150)),
+            (
+                2,
+                WireValue::Fixed64(0x0807060504030201)
+            ),
+            (
+                3,
+                WireValue::LengthDelimited(b"hi")
+            ),
+            (
+                4,
+                WireValue::Group(&[0x08, 0x05])
+            ),
+            (
+                5,
+                WireValue::Fixed32(u32::MAX)
+            ),
+            (
+                536870911,
+                WireValue::Varint(0)
+            ),
+        ]
+    );
+    assert_eq!(
+        fields
+            .iter()
+            .map(|(_, v)| v.wire_type())
+            .collect::<Vec<_>>(),
+        [0, 1, 2, 3, 5, 0]
+    );
+}
+
+#[test]
+fn nested_groups() {
+    // 1: group { 2: group { 3: 7 }, 4: "a" }, 5: 1
+    let bytes = [
+        0x0b, 0x13, 0x18, 0x07, 0x14, 0x22, 0x01, b'a', 0x0c, 0x28, 0x01,
+    ];
+    let (fields, err) = walk(&bytes);
+    assert!(err.is_none());
+    assert_eq!(
+        fields,
+        [
+            (
+                1,
+                WireValue::Group(&bytes[1..8])
+            ),
+            (5, WireValue::Varint(1)),
+        ]
+    );
+    // the content of a group is walked again
+    let WireValue::Group(inner) = fields[0].1 else {
+        unreachable!()
+    };
+    assert_eq!(
+        walk(inner).0,
+        [
+            (
+                2,
+                WireValue::Group(&[0x18, 0x07])
+            ),
+            (
+                4,
+                WireValue::LengthDelimited(b"a")
+            ),
+        ]
+    );
+}
+
+#[test]
+fn truncated() {
+    let cases: [(&[u8], &str); 5] = [
+        // a varint missing its last byte
+        (
+            &[0x08, 0x96],
+            "UnexpectedEndOfBuffer",
+        ),
+        // 4 of the 8 bytes of a fixed64
+        (
+            &[0x11, 1, 2, 3, 4],
+            "UnexpectedEndOfBuffer",
+        ),
+        // 2 of the 3 bytes of a string
+        (
+            &[0x1a, 0x03, b'h', b'i'],
+            "LengthOutOfBounds { offset: 4, declared: 3, available: 2 }",
+        ),
+        // a group without its end tag
+        (
+            &[0x23, 0x08, 0x05],
+            "UnexpectedEndOfBuffer",
+        ),
+        // a tag without value
+        (
+            &[0x2d],
+            "UnexpectedEndOfBuffer",
+        ),
+    ];
+    for (bytes, expected) in cases {
+        // the fields before the truncated one are read
+        let mut input = vec![0x08, 0x01];
+        input.extend_from_slice(bytes);
+        let (fields, err) = walk(&input);
+        assert_eq!(
+            fields,
+            [(1, WireValue::Varint(1))]
+        );
+        assert_eq!(
+            format!("{:?}", err.unwrap().kind()),
+            expected,
+            "{:?}",
+            bytes
+        );
+    }
+}
+
+#[test]
+fn malformed() {
+    // an end group tag without a start
+    let (fields, err) = walk(&[0x08, 0x01, 0x0c]);
+    assert_eq!(fields.len(), 1);
+    assert!(matches!(
+        err.unwrap().kind(),
+        Error::UnexpectedEndGroup(1)
+    ));
+
+    // a group closed by the end tag of another field
+    let (fields, err) = walk(&[0x0b, 0x14]);
+    assert!(fields.is_empty());
+    assert!(matches!(
+        err.unwrap().kind(),
+        Error::UnexpectedEndGroup(2)
+    ));
+
+    // wire types 6 and 7 do not exist
+    let (_, err) = walk(&[0x0e]);
+    assert!(matches!(
+        err.unwrap().kind(),
+        Error::UnknownWireType(6)
+    ));
+
+    // an empty message has no field
+    assert_eq!(FieldIter::new(&[]).count(), 0);
+}